@@ -48,6 +48,889 @@ impl Parse for FieldThenParams {
     }
 }
 
+/// One alternative of a leaf with `alternatives = [(pattern, transform), ...]`: a pattern,
+/// along with the transform that is applied to the token's text when it is the one matched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeafAlternative {
+    pub pattern: LitStr,
+    pub transform: Expr,
+}
+
+impl LeafAlternative {
+    /// The name of the rule matching this alternative, which is the kind of its nodes.
+    pub fn rule_name(path: &str, index: usize) -> String {
+        format!("{path}_alt_{index}")
+    }
+}
+
+/// Whether a variant has no fields that appear in the grammar, such as a unit variant,
+/// an empty tuple or struct variant, or one whose fields are all skipped. Such a variant is
+/// matched by its name, since a rule with nothing in it would match the empty string.
+pub fn is_fieldless(fields: &Fields) -> bool {
+    fields.iter().all(|field| {
+        field
+            .attrs
+            .iter()
+            .any(|attr| attr.path == syn::parse_quote!(rust_sitter::skip))
+    })
+}
+
+/// The text of a unit variant without any leaf, which is the name of the variant in the case
+/// given by `#[rust_sitter::rename_all(...)]` on its enum, or in snake case by default.
+pub fn unit_variant_text(variant: &Ident, enum_attrs: &[Attribute]) -> String {
+    let case = enum_attrs
+        .iter()
+        .find(|a| a.path == syn::parse_quote!(rust_sitter::rename_all))
+        .map(|a| {
+            a.parse_args::<LitStr>()
+                .expect("Expected a string literal for `rename_all`")
+                .value()
+        })
+        .unwrap_or_else(|| "snake_case".to_string());
+
+    let name = variant.to_string();
+    let words = split_words(&name);
+
+    match case.as_str() {
+        "snake_case" => words.join("_").to_lowercase(),
+        "kebab-case" => words.join("-").to_lowercase(),
+        "SCREAMING_SNAKE_CASE" => words.join("_").to_uppercase(),
+        "lowercase" => name.to_lowercase(),
+        "UPPERCASE" => name.to_uppercase(),
+        "PascalCase" => name,
+        _ => panic!("Unknown case `{case}` in `rename_all`, expected one of `snake_case`, `kebab-case`, `SCREAMING_SNAKE_CASE`, `lowercase`, `UPPERCASE` or `PascalCase`"),
+    }
+}
+
+/// Splits a name into words before each uppercase letter that starts a new word, so that
+/// `IfLet` becomes `If` and `Let`, and `HTTPGet` becomes `HTTP` and `Get`.
+fn split_words(name: &str) -> Vec<String> {
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut words: Vec<String> = vec![];
+    chars.iter().enumerate().for_each(|(i, c)| {
+        let starts_word = i > 0
+            && c.is_uppercase()
+            && (!chars[i - 1].is_uppercase()
+                || chars.get(i + 1).map(|n| n.is_lowercase()).unwrap_or(false));
+        match words.last_mut() {
+            Some(word) if !starts_word => word.push(*c),
+            _ => words.push(c.to_string()),
+        }
+    });
+
+    words
+}
+
+/// The case of the names of the rules of a grammar, and so of its node kinds, which is
+/// given by `#[rust_sitter::rename_all(...)]` on the grammar module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleCase {
+    /// Rules are named after their types and variants, such as `Expression_Number`.
+    Unchanged,
+    /// Rules are named in snake case, such as `expression_number`, following the
+    /// convention of grammars written for Tree Sitter directly.
+    SnakeCase,
+}
+
+impl RuleCase {
+    /// Finds the case given by `#[rust_sitter::rename_all(...)]` among the attributes of a
+    /// grammar module.
+    pub fn find(module_attrs: &[Attribute]) -> RuleCase {
+        module_attrs
+            .iter()
+            .find(|a| a.path == syn::parse_quote!(rust_sitter::rename_all))
+            .map(|a| {
+                let case = a
+                    .parse_args::<LitStr>()
+                    .expect("Expected a string literal for `rename_all`")
+                    .value();
+                match case.as_str() {
+                    "snake_case" => RuleCase::SnakeCase,
+                    _ => panic!("Unknown case `{case}` in `rename_all` on a grammar, rules can only be renamed to `snake_case`"),
+                }
+            })
+            .unwrap_or(RuleCase::Unchanged)
+    }
+
+    /// Puts the name of a rule in this case. Each part of the name between underscores is
+    /// converted on its own, so that converting a name made by joining parts with
+    /// underscores gives the same result as joining the converted parts, and a leading
+    /// underscore keeps a hidden rule hidden.
+    pub fn apply(self, name: &str) -> String {
+        match self {
+            RuleCase::Unchanged => name.to_string(),
+            RuleCase::SnakeCase => name
+                .split('_')
+                .map(|part| split_words(part).join("_").to_lowercase())
+                .collect::<Vec<_>>()
+                .join("_"),
+        }
+    }
+}
+
+/// Finds the literal text of a leaf annotated with `text = "..."`, if it has one, or the
+/// text that matches the bytes of a leaf annotated with `bytes = [...]`.
+pub fn find_text_param(attrs: &[Attribute]) -> Option<String> {
+    find_leaf_str_param(attrs, "text").or_else(|| {
+        let leaf_attr = attrs
+            .iter()
+            .find(|attr| attr.path == syn::parse_quote!(rust_sitter::leaf))?;
+        leaf_attr
+            .parse_args_with(Punctuated::<NameValueExpr, Token![,]>::parse_terminated)
+            .ok()?
+            .iter()
+            .find(|param| param.path == "bytes")
+            .map(|param| bytes_leaf_text(&param.expr))
+    })
+}
+
+/// Turns the array of a `bytes = [...]` leaf into the text that matches it, in which each
+/// byte is the character with the same code point, as the bytes are decoded by
+/// `parse_bytes`.
+pub fn bytes_leaf_text(expr: &Expr) -> String {
+    let Expr::Array(array) = expr else {
+        panic!("Expected an array of byte literals such as `[0x7F, 0x45]` for `bytes`");
+    };
+    if array.elems.is_empty() {
+        panic!("Expected at least one byte in `bytes`");
+    }
+
+    array
+        .elems
+        .iter()
+        .map(|e| match e {
+            Expr::Lit(ExprLit {
+                lit: Lit::Int(i), ..
+            }) => i
+                .base10_parse::<u8>()
+                .unwrap_or_else(|_| panic!("`{i}` in `bytes` is not a byte"))
+                as char,
+            _ => panic!("Expected byte literals such as `0x7F` in `bytes`"),
+        })
+        .collect()
+}
+
+/// Finds the node kind given to a `text` leaf with `name = "..."`, if it has one.
+pub fn find_leaf_name(attrs: &[Attribute]) -> Option<String> {
+    find_leaf_str_param(attrs, "name")
+}
+
+fn find_leaf_str_param(attrs: &[Attribute], name: &str) -> Option<String> {
+    let leaf_attr = attrs
+        .iter()
+        .find(|attr| attr.path == syn::parse_quote!(rust_sitter::leaf))?;
+    let leaf_params = leaf_attr
+        .parse_args_with(Punctuated::<NameValueExpr, Token![,]>::parse_terminated)
+        .ok()?;
+
+    leaf_params
+        .iter()
+        .find(|param| param.path == name)
+        .and_then(|p| match &p.expr {
+            Expr::Lit(ExprLit {
+                lit: Lit::Str(s), ..
+            }) => Some(s.value()),
+            _ => None,
+        })
+}
+
+/// The leaf field of a struct annotated with `#[rust_sitter::token]`, which is a standalone
+/// rule made of just that leaf, so that other rules can refer to the leaf by the type of
+/// the struct. The rule of the leaf is named after the struct rather than after its field.
+pub fn find_token_field(s: &ItemStruct) -> Option<&Field> {
+    if !s
+        .attrs
+        .iter()
+        .any(|attr| attr.path == syn::parse_quote!(rust_sitter::token))
+    {
+        return None;
+    }
+
+    match s.fields.iter().collect::<Vec<_>>().as_slice() {
+        [field]
+            if field
+                .attrs
+                .iter()
+                .any(|attr| attr.path == syn::parse_quote!(rust_sitter::leaf)) =>
+        {
+            let skip_over = HashSet::new();
+            if try_extract_inner_type(&field.ty, "Option", &skip_over).1
+                || try_extract_repeated_type(&field.ty, &skip_over).1
+            {
+                panic!(
+                    "The field of the token `{}` is its whole rule, so it cannot be optional or repeated",
+                    s.ident
+                );
+            }
+            Some(field)
+        }
+        _ => panic!(
+            "The token `{}` must have a single field with `#[rust_sitter::leaf(...)]`",
+            s.ident
+        ),
+    }
+}
+
+/// The primitive types that the text of a leaf can be parsed into with `FromStr`.
+pub const PRIMITIVE_LEAF_TYPES: &[&str] = &[
+    "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize", "f32",
+    "f64", "bool", "char", "String",
+];
+
+/// The inner type of a newtype that a leaf can be extracted into without a `transform`,
+/// which is a tuple struct without annotations whose single field has one of the
+/// [`PRIMITIVE_LEAF_TYPES`], such as `struct LineNo(u32)`. Such a struct is not a rule of
+/// the grammar; the text of the leaf is parsed into the inner type and wrapped instead.
+pub fn find_newtype_inner(s: &ItemStruct) -> Option<&Type> {
+    let is_sitter_attr = |attr: &Attribute| {
+        attr.path
+            .segments
+            .first()
+            .is_some_and(|s| s.ident == "rust_sitter")
+    };
+
+    match &s.fields {
+        Fields::Unnamed(fields)
+            if fields.unnamed.len() == 1
+                && !s.attrs.iter().any(is_sitter_attr)
+                && !fields.unnamed[0].attrs.iter().any(is_sitter_attr) =>
+        {
+            let ty = &fields.unnamed[0].ty;
+            match ty {
+                Type::Path(p)
+                    if p.qself.is_none()
+                        && p.path.get_ident().is_some_and(|i| {
+                            PRIMITIVE_LEAF_TYPES.contains(&i.to_string().as_str())
+                        }) =>
+                {
+                    Some(ty)
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The types of a grammar annotated with `#[rust_sitter::language]`, in order. A grammar
+/// has at least one, and its `source_file` matches any of them when it has several.
+pub fn find_root_types(items: &[Item]) -> Vec<Ident> {
+    items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Enum(ItemEnum { ident, attrs, .. })
+            | Item::Struct(ItemStruct { ident, attrs, .. }) => attrs
+                .iter()
+                .any(|attr| attr.path == syn::parse_quote!(rust_sitter::language))
+                .then(|| ident.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Whether a struct is matched as a single token without the extras of the grammar, as
+/// marked with `#[rust_sitter::no_extras]` or `#[rust_sitter::scoped_extras(none)]`.
+pub fn has_no_extras(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        if attr.path == syn::parse_quote!(rust_sitter::no_extras) {
+            true
+        } else if attr.path == syn::parse_quote!(rust_sitter::scoped_extras) {
+            match attr.parse_args::<Ident>() {
+                Ok(scope) if scope == "none" => true,
+                _ => panic!(
+                    "Expected `none` for `scoped_extras`, which is the only scope of extras that is supported"
+                ),
+            }
+        } else {
+            false
+        }
+    })
+}
+
+/// Whether a field is marked with `#[rust_sitter::optional]`, which makes it optional in
+/// the grammar while keeping its type, so that the default value of the type is extracted
+/// when it is absent. A field cannot be both marked and of type `Option<_>`.
+pub fn is_optional_field(attrs: &[Attribute], ty: &Type) -> bool {
+    let marked = attrs
+        .iter()
+        .any(|attr| attr.path == syn::parse_quote!(rust_sitter::optional));
+
+    let mut skip_over = HashSet::new();
+    skip_over.insert("Spanned");
+    skip_over.insert("Box");
+    if marked && try_extract_inner_type(ty, "Option", &skip_over).1 {
+        panic!("`optional` cannot be combined with a field of type `Option<_>`, which is already optional");
+    }
+
+    marked
+}
+
+/// Whether a field is present in every node of the rule of its type, outside of error
+/// recovery. Skipped fields, `Option`s, fields marked `#[rust_sitter::optional]`, `bool`
+/// flags of a `text` leaf and repetitions that can be empty can all be absent.
+pub fn is_required_field(field: &Field) -> bool {
+    if field
+        .attrs
+        .iter()
+        .any(|attr| attr.path == syn::parse_quote!(rust_sitter::skip))
+    {
+        return false;
+    }
+
+    let mut skip_over = HashSet::new();
+    skip_over.insert("Spanned");
+    skip_over.insert("Box");
+    if try_extract_inner_type(&field.ty, "Option", &skip_over).1
+        || is_optional_field(&field.attrs, &field.ty)
+    {
+        return false;
+    }
+
+    let attrs = expand_operators(&field.attrs);
+    let leaf_params = attrs
+        .iter()
+        .find(|attr| attr.path == syn::parse_quote!(rust_sitter::leaf))
+        .and_then(|a| {
+            a.parse_args_with(Punctuated::<NameValueExpr, Token![,]>::parse_terminated)
+                .ok()
+        })
+        .unwrap_or_default();
+    let is_flag = find_text_param(&attrs).is_some()
+        && field.ty == syn::parse_quote!(bool)
+        && !leaf_params.iter().any(|param| param.path == "transform");
+    if is_flag {
+        return false;
+    }
+
+    let is_repeated =
+        !matches!(field.ty, Type::Array(_)) && try_extract_repeated_type(&field.ty, &skip_over).1;
+    !is_repeated
+        || find_repeat_count(&field.attrs, &field.ty).is_some()
+        || repeat_params(&field.attrs)
+            .iter()
+            .any(|param| param.path == "non_empty" && param.expr == syn::parse_quote!(true))
+}
+
+/// The name of the Tree Sitter field that holds the field at the given index, which is the
+/// name given by `#[rust_sitter::field("...")]`, such as `type` for a Rust field named
+/// `ty`, or else the name of the Rust field, or its index in a tuple. The names of the rules
+/// of the field keep following the Rust name.
+pub fn ts_field_name(field: &Field, index: usize) -> String {
+    let rust_name = field
+        .ident
+        .as_ref()
+        .map(|v| v.to_string())
+        .unwrap_or(format!("{index}"));
+    match field
+        .attrs
+        .iter()
+        .find(|attr| attr.path == syn::parse_quote!(rust_sitter::field))
+    {
+        Some(attr) => {
+            let name = attr
+                .parse_args::<LitStr>()
+                .unwrap_or_else(|_| {
+                    panic!("Expected `#[rust_sitter::field(\"...\")]` on the field `{rust_name}`")
+                })
+                .value();
+            if name.is_empty() {
+                panic!("The field name given to `{rust_name}` cannot be empty");
+            }
+            name
+        }
+        None => rust_name,
+    }
+}
+
+/// Folds a `#[rust_sitter::operators([...])]` attribute on a field into its `leaf`
+/// attribute, as a `text` array of the operators ordered from the longest to the shortest,
+/// so that the set is matched as a single token and the longest operator wins. The text of
+/// the matched operator is kept as a `String` unless the leaf has a `transform`. Fields
+/// without the attribute are returned unchanged.
+pub fn expand_operators(attrs: &[Attribute]) -> Vec<Attribute> {
+    let operators_attr = match attrs
+        .iter()
+        .find(|attr| attr.path == syn::parse_quote!(rust_sitter::operators))
+    {
+        Some(attr) => attr,
+        None => return attrs.to_vec(),
+    };
+
+    let mut operators = match operators_attr.parse_args::<Expr>() {
+        Ok(Expr::Array(array)) => array
+            .elems
+            .iter()
+            .map(|e| match e {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(s), ..
+                }) => s.value(),
+                _ => panic!("Expected string literals in `operators`"),
+            })
+            .collect::<Vec<_>>(),
+        _ => panic!("Expected an array of string literals for `operators`"),
+    };
+
+    if operators.is_empty() {
+        panic!("Expected at least one string literal in `operators`");
+    }
+
+    operators.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+    if let Some(duplicate) = operators.windows(2).find(|pair| pair[0] == pair[1]) {
+        panic!("The operator `{}` is listed more than once", duplicate[0]);
+    }
+
+    let mut leaf_params = attrs
+        .iter()
+        .find(|attr| attr.path == syn::parse_quote!(rust_sitter::leaf))
+        .map(|leaf| {
+            leaf.parse_args_with(Punctuated::<NameValueExpr, Token![,]>::parse_terminated)
+                .expect("Expected `key = value` parameters for `leaf`")
+                .into_iter()
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    if let Some(param) = leaf_params.iter().find(|param| {
+        ["text", "bytes", "pattern", "alternatives"].contains(&param.path.to_string().as_str())
+    }) {
+        panic!(
+            "`operators` cannot be combined with `{}` on the leaf, since it gives the texts to match",
+            param.path
+        );
+    }
+
+    leaf_params.push(syn::parse_quote!(text = [#(#operators),*]));
+    if !leaf_params.iter().any(|param| param.path == "transform") {
+        leaf_params.push(syn::parse_quote!(transform = |v| v.to_string()));
+    }
+
+    let leaf_params = leaf_params.iter().map(|param| {
+        let (path, expr) = (&param.path, &param.expr);
+        quote::quote!(#path = #expr)
+    });
+    attrs
+        .iter()
+        .filter(|attr| {
+            attr.path != syn::parse_quote!(rust_sitter::operators)
+                && attr.path != syn::parse_quote!(rust_sitter::leaf)
+        })
+        .cloned()
+        .chain(std::iter::once(syn::parse_quote!(
+            #[rust_sitter::leaf(#(#leaf_params),*)]
+        )))
+        .collect()
+}
+
+/// The `key = value` parameters of the `#[rust_sitter::repeat(...)]` attribute of a field.
+fn repeat_params(attrs: &[Attribute]) -> Vec<NameValueExpr> {
+    attrs
+        .iter()
+        .find(|attr| attr.path == syn::parse_quote!(rust_sitter::repeat))
+        .map(|repeat| {
+            repeat
+                .parse_args_with(Punctuated::<NameValueExpr, Token![,]>::parse_terminated)
+                .expect("Expected `key = value` parameters for `repeat`")
+                .into_iter()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Folds the `sep = "..."` parameter of a `#[rust_sitter::repeat(...)]` attribute on a field
+/// into a `#[rust_sitter::delimited(...)]` attribute with a `text` leaf of the separator,
+/// which it is a shorthand for, along with the `sep_prec` of the separator. Fields without
+/// the parameter are returned unchanged.
+pub fn expand_repeat_separator(attrs: &[Attribute]) -> Vec<Attribute> {
+    let params = repeat_params(attrs);
+    let sep = match params.iter().find(|param| param.path == "sep") {
+        Some(param) => match &param.expr {
+            Expr::Lit(ExprLit {
+                lit: Lit::Str(s), ..
+            }) => s.clone(),
+            _ => panic!("Expected a string literal for `sep`"),
+        },
+        None if params.iter().any(|param| param.path == "sep_prec") => {
+            panic!("`sep_prec` on `repeat` needs a `sep` to give the precedence of")
+        }
+        None => return attrs.to_vec(),
+    };
+    let sep_prec = params
+        .iter()
+        .find(|param| param.path == "sep_prec")
+        .map(|param| {
+            let expr = &param.expr;
+            quote::quote!(, sep_prec = #expr)
+        });
+
+    if attrs
+        .iter()
+        .any(|attr| attr.path == syn::parse_quote!(rust_sitter::delimited))
+    {
+        panic!("`sep` cannot be combined with `delimited`, since it gives the delimiter");
+    }
+
+    let params = params
+        .iter()
+        .filter(|param| param.path != "sep" && param.path != "sep_prec")
+        .map(|param| {
+            let (path, expr) = (&param.path, &param.expr);
+            quote::quote!(#path = #expr)
+        });
+    attrs
+        .iter()
+        .filter(|attr| attr.path != syn::parse_quote!(rust_sitter::repeat))
+        .cloned()
+        .chain([
+            syn::parse_quote!(#[rust_sitter::repeat(#(#params),*)]),
+            syn::parse_quote!(#[rust_sitter::delimited(#[rust_sitter::leaf(text = #sep)] () #sep_prec)]),
+        ])
+        .collect()
+}
+
+/// The number of elements that a repeated field must have, if it is fixed, which is the
+/// length of a field of an array type or the `count = N` parameter of its
+/// `#[rust_sitter::repeat(...)]` attribute.
+pub fn find_repeat_count(attrs: &[Attribute], ty: &Type) -> Option<usize> {
+    let parse_count = |expr: &Expr| match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(i), ..
+        }) => i.base10_parse::<usize>().unwrap(),
+        _ => panic!("Expected an integer literal for the number of elements"),
+    };
+
+    let count = repeat_params(attrs)
+        .iter()
+        .find(|param| param.path == "count")
+        .map(|param| parse_count(&param.expr));
+    let length = match ty {
+        Type::Array(array) => Some(parse_count(&array.len)),
+        _ => None,
+    };
+
+    match (count, length) {
+        (Some(count), Some(length)) if count != length => {
+            panic!("`count = {count}` does not match the length of the array, which is {length}")
+        }
+        (Some(0), _) | (_, Some(0)) => panic!("A repeated field must have at least one element"),
+        (count, length) => count.or(length),
+    }
+}
+
+/// The value of a field in the empty AST of a root type marked with
+/// `#[rust_sitter::default_empty]`, which is the value of a skipped field, an empty
+/// repetition or an absent optional field, or `None` if the field holds something that is
+/// always matched, so that the root type has no empty AST.
+pub fn empty_field_value(field: &Field) -> Option<Expr> {
+    if let Some(skip_attr) = field
+        .attrs
+        .iter()
+        .find(|attr| attr.path == syn::parse_quote!(rust_sitter::skip))
+    {
+        return Some(skip_attr.parse_args::<Expr>().unwrap());
+    }
+
+    if field.ty == syn::parse_quote!(()) {
+        return Some(syn::parse_quote!(()));
+    }
+
+    let skip_over = HashSet::new();
+    let is_repeated = !matches!(field.ty, Type::Array(_))
+        && try_extract_repeated_type(&field.ty, &skip_over).1
+        && find_repeat_count(&field.attrs, &field.ty).is_none()
+        && !repeat_params(&field.attrs)
+            .iter()
+            .any(|param| param.path == "non_empty" && param.expr == syn::parse_quote!(true));
+    let is_option = try_extract_inner_type(&field.ty, "Option", &skip_over).1;
+    (is_repeated || is_option || is_optional_field(&field.attrs, &field.ty))
+        .then(|| syn::parse_quote!(::core::default::Default::default()))
+}
+
+/// The name of the rule matching the text of a leaf with `anchored = true` when it runs on
+/// into more word characters, which is the kind of the nodes that are reported as errors.
+pub fn anchored_overrun_rule_name(path: &str) -> String {
+    format!("{path}_overrun")
+}
+
+/// Splits the named capture groups (`(?P<name>...)` or `(?<name>...)`) out of the pattern
+/// of a leaf, returning the pattern with plain groups in their place, which Tree Sitter
+/// can match, along with the names of the groups in order.
+pub fn split_capture_groups(pattern: &str) -> (String, Vec<String>) {
+    let mut out = String::with_capacity(pattern.len());
+    let mut names = vec![];
+    let mut in_class = false;
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                out.push(c);
+                out.extend(chars.next());
+            }
+            '[' => {
+                in_class = true;
+                out.push(c);
+            }
+            ']' => {
+                in_class = false;
+                out.push(c);
+            }
+            '(' if !in_class => {
+                out.push(c);
+                let rest = chars.clone().collect::<String>();
+                let name_start = ["?P<", "?<"]
+                    .iter()
+                    .find(|prefix| rest.starts_with(*prefix))
+                    .map(|prefix| prefix.len());
+                if let Some(name_start) = name_start {
+                    let name = rest[name_start..]
+                        .chars()
+                        .take_while(|c| c.is_alphanumeric() || *c == '_')
+                        .collect::<String>();
+                    // lookbehinds such as `(?<=...)` also start with `?<`, but have no name
+                    if !name.is_empty() && rest[name_start + name.len()..].starts_with('>') {
+                        (0..name_start + name.len() + 1).for_each(|_| {
+                            chars.next();
+                        });
+                        names.push(name);
+                    }
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+
+    (out, names)
+}
+
+/// Parses the value of the `alternatives` parameter of a leaf, which is an array of
+/// `(pattern, transform)` pairs.
+pub fn parse_leaf_alternatives(expr: &Expr) -> Vec<LeafAlternative> {
+    let elems = match expr {
+        Expr::Array(array) => &array.elems,
+        _ => panic!("Expected an array of `(pattern, transform)` pairs for `alternatives`"),
+    };
+
+    let alternatives = elems
+        .iter()
+        .map(|e| match e {
+            Expr::Tuple(tuple) if tuple.elems.len() == 2 => match &tuple.elems[0] {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(pattern),
+                    ..
+                }) => LeafAlternative {
+                    pattern: pattern.clone(),
+                    transform: tuple.elems[1].clone(),
+                },
+                _ => panic!("Expected a string literal pattern in `alternatives`"),
+            },
+            _ => panic!("Expected a `(pattern, transform)` pair in `alternatives`"),
+        })
+        .collect::<Vec<_>>();
+
+    if alternatives.is_empty() {
+        panic!("Expected at least one `(pattern, transform)` pair in `alternatives`");
+    }
+
+    alternatives
+}
+
+/// The arguments to `#[rust_sitter::grammar(...)]`: an optional grammar name, followed
+/// by optional named parameters configuring the generated grammar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrammarThenParams {
+    pub name: Option<LitStr>,
+    pub comma: Option<Token![,]>,
+    pub params: Punctuated<NameValueExpr, Token![,]>,
+}
+
+impl Parse for GrammarThenParams {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name: Option<LitStr> = if input.peek(LitStr) {
+            Some(input.parse()?)
+        } else {
+            None
+        };
+        let comma: Option<Token![,]> = if name.is_some() { input.parse()? } else { None };
+        let params = if name.is_none() || comma.is_some() {
+            input.parse_terminated(NameValueExpr::parse)?
+        } else {
+            Punctuated::new()
+        };
+
+        Ok(GrammarThenParams {
+            name,
+            comma,
+            params,
+        })
+    }
+}
+
+impl GrammarThenParams {
+    /// Finds the value of the named parameter, if it was given.
+    pub fn param(&self, name: &str) -> Option<&Expr> {
+        self.params
+            .iter()
+            .find(|param| param.path == name)
+            .map(|p| &p.expr)
+    }
+
+    /// The name of the grammar, which is used in the symbols of the generated parser. This
+    /// is the `name` parameter if it was given, then the unnamed argument, and otherwise
+    /// the name of the grammar module, sanitized with [`sanitize_grammar_name`].
+    pub fn grammar_name(&self, module_ident: &Ident) -> String {
+        let name = match self.param("name") {
+            Some(Expr::Lit(ExprLit {
+                lit: Lit::Str(s), ..
+            })) => s.value(),
+            Some(_) => panic!("Expected string literal for the `name` of the grammar"),
+            None => self
+                .name
+                .as_ref()
+                .map(|n| n.value())
+                .unwrap_or_else(|| module_ident.to_string()),
+        };
+
+        sanitize_grammar_name(&name)
+    }
+
+    /// The prefix of every rule name (and so of every node kind) of the grammar, which is
+    /// the grammar name followed by an underscore when `prefix_rules = true` is given, so
+    /// that several grammars linked into one binary have distinct rule names.
+    pub fn rule_prefix(&self, module_ident: &Ident) -> String {
+        match self.param("prefix_rules") {
+            Some(Expr::Lit(ExprLit {
+                lit: Lit::Bool(b), ..
+            })) if b.value => format!("{}_", self.grammar_name(module_ident)),
+            Some(Expr::Lit(ExprLit {
+                lit: Lit::Bool(_), ..
+            }))
+            | None => String::new(),
+            Some(_) => panic!("Expected boolean literal for `prefix_rules`"),
+        }
+    }
+
+    /// Whether the rule of the root type is the start rule of the grammar, as given by
+    /// `named_root = true`, instead of being wrapped in a `source_file` rule.
+    pub fn named_root(&self) -> bool {
+        match self.param("named_root") {
+            Some(Expr::Lit(ExprLit {
+                lit: Lit::Bool(b), ..
+            })) => b.value,
+            Some(_) => panic!("Expected boolean literal for `named_root`"),
+            None => false,
+        }
+    }
+
+    /// The maximum length in bytes of a token, given by `max_token_len = 4096`, past which
+    /// parsing fails with a `ParseErrorReason::TokenTooLong` error.
+    pub fn max_token_len(&self) -> Option<usize> {
+        match self.param("max_token_len") {
+            Some(Expr::Lit(ExprLit {
+                lit: Lit::Int(i), ..
+            })) => Some(
+                i.base10_parse()
+                    .expect("Expected integer literal for `max_token_len`"),
+            ),
+            Some(_) => panic!("Expected integer literal for `max_token_len`"),
+            None => None,
+        }
+    }
+}
+
+/// Turns a grammar name into one that Tree Sitter accepts, which must match
+/// `^[a-z][a-z0-9_]*$`. Characters that cannot appear in a C identifier are replaced with
+/// underscores, and the words of a camel case name are lowercased and joined with
+/// underscores, so that `MyLang` becomes `my_lang`. Panics if the result does not start
+/// with a letter, such as for an empty name.
+pub fn sanitize_grammar_name(name: &str) -> String {
+    let replaced: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    let sanitized = split_words(&replaced)
+        .iter()
+        .fold(String::new(), |mut sanitized, word| {
+            if !sanitized.is_empty() && !sanitized.ends_with('_') {
+                sanitized.push('_');
+            }
+            sanitized.push_str(&word.to_ascii_lowercase());
+            sanitized
+        });
+
+    if !sanitized.starts_with(|c: char| c.is_ascii_lowercase()) {
+        panic!(
+            "The grammar name `{name}` becomes `{sanitized}`, but Tree Sitter grammar names must start with a letter"
+        );
+    }
+
+    sanitized
+}
+
+/// The arguments to `#[rust_sitter::alias(...)]`: the node kind to use for a type,
+/// followed by optional named parameters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AliasThenParams {
+    pub value: LitStr,
+    pub comma: Option<Token![,]>,
+    pub params: Punctuated<NameValueExpr, Token![,]>,
+}
+
+impl Parse for AliasThenParams {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let value = input.parse()?;
+        let comma: Option<Token![,]> = input.parse()?;
+        let params = if comma.is_some() {
+            input.parse_terminated(NameValueExpr::parse)?
+        } else {
+            Punctuated::new()
+        };
+
+        Ok(AliasThenParams {
+            value,
+            comma,
+            params,
+        })
+    }
+}
+
+/// Finds the `#[rust_sitter::grammar(...)]` attribute among the given attributes
+/// and parses its arguments.
+pub fn find_grammar_params(attrs: &[Attribute]) -> Option<GrammarThenParams> {
+    attrs.iter().find_map(|a| {
+        if a.path == syn::parse_quote!(rust_sitter::grammar) {
+            if a.tokens.is_empty() {
+                return Some(GrammarThenParams {
+                    name: None,
+                    comma: None,
+                    params: Punctuated::new(),
+                });
+            }
+
+            Some(
+                a.parse_args_with(GrammarThenParams::parse)
+                    .unwrap_or_else(|_| {
+                        panic!(
+                            "Expected a string literal for the grammar name and named parameters"
+                        )
+                    }),
+            )
+        } else {
+            None
+        }
+    })
+}
+
+/// Finds the name of the grammar defined by a module marked with
+/// `#[rust_sitter::grammar(...)]` (see [`GrammarThenParams::grammar_name`]).
+pub fn find_grammar_name(module: &ItemMod) -> Option<String> {
+    find_grammar_params(&module.attrs).map(|p| p.grammar_name(&module.ident))
+}
+
 pub fn try_extract_inner_type(
     ty: &Type,
     inner_of: &str,
@@ -85,6 +968,30 @@ pub fn try_extract_inner_type(
     }
 }
 
+/// The collections that a repeated field can be extracted into. Each one takes the type
+/// of its elements as its first argument, except for `SmallVec`, which takes an array of
+/// its elements.
+pub const REPEATED_COLLECTIONS: &[&str] = &["Vec", "SmallVec"];
+
+/// Like [`try_extract_inner_type`], but extracts the type of the elements of any of the
+/// [`REPEATED_COLLECTIONS`].
+pub fn try_extract_repeated_type(ty: &Type, skip_over: &HashSet<&str>) -> (Type, bool) {
+    // the elements of a fixed-size array are repeated a fixed number of times
+    if let Type::Array(array) = ty {
+        return (*array.elem.clone(), true);
+    }
+
+    REPEATED_COLLECTIONS
+        .iter()
+        .map(|collection| try_extract_inner_type(ty, collection, skip_over))
+        .find(|(_, is_repeated)| *is_repeated)
+        .map(|(inner, is_repeated)| match inner {
+            Type::Array(array) => (*array.elem, is_repeated),
+            inner => (inner, is_repeated),
+        })
+        .unwrap_or((ty.clone(), false))
+}
+
 pub fn filter_inner_type(ty: &Type, skip_over: &HashSet<&str>) -> Type {
     if let Type::Path(p) = &ty {
         let type_segment = p.path.segments.last().unwrap();
@@ -106,15 +1013,54 @@ pub fn filter_inner_type(ty: &Type, skip_over: &HashSet<&str>) -> Type {
     }
 }
 
+/// Replaces every reference in a type with the type it refers to, since the `&'a` references
+/// of an AST extracted into an arena match the same rules as the types themselves.
+pub fn strip_references(ty: &Type) -> Type {
+    match ty {
+        Type::Reference(r) => strip_references(&r.elem),
+        Type::Path(p) => {
+            let mut p = p.clone();
+            p.path.segments.iter_mut().for_each(|segment| {
+                if let PathArguments::AngleBracketed(args) = &mut segment.arguments {
+                    args.args.iter_mut().for_each(|a| {
+                        if let GenericArgument::Type(t) = a {
+                            *t = strip_references(t);
+                        }
+                    });
+                }
+            });
+            Type::Path(p)
+        }
+        ty => ty.clone(),
+    }
+}
+
 pub fn wrap_leaf_type(ty: &Type, skip_over: &HashSet<&str>) -> Type {
+    wrap_leaf_type_in(ty, skip_over, &parse_quote!(rust_sitter::WithLeaf))
+}
+
+/// Like [`wrap_leaf_type`], but wraps the leaf in the given type instead of `WithLeaf`.
+pub fn wrap_leaf_type_in(ty: &Type, skip_over: &HashSet<&str>, wrapper: &Path) -> Type {
     let mut ty = ty.clone();
+    if let Type::Array(array) = &mut ty {
+        *array.elem = wrap_leaf_type_in(&array.elem, skip_over, wrapper);
+        return ty;
+    }
+
     if let Type::Path(p) = &mut ty {
         let type_segment = p.path.segments.last_mut().unwrap();
         if skip_over.contains(type_segment.ident.to_string().as_str()) {
             if let PathArguments::AngleBracketed(args) = &mut type_segment.arguments {
                 for a in args.args.iter_mut() {
-                    if let syn::GenericArgument::Type(t) = a {
-                        *t = wrap_leaf_type(t, skip_over);
+                    match a {
+                        // the elements of a `SmallVec` are given as an array type
+                        syn::GenericArgument::Type(Type::Array(array)) => {
+                            *array.elem = wrap_leaf_type_in(&array.elem, skip_over, wrapper);
+                        }
+                        syn::GenericArgument::Type(t) => {
+                            *t = wrap_leaf_type_in(t, skip_over, wrapper);
+                        }
+                        _ => {}
                     }
                 }
 
@@ -123,9 +1069,9 @@ pub fn wrap_leaf_type(ty: &Type, skip_over: &HashSet<&str>) -> Type {
                 panic!("Expected angle bracketed path");
             }
         } else {
-            parse_quote!(rust_sitter::WithLeaf<#ty>)
+            parse_quote!(#wrapper<#ty>)
         }
     } else {
-        parse_quote!(rust_sitter::WithLeaf<#ty>)
+        parse_quote!(#wrapper<#ty>)
     }
 }
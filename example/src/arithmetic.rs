@@ -1,6 +1,7 @@
 #[rust_sitter::grammar("arithmetic")]
 pub mod grammar {
     #[rust_sitter::language]
+    #[rust_sitter::serde]
     #[derive(PartialEq, Eq, Debug)]
     pub enum Expression {
         Number(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] i32),
@@ -25,6 +26,145 @@ pub mod grammar {
     }
 }
 
+#[rust_sitter::grammar("arithmetic_operators")]
+pub mod grammar2 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum Expression {
+        Number(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] i32),
+        #[rust_sitter::prec(2)]
+        Unary(UnaryOp, Box<Expression>),
+        #[rust_sitter::prec_left(1)]
+        Binary(Box<Expression>, BinaryOp, Box<Expression>),
+    }
+
+    #[rust_sitter::alias("operator", named = true)]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum UnaryOp {
+        #[rust_sitter::leaf(text = "-")]
+        Neg,
+    }
+
+    #[rust_sitter::alias("operator", named = true)]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum BinaryOp {
+        #[rust_sitter::leaf(text = "+")]
+        Add,
+        #[rust_sitter::leaf(text = "*")]
+        Mul,
+    }
+}
+
+#[rust_sitter::grammar("arithmetic_arena")]
+#[rust_sitter::arena]
+pub mod grammar3 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum Expression<'a> {
+        Number(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] i32),
+        #[rust_sitter::prec_left(1)]
+        Sub(
+            &'a Expression<'a>,
+            #[rust_sitter::leaf(text = "-")] (),
+            &'a Expression<'a>,
+        ),
+        #[rust_sitter::prec_left(2)]
+        Mul(
+            &'a Expression<'a>,
+            #[rust_sitter::leaf(text = "*")] (),
+            &'a Expression<'a>,
+        ),
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[rust_sitter::grammar("arithmetic_shifts")]
+pub mod grammar4 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum Expression {
+        Number(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] i32),
+        #[rust_sitter::prec_left(1)]
+        Binary(
+            Box<Expression>,
+            #[rust_sitter::operators(["<", "<=", "<<", "<<="])] String,
+            Box<Expression>,
+        ),
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[rust_sitter::grammar("arithmetic_named_precedences")]
+#[rust_sitter::precedences(["postfix" > "unary" > "binary"])]
+pub mod grammar5 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum Expression {
+        Number(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] i32),
+        #[rust_sitter::prec_left("postfix")]
+        Factorial(Box<Expression>, #[rust_sitter::leaf(text = "!")] ()),
+        #[rust_sitter::prec_left("unary")]
+        Neg(#[rust_sitter::leaf(text = "-")] (), Box<Expression>),
+        #[rust_sitter::prec_left("binary")]
+        Sub(
+            Box<Expression>,
+            #[rust_sitter::leaf(text = "-")] (),
+            Box<Expression>,
+        ),
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[rust_sitter::grammar("arithmetic_dialects")]
+pub mod grammar6 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum Expression {
+        Number(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] i32),
+        #[rust_sitter::prec_left(1)]
+        Sub(
+            Box<Expression>,
+            #[rust_sitter::leaf(text = "-")] (),
+            Box<Expression>,
+        ),
+        #[rust_sitter::dialect("extended")]
+        #[rust_sitter::prec_right(2)]
+        Pow(
+            Box<Expression>,
+            #[rust_sitter::leaf(text = "**")] (),
+            Box<Expression>,
+        ),
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -99,6 +239,539 @@ mod tests {
         );
     }
 
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    #[test]
+    fn variant_kinds() {
+        assert_eq!(grammar::parse("1").unwrap().kind(), "Expression_Number");
+        assert_eq!(grammar::parse("1 - 2").unwrap().kind(), "Expression_Sub");
+        assert_eq!(
+            grammar::parse("1 * 2 - 3").unwrap().kind(),
+            "Expression_Sub"
+        );
+        assert_eq!(grammar::parse("1 * 2").unwrap().kind(), "Expression_Mul");
+    }
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    #[test]
+    fn expected_tokens() {
+        assert_eq!(grammar::expected_at("1 - ", 4), vec!["Expression_Number_0"]);
+        assert_eq!(
+            grammar::expected_at("1 - 2", 4),
+            vec!["Expression_Number_0"]
+        );
+        assert_eq!(grammar::expected_at("1", 1), vec!["*", "-"]);
+        assert_eq!(grammar::expected_at("1 - 2", 1), vec!["*", "-"]);
+    }
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    #[test]
+    fn chunked_parses() {
+        use std::io::{Read, Seek, SeekFrom};
+
+        let input = "12 - 345 * 6";
+        for chunk_size in 1..=input.len() {
+            let mut reader = std::io::Cursor::new(input.as_bytes());
+            let parsed = grammar::parse_with(|offset| {
+                reader.seek(SeekFrom::Start(offset as u64)).unwrap();
+                let mut chunk = vec![0; chunk_size];
+                let read = reader.read(&mut chunk).unwrap();
+                chunk.truncate(read);
+                chunk
+            });
+
+            assert_eq!(parsed.unwrap(), grammar::parse(input).unwrap());
+        }
+
+        assert!(grammar::parse_with(|offset| &"1 -".as_bytes()[offset..]).is_err());
+    }
+
+    #[test]
+    fn chunked_text_borrows() {
+        use rust_sitter::{ChunkedSource, Source};
+        use std::borrow::Cow;
+
+        let input = "12 - 345";
+        let source =
+            ChunkedSource::new(|offset: usize| &input[offset..(offset + 4).min(input.len())]);
+        assert!(matches!(source.text(5, 8), Cow::Borrowed("345")));
+        assert!(matches!(source.text(0, 6), Cow::Owned(text) if text == "12 - 3"));
+    }
+
+    #[test]
+    fn parse_metrics() {
+        let (parsed, metrics) = grammar::parse_with_metrics("1 - 2");
+        assert_eq!(parsed.unwrap(), grammar::parse("1 - 2").unwrap());
+        assert_eq!(metrics.node_count, 13);
+        assert_eq!(metrics.error_count, 0);
+        assert_eq!(metrics.max_depth, 5);
+
+        let (parsed, metrics) = grammar::parse_with_metrics("1 *");
+        assert!(parsed.is_err());
+        assert_eq!(metrics.node_count, 11);
+        assert_eq!(metrics.error_count, 1);
+        assert_eq!(metrics.max_depth, 5);
+    }
+
+    #[test]
+    fn parse_timeouts() {
+        use rust_sitter::errors::ParseErrorReason;
+
+        assert_eq!(
+            grammar::parse_with_timeout("1 - 2", 10_000_000).unwrap(),
+            grammar::parse("1 - 2").unwrap()
+        );
+
+        // a long input with errors everywhere makes the parser recover over and over
+        let pathological = "1 - * ".repeat(20_000);
+        let errors = grammar::parse_with_timeout(&pathological, 1).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0].reason,
+            ParseErrorReason::Timeout { micros: 1 }
+        ));
+        assert_eq!((errors[0].start, errors[0].end), (0, pathological.len()));
+    }
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    #[test]
+    fn extract_external_tree() {
+        let mut parser = rust_sitter::tree_sitter::Parser::new();
+        parser.set_language(grammar::language()).unwrap();
+
+        let input = "1 - 2 * 3";
+        let tree = parser.parse(input, None).unwrap();
+        assert_eq!(
+            grammar::extract(&tree, input).unwrap(),
+            grammar::parse(input).unwrap()
+        );
+
+        let tree = parser.parse("1 *", None).unwrap();
+        assert!(grammar::extract(&tree, "1 *").is_err());
+    }
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    #[test]
+    fn shared_alias() {
+        use grammar2::{BinaryOp, Expression as Expr, UnaryOp};
+
+        let input = "-1 + 2";
+        assert_eq!(
+            grammar2::parse(input).unwrap(),
+            Expr::Binary(
+                Box::new(Expr::Unary(UnaryOp::Neg, Box::new(Expr::Number(1)))),
+                BinaryOp::Add,
+                Box::new(Expr::Number(2))
+            )
+        );
+
+        let mut parser = rust_sitter::tree_sitter::Parser::new();
+        parser.set_language(grammar2::language()).unwrap();
+        let tree = parser.parse(input, None).unwrap();
+
+        let mut operators = vec![];
+        let mut cursor = tree.walk();
+        loop {
+            let node = cursor.node();
+            if node.kind() == "operator" {
+                operators.push(&input[node.start_byte()..node.end_byte()]);
+            }
+
+            if cursor.goto_first_child() {
+                continue;
+            }
+
+            while !cursor.goto_next_sibling() {
+                if !cursor.goto_parent() {
+                    assert_eq!(operators, vec!["-", "+"]);
+                    return;
+                }
+            }
+        }
+    }
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    #[test]
+    fn changed_ranges() {
+        use rust_sitter::tree_sitter::{InputEdit, Parser, Point};
+
+        let mut parser = Parser::new();
+        parser.set_language(grammar::language()).unwrap();
+        let mut old = parser.parse("1 - 2 - 3", None).unwrap();
+
+        old.edit(&InputEdit {
+            start_byte: 5,
+            old_end_byte: 5,
+            new_end_byte: 9,
+            start_position: Point::new(0, 5),
+            old_end_position: Point::new(0, 5),
+            new_end_position: Point::new(0, 9),
+        });
+        let new = parser.parse("1 - 2 * 4 - 3", Some(&old)).unwrap();
+
+        assert_eq!(rust_sitter::changed_ranges(&old, &new), vec![4..9]);
+        assert!(rust_sitter::changed_ranges(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn longest_operator_match() {
+        let binary = |op: &str| {
+            grammar4::Expression::Binary(
+                Box::new(grammar4::Expression::Number(1)),
+                op.to_string(),
+                Box::new(grammar4::Expression::Number(2)),
+            )
+        };
+        for op in ["<", "<=", "<<", "<<="] {
+            assert_eq!(grammar4::parse(&format!("1 {op} 2")).unwrap(), binary(op));
+        }
+
+        // `<<=` is a single token rather than `<<` followed by `=` or three tokens
+        let mut parser = rust_sitter::tree_sitter::Parser::new();
+        parser.set_language(grammar4::language()).unwrap();
+        let tree = parser.parse("1<<=2", None).unwrap();
+        let binary_node = tree.root_node().child(0).unwrap();
+        assert_eq!(binary_node.kind(), "Expression_Binary");
+        assert_eq!(binary_node.child_count(), 3);
+        assert_eq!(
+            binary_node.child_by_field_name("1").unwrap().byte_range(),
+            1..4
+        );
+
+        assert!(grammar4::parse("1 < < 2").is_err());
+    }
+
+    #[test]
+    fn streaming_events() {
+        use rust_sitter::streaming::{NodeEvent, NodePhase};
+
+        let mut events = vec![];
+        grammar::parse_streaming("1 - 2", |event| events.push(event)).unwrap();
+
+        let event = |kind, span, phase| NodeEvent { kind, span, phase };
+        assert_eq!(
+            events,
+            [
+                event("source_file", (0, 5), NodePhase::Enter),
+                event("Expression_Sub", (0, 5), NodePhase::Enter),
+                event("Expression_Number", (0, 1), NodePhase::Enter),
+                event("Expression_Number", (0, 1), NodePhase::Leave),
+                event("Expression_Number", (4, 5), NodePhase::Enter),
+                event("Expression_Number", (4, 5), NodePhase::Leave),
+                event("Expression_Sub", (0, 5), NodePhase::Leave),
+                event("source_file", (0, 5), NodePhase::Leave),
+            ]
+        );
+
+        assert!(grammar::parse_streaming("1 -", |_| panic!()).is_err());
+    }
+
+    #[test]
+    fn typed_cursor() {
+        use grammar::{Expression, NodeRef};
+        use rust_sitter::Extract;
+
+        let input = "1 - 2 * 3";
+        let mut parser = rust_sitter::tree_sitter::Parser::new();
+        parser.set_language(grammar::language()).unwrap();
+        let tree = parser.parse(input, None).unwrap();
+
+        let mut cursor = grammar::typed_cursor(&tree);
+        assert!(matches!(cursor.typed(), Some(NodeRef::Expression(_))));
+        assert!(cursor.goto_first_child());
+        assert_eq!(cursor.node().kind(), "Expression_Sub");
+
+        // the right operand, whose node is wrapped in the hidden choice of `Expression`
+        assert!(cursor.goto_field("2"));
+        assert!(cursor.typed().is_none());
+        assert!(cursor.goto_first_child());
+        let node = match cursor.typed() {
+            Some(NodeRef::Expression(node)) => node,
+            other => panic!("Expected an expression, got {other:?}"),
+        };
+        assert_eq!(node.kind(), "Expression_Mul");
+        assert_eq!(
+            <Expression as Extract<_>>::extract(
+                Some(node),
+                input.as_bytes(),
+                Default::default(),
+                None
+            ),
+            Expression::Mul(
+                Box::new(Expression::Number(2)),
+                (),
+                Box::new(Expression::Number(3))
+            )
+        );
+
+        // the operator token, after the left operand and whitespace, has no type
+        assert!(!cursor.goto_field("3"));
+        assert!(cursor.goto_field("0"));
+        assert!(cursor.goto_next_sibling());
+        assert!(cursor.goto_next_sibling());
+        assert_eq!(cursor.field_name(), Some("1"));
+        assert!(cursor.typed().is_none());
+
+        assert!(cursor.goto_parent());
+        assert_eq!(cursor.node().kind(), "Expression_Mul");
+        assert!(cursor.goto_parent());
+        assert!(cursor.goto_parent());
+        assert_eq!(cursor.node().kind(), "Expression_Sub");
+    }
+
+    #[test]
+    fn trace_extraction() {
+        use std::cell::RefCell;
+
+        thread_local! {
+            static RECORDS: RefCell<Vec<String>> = const { RefCell::new(vec![]) };
+        }
+
+        // records the messages of the current thread, so that other tests are ignored
+        struct CapturingLogger;
+        impl log::Log for CapturingLogger {
+            fn enabled(&self, metadata: &log::Metadata) -> bool {
+                metadata.target() == rust_sitter::trace::TARGET
+            }
+
+            fn log(&self, record: &log::Record) {
+                if self.enabled(record.metadata()) && record.level() == log::Level::Trace {
+                    RECORDS.with(|r| r.borrow_mut().push(record.args().to_string()));
+                }
+            }
+
+            fn flush(&self) {}
+        }
+
+        log::set_logger(&CapturingLogger).unwrap();
+        log::set_max_level(log::LevelFilter::Trace);
+
+        grammar::parse("1 - 2").unwrap();
+        assert_eq!(
+            RECORDS.with(|r| r.take()),
+            [
+                "enter rule `Expression_Sub` for `Expression::Sub` at 0..5",
+                "populate field `Expression::Sub.0` of rule `Expression_Sub` from 0..1",
+                "enter rule `Expression_Number` for `Expression::Number` at 0..1",
+                "populate field `Expression::Number.0` of rule `Expression_Number` from 0..1",
+                "populate field `Expression::Sub.1` of rule `Expression_Sub` from 2..3",
+                "populate field `Expression::Sub.2` of rule `Expression_Sub` from 4..5",
+                "enter rule `Expression_Number` for `Expression::Number` at 4..5",
+                "populate field `Expression::Number.0` of rule `Expression_Number` from 4..5",
+            ]
+        );
+    }
+
+    #[test]
+    fn named_precedences() {
+        use grammar5::Expression;
+
+        let number = |n| Box::new(Expression::Number(n));
+        assert_eq!(
+            grammar5::parse("-1 - 2").unwrap(),
+            Expression::Sub(Box::new(Expression::Neg((), number(1))), (), number(2))
+        );
+        assert_eq!(
+            grammar5::parse("-3!").unwrap(),
+            Expression::Neg((), Box::new(Expression::Factorial(number(3), ())))
+        );
+    }
+
+    #[test]
+    fn dialects() {
+        use grammar6::Expression;
+        use rust_sitter::errors::ParseErrorReason;
+
+        let number = |n| Box::new(Expression::Number(n));
+        assert_eq!(
+            grammar6::parse_with_dialects("2 ** 3", &["extended"]).unwrap(),
+            Expression::Pow(number(2), (), number(3))
+        );
+        assert!(grammar6::parse_with_dialects("1 - 2", &[]).is_ok());
+        // parsing without choosing dialects accepts every construct
+        assert!(grammar6::parse("2 ** 3").is_ok());
+
+        let errors = grammar6::parse_with_dialects("1 - 2 ** 3", &[]).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0].reason,
+            ParseErrorReason::DisabledDialect { dialect } if dialect == "extended"
+        ));
+        assert_eq!((errors[0].start, errors[0].end), (4, 10));
+        assert_eq!(
+            errors[0].to_string(),
+            "the `extended` dialect is not enabled in `Expression_Pow` at 4..10"
+        );
+    }
+
+    #[test]
+    fn parse_options_combine() {
+        use grammar6::Expression;
+        use rust_sitter::errors::{ParseError, ParseErrorReason};
+        use rust_sitter::pipeline::ParseOptions;
+
+        let options = ParseOptions {
+            dialects: Some(&[]),
+            max_depth: Some(64),
+            timeout_micros: 1_000_000,
+            ..Default::default()
+        };
+        let (ast, errors) = grammar6::parse_with_options("2 ** 3", &options);
+        assert!(ast.is_none());
+        assert!(matches!(
+            &errors[..],
+            [ParseError {
+                reason: ParseErrorReason::DisabledDialect { .. },
+                ..
+            }]
+        ));
+
+        let (ast, errors) = grammar6::parse_with_options("1 - 2", &options);
+        assert!(matches!(ast, Some(Expression::Sub(..))));
+        assert!(errors.is_empty());
+
+        let options = ParseOptions {
+            max_depth: Some(2),
+            ..options
+        };
+        let (ast, errors) = grammar6::parse_with_options("1 - 2", &options);
+        assert!(ast.is_none());
+        assert!(matches!(
+            &errors[..],
+            [ParseError {
+                reason: ParseErrorReason::TooDeep { .. },
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn sexp_snapshots() {
+        let mut parser = rust_sitter::tree_sitter::Parser::new();
+        parser.set_language(grammar::language()).unwrap();
+
+        let input = "1 - 2 * 3";
+        let tree = parser.parse(input, None).unwrap();
+        insta::assert_snapshot!(rust_sitter::to_sexp(&tree, input));
+
+        let input = "1 - $2";
+        let tree = parser.parse(input, None).unwrap();
+        insta::assert_snapshot!(rust_sitter::to_sexp(&tree, input));
+
+        let input = "1 *";
+        let tree = parser.parse(input, None).unwrap();
+        insta::assert_snapshot!(rust_sitter::to_sexp(&tree, input));
+    }
+
+    #[test]
+    fn constructors() {
+        let built = Expression::sub(
+            Expression::number(1),
+            Expression::mul(Expression::number(2), Expression::number(3)),
+        );
+        assert_eq!(built, grammar::parse("1 - 2 * 3").unwrap());
+        assert_eq!(
+            serde_json::to_string(&built).unwrap(),
+            r#"{"Sub":[{"Number":1},null,{"Mul":[{"Number":2},null,{"Number":3}]}]}"#
+        );
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let parsed = grammar::parse("1 - 2 * 3").unwrap();
+        let json = serde_json::to_string(&parsed).unwrap();
+        assert_eq!(
+            json,
+            r#"{"Sub":[{"Number":1},null,{"Mul":[{"Number":2},null,{"Number":3}]}]}"#
+        );
+        assert_eq!(serde_json::from_str::<Expression>(&json).unwrap(), parsed);
+    }
+
+    /// Checks a value against the subset of JSON Schema that the generated schemas use.
+    fn matches_schema(
+        value: &serde_json::Value,
+        schema: &serde_json::Value,
+        root: &serde_json::Value,
+    ) -> bool {
+        use serde_json::Value;
+
+        let schema = match schema["$ref"].as_str() {
+            Some(path) => &root["definitions"][path.trim_start_matches("#/definitions/")],
+            None => schema,
+        };
+        let type_matches = match schema["type"].as_str() {
+            Some("null") => value.is_null(),
+            Some("boolean") => value.is_boolean(),
+            Some("integer") => value.is_i64() || value.is_u64(),
+            Some("number") => value.is_number(),
+            Some("string") => value.is_string(),
+            Some("array") => value.is_array(),
+            Some("object") => value.is_object(),
+            _ => true,
+        };
+        let count = |key: &str| {
+            schema[key]
+                .as_array()
+                .map(|s| s.iter().filter(|s| matches_schema(value, s, root)).count())
+        };
+        let properties_match = match (value, &schema["properties"]) {
+            (Value::Object(fields), Value::Object(properties)) => {
+                fields.iter().all(|(k, v)| match properties.get(k) {
+                    Some(s) => matches_schema(v, s, root),
+                    None => schema["additionalProperties"] != false,
+                }) && schema["required"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .all(|k| fields.contains_key(k.as_str().unwrap()))
+            }
+            _ => true,
+        };
+        let items_match = match (value, &schema["items"]) {
+            (Value::Array(elems), Value::Array(items)) => {
+                elems.len() == items.len()
+                    && elems
+                        .iter()
+                        .zip(items)
+                        .all(|(v, s)| matches_schema(v, s, root))
+            }
+            (Value::Array(elems), Value::Object(_)) => elems
+                .iter()
+                .all(|v| matches_schema(v, &schema["items"], root)),
+            _ => true,
+        };
+
+        type_matches
+            && properties_match
+            && items_match
+            && schema.get("const").is_none_or(|c| c == value)
+            && schema["minimum"]
+                .as_i64()
+                .is_none_or(|m| value.as_i64().unwrap() >= m)
+            && count("anyOf").is_none_or(|n| n > 0)
+            && count("oneOf").is_none_or(|n| n == 1)
+    }
+
+    #[test]
+    fn json_schema() {
+        let schema: serde_json::Value = serde_json::from_str(include_str!(concat!(
+            env!("OUT_DIR"),
+            "/arithmetic.schema.json"
+        )))
+        .unwrap();
+
+        let parsed = grammar::parse("1 - 2 * 3 - 4").unwrap();
+        let json = serde_json::to_value(&parsed).unwrap();
+        assert!(matches_schema(&json, &schema, &schema));
+
+        let missing_operand = serde_json::json!({ "Sub": [{ "Number": 1 }, null] });
+        assert!(!matches_schema(&missing_operand, &schema, &schema));
+        let unknown_variant =
+            serde_json::json!({ "Add": [{ "Number": 1 }, null, { "Number": 2 }] });
+        assert!(!matches_schema(&unknown_variant, &schema, &schema));
+        let not_a_number = serde_json::json!({ "Number": "1" });
+        assert!(!matches_schema(&not_a_number, &schema, &schema));
+    }
+
     #[test]
     fn failed_parses() {
         insta::assert_debug_snapshot!(grammar::parse("1 + 2"));
@@ -106,4 +779,211 @@ mod tests {
         insta::assert_debug_snapshot!(grammar::parse("a1"));
         insta::assert_debug_snapshot!(grammar::parse("1a"));
     }
+
+    #[test]
+    fn strict_parse_stops_at_first_error() {
+        let input = "1 - - 2 - * 3";
+        let all_errors = grammar::parse(input).unwrap_err();
+        assert!(all_errors.len() > 1);
+
+        let strict_errors = grammar::parse_strict(input).unwrap_err();
+        assert_eq!(strict_errors.len(), 1);
+        assert_eq!(strict_errors[0].start, all_errors[0].start);
+        assert_eq!(strict_errors[0].end, all_errors[0].end);
+
+        assert!(grammar::parse_strict("1 - 2 * 3").is_ok());
+    }
+
+    #[test]
+    fn strict_error_message() {
+        let errors = grammar::parse_strict("1 - * 2").unwrap_err();
+        assert_eq!(errors[0].rule.as_deref(), Some("Expression_Sub"));
+        assert_eq!(errors[0].expected, vec!["Expression_Number_0"]);
+        assert_eq!(
+            errors[0].to_string(),
+            "expected one of `Expression_Number_0`, found unexpected input in `Expression_Sub` at 4..5"
+        );
+
+        // an error that Tree Sitter could not place inside any rule spans the whole input
+        let errors = grammar::parse_strict("1 - }").unwrap_err();
+        assert_eq!(errors[0].rule, None);
+        assert_eq!(
+            errors[0].to_string(),
+            "expected one of `Expression_Number_0`, found unexpected input at 0..5"
+        );
+    }
+
+    #[test]
+    fn recovering_parse_keeps_partial_ast() {
+        use rust_sitter::errors::ParseErrorReason;
+
+        // the extra `-` is skipped as an error, and the rest is still a subtraction
+        let (ast, errors) = grammar::parse_recovering("1 - - 2");
+        assert_eq!(
+            ast,
+            Some(Expression::Sub(
+                Box::new(Expression::Number(1)),
+                (),
+                Box::new(Expression::Number(2))
+            ))
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!((errors[0].start, errors[0].end), (4, 5));
+
+        let (ast, errors) = grammar::parse_recovering("1 - 2");
+        assert_eq!(ast, grammar::parse("1 - 2").ok());
+        assert!(errors.is_empty());
+
+        // the missing number cannot be extracted, which is explained by the syntax error
+        let (ast, errors) = grammar::parse_recovering("1 -");
+        assert_eq!(ast, None);
+        assert!(!errors.is_empty());
+        assert!(errors
+            .iter()
+            .all(|e| !matches!(e.reason, ParseErrorReason::ExtractionFailed(_))));
+    }
+
+    #[test]
+    fn try_parse_untrusted() {
+        use rust_sitter::errors::ParseErrorReason;
+
+        assert_eq!(
+            grammar::try_parse(b"1 - 2", 256).unwrap(),
+            grammar::parse("1 - 2").unwrap()
+        );
+
+        let errors = grammar::try_parse(b"1 - \xff2", 256).unwrap_err();
+        assert!(matches!(errors[0].reason, ParseErrorReason::InvalidUtf8));
+        assert_eq!((errors[0].start, errors[0].end), (4, 5));
+
+        // the transform of `Number` panics when the number does not fit in an `i32`, which
+        // is reported at the number
+        let errors = grammar::try_parse(b"1 - 99999999999", 256).unwrap_err();
+        assert!(matches!(
+            &errors[0].reason,
+            ParseErrorReason::ExtractionFailed(_)
+        ));
+        assert_eq!((errors[0].start, errors[0].end), (4, 15));
+
+        let deep = vec!["1"; 200].join(" - ");
+        let errors = grammar::try_parse(deep.as_bytes(), 256).unwrap_err();
+        assert!(matches!(errors[0].reason, ParseErrorReason::TooDeep { .. }));
+        assert!(grammar::try_parse(deep.as_bytes(), 1024).is_ok());
+    }
+
+    #[test]
+    fn utf8_checked_parses() {
+        use rust_sitter::errors::ParseErrorReason;
+
+        assert_eq!(
+            grammar::parse_utf8_checked("1 - 2".as_bytes()).unwrap(),
+            grammar::parse("1 - 2").unwrap()
+        );
+
+        // the error starts at the first invalid byte and spans only the invalid sequence
+        let errors = grammar::parse_utf8_checked(b"12 - 3\xe2\x28\xa1").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0].reason, ParseErrorReason::InvalidUtf8));
+        assert_eq!((errors[0].start, errors[0].end), (6, 7));
+
+        // a sequence cut off by the end of the input spans the rest of the input
+        let errors = grammar::parse_utf8_checked(b"1 - 2\xe2\x82").unwrap_err();
+        assert_eq!((errors[0].start, errors[0].end), (5, 7));
+
+        // valid input that does not parse still reports syntax errors
+        let errors = grammar::parse_utf8_checked("1 - ".as_bytes()).unwrap_err();
+        assert!(!matches!(errors[0].reason, ParseErrorReason::InvalidUtf8));
+    }
+
+    #[test]
+    fn try_parse_random_bytes() {
+        // a small xorshift generator, biased towards bytes of the grammar so that some of
+        // the inputs parse
+        let mut state = 0x2545f4914f6cdd1du64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        for _ in 0..2000 {
+            let len = (next() % 32) as usize;
+            let bytes = (0..len)
+                .map(|_| match next() % 4 {
+                    0 => b"0123456789"[(next() % 10) as usize],
+                    1 => b"-* "[(next() % 3) as usize],
+                    _ => next() as u8,
+                })
+                .collect::<Vec<u8>>();
+
+            let _ = grammar::try_parse(&bytes, 256);
+        }
+    }
+
+    #[test]
+    fn arena_parses() {
+        use grammar3::Expression as ArenaExpression;
+
+        let arena = rust_sitter::arena::Bump::new();
+        let parsed = grammar3::parse_in("1 - 2 * 3", &arena).unwrap();
+        assert_eq!(
+            parsed,
+            ArenaExpression::Sub(
+                &ArenaExpression::Number(1),
+                (),
+                &ArenaExpression::Mul(&ArenaExpression::Number(2), (), &ArenaExpression::Number(3)),
+            )
+        );
+        assert!(grammar3::parse_in("1 -", &arena).is_err());
+    }
+
+    /// Counts the allocations made on the current thread, so that tests running in parallel
+    /// do not affect each other's counts.
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    }
+
+    unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+            ALLOCATIONS.with(|a| a.set(a.get() + 1));
+            std::alloc::System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+            std::alloc::System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    fn count_allocations<R>(f: impl FnOnce() -> R) -> (R, usize) {
+        let before = ALLOCATIONS.with(|a| a.get());
+        let out = f();
+        (out, ALLOCATIONS.with(|a| a.get()) - before)
+    }
+
+    #[test]
+    fn arena_saves_allocations() {
+        // a left-nested subtraction with a box for each operand
+        let input = vec!["1"; 200].join(" - ");
+
+        let (boxed, boxed_allocations) = count_allocations(|| grammar::parse(&input).unwrap());
+        let arena = rust_sitter::arena::Bump::new();
+        let (in_arena, arena_allocations) =
+            count_allocations(|| grammar3::parse_in(&input, &arena).unwrap());
+
+        assert!(matches!(boxed, Expression::Sub(..)));
+        assert!(matches!(in_arena, grammar3::Expression::Sub(..)));
+
+        // parsing itself allocates the same in both, while every one of the 398 boxes is an
+        // allocation that the arena replaces with a handful of chunks
+        assert!(
+            boxed_allocations - arena_allocations > 350,
+            "{boxed_allocations} allocations with boxes, {arena_allocations} in an arena"
+        );
+    }
 }
@@ -0,0 +1,305 @@
+#[rust_sitter::grammar("assignments")]
+#[rust_sitter::use_rules(crate::identifiers::grammar::*)]
+pub mod grammar {
+    use crate::identifiers::grammar::{Atom, Identifier};
+
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Assignment {
+        pub target: Identifier,
+        #[rust_sitter::leaf(text = "=")]
+        _eq: (),
+        pub value: Atom,
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[rust_sitter::grammar("assignments_comparisons")]
+pub mod grammar2 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum Statement {
+        Comparison(
+            #[rust_sitter::leaf(pattern = r"[a-z]+", transform = String::from)] String,
+            // the assignment operator also matches `==`, so the comparison needs a hint
+            #[rust_sitter::leaf(pattern = r"==", token_prec = 1)] (),
+            #[rust_sitter::leaf(pattern = r"[a-z]+", transform = String::from)] String,
+        ),
+        Assignment(
+            #[rust_sitter::leaf(pattern = r"[a-z]+", transform = String::from)] String,
+            #[rust_sitter::leaf(pattern = r"[:=]?=")] (),
+            #[rust_sitter::leaf(pattern = r"[a-z]+", transform = String::from)] String,
+        ),
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[rust_sitter::grammar("assignments_blocks")]
+pub mod grammar3 {
+    #[rust_sitter::language]
+    #[rust_sitter::scope]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Block {
+        #[rust_sitter::leaf(text = "{")]
+        _open: (),
+        pub statements: Vec<Statement>,
+        #[rust_sitter::leaf(text = "}")]
+        _close: (),
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    pub enum Statement {
+        #[rust_sitter::definition(field = "name")]
+        Let {
+            #[rust_sitter::highlight("keyword")]
+            #[rust_sitter::leaf(text = "let")]
+            _let: (),
+            name: Variable,
+            #[rust_sitter::highlight("operator")]
+            #[rust_sitter::leaf(text = "=")]
+            _eq: (),
+            value: Variable,
+        },
+        Block(Block),
+    }
+
+    #[rust_sitter::reference]
+    #[rust_sitter::highlight("variable")]
+    #[derive(PartialEq, Eq, Debug)]
+    pub struct Variable {
+        #[rust_sitter::leaf(pattern = r"[a-z]+", transform = String::from)]
+        pub name: String,
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[rust_sitter::grammar("assignments_typed")]
+pub mod grammar4 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Declaration {
+        #[rust_sitter::leaf(pattern = r"[a-z]+", transform = String::from)]
+        pub name: String,
+        #[rust_sitter::leaf(text = ":")]
+        _colon: (),
+        #[rust_sitter::field("type")]
+        #[rust_sitter::leaf(pattern = r"[a-z]+", transform = String::from)]
+        pub ty: String,
+        #[rust_sitter::leaf(text = "=")]
+        _eq: (),
+        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+        pub value: u32,
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identifiers::grammar::{Atom, Identifier};
+
+    #[test]
+    fn imported_rules() {
+        let assignment = grammar::parse("x = y").unwrap();
+        assert_eq!(
+            assignment.target,
+            Identifier {
+                name: "x".to_string()
+            }
+        );
+        assert_eq!(
+            assignment.value,
+            Atom::Identifier(Identifier {
+                name: "y".to_string()
+            })
+        );
+        assert_eq!(grammar::parse("count = 3").unwrap().value, Atom::Number(3));
+        assert!(grammar::parse("x = ").is_err());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(
+        expected = "The node for `Assignment` has 3 fields (0, 1, 2) but its type has 3 (target, _eq, value)"
+    )]
+    fn mismatched_fields() {
+        use rust_sitter::Extract;
+
+        // a node from another grammar stands in for one from an out of date parser
+        let mut parser = rust_sitter::tree_sitter::Parser::new();
+        parser
+            .set_language(crate::arithmetic::grammar::language())
+            .unwrap();
+        let tree = parser.parse("1 - 2", None).unwrap();
+        let node = tree.root_node().child(0).unwrap();
+
+        <grammar::Assignment as Extract<_>>::extract(
+            Some(node),
+            "1 - 2".as_bytes(),
+            Default::default(),
+            None,
+        );
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(
+        expected = "The node for `Declaration` has 1 fields (name) but its type has 5 (name, _colon, type, _eq, value)"
+    )]
+    fn missing_fields() {
+        use rust_sitter::Extract;
+
+        // a node with only some of the fields stands in for one from a parser that was built
+        // before the other fields were added
+        let mut parser = rust_sitter::tree_sitter::Parser::new();
+        parser
+            .set_language(crate::identifiers::grammar::language())
+            .unwrap();
+        let tree = parser.parse("x", None).unwrap();
+        let mut node = tree.root_node();
+        while node.kind() != "Identifier" {
+            node = node.child(0).unwrap();
+        }
+
+        <grammar4::Declaration as Extract<_>>::extract(
+            Some(node),
+            "x".as_bytes(),
+            Default::default(),
+            None,
+        );
+    }
+
+    #[test]
+    fn token_precedence() {
+        assert_eq!(
+            grammar2::parse("a == b").unwrap(),
+            grammar2::Statement::Comparison("a".to_string(), (), "b".to_string())
+        );
+        assert_eq!(
+            grammar2::parse("a = b").unwrap(),
+            grammar2::Statement::Assignment("a".to_string(), (), "b".to_string())
+        );
+        assert_eq!(
+            grammar2::parse("a := b").unwrap(),
+            grammar2::Statement::Assignment("a".to_string(), (), "b".to_string())
+        );
+    }
+
+    #[test]
+    fn locals_query() {
+        use rust_sitter::tree_sitter::{Parser, Query, QueryCursor};
+
+        let locals = include_str!(concat!(env!("OUT_DIR"), "/assignments_blocks.locals.scm"));
+        let query = Query::new(grammar3::language(), locals).unwrap();
+
+        let input = "{ let x = y { let z = x } }";
+        let mut parser = Parser::new();
+        parser.set_language(grammar3::language()).unwrap();
+        let tree = parser.parse(input, None).unwrap();
+
+        let mut captures = QueryCursor::new()
+            .captures(&query, tree.root_node(), input.as_bytes())
+            .map(|(m, i)| {
+                let capture = m.captures[i];
+                (
+                    query.capture_names()[capture.index as usize].as_str(),
+                    &input[capture.node.byte_range()],
+                )
+            })
+            .collect::<Vec<_>>();
+        captures.sort();
+        captures.dedup();
+
+        assert_eq!(
+            captures,
+            vec![
+                ("local.definition", "x"),
+                ("local.definition", "z"),
+                ("local.reference", "x"),
+                ("local.reference", "y"),
+                ("local.reference", "z"),
+                ("local.scope", "{ let x = y { let z = x } }"),
+                ("local.scope", "{ let z = x }"),
+            ]
+        );
+    }
+
+    #[test]
+    fn highlights_query() {
+        use rust_sitter::tree_sitter::{Parser, Query, QueryCursor};
+
+        let highlights = include_str!(concat!(
+            env!("OUT_DIR"),
+            "/assignments_blocks.highlights.scm"
+        ));
+        let query = Query::new(grammar3::language(), highlights).unwrap();
+
+        let input = "{ let x = y }";
+        let mut parser = Parser::new();
+        parser.set_language(grammar3::language()).unwrap();
+        let tree = parser.parse(input, None).unwrap();
+
+        let captures = QueryCursor::new()
+            .captures(&query, tree.root_node(), input.as_bytes())
+            .map(|(m, i)| {
+                let capture = m.captures[i];
+                (
+                    query.capture_names()[capture.index as usize].as_str(),
+                    &input[capture.node.byte_range()],
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            captures,
+            vec![
+                ("keyword", "let"),
+                ("variable", "x"),
+                ("operator", "="),
+                ("variable", "y"),
+            ]
+        );
+    }
+
+    #[test]
+    fn renamed_fields() {
+        use rust_sitter::tree_sitter::Parser;
+
+        let declaration = grammar4::parse("x: int = 3").unwrap();
+        assert_eq!(declaration.ty, "int");
+        assert_eq!(declaration.value, 3);
+
+        let input = "x: int = 3";
+        let mut parser = Parser::new();
+        parser.set_language(grammar4::language()).unwrap();
+        let tree = parser.parse(input, None).unwrap();
+        let root = tree.root_node();
+        let ty = root.child_by_field_name("type").unwrap();
+        assert_eq!(&input[ty.byte_range()], "int");
+        assert!(root.child_by_field_name("ty").is_none());
+    }
+}
@@ -0,0 +1,68 @@
+#[rust_sitter::grammar("configs")]
+pub mod grammar {
+    #[rust_sitter::language]
+    #[rust_sitter::unordered]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Config {
+        #[rust_sitter::syntax(before = "name")]
+        pub name: Value,
+        #[rust_sitter::syntax(before = "version")]
+        pub version: Value,
+        #[rust_sitter::syntax(before = "edition")]
+        pub edition: Value,
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Value {
+        #[rust_sitter::leaf(text = "=")]
+        _eq: (),
+        #[rust_sitter::leaf(pattern = r#""[^"]*""#, transform = |v| v[1..v.len() - 1].to_string())]
+        pub text: String,
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_sitter::errors::ParseErrorReason;
+
+    #[test]
+    fn unordered_fields() {
+        let config =
+            grammar::parse("name = \"demo\"\nversion = \"1.0\"\nedition = \"2021\"").unwrap();
+        assert_eq!(config.name.text, "demo");
+        assert_eq!(config.version.text, "1.0");
+        assert_eq!(config.edition.text, "2021");
+
+        let reordered =
+            grammar::parse("edition = \"2021\"\nname = \"demo\"\nversion = \"1.0\"").unwrap();
+        assert_eq!(reordered, config);
+    }
+
+    #[test]
+    fn missing_and_repeated_fields() {
+        let errors = grammar::parse("name = \"demo\" version = \"1.0\"").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0].reason,
+            ParseErrorReason::MissingField { field } if field == "edition"
+        ));
+
+        let input = "name = \"a\" version = \"1.0\" name = \"b\" edition = \"2021\"";
+        let errors = grammar::parse(input).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0].reason,
+            ParseErrorReason::DuplicateField { field } if field == "name"
+        ));
+        assert_eq!((errors[0].start, errors[0].end), (32, 37));
+    }
+}
@@ -0,0 +1,96 @@
+#[rust_sitter::grammar("externals")]
+#[rust_sitter::external(Never)]
+pub mod grammar {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Pair {
+        pub left: Number,
+        #[rust_sitter::leaf(text = ",")]
+        _comma: (),
+        pub right: Number,
+    }
+
+    #[rust_sitter::entry]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Number {
+        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+        pub v: i32,
+    }
+}
+
+#[rust_sitter::grammar("externals_indents")]
+#[rust_sitter::external(INDENT, DEDENT, NEWLINE)]
+#[rust_sitter::extras(r"[ \t]")]
+pub mod grammar2 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Program {
+        pub statements: Vec<Statement>,
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum Statement {
+        Line(
+            #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())] String,
+            #[rust_sitter::external_token(NEWLINE)] (),
+        ),
+        Block(Block),
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Block {
+        #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+        pub name: String,
+        #[rust_sitter::leaf(text = ":")]
+        _colon: (),
+        #[rust_sitter::external_token(NEWLINE)]
+        _newline: (),
+        #[rust_sitter::external_token(INDENT)]
+        _indent: (),
+        #[rust_sitter::repeat(non_empty = true)]
+        pub body: Vec<Statement>,
+        #[rust_sitter::external_token(DEDENT)]
+        _dedent: (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn external_scanner() {
+        let pair = grammar::parse("1,2").unwrap();
+        assert_eq!((pair.left.v, pair.right.v), (1, 2));
+        assert_eq!(grammar::parse_as::<grammar::Number>("3").unwrap().v, 3);
+    }
+
+    #[test]
+    fn external_token_references() {
+        use grammar2::Statement;
+
+        fn outline(statements: &[Statement]) -> String {
+            statements
+                .iter()
+                .map(|statement| match statement {
+                    Statement::Line(name, _) => name.clone(),
+                    Statement::Block(block) => {
+                        format!("{}: [{}]", block.name, outline(&block.body))
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        }
+
+        let program = grammar2::parse("a\nb:\n  c\n  d:\n    e\nf\n").unwrap();
+        assert_eq!(outline(&program.statements), "a, b: [c, d: [e]], f");
+
+        // the body of a block has to be indented
+        assert!(grammar2::parse("b:\nc\n").is_err());
+    }
+}
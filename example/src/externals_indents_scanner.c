@@ -0,0 +1,80 @@
+#include <stdlib.h>
+#include <tree_sitter/parser.h>
+
+enum TokenType {
+  INDENT,
+  DEDENT,
+  NEWLINE,
+};
+
+// the columns of the enclosing indented blocks, innermost last
+typedef struct {
+  unsigned char len;
+  unsigned char columns[255];
+} Indents;
+
+void *tree_sitter_externals_indents_external_scanner_create(void) {
+  return calloc(1, sizeof(Indents));
+}
+
+void tree_sitter_externals_indents_external_scanner_destroy(void *payload) {
+  free(payload);
+}
+
+unsigned tree_sitter_externals_indents_external_scanner_serialize(void *payload, char *buffer) {
+  Indents *state = payload;
+  buffer[0] = (char)state->len;
+  for (unsigned i = 0; i < state->len; i++) {
+    buffer[i + 1] = (char)state->columns[i];
+  }
+  return state->len + 1;
+}
+
+void tree_sitter_externals_indents_external_scanner_deserialize(void *payload, const char *buffer, unsigned length) {
+  Indents *state = payload;
+  state->len = length > 0 ? (unsigned char)buffer[0] : 0;
+  for (unsigned i = 0; i < state->len; i++) {
+    state->columns[i] = (unsigned char)buffer[i + 1];
+  }
+}
+
+bool tree_sitter_externals_indents_external_scanner_scan(void *payload, TSLexer *lexer, const bool *valid_symbols) {
+  Indents *state = payload;
+
+  // every token is valid while recovering from an error, where the scanner stays out of the way
+  if (valid_symbols[INDENT] && valid_symbols[DEDENT] && valid_symbols[NEWLINE]) {
+    return false;
+  }
+
+  while (lexer->lookahead == ' ' || lexer->lookahead == '\t') {
+    lexer->advance(lexer, true);
+  }
+
+  if (lexer->lookahead == '\n') {
+    if (valid_symbols[NEWLINE]) {
+      lexer->advance(lexer, false);
+      lexer->mark_end(lexer);
+      lexer->result_symbol = NEWLINE;
+      return true;
+    }
+    return false;
+  }
+
+  unsigned column = lexer->eof(lexer) ? 0 : lexer->get_column(lexer);
+  unsigned current = state->len > 0 ? state->columns[state->len - 1] : 0;
+  lexer->mark_end(lexer);
+
+  if (valid_symbols[INDENT] && column > current && state->len < 255) {
+    state->columns[state->len++] = (unsigned char)column;
+    lexer->result_symbol = INDENT;
+    return true;
+  }
+
+  if (valid_symbols[DEDENT] && column < current) {
+    state->len--;
+    lexer->result_symbol = DEDENT;
+    return true;
+  }
+
+  return false;
+}
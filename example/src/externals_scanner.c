@@ -0,0 +1,22 @@
+#include <tree_sitter/parser.h>
+
+enum TokenType {
+  NEVER,
+};
+
+void *tree_sitter_externals_external_scanner_create(void) {
+  return NULL;
+}
+
+void tree_sitter_externals_external_scanner_destroy(void *payload) {}
+
+unsigned tree_sitter_externals_external_scanner_serialize(void *payload, char *buffer) {
+  return 0;
+}
+
+void tree_sitter_externals_external_scanner_deserialize(void *payload, const char *buffer, unsigned length) {}
+
+bool tree_sitter_externals_external_scanner_scan(void *payload, TSLexer *lexer, const bool *valid_symbols) {
+  // the `Never` token is never recognized
+  return false;
+}
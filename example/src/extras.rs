@@ -0,0 +1,380 @@
+#[rust_sitter::grammar("extras_default")]
+pub mod grammar {
+    #[rust_sitter::language]
+    #[derive(Debug, PartialEq, Eq)]
+    #[allow(dead_code)]
+    pub struct Pair {
+        #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+        pub first: String,
+        #[rust_sitter::leaf(text = ",")]
+        _comma: (),
+        #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+        pub second: String,
+    }
+}
+
+#[rust_sitter::grammar("extras_empty")]
+#[rust_sitter::extras()]
+pub mod grammar_empty {
+    #[rust_sitter::language]
+    #[derive(Debug, PartialEq, Eq)]
+    #[allow(dead_code)]
+    pub struct Pair {
+        #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+        pub first: String,
+        #[rust_sitter::leaf(text = ",")]
+        _comma: (),
+        #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+        pub second: String,
+    }
+}
+
+#[rust_sitter::grammar("extras_no_extras")]
+pub mod grammar_no_extras {
+    use rust_sitter::Spanned;
+
+    #[rust_sitter::language]
+    #[derive(Debug)]
+    #[allow(dead_code)]
+    pub struct Pair {
+        pub first: Spanned<Quoted>,
+        #[rust_sitter::leaf(text = ",")]
+        _comma: (),
+        pub second: Spanned<Quoted>,
+    }
+
+    #[rust_sitter::no_extras]
+    #[derive(Debug)]
+    pub struct Quoted {
+        #[rust_sitter::leaf(text = "\"")]
+        _open: (),
+        #[rust_sitter::leaf(pattern = r#"[^"]*"#)]
+        _contents: (),
+        #[rust_sitter::leaf(text = "\"")]
+        _close: (),
+    }
+}
+
+#[rust_sitter::grammar("extras_immediate_seq")]
+pub mod grammar_immediate_seq {
+    #[rust_sitter::language]
+    #[rust_sitter::immediate_seq]
+    #[derive(Debug, PartialEq, Eq)]
+    #[allow(dead_code)]
+    pub struct Float {
+        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.to_string())]
+        pub integer: String,
+        #[rust_sitter::leaf(text = ".")]
+        _dot: (),
+        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.to_string())]
+        pub fraction: String,
+        #[rust_sitter::leaf(pattern = r"[eE][+-]?\d+", transform = |v| v.to_string())]
+        pub exponent: Option<String>,
+    }
+}
+
+#[rust_sitter::grammar("extras_shebang")]
+#[rust_sitter::allow_shebang]
+pub mod grammar_shebang {
+    #[rust_sitter::language]
+    #[derive(Debug, PartialEq, Eq)]
+    #[allow(dead_code)]
+    pub struct Words {
+        #[rust_sitter::repeat(non_empty = true)]
+        #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+        pub words: Vec<String>,
+    }
+}
+
+#[rust_sitter::grammar("extras_trailing_newline")]
+#[rust_sitter::require_trailing_newline]
+pub mod grammar_trailing_newline {
+    #[rust_sitter::language]
+    #[derive(Debug, PartialEq, Eq)]
+    #[allow(dead_code)]
+    pub struct Words {
+        #[rust_sitter::repeat(non_empty = true)]
+        #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+        pub words: Vec<String>,
+    }
+}
+
+#[rust_sitter::grammar("extras_structured")]
+pub mod grammar_structured {
+    #[rust_sitter::language]
+    #[derive(Debug, PartialEq, Eq)]
+    #[allow(dead_code)]
+    pub struct Pair {
+        #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+        pub first: String,
+        #[rust_sitter::leaf(text = ",")]
+        _comma: (),
+        #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+        pub second: String,
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+
+    // extras must have unambiguous endings, so the contents run up to and including the
+    // end of the line rather than being optional
+    #[rust_sitter::extra]
+    struct Comment {
+        #[rust_sitter::leaf(text = "//")]
+        _prefix: (),
+        #[rust_sitter::leaf(pattern = r"[^\n]*\n")]
+        _contents: (),
+    }
+}
+
+#[rust_sitter::grammar("extras_scoped")]
+pub mod grammar_scoped {
+    use rust_sitter::Spanned;
+
+    #[rust_sitter::language]
+    #[derive(Debug)]
+    #[allow(dead_code)]
+    pub struct Pair {
+        pub first: Spanned<Qualified>,
+        #[rust_sitter::leaf(text = ",")]
+        _comma: (),
+        pub second: Spanned<Qualified>,
+    }
+
+    #[rust_sitter::scoped_extras(none)]
+    #[derive(Debug)]
+    pub struct Qualified {
+        #[rust_sitter::leaf(pattern = r"[a-z]+")]
+        _module: (),
+        #[rust_sitter::leaf(text = "::")]
+        _separator: (),
+        #[rust_sitter::leaf(pattern = r"[a-z]+")]
+        _name: (),
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+
+    #[rust_sitter::extra]
+    struct Comment {
+        #[rust_sitter::leaf(text = "//")]
+        _prefix: (),
+        #[rust_sitter::leaf(pattern = r"[^\n]*\n")]
+        _contents: (),
+    }
+}
+
+#[rust_sitter::grammar("extras_newlines")]
+#[rust_sitter::extras(r"[ \t]")]
+pub mod grammar_newlines {
+    #[rust_sitter::language]
+    #[allow(dead_code)]
+    #[derive(Debug)]
+    pub struct Program {
+        pub statements: Vec<Statement>,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Debug)]
+    pub struct Statement {
+        pub expr: Expr,
+        #[rust_sitter::newline]
+        _end: (),
+    }
+
+    #[allow(dead_code)]
+    #[derive(PartialEq, Eq, Debug)]
+    pub enum Expr {
+        Number(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] i32),
+        #[rust_sitter::prec_left(1)]
+        Add(Box<Expr>, #[rust_sitter::leaf(text = "+")] (), Box<Expr>),
+        Paren(#[rust_sitter::bracketed(open = "(", close = ")")] Box<Expr>),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    #[test]
+    fn default_extras_skip_whitespace() {
+        let pair = grammar::parse("a , b").unwrap();
+        assert_eq!(pair.first, "a");
+        assert_eq!(pair.second, "b");
+    }
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    #[test]
+    fn empty_extras_make_whitespace_significant() {
+        let pair = grammar_empty::parse("a,b").unwrap();
+        assert_eq!(pair.first, "a");
+        assert_eq!(pair.second, "b");
+
+        assert!(grammar_empty::parse("a , b").is_err());
+        assert!(grammar_empty::parse(" a,b").is_err());
+    }
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    #[test]
+    fn no_extras_keep_whitespace() {
+        let input = r#" " a  b " , "c" "#;
+        let pair = grammar_no_extras::parse(input).unwrap();
+        assert_eq!(&input[pair.first.span.0..pair.first.span.1], r#"" a  b ""#);
+        assert_eq!(&input[pair.second.span.0..pair.second.span.1], r#""c""#);
+    }
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    #[test]
+    fn scoped_extras_reject_inner_comments() {
+        let input = "a::b // first\n, c::d";
+        let pair = grammar_scoped::parse(input).unwrap();
+        assert_eq!(&input[pair.first.span.0..pair.first.span.1], "a::b");
+        assert_eq!(&input[pair.second.span.0..pair.second.span.1], "c::d");
+
+        // the comments and whitespace allowed around the names are not skipped inside them
+        assert!(grammar_scoped::parse("a// inner\n::b, c::d").is_err());
+        assert!(grammar_scoped::parse("a::b, c ::d").is_err());
+    }
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    #[test]
+    fn immediate_seq_is_contiguous() {
+        let float = grammar_immediate_seq::parse(" 3.14e-2").unwrap();
+        assert_eq!(float.integer, "3");
+        assert_eq!(float.fraction, "14");
+        assert_eq!(float.exponent.as_deref(), Some("e-2"));
+        assert_eq!(grammar_immediate_seq::parse("3.14").unwrap().exponent, None);
+
+        assert!(grammar_immediate_seq::parse("3 . 14").is_err());
+        assert!(grammar_immediate_seq::parse("3. 14").is_err());
+        assert!(grammar_immediate_seq::parse("3.14 e-2").is_err());
+    }
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    #[test]
+    fn optional_shebang_prefix() {
+        let words = vec!["run".to_string(), "this".to_string()];
+        assert_eq!(grammar_shebang::parse("run this").unwrap().words, words);
+        assert_eq!(
+            grammar_shebang::parse("#!/usr/bin/env words\nrun this")
+                .unwrap()
+                .words,
+            words
+        );
+        assert_eq!(
+            grammar_shebang::parse("\u{feff}#!/bin/words -x\nrun this")
+                .unwrap()
+                .words,
+            words
+        );
+        assert_eq!(
+            grammar_shebang::parse("\u{feff}run this").unwrap().words,
+            words
+        );
+
+        // only the first line can be a shebang
+        assert!(grammar_shebang::parse("run\n#!/bin/words\nthis").is_err());
+        assert!(grammar_shebang::parse("#!/bin/a\n#!/bin/b\nrun").is_err());
+    }
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    #[test]
+    fn structured_comment_extra() {
+        let input = "a // first\n,//\n b // last\n";
+        let pair = grammar_structured::parse(input).unwrap();
+        assert_eq!(pair.first, "a");
+        assert_eq!(pair.second, "b");
+
+        let mut parser = rust_sitter::tree_sitter::Parser::new();
+        parser.set_language(grammar_structured::language()).unwrap();
+        let tree = parser.parse(input, None).unwrap();
+        let mut cursor = tree.walk();
+        let comments = tree
+            .root_node()
+            .children(&mut cursor)
+            .filter(|n| n.kind() == "Comment")
+            .count();
+        assert_eq!(comments, 3);
+    }
+
+    #[test]
+    fn lossless_round_trip() {
+        let input = "\n  // leading\n  abc  ,// odd\n\t\t xyz // last\n\n  ";
+        let mut tree = grammar_structured::parse_lossless(input).unwrap();
+        assert_eq!(tree.to_source(), input);
+
+        // editing a node leaves every other byte as it was
+        let comments = tree.children.iter().filter(|c| c.kind == "Comment");
+        assert!(comments.clone().all(|c| c.is_extra));
+        assert_eq!(comments.count(), 3);
+        tree.child_by_field_mut("second")
+            .unwrap()
+            .set_text("renamed");
+        assert_eq!(
+            tree.to_source(),
+            "\n  // leading\n  abc  ,// odd\n\t\t renamed // last\n\n  "
+        );
+        assert_eq!(
+            grammar_structured::parse(&tree.to_source()).unwrap().second,
+            "renamed"
+        );
+
+        // the root of the Tree Sitter tree does not cover the whitespace around it
+        let input = "  a ,\n\n   b \t";
+        assert_eq!(grammar::parse_lossless(input).unwrap().to_source(), input);
+        assert!(grammar::parse_lossless("a b").is_err());
+    }
+
+    #[test]
+    fn newlines_end_statements_outside_brackets() {
+        use grammar_newlines::Expr;
+
+        let exprs = |input| {
+            grammar_newlines::parse(input).map(|program| {
+                program
+                    .statements
+                    .into_iter()
+                    .map(|statement| statement.expr)
+                    .collect::<Vec<_>>()
+            })
+        };
+        let add = |a, b| Expr::Add(Box::new(a), (), Box::new(b));
+
+        assert_eq!(
+            exprs("1 + 2\n\n3\n").unwrap(),
+            vec![add(Expr::Number(1), Expr::Number(2)), Expr::Number(3)]
+        );
+
+        // the newline inside of the brackets is skipped, since no statement can end there
+        assert_eq!(
+            exprs("(1 +\n  2)\n").unwrap(),
+            vec![Expr::Paren(Box::new(add(Expr::Number(1), Expr::Number(2))))]
+        );
+
+        // a newline where a statement can end does end it
+        assert!(exprs("1\n+ 2\n").is_err());
+        assert!(exprs("1 2\n").is_err());
+    }
+    #[test]
+    fn required_trailing_newline() {
+        let words = |input| grammar_trailing_newline::parse(input).map(|w| w.words);
+        assert_eq!(
+            words("run this\n").unwrap(),
+            vec!["run".to_string(), "this".to_string()]
+        );
+        assert_eq!(words("run this  \n").unwrap().len(), 2);
+
+        assert!(words("run this").is_err());
+        assert!(words("run this  ").is_err());
+        // newlines are significant, so the input ends at the first one
+        assert!(words("run\nthis\n").is_err());
+        assert!(words("run this\n\n").is_err());
+    }
+}
@@ -0,0 +1,273 @@
+#[rust_sitter::grammar("identifiers")]
+pub mod grammar {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum Atom {
+        Identifier(Identifier),
+        Number(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] i32),
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    pub struct Identifier {
+        #[rust_sitter::leaf(pattern = r"[a-z_]+", transform = String::from)]
+        pub name: String,
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[rust_sitter::grammar("identifiers_aliased")]
+pub mod grammar2 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Let {
+        #[rust_sitter::leaf(text = "let")]
+        _let: (),
+        #[rust_sitter::alias_as("variable", named = true)]
+        pub name: Identifier,
+        #[rust_sitter::leaf(text = ":")]
+        _colon: (),
+        #[rust_sitter::alias_as("type_identifier", named = true)]
+        pub ty: Identifier,
+        #[rust_sitter::leaf(text = "=")]
+        _eq: (),
+        pub value: Identifier,
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Identifier {
+        #[rust_sitter::leaf(pattern = r"[a-z_]+", transform = String::from)]
+        pub name: String,
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+/// The same rules as `grammar`, with identifiers that may also contain capitals, built as a
+/// separate parser to stand in for it.
+#[rust_sitter::grammar("identifiers_capitalized")]
+#[rust_sitter::untyped]
+pub mod grammar3 {
+    #[rust_sitter::language]
+    pub enum Atom {
+        Identifier(Identifier),
+        Number(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] i32),
+    }
+
+    pub struct Identifier {
+        #[rust_sitter::leaf(pattern = r"[a-zA-Z_]+", transform = String::from)]
+        pub name: String,
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[rust_sitter::grammar("identifiers_named_tokens")]
+pub mod grammar4 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Mapping {
+        pub from: Identifier,
+        #[rust_sitter::leaf(text = "->", name = "arrow")]
+        _arrow: (),
+        pub to: Identifier,
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    pub struct Identifier {
+        #[rust_sitter::leaf(pattern = r"[a-z_]+", transform = String::from)]
+        pub name: String,
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[rust_sitter::grammar("identifiers_tokens")]
+pub mod grammar5 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum Statement {
+        Call {
+            callee: Identifier,
+            #[rust_sitter::leaf(text = "(")]
+            _open: (),
+            #[rust_sitter::delimited(
+                #[rust_sitter::leaf(text = ",")]
+                ()
+            )]
+            args: Vec<Identifier>,
+            #[rust_sitter::leaf(text = ")")]
+            _close: (),
+        },
+        Assign {
+            target: Identifier,
+            #[rust_sitter::leaf(text = "=")]
+            _eq: (),
+            value: Identifier,
+        },
+    }
+
+    #[rust_sitter::token]
+    #[derive(PartialEq, Eq, Debug)]
+    pub struct Identifier {
+        #[rust_sitter::leaf(pattern = r"[a-z_]+", transform = String::from)]
+        pub name: String,
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grammar::{Identifier, NodeRef};
+    use rust_sitter::Extract;
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    #[test]
+    fn node_at_offset() {
+        let input = "counter";
+        let mut parser = rust_sitter::tree_sitter::Parser::new();
+        parser.set_language(grammar::language()).unwrap();
+        let tree = parser.parse(input, None).unwrap();
+
+        // the deepest node is the leaf of the name, which belongs to the identifier
+        let node = match grammar::node_at(&tree, 3) {
+            Some(NodeRef::Identifier(node)) => node,
+            other => panic!("Expected an identifier, got {other:?}"),
+        };
+        assert_eq!((node.start_byte(), node.end_byte()), (0, 7));
+        assert_eq!(
+            <Identifier as Extract<_>>::extract(
+                Some(node),
+                input.as_bytes(),
+                Default::default(),
+                None
+            ),
+            Identifier {
+                name: "counter".to_string()
+            }
+        );
+
+        let tree = parser.parse("42", None).unwrap();
+        assert!(matches!(grammar::node_at(&tree, 1), Some(NodeRef::Atom(_))));
+        assert!(grammar::node_at(&tree, 3).is_none());
+    }
+
+    #[test]
+    fn aliased_occurrences() {
+        let input = "let x: int = y";
+        let statement = grammar2::parse(input).unwrap();
+        assert_eq!(statement.name.name, "x");
+        assert_eq!(statement.ty.name, "int");
+        assert_eq!(statement.value.name, "y");
+
+        // the same rule appears under a different kind at each aliased field
+        let mut parser = rust_sitter::tree_sitter::Parser::new();
+        parser.set_language(grammar2::language()).unwrap();
+        let tree = parser.parse(input, None).unwrap();
+        let root = tree.root_node();
+        let kind_of = |field| root.child_by_field_name(field).unwrap().kind();
+        assert_eq!(kind_of("name"), "variable");
+        assert_eq!(kind_of("ty"), "type_identifier");
+        assert_eq!(kind_of("value"), "Identifier");
+    }
+
+    #[test]
+    fn language_override() {
+        use grammar::Atom;
+
+        assert!(grammar::parse("Counter").is_err());
+        assert_eq!(
+            grammar::parse_with_language("Counter", grammar3::language()).unwrap(),
+            Atom::Identifier(Identifier {
+                name: "Counter".to_string()
+            })
+        );
+        assert_eq!(
+            grammar::parse_with_language("42", grammar3::language()).unwrap(),
+            Atom::Number(42)
+        );
+    }
+
+    #[test]
+    fn named_text_leaf() {
+        use rust_sitter::tree_sitter::{Parser, Query, QueryCursor};
+
+        let input = "a -> b";
+        let mapping = grammar4::parse(input).unwrap();
+        assert_eq!(
+            (mapping.from.name.as_str(), mapping.to.name.as_str()),
+            ("a", "b")
+        );
+
+        // the text leaf is a named node of its own kind, rather than its literal text
+        let query = Query::new(grammar4::language(), "(arrow) @operator").unwrap();
+        let mut parser = Parser::new();
+        parser.set_language(grammar4::language()).unwrap();
+        let tree = parser.parse(input, None).unwrap();
+        let captures = QueryCursor::new()
+            .captures(&query, tree.root_node(), input.as_bytes())
+            .map(|(m, i)| &input[m.captures[i].node.byte_range()])
+            .collect::<Vec<_>>();
+        assert_eq!(captures, vec!["->"]);
+    }
+    #[test]
+    fn token_rules() {
+        use grammar5::{Identifier, Statement};
+
+        let ident = |name: &str| Identifier {
+            name: name.to_string(),
+        };
+        assert_eq!(
+            grammar5::parse("f(a, b)").unwrap(),
+            Statement::Call {
+                callee: ident("f"),
+                _open: (),
+                args: vec![ident("a"), ident("b")],
+                _close: (),
+            }
+        );
+        assert_eq!(
+            grammar5::parse("x = y").unwrap(),
+            Statement::Assign {
+                target: ident("x"),
+                _eq: (),
+                value: ident("y"),
+            }
+        );
+
+        // the identifier is a leaf of its own, with no node wrapping its pattern
+        let mut parser = rust_sitter::tree_sitter::Parser::new();
+        parser.set_language(grammar5::language()).unwrap();
+        let tree = parser.parse("x = y", None).unwrap();
+        let target = tree.root_node().child(0).unwrap().child(0).unwrap();
+        assert_eq!(target.kind(), "Identifier");
+        assert_eq!(target.child_count(), 0);
+    }
+}
@@ -0,0 +1,300 @@
+#[rust_sitter::grammar("keywords")]
+pub mod grammar {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum Value {
+        #[rust_sitter::leaf(text = "true")]
+        True,
+        #[rust_sitter::leaf(text = "false")]
+        False,
+        Number(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] i32),
+        Not(#[rust_sitter::leaf(text = "!")] (), Box<Value>),
+        Lambda {
+            #[rust_sitter::leaf(text = ["fn", "function"], transform = |v| v.to_string())]
+            keyword: String,
+            #[rust_sitter::leaf(text = ["->", "→"])]
+            _arrow: (),
+            body: Box<Value>,
+        },
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[rust_sitter::grammar("keywords_loops")]
+pub mod grammar2 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Loop {
+        #[rust_sitter::leaf(text = "for")]
+        _for: (),
+        pub var: Ident,
+        pub kind: LoopKind,
+        pub iter: Ident,
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum LoopKind {
+        #[rust_sitter::keyword("in")]
+        In,
+        #[rust_sitter::keyword("of")]
+        Of,
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Ident {
+        #[rust_sitter::leaf(pattern = r"[a-z_][a-z0-9_]*", transform = String::from)]
+        pub name: String,
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[rust_sitter::grammar("keywords_derived")]
+pub mod grammar3 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Statement {
+        pub keywords: Vec<Keyword>,
+        pub constant: Option<Constant>,
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum Keyword {
+        If,
+        Else,
+        While,
+        ElseIf,
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    #[rust_sitter::rename_all("SCREAMING_SNAKE_CASE")]
+    pub enum Constant {
+        MaxValue,
+        MinValue,
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[rust_sitter::grammar("keywords_mixed_arity")]
+pub mod grammar4 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum Command {
+        Reset,
+        Clear(),
+        Skip {},
+        Wait(#[rust_sitter::skip(1)] u32),
+        Goto(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] u32),
+        Move {
+            #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+            x: u32,
+            #[rust_sitter::leaf(text = ",")]
+            _comma: (),
+            #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+            y: u32,
+        },
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[rust_sitter::grammar("keywords_tagged")]
+pub mod grammar5 {
+    #[rust_sitter::language]
+    #[rust_sitter::tags]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum Command {
+        Reset,
+        Goto(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] u32),
+        Move {
+            #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+            x: u32,
+            #[rust_sitter::leaf(text = ",")]
+            _comma: (),
+            #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+            y: u32,
+        },
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[rust_sitter::grammar("keywords_tagged_reordered")]
+pub mod grammar6 {
+    #[rust_sitter::language]
+    #[rust_sitter::tags]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum Command {
+        Move {
+            #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+            x: u32,
+            #[rust_sitter::leaf(text = ",")]
+            _comma: (),
+            #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+            y: u32,
+        },
+        Goto(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] u32),
+        Reset,
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grammar::Value;
+
+    #[test]
+    fn keyword_variants() {
+        assert_eq!(grammar::parse("true").unwrap(), Value::True);
+        assert_eq!(grammar::parse(" false ").unwrap(), Value::False);
+        assert_eq!(
+            grammar::parse("!false").unwrap(),
+            Value::Not((), Box::new(Value::False))
+        );
+        assert_eq!(
+            grammar::parse("!12").unwrap(),
+            Value::Not((), Box::new(Value::Number(12)))
+        );
+        assert_eq!(grammar::parse("true").unwrap().kind(), "Value_True");
+        assert!(grammar::parse("truefalse").is_err());
+    }
+
+    #[test]
+    fn keyword_synonyms() {
+        assert_eq!(
+            grammar::parse("fn -> true").unwrap(),
+            Value::Lambda {
+                keyword: "fn".to_string(),
+                _arrow: (),
+                body: Box::new(Value::True)
+            }
+        );
+        assert_eq!(
+            grammar::parse("function → 1").unwrap(),
+            Value::Lambda {
+                keyword: "function".to_string(),
+                _arrow: (),
+                body: Box::new(Value::Number(1))
+            }
+        );
+        assert!(grammar::parse("func -> 1").is_err());
+    }
+
+    #[test]
+    fn whole_word_keywords() {
+        use grammar2::LoopKind;
+
+        let parsed = grammar2::parse("for x in xs").unwrap();
+        assert_eq!(parsed.kind, LoopKind::In);
+        assert_eq!(parsed.iter.name, "xs");
+        assert_eq!(grammar2::parse("for x of xs").unwrap().kind, LoopKind::Of);
+        assert_eq!(
+            grammar2::parse("for x in index").unwrap().iter.name,
+            "index"
+        );
+
+        // `index` is an identifier, not the keyword `in` followed by `dex`
+        assert!(grammar2::parse("for x index").is_err());
+    }
+
+    #[test]
+    fn name_derived_keywords() {
+        use grammar3::{Constant, Keyword};
+
+        let parsed = grammar3::parse("if else while else_if MAX_VALUE").unwrap();
+        assert_eq!(
+            parsed.keywords,
+            vec![Keyword::If, Keyword::Else, Keyword::While, Keyword::ElseIf]
+        );
+        assert_eq!(parsed.constant, Some(Constant::MaxValue));
+        assert!(grammar3::parse("If").is_err());
+        assert!(grammar3::parse("max_value").is_err());
+    }
+
+    #[test]
+    fn mixed_arity_variants() {
+        use grammar4::Command;
+
+        assert_eq!(grammar4::parse("reset").unwrap(), Command::Reset);
+        assert_eq!(grammar4::parse("clear").unwrap(), Command::Clear());
+        assert_eq!(grammar4::parse(" skip ").unwrap(), Command::Skip {});
+        assert_eq!(grammar4::parse("wait").unwrap(), Command::Wait(1));
+        assert_eq!(grammar4::parse("12").unwrap(), Command::Goto(12));
+        assert_eq!(
+            grammar4::parse("3, 4").unwrap(),
+            Command::Move {
+                x: 3,
+                _comma: (),
+                y: 4
+            }
+        );
+        assert!(grammar4::parse("").is_err());
+        assert!(grammar4::parse("clear()").is_err());
+    }
+
+    #[test]
+    fn variant_tags() {
+        use rust_sitter::Tagged;
+
+        // the tags follow the names of the variants rather than their order
+        assert_eq!(
+            grammar5::Command::TAGS,
+            &[("Goto", 0), ("Move", 1), ("Reset", 2)]
+        );
+        assert_eq!(grammar5::Command::TAGS, grammar6::Command::TAGS);
+        for (input, tag) in [("12", 0), ("3, 4", 1), ("reset", 2)] {
+            assert_eq!(grammar5::parse(input).unwrap().tag(), tag);
+            assert_eq!(grammar6::parse(input).unwrap().tag(), tag);
+        }
+
+        // only variants without fields can be reconstructed from their tag
+        assert_eq!(
+            grammar5::Command::from_tag(2),
+            Some(grammar5::Command::Reset)
+        );
+        assert_eq!(
+            grammar6::Command::from_tag(2),
+            Some(grammar6::Command::Reset)
+        );
+        assert_eq!(grammar5::Command::from_tag(0), None);
+        assert_eq!(grammar5::Command::from_tag(3), None);
+    }
+}
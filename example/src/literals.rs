@@ -0,0 +1,221 @@
+#[rust_sitter::grammar("literals")]
+pub mod grammar {
+    use rust_sitter::WithRaw;
+
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum Literal {
+        Hex(
+            #[rust_sitter::leaf(pattern = r"0x[0-9a-fA-F]+", keep_raw = true, transform = |v| i64::from_str_radix(&v[2..], 16).unwrap())]
+             WithRaw<i64>,
+        ),
+        Decimal(
+            #[rust_sitter::leaf(pattern = r"\d+", keep_raw = true, transform = |v| v.parse().unwrap())]
+             WithRaw<i64>,
+        ),
+    }
+}
+
+#[rust_sitter::grammar("literals_alternatives")]
+pub mod grammar2 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Numbers {
+        #[rust_sitter::delimited(
+            #[rust_sitter::leaf(text = ",")]
+            ()
+        )]
+        #[rust_sitter::leaf(alternatives = [(r"0x[0-9a-fA-F]+", parse_hex), (r"\d+", parse_dec)])]
+        pub values: Vec<i64>,
+    }
+
+    fn parse_hex(v: &str) -> i64 {
+        i64::from_str_radix(&v[2..], 16).unwrap()
+    }
+
+    fn parse_dec(v: &str) -> i64 {
+        v.parse().unwrap()
+    }
+}
+
+#[rust_sitter::grammar("literals_dates")]
+pub mod grammar3 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Dates {
+        #[rust_sitter::delimited(
+            #[rust_sitter::leaf(text = ",")]
+            ()
+        )]
+        pub dates: Vec<Date>,
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    #[rust_sitter::leaf(pattern = r"(?P<y>\d{4})-(?P<m>\d{2})-(?P<d>\d{2})(T(?P<hour>\d{2}))?")]
+    pub struct Date {
+        pub y: u16,
+        pub m: u8,
+        pub d: u8,
+        pub hour: Option<u8>,
+    }
+}
+
+#[rust_sitter::grammar("literals_grouped")]
+pub mod grammar4 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Assignment {
+        #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+        pub name: String,
+        #[rust_sitter::leaf(text = "=")]
+        _eq: (),
+        pub value: Literal,
+    }
+
+    #[rust_sitter::token_group(_literal)]
+    #[derive(PartialEq, Eq, Debug)]
+    pub enum Literal {
+        Number(Number),
+        Boolean(Boolean),
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    pub struct Number(
+        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] pub i64,
+    );
+
+    #[derive(PartialEq, Eq, Debug)]
+    pub struct Boolean(
+        #[rust_sitter::leaf(pattern = r"true|false", transform = |v| v == "true")] pub bool,
+    );
+}
+
+#[rust_sitter::grammar("literals_bytes")]
+#[rust_sitter::extras()]
+pub mod grammar5 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Header {
+        #[rust_sitter::leaf(bytes = [0x7F, 0x45, 0x4C, 0x46])]
+        _magic: (),
+        pub class: Class,
+        #[rust_sitter::leaf(pattern = r"[\x01-\xff]+", transform = |v| rust_sitter::bytes::encode(v).len())]
+        pub payload_len: usize,
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    pub enum Class {
+        #[rust_sitter::leaf(bytes = [0x01])]
+        Elf32,
+        #[rust_sitter::leaf(bytes = [0x02])]
+        Elf64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grammar::Literal;
+    use rust_sitter::WithRaw;
+
+    #[test]
+    fn raw_text_is_kept() {
+        assert_eq!(
+            grammar::parse("0x10").unwrap(),
+            Literal::Hex(WithRaw {
+                value: 16,
+                raw: "0x10".to_string()
+            })
+        );
+        assert_eq!(
+            grammar::parse("16").unwrap(),
+            Literal::Decimal(WithRaw {
+                value: 16,
+                raw: "16".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn transform_per_alternative() {
+        assert_eq!(
+            grammar2::parse("0x10, 16, 0xff").unwrap(),
+            grammar2::Numbers {
+                values: vec![16, 16, 255]
+            }
+        );
+        assert!(grammar2::parse("0xg").is_err());
+    }
+
+    #[test]
+    fn capture_groups_fill_fields() {
+        use grammar3::Date;
+
+        assert_eq!(
+            grammar3::parse("2024-01-02, 1999-12-31T23").unwrap(),
+            grammar3::Dates {
+                dates: vec![
+                    Date {
+                        y: 2024,
+                        m: 1,
+                        d: 2,
+                        hour: None
+                    },
+                    Date {
+                        y: 1999,
+                        m: 12,
+                        d: 31,
+                        hour: Some(23)
+                    },
+                ]
+            }
+        );
+
+        // a malformed date is not matched by the pattern at all
+        let errors = grammar3::parse("2024-1-02").unwrap_err();
+        assert!(!errors.is_empty());
+        assert!(grammar3::parse("2024-01-02T").is_err());
+    }
+
+    #[test]
+    fn token_group_stays_hidden() {
+        use grammar4::{Boolean, Literal, Number};
+
+        assert_eq!(
+            grammar4::parse("x = 42").unwrap().value,
+            Literal::Number(Number(42))
+        );
+        assert_eq!(
+            grammar4::parse("x = true").unwrap().value,
+            Literal::Boolean(Boolean(true))
+        );
+        assert!(grammar4::parse("x = y").is_err());
+
+        // the member is the value of the field, without a node for the group around it
+        let mut parser = rust_sitter::tree_sitter::Parser::new();
+        parser.set_language(grammar4::language()).unwrap();
+        let tree = parser.parse("x = false", None).unwrap();
+        let value = tree.root_node().child_by_field_name("value").unwrap();
+        assert_eq!(value.kind(), "Boolean");
+        assert!(!rust_sitter::to_sexp(&tree, "x = false").contains("literal"));
+    }
+
+    #[test]
+    fn byte_sequence_leaves() {
+        use grammar5::Class;
+
+        // the payload is not valid UTF-8, and each of its bytes counts once
+        let header = grammar5::parse_bytes(b"\x7fELF\x02\xca\xfe\x01").unwrap();
+        assert_eq!((header.class, header.payload_len), (Class::Elf64, 3));
+        assert!(grammar5::parse_bytes(b"\x7fELG\x01\x01").is_err());
+
+        // the offsets of errors are into the bytes rather than into the decoded text
+        let errors = grammar5::parse_bytes(b"\xff\xff\x7fELF\x01\x01").unwrap_err();
+        assert_eq!((errors[0].start, errors[0].end), (0, 2));
+    }
+}
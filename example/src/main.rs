@@ -5,8 +5,22 @@ use codemap_diagnostic::{ColorConfig, Diagnostic, Emitter, Level, SpanLabel, Spa
 use rust_sitter::errors::{ParseError, ParseErrorReason};
 
 mod arithmetic;
+mod assignments;
+mod configs;
+mod externals;
+mod extras;
+mod identifiers;
+mod keywords;
+mod literals;
+mod nesting;
 mod optionals;
 mod repetitions;
+mod roots;
+mod sql;
+mod strings;
+mod supertypes;
+mod transforms;
+mod untyped;
 mod words;
 
 fn convert_parse_error_to_diagnostics(
@@ -37,6 +51,133 @@ fn convert_parse_error_to_diagnostics(
             }],
         }),
 
+        ParseErrorReason::InvalidEscape(seq) => diagnostics.push(Diagnostic {
+            level: Level::Error,
+            message: format!("Invalid escape sequence: \"{seq}\""),
+            code: Some("S000".to_string()),
+            spans: vec![SpanLabel {
+                span: file_span.subspan(error.start as u64, error.end as u64),
+                style: SpanStyle::Primary,
+                label: Some("invalid escape".to_string()),
+            }],
+        }),
+
+        ParseErrorReason::InvalidValue { text, message } => diagnostics.push(Diagnostic {
+            level: Level::Error,
+            message: match message {
+                Some(message) => format!("Invalid value \"{text}\": {message}"),
+                None => format!("Invalid value \"{text}\""),
+            },
+            code: Some("S000".to_string()),
+            spans: vec![SpanLabel {
+                span: file_span.subspan(error.start as u64, error.end as u64),
+                style: SpanStyle::Primary,
+                label: Some("invalid value".to_string()),
+            }],
+        }),
+
+        ParseErrorReason::TooDeep { depth } => diagnostics.push(Diagnostic {
+            level: Level::Error,
+            message: format!("Input is nested too deeply ({depth} levels)"),
+            code: Some("S000".to_string()),
+            spans: vec![SpanLabel {
+                span: file_span.subspan(error.start as u64, error.end as u64),
+                style: SpanStyle::Primary,
+                label: Some("nested too deeply".to_string()),
+            }],
+        }),
+
+        ParseErrorReason::TooManyNodes { limit } => diagnostics.push(Diagnostic {
+            level: Level::Error,
+            message: format!("Input has too many nodes (more than {limit})"),
+            code: Some("S000".to_string()),
+            spans: vec![SpanLabel {
+                span: file_span.subspan(error.start as u64, error.end as u64),
+                style: SpanStyle::Primary,
+                label: Some("too many nodes".to_string()),
+            }],
+        }),
+
+        ParseErrorReason::InvalidUtf8 => diagnostics.push(Diagnostic {
+            level: Level::Error,
+            message: "Input is not valid UTF-8".to_string(),
+            code: Some("S000".to_string()),
+            spans: vec![SpanLabel {
+                span: file_span.subspan(error.start as u64, error.end as u64),
+                style: SpanStyle::Primary,
+                label: Some("invalid UTF-8".to_string()),
+            }],
+        }),
+
+        ParseErrorReason::ParserFailed => diagnostics.push(Diagnostic {
+            level: Level::Error,
+            message: "The parser did not produce a tree".to_string(),
+            code: Some("S000".to_string()),
+            spans: vec![],
+        }),
+
+        ParseErrorReason::ExtractionFailed(message) => diagnostics.push(Diagnostic {
+            level: Level::Error,
+            message: format!("Failed to extract the AST: {message}"),
+            code: Some("S000".to_string()),
+            spans: vec![SpanLabel {
+                span: file_span.subspan(error.start as u64, error.end as u64),
+                style: SpanStyle::Primary,
+                label: Some("failed".to_string()),
+            }],
+        }),
+
+        ParseErrorReason::DisabledDialect { dialect } => diagnostics.push(Diagnostic {
+            level: Level::Error,
+            message: format!("The \"{dialect}\" dialect is not enabled"),
+            code: Some("S000".to_string()),
+            spans: vec![SpanLabel {
+                span: file_span.subspan(error.start as u64, error.end as u64),
+                style: SpanStyle::Primary,
+                label: Some(format!("requires the \"{dialect}\" dialect")),
+            }],
+        }),
+
+        ParseErrorReason::TokenTooLong { rule, len } => diagnostics.push(Diagnostic {
+            level: Level::Error,
+            message: format!("A \"{rule}\" token of {len} bytes is too long"),
+            code: Some("S000".to_string()),
+            spans: vec![SpanLabel {
+                span: file_span.subspan(error.start as u64, error.end as u64),
+                style: SpanStyle::Primary,
+                label: Some("token too long".to_string()),
+            }],
+        }),
+
+        ParseErrorReason::MissingField { field } => diagnostics.push(Diagnostic {
+            level: Level::Error,
+            message: format!("Missing the \"{field}\" field"),
+            code: Some("S000".to_string()),
+            spans: vec![SpanLabel {
+                span: file_span.subspan(error.start as u64, error.end as u64),
+                style: SpanStyle::Primary,
+                label: Some(format!("missing \"{field}\"")),
+            }],
+        }),
+
+        ParseErrorReason::DuplicateField { field } => diagnostics.push(Diagnostic {
+            level: Level::Error,
+            message: format!("The \"{field}\" field appears more than once"),
+            code: Some("S000".to_string()),
+            spans: vec![SpanLabel {
+                span: file_span.subspan(error.start as u64, error.end as u64),
+                style: SpanStyle::Primary,
+                label: Some("repeated field".to_string()),
+            }],
+        }),
+
+        ParseErrorReason::Timeout { micros } => diagnostics.push(Diagnostic {
+            level: Level::Error,
+            message: format!("Parsing took longer than {micros} microseconds"),
+            code: Some("S000".to_string()),
+            spans: vec![],
+        }),
+
         ParseErrorReason::FailedNode(errors) => {
             if errors.is_empty() {
                 diagnostics.push(Diagnostic {
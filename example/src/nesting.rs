@@ -0,0 +1,488 @@
+#[rust_sitter::grammar("nesting")]
+pub mod grammar {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum Term {
+        #[rust_sitter::leaf(text = "x")]
+        Atom,
+        Group(
+            #[rust_sitter::leaf(text = "(")] (),
+            Box<Term>,
+            #[rust_sitter::leaf(text = ")")] (),
+        ),
+    }
+}
+
+#[rust_sitter::grammar("nesting_brackets")]
+pub mod grammar2 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum Group {
+        Paren(#[rust_sitter::bracketed(open = "(", close = ")")] Items),
+        Square(#[rust_sitter::bracketed(open = "[", close = "]")] Items),
+        Curly(#[rust_sitter::bracketed(open = "{", close = "}")] Items),
+    }
+
+    #[derive(PartialEq, Eq, Debug, Clone)]
+    pub struct Items {
+        #[rust_sitter::repeat(non_empty = true)]
+        #[rust_sitter::delimited(
+            #[rust_sitter::leaf(text = ",")]
+            ()
+        )]
+        pub numbers: Vec<Number>,
+    }
+
+    #[derive(PartialEq, Eq, Debug, Clone)]
+    pub struct Number {
+        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+        pub value: u32,
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[rust_sitter::grammar("nesting_prefixed", prefix_rules = true)]
+pub mod grammar3 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum Group {
+        Paren(#[rust_sitter::bracketed(open = "(", close = ")")] Items),
+        #[rust_sitter::leaf(text = "()")]
+        Empty,
+    }
+
+    #[derive(PartialEq, Eq, Debug, Clone)]
+    pub struct Items {
+        #[rust_sitter::repeat(non_empty = true)]
+        #[rust_sitter::delimited(
+            #[rust_sitter::leaf(text = ",")]
+            ()
+        )]
+        #[rust_sitter::leaf(alternatives = [(r"0x[0-9a-f]+", parse_hex), (r"\d+", parse_dec)])]
+        pub numbers: Vec<u32>,
+    }
+
+    fn parse_hex(v: &str) -> u32 {
+        u32::from_str_radix(&v[2..], 16).unwrap()
+    }
+
+    fn parse_dec(v: &str) -> u32 {
+        v.parse().unwrap()
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[rust_sitter::grammar("nesting_hidden")]
+#[rust_sitter::hide_anonymous]
+pub mod grammar4 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Call {
+        #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+        pub name: String,
+        #[rust_sitter::leaf(text = "(")]
+        _open: (),
+        #[rust_sitter::delimited(
+            #[rust_sitter::leaf(text = ",")]
+            ()
+        )]
+        pub args: Vec<Arg>,
+        #[rust_sitter::leaf(text = ")")]
+        _close: (),
+        #[rust_sitter::leaf(text = ";")]
+        pub semicolon: Option<()>,
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    pub enum Arg {
+        Number(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] u32),
+        #[rust_sitter::leaf(text = "_")]
+        Hole,
+    }
+}
+
+#[rust_sitter::grammar("nesting_positional")]
+pub mod grammar5 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Call(
+        pub Identifier,
+        #[rust_sitter::leaf(text = "(")] (),
+        pub Vec<Expr>,
+        #[rust_sitter::leaf(text = ")")] (),
+    );
+
+    #[derive(PartialEq, Eq, Debug)]
+    pub enum Expr {
+        Number(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] u32),
+        Call(Call),
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    pub struct Identifier(
+        #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())] pub String,
+    );
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[rust_sitter::grammar("nesting_snake")]
+#[rust_sitter::rename_all("snake_case")]
+pub mod grammar6 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum NumberGroup {
+        Paren(#[rust_sitter::bracketed(open = "(", close = ")")] NumberItems),
+        #[rust_sitter::leaf(text = "()")]
+        Empty,
+    }
+
+    #[derive(PartialEq, Eq, Debug, Clone)]
+    pub struct NumberItems {
+        #[rust_sitter::repeat(non_empty = true)]
+        #[rust_sitter::delimited(
+            #[rust_sitter::leaf(text = ",")]
+            ()
+        )]
+        #[rust_sitter::leaf(alternatives = [(r"0x[0-9a-f]+", parse_hex), (r"\d+", parse_dec)])]
+        pub numbers: Vec<u32>,
+    }
+
+    fn parse_hex(v: &str) -> u32 {
+        u32::from_str_radix(&v[2..], 16).unwrap()
+    }
+
+    fn parse_dec(v: &str) -> u32 {
+        v.parse().unwrap()
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[rust_sitter::grammar("nesting_recursive")]
+// the boxes are redundant, but recursive fields can be wrapped in them at any level
+#[allow(clippy::vec_box, clippy::box_collection)]
+pub mod grammar7 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum Tree {
+        #[rust_sitter::leaf(text = "x")]
+        Atom,
+        List(
+            #[rust_sitter::leaf(text = "[")] (),
+            #[rust_sitter::delimited(
+                #[rust_sitter::leaf(text = ",")]
+                ()
+            )]
+            Vec<Box<Tree>>,
+            #[rust_sitter::leaf(text = "]")] (),
+        ),
+        Maybe(
+            #[rust_sitter::leaf(text = "?(")] (),
+            Option<Box<Tree>>,
+            #[rust_sitter::leaf(text = ")")] (),
+        ),
+        Block(
+            #[rust_sitter::leaf(text = "{")] (),
+            Option<Vec<Box<Tree>>>,
+            #[rust_sitter::leaf(text = "}")] (),
+        ),
+        Boxed(
+            #[rust_sitter::leaf(text = "!(")] (),
+            Box<Option<Box<Tree>>>,
+            #[rust_sitter::leaf(text = ")")] (),
+        ),
+        Group(
+            #[rust_sitter::leaf(text = "(")] (),
+            Option<Box<Vec<Box<Tree>>>>,
+            #[rust_sitter::leaf(text = ")")] (),
+        ),
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[rust_sitter::grammar("nesting_syntax")]
+pub mod grammar8 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum Expr {
+        Number(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] i32),
+        Paren(Paren),
+        Neg(#[rust_sitter::syntax(before = "-")] Box<Expr>),
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Paren {
+        #[rust_sitter::syntax(before = "(", after = ")")]
+        pub inner: Box<Expr>,
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grammar::Term;
+    use rust_sitter::errors::ParseErrorReason;
+
+    #[test]
+    fn depth_limit() {
+        assert_eq!(
+            grammar::parse_with_max_depth("((x))", 100).unwrap(),
+            Term::group(Term::group(Term::Atom))
+        );
+
+        let input = format!("{}x{}", "(".repeat(10_000), ")".repeat(10_000));
+        let errors = grammar::parse_with_max_depth(&input, 100).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0].reason, ParseErrorReason::TooDeep { depth } if depth > 100));
+    }
+
+    #[test]
+    fn larger_stack_extraction() {
+        // deep enough that extracting on the stack of a test thread overflows it
+        let depth = 20_000;
+        let input = format!("{}x{}", "(".repeat(depth), ")".repeat(depth));
+        let mut term = grammar::parse_as_on_larger_stack::<Term>(&input).unwrap();
+
+        // the AST is unwrapped a level at a time, since dropping it whole would recurse too
+        let mut groups = 0;
+        while let Term::Group((), inner, ()) = term {
+            groups += 1;
+            term = *inner;
+        }
+        assert_eq!((groups, term), (depth, Term::Atom));
+
+        assert_eq!(
+            grammar::parse_as_on_larger_stack::<Term>("((x))").unwrap(),
+            Term::group(Term::group(Term::Atom))
+        );
+    }
+
+    #[test]
+    fn bracket_kinds() {
+        use grammar2::{Group, Items, Number};
+
+        let items = Items {
+            numbers: vec![Number { value: 1 }, Number { value: 2 }],
+        };
+        assert_eq!(
+            grammar2::parse("(1, 2)").unwrap(),
+            Group::Paren(items.clone())
+        );
+        assert_eq!(
+            grammar2::parse("[1, 2]").unwrap(),
+            Group::Square(items.clone())
+        );
+        assert_eq!(grammar2::parse("{1, 2}").unwrap(), Group::Curly(items));
+        assert!(grammar2::parse("[]").is_err());
+        assert!(grammar2::parse("(1, 2]").is_err());
+    }
+
+    #[test]
+    fn prefixed_rule_names() {
+        use grammar3::{Group, Items, NodeRef};
+
+        let group = grammar3::parse("(1, 0x1f)").unwrap();
+        assert_eq!(
+            group,
+            Group::Paren(Items {
+                numbers: vec![1, 31]
+            })
+        );
+        assert_eq!(group.kind(), "nesting_prefixed_Group_Paren");
+        assert_eq!(grammar3::parse("()").unwrap(), Group::Empty);
+
+        let mut parser = rust_sitter::tree_sitter::Parser::new();
+        parser.set_language(grammar3::language()).unwrap();
+        let tree = parser.parse("(2)", None).unwrap();
+        assert_eq!(tree.root_node().kind(), "nesting_prefixed_source_file");
+        assert!(matches!(
+            grammar3::node_at(&tree, 1),
+            Some(NodeRef::Items(_))
+        ));
+    }
+
+    #[test]
+    fn snake_case_rule_names() {
+        use grammar6::{NodeRef, NumberGroup, NumberItems};
+
+        let group = grammar6::parse("(1, 0x1f)").unwrap();
+        assert_eq!(
+            group,
+            NumberGroup::Paren(NumberItems {
+                numbers: vec![1, 31]
+            })
+        );
+        assert_eq!(group.kind(), "number_group_paren");
+        assert_eq!(grammar6::parse("()").unwrap(), NumberGroup::Empty);
+
+        let mut parser = rust_sitter::tree_sitter::Parser::new();
+        parser.set_language(grammar6::language()).unwrap();
+        let input = "(2)";
+        let tree = parser.parse(input, None).unwrap();
+        assert_eq!(
+            rust_sitter::to_sexp(&tree, input),
+            "(source_file (number_group_paren 0: (number_items numbers: (number_items_numbers_vec_contents number_items_numbers_vec_element: (number_items_numbers_alt_1)))))"
+        );
+        assert!(matches!(
+            grammar6::node_at(&tree, 1),
+            Some(NodeRef::NumberItems(_))
+        ));
+    }
+
+    #[test]
+    fn anonymous_punctuation() {
+        use grammar4::Arg;
+
+        let call = grammar4::parse("f(1, _);").unwrap();
+        assert_eq!(call.name, "f");
+        assert_eq!(call.args, vec![Arg::Number(1), Arg::Hole]);
+        assert_eq!(call.semicolon, Some(()));
+        assert_eq!(grammar4::parse("f()").unwrap().semicolon, None);
+
+        // the punctuation is matched by anonymous nodes, while the unit variant stays named
+        let mut parser = rust_sitter::tree_sitter::Parser::new();
+        parser.set_language(grammar4::language()).unwrap();
+        let input = "f(1, _);";
+        let tree = parser.parse(input, None).unwrap();
+        assert_eq!(
+            rust_sitter::to_sexp(&tree, input),
+            "(source_file name: (Call_name) args: (Call_args_vec_contents Call_args_vec_element: (Arg (Arg_Number 0: (Arg_Number_0))) Call_args_vec_element: (Arg (Arg_Hole))))"
+        );
+    }
+
+    #[test]
+    fn positional_call() {
+        use grammar5::{Call, Expr, Identifier};
+
+        // the `()` leaves are filled in by the constructor
+        let ident = |name: &str| Identifier::new(name.to_string());
+        assert_eq!(
+            grammar5::parse("f(1 g() 2)").unwrap(),
+            Call::new(
+                ident("f"),
+                vec![
+                    Expr::number(1),
+                    Expr::call(Call::new(ident("g"), vec![])),
+                    Expr::number(2)
+                ]
+            )
+        );
+        assert!(grammar5::parse("f(1").is_err());
+    }
+
+    #[test]
+    fn nested_recursion() {
+        use grammar7::Tree;
+
+        let atom = || Box::new(Tree::Atom);
+        assert_eq!(
+            grammar7::parse("[x, [x], ?(x)]").unwrap(),
+            Tree::List(
+                (),
+                vec![
+                    atom(),
+                    Box::new(Tree::List((), vec![atom()], ())),
+                    Box::new(Tree::Maybe((), Some(atom()), ())),
+                ],
+                ()
+            )
+        );
+        assert_eq!(grammar7::parse("?()").unwrap(), Tree::Maybe((), None, ()));
+
+        // three levels of `Option`, `Vec` and `Box` around the recursive type
+        assert_eq!(
+            grammar7::parse("{x ?() {}}").unwrap(),
+            Tree::Block(
+                (),
+                Some(vec![
+                    atom(),
+                    Box::new(Tree::Maybe((), None, ())),
+                    Box::new(Tree::Block((), None, ())),
+                ]),
+                ()
+            )
+        );
+        assert_eq!(
+            grammar7::parse("!(!())").unwrap(),
+            Tree::Boxed(
+                (),
+                Box::new(Some(Box::new(Tree::Boxed((), Box::new(None), ())))),
+                ()
+            )
+        );
+        assert_eq!(
+            grammar7::parse("((x) ())").unwrap(),
+            Tree::Group(
+                (),
+                Some(Box::new(vec![
+                    Box::new(Tree::Group((), Some(Box::new(vec![atom()])), ())),
+                    Box::new(Tree::Group((), None, ())),
+                ])),
+                ()
+            )
+        );
+    }
+
+    #[test]
+    fn syntax_only_leaves() {
+        use grammar8::{Expr, Paren};
+
+        // the parentheses and the minus sign are matched, but have no fields in the types
+        assert_eq!(
+            grammar8::parse("-( 1 )").unwrap(),
+            Expr::Neg(Box::new(Expr::Paren(Paren {
+                inner: Box::new(Expr::Number(1))
+            })))
+        );
+        assert!(grammar8::parse("(1").is_err());
+        assert!(grammar8::parse("1)").is_err());
+
+        let mut parser = rust_sitter::tree_sitter::Parser::new();
+        parser.set_language(grammar8::language()).unwrap();
+        let input = "(2)";
+        let tree = parser.parse(input, None).unwrap();
+        assert_eq!(
+            rust_sitter::to_sexp(&tree, input),
+            "(source_file (Expr_Paren 0: (Paren inner: (Expr (Expr_Number 0: (Expr_Number_0))))))"
+        );
+    }
+}
@@ -15,6 +15,7 @@ mod grammar {
         _d: Option<()>,
     }
 
+    #[rust_sitter::entry]
     #[derive(Debug)]
     pub struct Number {
         #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
@@ -22,6 +23,79 @@ mod grammar {
     }
 }
 
+#[rust_sitter::grammar("optionals_flags")]
+#[allow(dead_code)]
+mod grammar2 {
+    #[rust_sitter::language]
+    #[derive(Debug)]
+    pub struct Function {
+        #[rust_sitter::leaf(text = "pub")]
+        pub public: bool,
+        #[rust_sitter::leaf(text = "fn")]
+        _fn: (),
+        #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+        pub name: String,
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[rust_sitter::grammar("optionals_attribute")]
+#[allow(dead_code)]
+mod grammar3 {
+    #[rust_sitter::language]
+    #[derive(Debug)]
+    pub struct Version {
+        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+        pub major: u32,
+        #[rust_sitter::optional]
+        #[rust_sitter::leaf(pattern = r"\.\d+", transform = |v| v[1..].parse().unwrap())]
+        pub minor: u32,
+        #[rust_sitter::optional]
+        pub tag: Tag,
+    }
+
+    #[derive(Debug, Default)]
+    pub struct Tag {
+        #[rust_sitter::leaf(text = "-")]
+        _dash: (),
+        #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+        pub name: String,
+    }
+}
+
+#[rust_sitter::grammar("optionals_clauses")]
+#[allow(dead_code)]
+mod grammar4 {
+    #[rust_sitter::language]
+    #[derive(Debug, PartialEq, Eq)]
+    pub struct Import {
+        #[rust_sitter::leaf(text = "import")]
+        _import: (),
+        #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+        pub name: String,
+        pub alias: Option<AsClause>,
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    pub struct AsClause {
+        #[rust_sitter::leaf(text = "as")]
+        _as: (),
+        #[rust_sitter::leaf(pattern = r"[A-Z][a-z]*", transform = |v| v.to_string())]
+        pub ty: String,
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -37,4 +111,53 @@ mod tests {
         insta::assert_debug_snapshot!(grammar::parse("_2"));
         insta::assert_debug_snapshot!(grammar::parse("_2."));
     }
+
+    #[test]
+    fn entry_point() {
+        assert_eq!(
+            format!("{:?}", grammar::parse_as::<grammar::Number>("12").unwrap()),
+            "Number { v: 12 }"
+        );
+        assert!(grammar::parse_as::<grammar::Number>("12_").is_err());
+        assert!(grammar::parse_as::<grammar::Language>("12_").is_ok());
+    }
+
+    #[test]
+    fn optional_keyword_flag() {
+        let function = grammar2::parse("pub fn main").unwrap();
+        assert!(function.public);
+        assert_eq!(function.name, "main");
+
+        let function = grammar2::parse("fn main").unwrap();
+        assert!(!function.public);
+        assert_eq!(function.name, "main");
+    }
+
+    #[test]
+    fn optional_attribute_defaults() {
+        let version = grammar3::parse("1.2-beta").unwrap();
+        assert_eq!((version.major, version.minor), (1, 2));
+        assert_eq!(version.tag.name, "beta");
+
+        let version = grammar3::parse("1").unwrap();
+        assert_eq!((version.major, version.minor), (1, 0));
+        assert_eq!(version.tag.name, "");
+
+        let version = grammar3::parse("1-rc").unwrap();
+        assert_eq!((version.major, version.minor), (1, 0));
+        assert_eq!(version.tag.name, "rc");
+    }
+
+    #[test]
+    fn coupled_optional_fields() {
+        let import = grammar4::parse("import json as Json").unwrap();
+        assert_eq!(import.name, "json");
+        assert_eq!(import.alias.unwrap().ty, "Json");
+
+        let import = grammar4::parse("import json").unwrap();
+        assert_eq!(import.alias, None);
+
+        // the keyword cannot appear without the type
+        assert!(grammar4::parse("import json as").is_err());
+    }
 }
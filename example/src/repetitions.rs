@@ -3,6 +3,8 @@ pub mod grammar {
     use rust_sitter::Spanned;
 
     #[rust_sitter::language]
+    #[rust_sitter::serde]
+    #[rust_sitter::semantic_eq]
     #[derive(Debug)]
     #[allow(dead_code)]
     pub struct NumberList {
@@ -31,7 +33,7 @@ pub mod grammar2 {
     #[allow(dead_code)]
     pub struct NumberList {
         #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
-        numbers: Spanned<Vec<Spanned<i32>>>,
+        pub numbers: Spanned<Vec<Spanned<i32>>>,
     }
 
     #[rust_sitter::extra]
@@ -66,6 +68,243 @@ pub mod grammar3 {
     }
 }
 
+#[rust_sitter::grammar("repetitions_boxed")]
+#[allow(clippy::vec_box, clippy::box_collection)]
+pub mod grammar4 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum Expression {
+        Number(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] i32),
+        List(
+            #[rust_sitter::leaf(text = "[")] (),
+            #[rust_sitter::delimited(
+                #[rust_sitter::leaf(text = ",")]
+                ()
+            )]
+            Vec<Expression>,
+            #[rust_sitter::leaf(text = "]")] (),
+        ),
+        BoxedElements(
+            #[rust_sitter::leaf(text = "(")] (),
+            #[rust_sitter::delimited(
+                #[rust_sitter::leaf(text = ",")]
+                ()
+            )]
+            Vec<Box<Expression>>,
+            #[rust_sitter::leaf(text = ")")] (),
+        ),
+        BoxedList(
+            #[rust_sitter::leaf(text = "{")] (),
+            #[rust_sitter::delimited(
+                #[rust_sitter::leaf(text = ",")]
+                ()
+            )]
+            Box<Vec<Expression>>,
+            #[rust_sitter::leaf(text = "}")] (),
+        ),
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[rust_sitter::grammar("repetitions_records")]
+#[rust_sitter::extras(r"[ \t]")]
+pub mod grammar5 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Records(
+        #[rust_sitter::delimited(
+            #[rust_sitter::leaf(pattern = r"\n\n+")]
+            ()
+        )]
+        pub Vec<Record>,
+    );
+
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Record {
+        #[rust_sitter::repeat(non_empty = true)]
+        #[rust_sitter::delimited(
+            #[rust_sitter::leaf(text = "\n")]
+            ()
+        )]
+        pub fields: Vec<Field>,
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Field {
+        #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+        pub key: String,
+        #[rust_sitter::leaf(text = "=")]
+        pub _eq: (),
+        #[rust_sitter::leaf(pattern = r"\w+", transform = |v| v.to_string())]
+        pub value: String,
+    }
+}
+
+#[rust_sitter::grammar("repetitions_small")]
+pub mod grammar6 {
+    use rust_sitter::{SmallVec, Spanned};
+
+    #[rust_sitter::language]
+    #[rust_sitter::serde]
+    #[rust_sitter::semantic_eq]
+    #[derive(Debug)]
+    #[allow(dead_code)]
+    pub struct NumberList {
+        #[rust_sitter::delimited(
+            #[rust_sitter::leaf(text = ",")]
+            ()
+        )]
+        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+        pub numbers: SmallVec<[Spanned<i32>; 4]>,
+        #[rust_sitter::leaf(text = ";")]
+        _semicolon: (),
+        pub groups: SmallVec<[Group; 2]>,
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    #[allow(dead_code)]
+    pub struct Group {
+        #[rust_sitter::leaf(text = "(")]
+        _open: (),
+        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+        pub numbers: Vec<i32>,
+        #[rust_sitter::leaf(text = ")")]
+        _close: (),
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[rust_sitter::grammar("repetitions_counted")]
+pub mod grammar7 {
+    #[rust_sitter::language]
+    #[derive(Debug, PartialEq, Eq)]
+    #[allow(dead_code)]
+    pub struct MacAddress {
+        #[rust_sitter::repeat(sep = ":")]
+        pub octets: [Octet; 6],
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    pub struct Octet {
+        #[rust_sitter::leaf(pattern = r"[0-9a-f][0-9a-f]", transform = |v| u8::from_str_radix(v, 16).unwrap())]
+        pub value: u8,
+    }
+}
+
+// the same rules with a different count, whose trees have the wrong number of octets for
+// the first grammar
+#[rust_sitter::grammar("repetitions_counted_short")]
+pub mod grammar7_short {
+    #[rust_sitter::language]
+    #[derive(Debug, PartialEq, Eq)]
+    #[allow(dead_code)]
+    pub struct MacAddress {
+        #[rust_sitter::repeat(sep = ":")]
+        pub octets: [Octet; 3],
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    pub struct Octet {
+        #[rust_sitter::leaf(pattern = r"[0-9a-f][0-9a-f]", transform = |v| u8::from_str_radix(v, 16).unwrap())]
+        pub value: u8,
+    }
+}
+
+#[rust_sitter::grammar("repetitions_counted_vec")]
+pub mod grammar8 {
+    #[rust_sitter::language]
+    #[derive(Debug, PartialEq, Eq)]
+    #[allow(dead_code)]
+    pub struct Ipv4Address {
+        #[rust_sitter::repeat(count = 4, sep = ".")]
+        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+        pub parts: Vec<u8>,
+    }
+}
+
+#[rust_sitter::grammar("repetitions_separator_prec")]
+pub mod grammar9 {
+    #[rust_sitter::language]
+    #[derive(Debug, PartialEq, Eq)]
+    #[allow(dead_code)]
+    pub enum Expr {
+        Number(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] u32),
+        #[rust_sitter::prec_left(1)]
+        Pair(Box<Expr>, #[rust_sitter::leaf(text = ",")] (), Box<Expr>),
+        Call(
+            #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())] String,
+            #[rust_sitter::leaf(text = "(")] (),
+            #[rust_sitter::repeat(sep = ",", sep_prec = 2)] Vec<Expr>,
+            #[rust_sitter::leaf(text = ")")] (),
+        ),
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[rust_sitter::grammar("repetitions_right_recursive")]
+pub mod grammar10 {
+    #[rust_sitter::language]
+    #[derive(Debug, PartialEq, Eq)]
+    #[allow(dead_code)]
+    pub struct Cons {
+        #[rust_sitter::right_recursive]
+        #[rust_sitter::repeat(sep = ":", non_empty = true)]
+        #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+        pub items: Vec<String>,
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[rust_sitter::grammar("repetitions_default_empty")]
+pub mod grammar11 {
+    #[rust_sitter::language]
+    #[rust_sitter::default_empty]
+    #[derive(Debug, PartialEq, Eq)]
+    #[allow(dead_code)]
+    pub struct Program {
+        pub statements: Vec<Statement>,
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    #[allow(dead_code)]
+    pub struct Statement {
+        #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+        pub name: String,
+        #[rust_sitter::leaf(text = ";")]
+        _semicolon: (),
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -77,6 +316,45 @@ mod tests {
         insta::assert_debug_snapshot!(grammar::parse("1, 2"));
     }
 
+    #[test]
+    fn node_count_limit() {
+        use rust_sitter::errors::ParseErrorReason;
+
+        assert!(grammar::parse_with_max_nodes("1, 2", 10).is_ok());
+
+        let input = vec!["1"; 10_000].join(", ");
+        let errors = grammar::parse_with_max_nodes(&input, 1000).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0].reason,
+            ParseErrorReason::TooManyNodes { limit: 1000 }
+        ));
+        assert!(errors[0].start < input.len() / 2);
+    }
+
+    #[test]
+    fn repetitions_serde_round_trip() {
+        let parsed = grammar::parse("1, 2").unwrap();
+        let json = serde_json::to_string(&parsed).unwrap();
+        assert_eq!(
+            json,
+            r#"{"numbers":{"value":[{"value":1,"span":[0,1],"points":[{"row":0,"column":0},{"row":0,"column":1}]},{"value":2,"span":[3,4],"points":[{"row":0,"column":3},{"row":0,"column":4}]}],"span":[0,4],"points":[{"row":0,"column":0},{"row":0,"column":4}]}}"#
+        );
+
+        let deserialized: grammar::NumberList = serde_json::from_str(&json).unwrap();
+        assert_eq!(format!("{deserialized:?}"), format!("{parsed:?}"));
+    }
+
+    #[test]
+    fn repetitions_semantic_eq() {
+        use rust_sitter::SemanticEq;
+
+        let parsed = grammar::parse("1, 2").unwrap();
+        assert!(parsed.semantic_eq(&grammar::parse("  1 ,2").unwrap()));
+        assert!(!parsed.semantic_eq(&grammar::parse("1, 3").unwrap()));
+        assert!(!parsed.semantic_eq(&grammar::parse("1, 2, 3").unwrap()));
+    }
+
     #[test]
     fn repetitions_grammar2() {
         insta::assert_debug_snapshot!(grammar2::parse(""));
@@ -92,4 +370,190 @@ mod tests {
         insta::assert_debug_snapshot!(grammar3::parse("1,, 2"));
         insta::assert_debug_snapshot!(grammar3::parse("1,, 2,"));
     }
+
+    #[test]
+    fn repetitions_grammar4() {
+        use grammar4::Expression;
+
+        assert_eq!(
+            grammar4::parse("[1, 2]").unwrap(),
+            Expression::List((), vec![Expression::Number(1), Expression::Number(2)], ())
+        );
+        assert_eq!(
+            grammar4::parse("(1, [2])").unwrap(),
+            Expression::BoxedElements(
+                (),
+                vec![
+                    Box::new(Expression::Number(1)),
+                    Box::new(Expression::List((), vec![Expression::Number(2)], ()))
+                ],
+                ()
+            )
+        );
+        assert_eq!(
+            grammar4::parse("{(1), 2}").unwrap(),
+            Expression::BoxedList(
+                (),
+                Box::new(vec![
+                    Expression::BoxedElements((), vec![Box::new(Expression::Number(1))], ()),
+                    Expression::Number(2)
+                ]),
+                ()
+            )
+        );
+        assert_eq!(
+            grammar4::parse("{}").unwrap(),
+            Expression::BoxedList((), Box::default(), ())
+        );
+    }
+
+    #[test]
+    fn repetitions_records() {
+        use grammar5::{Field, Record, Records};
+
+        let field = |key: &str, value: &str| Field {
+            key: key.to_string(),
+            _eq: (),
+            value: value.to_string(),
+        };
+
+        assert_eq!(
+            grammar5::parse("a = 1\nb = 2\n\nc = 3\n\n\nd = 4").unwrap(),
+            Records(vec![
+                Record {
+                    fields: vec![field("a", "1"), field("b", "2")]
+                },
+                Record {
+                    fields: vec![field("c", "3")]
+                },
+                Record {
+                    fields: vec![field("d", "4")]
+                },
+            ])
+        );
+        assert_eq!(grammar5::parse("").unwrap(), Records(vec![]));
+        assert!(grammar5::parse("a = 1\n\n\nb").is_err());
+    }
+
+    #[test]
+    fn repetitions_small_vec() {
+        let parsed = grammar6::parse("1, 2, 3; (4 5) ()").unwrap();
+        assert_eq!(
+            parsed.numbers.iter().map(|n| n.value).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(parsed.numbers[1].span, (3, 4));
+        assert!(!parsed.numbers.spilled());
+        assert_eq!(
+            parsed
+                .groups
+                .iter()
+                .map(|g| g.numbers.clone())
+                .collect::<Vec<_>>(),
+            vec![vec![4, 5], vec![]]
+        );
+
+        // longer lists than the inline capacity move to the heap
+        let input = format!("{};", ["1"; 10].join(", "));
+        let parsed = grammar6::parse(&input).unwrap();
+        assert_eq!(parsed.numbers.len(), 10);
+        assert!(parsed.numbers.spilled());
+        assert!(parsed.groups.is_empty());
+    }
+
+    #[test]
+    fn span_points() {
+        use rust_sitter::Point;
+
+        let parsed = grammar6::parse("1,\n  22;").unwrap();
+        assert_eq!(parsed.numbers[0].start_point(), Point { row: 0, column: 0 });
+        let second = &parsed.numbers[1];
+        assert_eq!(second.byte_range(), 5..7);
+        assert_eq!(second.start_point(), Point { row: 1, column: 2 });
+        assert_eq!(second.end_point(), Point { row: 1, column: 4 });
+
+        // an absent value is placed where extraction last moved to, with the position that
+        // Tree Sitter gives that offset
+        let input = "\n\n  ";
+        let parsed = grammar2::parse(input).unwrap();
+        assert!(parsed.numbers.is_empty());
+        let offset = parsed.numbers.span.0;
+        let before = &input[..offset];
+        let expected = Point {
+            row: before.matches('\n').count(),
+            column: offset - before.rfind('\n').map(|i| i + 1).unwrap_or(0),
+        };
+        assert_eq!(parsed.numbers.start_point(), expected);
+        assert_eq!(parsed.numbers.end_point(), expected);
+    }
+
+    #[test]
+    fn counted_repetitions() {
+        use grammar7::{MacAddress, Octet};
+
+        let octets = [0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0xff].map(|value| Octet { value });
+        assert_eq!(
+            grammar7::parse("00:1a:2b:3c:4d:ff").unwrap(),
+            MacAddress { octets }
+        );
+        assert!(grammar7::parse("00:1a:2b:3c:4d").is_err());
+        assert!(grammar7::parse("00:1a:2b:3c:4d:ff:ee").is_err());
+
+        // a tree with another number of elements is reported rather than panicking
+        let errors =
+            grammar7::parse_with_language("00:1a:2b", grammar7_short::language()).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0].reason,
+            rust_sitter::errors::ParseErrorReason::ExtractionFailed(message)
+                if message == "expected 6 elements, but found 3"
+        ));
+        assert_eq!((errors[0].start, errors[0].end), (0, 8));
+
+        assert_eq!(
+            grammar8::parse("192.168.0.1").unwrap().parts,
+            vec![192, 168, 0, 1]
+        );
+        assert!(grammar8::parse("192.168.0").is_err());
+        assert!(grammar8::parse("192.168.0.1.2").is_err());
+    }
+
+    #[test]
+    fn separator_prec() {
+        use grammar9::Expr;
+
+        // a `,` in the arguments of a call separates them rather than making a pair
+        assert_eq!(
+            grammar9::parse("f(1, 2)").unwrap(),
+            Expr::Call(
+                "f".to_string(),
+                (),
+                vec![Expr::Number(1), Expr::Number(2)],
+                ()
+            )
+        );
+        assert_eq!(
+            grammar9::parse("1, 2").unwrap(),
+            Expr::Pair(Box::new(Expr::Number(1)), (), Box::new(Expr::Number(2)))
+        );
+    }
+
+    #[test]
+    fn right_recursive_repetitions() {
+        assert_eq!(
+            grammar10::parse("a:b:c").unwrap().items,
+            vec!["a", "b", "c"]
+        );
+        assert_eq!(grammar10::parse("a : bc").unwrap().items, vec!["a", "bc"]);
+        assert_eq!(grammar10::parse("a").unwrap().items, vec!["a"]);
+        assert!(grammar10::parse("a:b:").is_err());
+    }
+
+    #[test]
+    fn default_empty_program() {
+        let program = grammar11::Program::default();
+        assert!(program.statements.is_empty());
+        assert_eq!(program, grammar11::parse("").unwrap());
+        assert_eq!(grammar11::parse("a; b;").unwrap().statements.len(), 2);
+    }
 }
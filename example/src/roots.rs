@@ -0,0 +1,96 @@
+#[rust_sitter::grammar("roots")]
+pub mod grammar {
+    /// A file that declares a module, with a declaration on each line.
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Module {
+        #[rust_sitter::leaf(text = "module")]
+        _module: (),
+        #[rust_sitter::leaf(pattern = r"[a-z]+", transform = String::from)]
+        pub name: String,
+        #[rust_sitter::leaf(text = ";")]
+        _semicolon: (),
+        pub declarations: Vec<Declaration>,
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Declaration {
+        #[rust_sitter::leaf(text = "let")]
+        _let: (),
+        #[rust_sitter::leaf(pattern = r"[a-z]+", transform = String::from)]
+        pub name: String,
+        #[rust_sitter::leaf(text = "=")]
+        _equals: (),
+        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+        pub value: i32,
+        #[rust_sitter::leaf(text = ";")]
+        _semicolon: (),
+    }
+
+    /// A file that is a single expression, as evaluated by a calculator.
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum Expression {
+        Number(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] i32),
+        #[rust_sitter::prec_left(1)]
+        Add(
+            Box<Expression>,
+            #[rust_sitter::leaf(text = "+")] (),
+            Box<Expression>,
+        ),
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::grammar::{self, Expression, SourceFile};
+
+    #[test]
+    fn either_root() {
+        let SourceFile::Module(module) =
+            grammar::parse("module maths;\nlet one = 1;\nlet two = 2;").unwrap()
+        else {
+            panic!("expected a module");
+        };
+        assert_eq!(module.name, "maths");
+        assert_eq!(
+            module
+                .declarations
+                .iter()
+                .map(|d| (d.name.as_str(), d.value))
+                .collect::<Vec<_>>(),
+            vec![("one", 1), ("two", 2)]
+        );
+
+        assert_eq!(
+            grammar::parse("1 + 2").unwrap(),
+            SourceFile::Expression(Expression::Add(
+                Box::new(Expression::Number(1)),
+                (),
+                Box::new(Expression::Number(2))
+            ))
+        );
+
+        // the forms cannot be mixed in one file
+        assert!(grammar::parse("module maths; 1 + 2").is_err());
+    }
+
+    #[test]
+    fn root_node_kinds() {
+        let mut parser = rust_sitter::tree_sitter::Parser::new();
+        parser.set_language(grammar::language()).unwrap();
+        let tree = parser.parse("1 + 2", None).unwrap();
+        let root = tree.root_node();
+        assert_eq!(root.kind(), "source_file");
+        assert_eq!(root.named_child(0).unwrap().kind(), "Expression");
+    }
+}
@@ -0,0 +1,52 @@
+#[rust_sitter::grammar("sql")]
+#[rust_sitter::case_insensitive_keywords]
+pub mod grammar {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Query {
+        #[rust_sitter::leaf(text = "select")]
+        pub _select: (),
+        #[rust_sitter::leaf(pattern = r"[a-z_]+", transform = |v| v.to_string())]
+        pub column: String,
+        #[rust_sitter::leaf(text = "from")]
+        pub _from: (),
+        #[rust_sitter::leaf(pattern = r"[a-z_]+", case_insensitive = true, transform = |v| v.to_string())]
+        pub table: String,
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grammar::Query;
+
+    #[test]
+    fn case_insensitive_keywords() {
+        let query = Query {
+            _select: (),
+            column: "name".to_string(),
+            _from: (),
+            table: "users".to_string(),
+        };
+
+        assert_eq!(grammar::parse("SELECT name FROM users").unwrap(), query);
+        assert_eq!(grammar::parse("select name from users").unwrap(), query);
+        assert_eq!(grammar::parse("Select name From users").unwrap(), query);
+    }
+
+    #[test]
+    fn case_insensitive_patterns_opt_in() {
+        assert_eq!(
+            grammar::parse("select name from Users").unwrap().table,
+            "Users"
+        );
+        assert!(grammar::parse("select Name from users").is_err());
+    }
+}
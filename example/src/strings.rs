@@ -0,0 +1,226 @@
+#[rust_sitter::grammar("strings")]
+pub mod grammar {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum Literal {
+        Quoted(
+            #[rust_sitter::leaf(pattern = r#""([^"\\]|\\.)*""#, unescape = true, transform = |v| v[1..v.len() - 1].to_string())]
+             String,
+        ),
+        Words(
+            #[rust_sitter::repeat(non_empty = true)]
+            #[rust_sitter::leaf(pattern = r"[a-z]([a-z\\]|\\.)*", unescape = true)]
+            Vec<String>,
+        ),
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[rust_sitter::grammar("strings_limited", max_token_len = 16)]
+pub mod grammar2 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Note {
+        #[rust_sitter::leaf(text = "note:")]
+        _note: (),
+        #[rust_sitter::leaf(pattern = r".*", transform = |v| v.trim().to_string())]
+        pub text: String,
+    }
+}
+
+#[rust_sitter::grammar("strings_escapes")]
+#[rust_sitter::extras()]
+pub mod grammar3 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Escapes {
+        pub escapes: Vec<Escape>,
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Escape {
+        #[rust_sitter::leaf(text = "\\")]
+        _backslash: (),
+        #[rust_sitter::leaf(any = true, transform = |v| v.chars().next().unwrap())]
+        pub escaped: char,
+    }
+}
+
+#[rust_sitter::grammar("strings_borrowed")]
+#[rust_sitter::arena]
+pub mod grammar4 {
+    use std::borrow::Cow;
+
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Words<'a> {
+        #[rust_sitter::repeat(non_empty = true)]
+        #[rust_sitter::leaf(pattern = r"[a-z]([a-z\\]|\\.)*", unescape = true)]
+        pub words: Vec<Cow<'a, str>>,
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[rust_sitter::grammar("strings_trimmed")]
+pub mod grammar5 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum Value {
+        Quoted(#[rust_sitter::leaf(pattern = r#""[^"]*""#, trim = "\"")] String),
+        Tagged(
+            #[rust_sitter::leaf(pattern = r"#\d+;", trim_start = "#", trim_end = ";", transform = |v| v.parse().unwrap())]
+             u32,
+        ),
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grammar::Literal;
+    use rust_sitter::errors::ParseErrorReason;
+
+    #[test]
+    fn unescaped_leaves() {
+        assert_eq!(
+            grammar::parse(r#""a\nb\tc""#).unwrap(),
+            Literal::Quoted("a\nb\tc".to_string())
+        );
+        assert_eq!(
+            grammar::parse(r#""\\ \"quoted\" \u0041\u00e9""#).unwrap(),
+            Literal::Quoted("\\ \"quoted\" A\u{e9}".to_string())
+        );
+        assert_eq!(
+            grammar::parse(r"a\\b c\n").unwrap(),
+            Literal::Words(vec!["a\\b".to_string(), "c\n".to_string()])
+        );
+    }
+
+    #[test]
+    fn invalid_escapes() {
+        let errors = grammar::parse(r#""ab\qc""#).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(&errors[0].reason, ParseErrorReason::InvalidEscape(seq) if seq == r"\q"));
+        assert_eq!((errors[0].start, errors[0].end), (3, 5));
+
+        let errors = grammar::parse(r#""\u00e9 \u12x""#).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(
+            matches!(&errors[0].reason, ParseErrorReason::InvalidEscape(seq) if seq == r"\u12")
+        );
+        assert_eq!((errors[0].start, errors[0].end), (8, 12));
+    }
+
+    #[test]
+    fn long_tokens() {
+        let note = grammar2::parse("note: short").unwrap();
+        assert_eq!(note.text, "short");
+
+        let errors = grammar2::parse("note: this line runs on for far too long").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0].reason,
+            ParseErrorReason::TokenTooLong { rule, len: 35 } if rule == "Note_text"
+        ));
+        assert_eq!((errors[0].start, errors[0].end), (5, 40));
+    }
+
+    #[test]
+    fn recovered_failed_checks() {
+        let (note, errors) = grammar2::parse_recovering("note: this line runs on for far too long");
+        assert_eq!(note.unwrap().text, "this line runs on for far too long");
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0].reason,
+            ParseErrorReason::TokenTooLong { .. }
+        ));
+
+        // the escape is reported, and the leaf keeps it as it is
+        let (literal, errors) = grammar::parse_recovering(r#""ab\qc""#);
+        assert_eq!(literal, Some(Literal::Quoted(r"ab\qc".to_string())));
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(&errors[0].reason, ParseErrorReason::InvalidEscape(seq) if seq == r"\q"));
+    }
+
+    #[test]
+    fn any_character_leaves() {
+        let escapes = grammar3::parse("\\a\\\n\\\\\\\u{e9}").unwrap();
+        assert_eq!(
+            escapes
+                .escapes
+                .iter()
+                .map(|e| e.escaped)
+                .collect::<Vec<_>>(),
+            vec!['a', '\n', '\\', '\u{e9}']
+        );
+
+        // the leaf matches exactly one character
+        assert!(grammar3::parse("\\ab").is_err());
+        assert!(grammar3::parse("\\").is_err());
+    }
+
+    #[test]
+    fn trimmed_leaves() {
+        use grammar5::Value;
+
+        assert_eq!(
+            grammar5::parse(r#""hello world""#).unwrap(),
+            Value::Quoted("hello world".to_string())
+        );
+        assert_eq!(
+            grammar5::parse(r#""""#).unwrap(),
+            Value::Quoted(String::new())
+        );
+
+        // the transform receives the text without the trimmed characters
+        assert_eq!(grammar5::parse("#42;").unwrap(), Value::Tagged(42));
+    }
+
+    #[test]
+    fn borrowed_unescaped_leaves() {
+        use std::borrow::Cow;
+
+        let arena = rust_sitter::arena::Bump::new();
+        let input = r"plain a\nb";
+        let words = grammar4::parse_in(input, &arena).unwrap().words;
+        assert_eq!(words, vec!["plain", "a\nb"]);
+
+        // only the leaf with an escape sequence is copied out of the input
+        assert!(matches!(words[0], Cow::Borrowed(word) if std::ptr::eq(word, &input[..5])));
+        assert!(matches!(words[1], Cow::Owned(_)));
+    }
+
+    #[test]
+    fn large_borrowed_leaf() {
+        use std::borrow::Cow;
+
+        // a leaf of about a megabyte, as found in inputs with embedded data
+        let arena = rust_sitter::arena::Bump::new();
+        let input = format!("{} b\\n", "a".repeat(1 << 20));
+        let words = grammar4::parse_in(&input, &arena).unwrap().words;
+        assert!(matches!(words[0], Cow::Borrowed(word) if std::ptr::eq(word, &input[..1 << 20])));
+        assert_eq!(words[1], "b\n");
+    }
+}
@@ -0,0 +1,72 @@
+#[rust_sitter::grammar("supertypes")]
+pub mod grammar {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Program {
+        #[rust_sitter::repeat(non_empty = true)]
+        pub exprs: Vec<Expr>,
+    }
+
+    #[rust_sitter::supertype]
+    #[derive(PartialEq, Eq, Debug)]
+    pub enum Expr {
+        Number(Number),
+        Negation(Negation),
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    pub struct Number {
+        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+        pub value: u32,
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    pub struct Negation {
+        #[rust_sitter::leaf(text = "-")]
+        pub _minus: (),
+        pub value: Box<Expr>,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grammar::{Expr, Negation, Number};
+    use rust_sitter::tree_sitter::{Parser, Query, QueryCursor};
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    #[test]
+    fn supertype_extracts_variants() {
+        let program = grammar::parse("1 --2").unwrap();
+        assert_eq!(
+            program.exprs,
+            vec![
+                Expr::Number(Number { value: 1 }),
+                Expr::Negation(Negation {
+                    _minus: (),
+                    value: Box::new(Expr::Negation(Negation {
+                        _minus: (),
+                        value: Box::new(Expr::Number(Number { value: 2 })),
+                    })),
+                }),
+            ]
+        );
+    }
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    #[test]
+    fn supertype_query_matches_subtypes() {
+        let input = "1 -2";
+        let mut parser = Parser::new();
+        parser.set_language(grammar::language()).unwrap();
+        let tree = parser.parse(input, None).unwrap();
+
+        let query = Query::new(grammar::language(), "(Expr) @expr").unwrap();
+        let kinds = QueryCursor::new()
+            .captures(&query, tree.root_node(), input.as_bytes())
+            .map(|(m, i)| m.captures[i].node.kind())
+            .collect::<Vec<_>>();
+        assert_eq!(kinds, vec!["Expr_Number", "Expr_Negation", "Expr_Number"]);
+    }
+}
@@ -0,0 +1,263 @@
+/// Parses the two hex digits of a color channel.
+pub fn parse_hex(v: &str) -> u8 {
+    u8::from_str_radix(v, 16).unwrap()
+}
+
+#[rust_sitter::grammar("transforms")]
+pub mod grammar {
+    use super::parse_hex;
+
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Color {
+        #[rust_sitter::leaf(pattern = r"[a-z]+", transform = String::from)]
+        pub name: String,
+        #[rust_sitter::leaf(text = "#")]
+        _hash: (),
+        #[rust_sitter::leaf(pattern = r"[0-9a-f][0-9a-f]", transform = parse_hex)]
+        pub red: u8,
+        #[rust_sitter::leaf(pattern = r"[0-9a-f][0-9a-f]", transform = parse_hex)]
+        pub green: u8,
+        #[rust_sitter::leaf(pattern = r"[0-9a-f][0-9a-f]", transform = super::parse_hex)]
+        pub blue: u8,
+    }
+}
+
+/// Interns the names of a parse, so that each distinct name is stored once.
+#[derive(Default)]
+pub struct Interner {
+    pub names: Vec<String>,
+}
+
+impl Interner {
+    pub fn intern(&mut self, name: &str) -> usize {
+        self.names
+            .iter()
+            .position(|n| n == name)
+            .unwrap_or_else(|| {
+                self.names.push(name.to_string());
+                self.names.len() - 1
+            })
+    }
+}
+
+#[rust_sitter::grammar("transforms_context")]
+pub mod grammar2 {
+    use super::Interner;
+
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Names {
+        #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v: &str, ctx: &mut Interner| ctx.intern(v))]
+        pub names: Vec<usize>,
+    }
+}
+
+#[rust_sitter::grammar("transforms_validated")]
+pub mod grammar3 {
+    use std::cell::Cell;
+
+    thread_local! {
+        pub static PARSED_PERCENTS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// Parses a percentage, counting how many times it is called.
+    pub fn parse_percent(text: &str) -> u32 {
+        PARSED_PERCENTS.with(|p| p.set(p.get() + 1));
+        text.parse().unwrap()
+    }
+
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Progress {
+        #[rust_sitter::leaf(
+            pattern = r"\d+",
+            transform = parse_percent,
+            validate = |v: &u32| (0..=100).contains(v)
+        )]
+        pub percent: u32,
+        #[rust_sitter::leaf(text = "%")]
+        _percent_sign: (),
+        #[rust_sitter::leaf(
+            pattern = r"[a-z]+",
+            transform = String::from,
+            validate = |v: &String| if v.len() <= 8 { Ok(()) } else { Err("labels have at most 8 letters") }
+        )]
+        pub label: Option<String>,
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[rust_sitter::grammar("transforms_newtype")]
+pub mod grammar4 {
+    #[derive(PartialEq, Eq, Debug)]
+    pub struct LineNo(pub u32);
+
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Goto {
+        #[rust_sitter::leaf(text = "goto")]
+        _goto: (),
+        #[rust_sitter::leaf(pattern = r"\d+")]
+        pub line: LineNo,
+        #[rust_sitter::leaf(pattern = r"\d+")]
+        pub fallback: Option<LineNo>,
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[rust_sitter::grammar("transforms_post_parse")]
+pub mod grammar5 {
+    use std::cell::Cell;
+
+    thread_local! {
+        pub static NUMBERINGS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// Numbers the nodes of a tree in the order they appear in the input.
+    pub fn number_nodes(tree: &mut Tree) {
+        fn number(tree: &mut Tree, next: &mut usize) {
+            tree.id = *next;
+            *next += 1;
+            tree.children
+                .iter_mut()
+                .for_each(|child| number(child, next));
+        }
+
+        NUMBERINGS.with(|n| n.set(n.get() + 1));
+        number(tree, &mut 0);
+    }
+
+    #[rust_sitter::language]
+    #[rust_sitter::post_parse(number_nodes)]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Tree {
+        #[rust_sitter::skip(0)]
+        pub id: usize,
+        #[rust_sitter::leaf(text = "(")]
+        _open: (),
+        pub children: Vec<Tree>,
+        #[rust_sitter::leaf(text = ")")]
+        _close: (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn named_transforms() {
+        assert_eq!(parse_hex("c8"), 200);
+
+        let color = grammar::parse("teal #008080").unwrap();
+        assert_eq!(color.name, "teal");
+        assert_eq!((color.red, color.green, color.blue), (0, 128, 128));
+    }
+
+    #[test]
+    fn context_transforms() {
+        let mut interner = Interner::default();
+        let names = grammar2::parse_with_context("a b a c b", &mut interner).unwrap();
+        assert_eq!(names.names, vec![0, 1, 0, 2, 1]);
+        assert_eq!(interner.names, vec!["a", "b", "c"]);
+
+        // the context is shared across parses
+        let names = grammar2::parse_with_context("c d", &mut interner).unwrap();
+        assert_eq!(names.names, vec![2, 3]);
+
+        // the context follows extraction onto the thread with a larger stack
+        let names = rust_sitter::context::provide_context(&mut interner, || {
+            grammar2::parse_as_on_larger_stack::<grammar2::Names>("d e")
+        })
+        .unwrap();
+        assert_eq!(names.names, vec![3, 4]);
+    }
+
+    #[test]
+    #[should_panic(expected = "must be parsed with `parse_with_context`")]
+    fn context_transforms_without_context() {
+        let _ = grammar2::parse("a");
+    }
+
+    #[test]
+    fn validated_leaves() {
+        use grammar3::PARSED_PERCENTS;
+        use rust_sitter::errors::ParseErrorReason;
+
+        PARSED_PERCENTS.with(|p| p.set(0));
+        let progress = grammar3::parse("50% done").unwrap();
+        assert_eq!(
+            (progress.percent, progress.label.as_deref()),
+            (50, Some("done"))
+        );
+        // the value is validated as it is extracted rather than transformed twice
+        assert_eq!(PARSED_PERCENTS.with(|p| p.get()), 1);
+
+        let errors = grammar3::parse("150%").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0].reason,
+            ParseErrorReason::InvalidValue { text, message: None } if text == "150"
+        ));
+        assert_eq!((errors[0].start, errors[0].end), (0, 3));
+
+        let errors = grammar3::parse("50% unfinished").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!((errors[0].start, errors[0].end), (4, 14));
+        assert_eq!(
+            errors[0].to_string(),
+            "invalid value `unfinished`: labels have at most 8 letters at 4..14"
+        );
+    }
+
+    #[test]
+    fn newtype_leaves() {
+        use grammar4::LineNo;
+
+        let goto = grammar4::parse("goto 42").unwrap();
+        assert_eq!(goto.line, LineNo(42));
+        assert_eq!(goto.fallback, None);
+
+        let goto = grammar4::parse("goto 42 7").unwrap();
+        assert_eq!(goto.fallback, Some(LineNo(7)));
+    }
+
+    #[test]
+    fn post_parse_hook() {
+        use grammar5::{Tree, NUMBERINGS};
+
+        fn ids(tree: &Tree, out: &mut Vec<usize>) {
+            out.push(tree.id);
+            tree.children.iter().for_each(|child| ids(child, out));
+        }
+
+        NUMBERINGS.with(|n| n.set(0));
+        let tree = grammar5::parse("(()(()))").unwrap();
+        let mut numbered = vec![];
+        ids(&tree, &mut numbered);
+        assert_eq!(numbered, vec![0, 1, 2, 3]);
+        // the nested trees are of the root type too, but the hook only runs on the whole tree
+        assert_eq!(NUMBERINGS.with(|n| n.get()), 1);
+
+        let (recovered, errors) = grammar5::parse_recovering("(())");
+        assert!(errors.is_empty());
+        assert_eq!(recovered.unwrap().children[0].id, 1);
+        assert_eq!(NUMBERINGS.with(|n| n.get()), 2);
+    }
+}
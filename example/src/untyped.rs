@@ -0,0 +1,90 @@
+#[rust_sitter::grammar("untyped")]
+#[rust_sitter::untyped]
+pub mod grammar {
+    #[rust_sitter::language]
+    pub struct Config {
+        #[rust_sitter::repeat(non_empty = true)]
+        entries: Vec<Entry>,
+    }
+
+    pub struct Entry {
+        #[rust_sitter::leaf(pattern = r"[a-z]+")]
+        key: (),
+        #[rust_sitter::leaf(text = "=")]
+        _eq: (),
+        #[rust_sitter::leaf(pattern = r"\d+")]
+        value: (),
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[rust_sitter::grammar("untyped_tokens")]
+#[rust_sitter::tokenizer_only]
+pub mod grammar2 {
+    #[rust_sitter::language]
+    pub enum Expr {
+        Number(#[rust_sitter::leaf(pattern = r"\d+")] ()),
+        Name(#[rust_sitter::leaf(pattern = r"[a-z]+")] ()),
+        #[rust_sitter::prec_left(1)]
+        Add(Box<Expr>, #[rust_sitter::leaf(text = "+")] (), Box<Expr>),
+        Paren(#[rust_sitter::bracketed(open = "(", close = ")")] Box<Expr>),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn untyped_tree() {
+        let tree = grammar::parse("a = 1\nbc = 23").unwrap();
+        assert_eq!(tree.kind, "source_file");
+
+        let entries = &tree.child_by_field("entries").unwrap().children;
+        assert_eq!(entries.len(), 2);
+
+        let pairs: Vec<(&str, &str)> = entries
+            .iter()
+            .map(|e| {
+                assert_eq!(e.kind, "Entry");
+                (
+                    e.child_by_field("key").unwrap().text.as_str(),
+                    e.child_by_field("value").unwrap().text.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(pairs, vec![("a", "1"), ("bc", "23")]);
+        assert_eq!(entries[1].span, (6, 13));
+
+        assert!(grammar::parse("a = b").is_err());
+    }
+
+    #[test]
+    fn tokenizer_only() {
+        let tokens = grammar2::tokenize("(a + 12) 3 +").unwrap();
+        assert_eq!(
+            tokens
+                .iter()
+                .map(|token| (token.kind, token.text.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("(", "("),
+                ("Expr_Name_0", "a"),
+                ("Expr_Add_1", "+"),
+                ("Expr_Number_0", "12"),
+                (")", ")"),
+                ("Expr_Number_0", "3"),
+                ("Expr_Add_1", "+"),
+            ]
+        );
+
+        // only the tokens are checked, not whether they make up an expression
+        assert!(grammar2::tokenize("").unwrap().is_empty());
+        assert!(grammar2::tokenize("a - b").is_err());
+    }
+}
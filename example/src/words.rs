@@ -2,6 +2,7 @@
 pub mod grammar {
     #[rust_sitter::language]
     #[derive(Debug)]
+    #[allow(dead_code)]
     pub struct Words {
         #[rust_sitter::leaf(text = r"if")]
         _keyword: (),
@@ -17,6 +18,33 @@ pub mod grammar {
     }
 }
 
+#[rust_sitter::grammar("words_anchored")]
+pub mod grammar2 {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Items {
+        #[rust_sitter::repeat(non_empty = true)]
+        pub items: Vec<Item>,
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum Item {
+        Number(
+            #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap(), anchored = true)]
+             u32,
+        ),
+        Word(#[rust_sitter::leaf(pattern = r"[a-z]+", transform = String::from)] String),
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -28,4 +56,27 @@ mod tests {
         insta::assert_debug_snapshot!(grammar::parse("ifhello"));
         insta::assert_debug_snapshot!(grammar::parse("if hello"));
     }
+
+    #[test]
+    fn anchored_leaves() {
+        use grammar2::Item;
+        use rust_sitter::errors::ParseErrorReason;
+
+        assert_eq!(
+            grammar2::parse("123 abc").unwrap().items,
+            vec![Item::Number(123), Item::Word("abc".to_string())]
+        );
+
+        // the word is not anchored, so it stops where the number starts
+        assert_eq!(
+            grammar2::parse("abc123").unwrap().items,
+            vec![Item::Word("abc".to_string()), Item::Number(123)]
+        );
+
+        // the number is anchored, so it cannot be a prefix of `123abc`
+        let errors = grammar2::parse("x 123abc").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(&errors[0].reason, ParseErrorReason::UnexpectedToken(t) if t == "123abc"));
+        assert_eq!((errors[0].start, errors[0].end), (2, 8));
+    }
 }
@@ -0,0 +1,20 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // libfuzzer aborts in its panic hook, which would also catch the panics that
+    // `try_parse` turns into errors (such as a number overflowing in the transform), so
+    // the hook is only called for the panics that escape `try_parse`
+    static SKIP_CAUGHT_PANICS: std::sync::Once = std::sync::Once::new();
+    SKIP_CAUGHT_PANICS.call_once(|| {
+        let report = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            if !rust_sitter::errors::catching_extraction_panic() {
+                report(info)
+            }
+        }));
+    });
+
+    let _ = rust_sitter_fuzz::grammar::try_parse(data, 256);
+});
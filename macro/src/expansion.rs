@@ -3,7 +3,7 @@ use std::collections::HashSet;
 use proc_macro2::Span;
 use quote::ToTokens;
 use rust_sitter_common::*;
-use syn::{parse::Parse, punctuated::Punctuated, *};
+use syn::{parse::Parse, punctuated::Punctuated, visit_mut::VisitMut, *};
 
 fn is_sitter_attr(attr: &Attribute) -> bool {
     let ident = &attr.path.segments.iter().next().unwrap().ident;
@@ -24,99 +24,323 @@ impl ToTokens for ParamOrField {
     }
 }
 
-fn gen_field(path: String, ident_str: String, leaf: Field, out: &mut Vec<Item>) {
-    let extract_ident = Ident::new(&format!("extract_{path}"), Span::call_site());
-    let leaf_type = leaf.ty;
-
-    let leaf_attr = leaf
-        .attrs
-        .iter()
-        .find(|attr| attr.path == syn::parse_quote!(rust_sitter::leaf));
-
-    let leaf_params = leaf_attr.and_then(|a| {
-        a.parse_args_with(Punctuated::<NameValueExpr, Token![,]>::parse_terminated)
-            .ok()
-    });
+/// The generic parameters of the functions that extract the fields of a type, which take
+/// the lifetimes of the type, such as the `'a` of an AST extracted into an arena, along with
+/// the type of the source.
+fn extract_fn_generics(generics: &Generics) -> proc_macro2::TokenStream {
+    let lifetimes = generics.lifetimes();
+    quote::quote!(<#(#lifetimes,)* S: rust_sitter::Source + ?Sized>)
+}
 
-    let transform_param = leaf_params.as_ref().and_then(|p| {
-        p.iter()
-            .find(|param| param.path == "transform")
-            .map(|p| p.expr.clone())
-    });
+/// Adds the extraction functions of the fields and variants of a type to the start of the
+/// `extract` method of its `Extract` impl. They are added to the parsed impl rather than
+/// interpolated into its tokens, since parsing every one of them a second time took up most
+/// of the expansion of large grammars.
+fn with_extract_fns(mut extract_impl: ItemImpl, extract_fns: Vec<Item>) -> Item {
+    let extract = extract_impl
+        .items
+        .iter_mut()
+        .find_map(|item| match item {
+            ImplItem::Method(m) if m.sig.ident == "extract" => Some(m),
+            _ => None,
+        })
+        .unwrap();
 
-    let mut skip_over = HashSet::new();
-    skip_over.insert("Spanned");
-    skip_over.insert("Box");
+    // after the `let node = node.unwrap();` that starts the method
+    extract
+        .block
+        .stmts
+        .splice(1..1, extract_fns.into_iter().map(Stmt::Item));
 
-    let (leaf_stmts, leaf_expr): (Vec<Stmt>, Expr) = match transform_param {
-        Some(closure) => {
-            let mut non_leaf = HashSet::new();
-            non_leaf.insert("Spanned");
-            non_leaf.insert("Box");
-            non_leaf.insert("Option");
-            non_leaf.insert("Vec");
-            let wrapped_leaf_type = wrap_leaf_type(&leaf_type, &non_leaf);
-
-            (
-                vec![],
-                syn::parse_quote!(<#wrapped_leaf_type as rust_sitter::Extract<_>>::extract(node, source, *last_idx, Some(&#closure))),
-            )
-        }
-        None => (
-            vec![],
-            syn::parse_quote!(<#leaf_type as rust_sitter::Extract<_>>::extract(node, source, *last_idx, None)),
-        ),
-    };
+    Item::Impl(extract_impl)
+}
 
-    out.push(syn::parse_quote! {
+/// The function that extracts a field, which is parsed once per grammar and then filled in
+/// for each field, since parsing it for every field took up most of the expansion of large
+/// grammars. The generics of the function are set for each type, and its name and type for
+/// each field, along with the placeholders for the field's name, the rule and Rust path it
+/// is traced with, and the expression that extracts its value.
+fn field_extract_template() -> ItemFn {
+    syn::parse_quote! {
         #[allow(non_snake_case)]
         #[allow(clippy::unused_unit)]
-        fn #extract_ident(cursor_opt: &mut Option<rust_sitter::tree_sitter::TreeCursor>, source: &[u8], last_idx: &mut usize) -> #leaf_type {
-            #(#leaf_stmts)*
-
+        fn extract_field(cursor_opt: &mut Option<rust_sitter::tree_sitter::TreeCursor>, source: &S, last_idx: &mut (usize, rust_sitter::Point)) -> () {
             if let Some(cursor) = cursor_opt.as_mut() {
                 loop {
                     let n = cursor.node();
                     if let Some(name) = cursor.field_name() {
-                        if name == #ident_str {
+                        if name == __field_name {
+                            rust_sitter::trace::populate_field(__rule, __rust_path, &n);
                             let node: Option<rust_sitter::tree_sitter::Node> = Some(n);
-                            let out = #leaf_expr;
+                            let out = __leaf_expr;
 
                             if !cursor.goto_next_sibling() {
                                 *cursor_opt = None;
                             };
 
-                            *last_idx = n.end_byte();
+                            *last_idx = (n.end_byte(), rust_sitter::Point::from(n.end_position()));
 
                             return out;
                         } else {
                             let node: Option<rust_sitter::tree_sitter::Node> = None;
-                            return #leaf_expr;
+                            return __leaf_expr;
                         }
                     } else {
-                        *last_idx = n.end_byte();
+                        *last_idx = (n.end_byte(), rust_sitter::Point::from(n.end_position()));
                     }
 
                     if !cursor.goto_next_sibling() {
                         let node: Option<rust_sitter::tree_sitter::Node> = None;
-                        return #leaf_expr;
+                        return __leaf_expr;
                     }
                 }
             } else {
                 let node: Option<rust_sitter::tree_sitter::Node> = None;
-                return #leaf_expr;
+                return __leaf_expr;
+            }
+        }
+    }
+}
+
+/// Replaces the placeholder paths of the [`field_extract_template`] with their expressions.
+struct FillPlaceholders<'a>(&'a [(&'a str, Expr)]);
+
+impl VisitMut for FillPlaceholders<'_> {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        if let Expr::Path(ExprPath { path, .. }) = expr {
+            if let Some((_, value)) = self.0.iter().find(|(name, _)| path.is_ident(name)) {
+                *expr = value.clone();
+                return;
+            }
+        }
+
+        visit_mut::visit_expr_mut(self, expr);
+    }
+}
+
+fn gen_field(
+    path: String,
+    ident_str: String,
+    rule: &str,
+    rust_path: String,
+    leaf: Field,
+    template: &ItemFn,
+    out: &mut Vec<Item>,
+) {
+    let extract_ident = Ident::new(&format!("extract_{path}"), Span::call_site());
+    let leaf_type = leaf.ty.clone();
+    let leaf_expr = gen_leaf_expr(&path, &leaf);
+
+    let mut extract_fn = template.clone();
+    extract_fn.sig.ident = extract_ident;
+    extract_fn.sig.output = syn::parse_quote!(-> #leaf_type);
+    FillPlaceholders(&[
+        ("__field_name", syn::parse_quote!(#ident_str)),
+        ("__rule", syn::parse_quote!(#rule)),
+        ("__rust_path", syn::parse_quote!(#rust_path)),
+        ("__leaf_expr", leaf_expr),
+    ])
+    .visit_block_mut(&mut extract_fn.block);
+    out.push(Item::Fn(extract_fn));
+}
+
+/// Builds the expression that extracts the value of a field from its `node`, if it has one,
+/// given the `source` and the `last_idx` before the field.
+fn gen_leaf_expr(path: &str, leaf: &Field) -> Expr {
+    let leaf_type = &leaf.ty;
+    let leaf_attrs = expand_operators(&leaf.attrs);
+
+    // an optional field is extracted as an `Option` of its type, which falls back to the
+    // default value of the type when the field is absent
+    let is_optional = is_optional_field(&leaf_attrs, leaf_type);
+    let extract_type: Type = if is_optional {
+        syn::parse_quote!(Option<#leaf_type>)
+    } else {
+        leaf_type.clone()
+    };
+
+    let leaf_attr = leaf_attrs
+        .iter()
+        .find(|attr| attr.path == syn::parse_quote!(rust_sitter::leaf));
+
+    let leaf_params = leaf_attr.and_then(|a| {
+        a.parse_args_with(Punctuated::<NameValueExpr, Token![,]>::parse_terminated)
+            .ok()
+    });
+
+    let transform_param = leaf_params.as_ref().and_then(|p| {
+        p.iter()
+            .find(|param| param.path == "transform")
+            .map(|p| p.expr.clone())
+    });
+
+    let validate_param = leaf_params.as_ref().and_then(|p| {
+        p.iter()
+            .find(|param| param.path == "validate")
+            .map(|p| p.expr.clone())
+    });
+
+    let alternatives_param = leaf_params.as_ref().and_then(|p| {
+        p.iter()
+            .find(|param| param.path == "alternatives")
+            .map(|p| parse_leaf_alternatives(&p.expr))
+    });
+
+    let mut non_leaf = HashSet::new();
+    non_leaf.insert("Spanned");
+    non_leaf.insert("Box");
+    non_leaf.insert("Option");
+    non_leaf.extend(REPEATED_COLLECTIONS);
+
+    let leaf_expr: Expr = if let Some(validate) = validate_param {
+        if alternatives_param.is_some() || find_bool_param(&leaf_attrs, "keep_raw") {
+            panic!("`validate` cannot be combined with `alternatives` or `keep_raw` on the leaf for `{path}`");
+        }
+
+        // the value is validated as it is extracted, so the transform only runs once
+        let transform = leaf_transform(transform_param, &leaf_attrs, path).unwrap_or_else(|| {
+            panic!("`validate` on the leaf for `{path}` needs a `transform` to produce the value it validates")
+        });
+        let wrapped_leaf_type = wrap_leaf_type_in(
+            &extract_type,
+            &non_leaf,
+            &syn::parse_quote!(rust_sitter::validation::WithValidation),
+        );
+
+        syn::parse_quote!(<#wrapped_leaf_type as rust_sitter::Extract<_>>::extract(node, source, *last_idx, Some(&|text: &str| {
+            let transform: &dyn Fn(&str) -> _ = &#transform;
+            let value = transform(text);
+            let result = rust_sitter::validation::validate(&value, #validate);
+            (value, result)
+        })))
+    } else if let Some(alternatives) = alternatives_param {
+        if transform_param.is_some() || find_bool_param(&leaf_attrs, "unescape") {
+            panic!("`alternatives` cannot be combined with `transform` or `unescape` on the leaf for `{path}`");
+        }
+
+        // the kind of the matched node is the rule of its alternative
+        let kinds = (0..alternatives.len()).map(|i| LeafAlternative::rule_name(path, i));
+        let transforms = alternatives
+            .into_iter()
+            .map(|a| leaf_transform(Some(a.transform), &leaf_attrs, path).unwrap());
+        let wrapped_leaf_type = wrap_leaf_type_in(
+            &extract_type,
+            &non_leaf,
+            &syn::parse_quote!(rust_sitter::WithAlternatives),
+        );
+
+        syn::parse_quote!(<#wrapped_leaf_type as rust_sitter::Extract<_>>::extract(node, source, *last_idx, Some(&|kind: &str, v: &str| match kind {
+            #(#kinds => (#transforms)(v),)*
+            _ => unreachable!(),
+        })))
+    } else if transform_param.is_none()
+        && find_bool_param(&leaf_attrs, "unescape")
+        && find_trim_chars(&leaf_attrs).is_none()
+        && !find_bool_param(&leaf_attrs, "keep_raw")
+        && is_cow_leaf(&extract_type, &non_leaf)
+    {
+        // a `Cow<str>` borrows the input when the leaf has no escape sequences
+        let wrapped_leaf_type = wrap_leaf_type_in(
+            &extract_type,
+            &non_leaf,
+            &syn::parse_quote!(rust_sitter::unescape::WithUnescapedCow),
+        );
+
+        syn::parse_quote!(<#wrapped_leaf_type as rust_sitter::Extract<_>>::extract(node, source, *last_idx, None))
+    } else {
+        match leaf_transform(transform_param, &leaf_attrs, path) {
+            Some(closure) => {
+                let wrapped_leaf_type = wrap_leaf_type(&extract_type, &non_leaf);
+
+                syn::parse_quote!(<#wrapped_leaf_type as rust_sitter::Extract<_>>::extract(node, source, *last_idx, Some(&#closure)))
+            }
+            None => {
+                syn::parse_quote!(<#extract_type as rust_sitter::Extract<_>>::extract(node, source, *last_idx, None))
             }
         }
+    };
+    if is_optional {
+        syn::parse_quote!(#leaf_expr.unwrap_or_default())
+    } else {
+        leaf_expr
+    }
+}
+
+/// Whether the leaf of a field, inside of any wrappers such as `Option`, is a `Cow`.
+fn is_cow_leaf(ty: &Type, skip_over: &HashSet<&str>) -> bool {
+    match filter_inner_type(ty, skip_over) {
+        Type::Path(p) => p.path.segments.last().unwrap().ident == "Cow",
+        _ => false,
+    }
+}
+
+/// Builds the function applied to the text of a leaf from its `transform`, taking into
+/// account the `trim`, `unescape` and `keep_raw` parameters of the leaf.
+fn leaf_transform(transform: Option<Expr>, attrs: &[Attribute], path: &str) -> Option<Expr> {
+    // a path to a function (such as `parse_hex` or `String::from`) is called from a closure,
+    // so that functions that are generic over the lifetime of the text are accepted too
+    let transform: Option<Expr> = transform.map(|t| match t {
+        Expr::Path(path) => syn::parse_quote!(|v| #path(v)),
+        // a second parameter borrows the context given to `parse_with_context`
+        Expr::Closure(c) if c.inputs.len() == 2 => syn::parse_quote!(
+            |v| rust_sitter::context::with_context(|ctx| (#c)(v, ctx))
+        ),
+        t => t,
     });
+
+    // escape sequences are processed before the text reaches the transform
+    let transform: Option<Expr> = match (transform, find_bool_param(attrs, "unescape")) {
+        (Some(closure), true) => {
+            Some(syn::parse_quote!(rust_sitter::unescape::unescaped(#closure)))
+        }
+        (None, true) => Some(syn::parse_quote!(rust_sitter::unescape::unescaped(
+            |v| v.to_string()
+        ))),
+        (transform, false) => transform,
+    };
+
+    // the trimmed characters are removed before escape sequences are processed
+    let transform: Option<Expr> = match find_trim_chars(attrs) {
+        Some((start, end)) => {
+            let transform = transform.unwrap_or_else(|| syn::parse_quote!(|v| v.to_string()));
+            Some(syn::parse_quote!(rust_sitter::trimmed(#start, #end, #transform)))
+        }
+        None => transform,
+    };
+
+    // the raw text is kept from before any escape sequences are processed
+    if find_bool_param(attrs, "keep_raw") {
+        let transform = transform.unwrap_or_else(|| {
+            panic!("`keep_raw` requires a `transform` or `unescape` on the leaf for `{path}`")
+        });
+        Some(syn::parse_quote!(rust_sitter::with_raw(#transform)))
+    } else {
+        transform
+    }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn gen_struct_or_variant(
     path: String,
     fields: Fields,
     variant_ident: Option<Ident>,
     containing_type: Ident,
+    generics: &Generics,
+    unordered: bool,
+    rule_case: RuleCase,
+    template: &ItemFn,
     out: &mut Vec<Item>,
 ) {
+    // the Rust path of the type or variant, which labels the rule in trace logs
+    let type_path = match &variant_ident {
+        Some(variant_ident) => format!("{containing_type}::{variant_ident}"),
+        None => containing_type.to_string(),
+    };
+
+    let extract_generics = extract_fn_generics(generics);
+    let mut template = template.clone();
+    template.sig.generics = syn::parse2(extract_generics.clone()).unwrap();
+
     fields.iter().enumerate().for_each(|(i, field)| {
         let ident_str = field
             .ident
@@ -130,15 +354,51 @@ fn gen_struct_or_variant(
             .any(|attr| attr.path == syn::parse_quote!(rust_sitter::skip))
         {
             gen_field(
-                format!("{}_{}", path.clone(), ident_str),
-                ident_str,
+                rule_case.apply(&format!("{}_{}", path.clone(), ident_str)),
+                ts_field_name(field, i),
+                &path,
+                format!("{type_path}.{ident_str}"),
                 field.clone(),
+                &template,
                 out,
             );
         }
     });
 
     let extract_ident = Ident::new(&format!("extract_{path}"), Span::call_site());
+    let (_, ty_generics, _) = generics.split_for_impl();
+
+    if let (Fields::Unit, Some(variant_ident)) = (&fields, &variant_ident) {
+        // unit variants (such as ones that are a single leaf) have nothing to extract
+        out.push(syn::parse_quote! {
+            #[allow(non_snake_case)]
+            fn #extract_ident #extract_generics(node: rust_sitter::tree_sitter::Node, _source: &S) -> #containing_type #ty_generics {
+                rust_sitter::trace::enter_rule(#path, #type_path, &node);
+                #containing_type::#variant_ident
+            }
+        });
+
+        return;
+    }
+
+    let field_names = fields
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| {
+            !field
+                .attrs
+                .iter()
+                .any(|attr| attr.path == syn::parse_quote!(rust_sitter::skip))
+        })
+        .map(|(i, field)| ts_field_name(field, i))
+        .collect::<Vec<String>>();
+    // the fields of an unordered type can be missing, which is reported as a parse error
+    let required_field_names = fields
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| !unordered && is_required_field(field))
+        .map(|(i, field)| ts_field_name(field, i))
+        .collect::<Vec<String>>();
 
     let mut have_named_field = false;
 
@@ -164,15 +424,24 @@ fn gen_struct_or_variant(
                     Span::call_site(),
                 );
 
-                syn::parse_quote! {
-                    #ident(&mut cursor, source, &mut last_idx)
+                if unordered {
+                    // the fields can appear in any order, so each one is found on its own
+                    let field_name = ts_field_name(field, i);
+                    syn::parse_quote! {
+                        {
+                            let mut cursor = rust_sitter::field_cursor(node, #field_name);
+                            let mut last_idx = (node.start_byte(), rust_sitter::Point::from(node.start_position()));
+                            #ident(&mut cursor, source, &mut last_idx)
+                        }
+                    }
+                } else {
+                    syn::parse_quote! {
+                        #ident(&mut cursor, source, &mut last_idx)
+                    }
                 }
             };
 
-            if field.ident.is_none() {
-                ParamOrField::Param(expr)
-            } else {
-                let field_name = field.ident.as_ref().unwrap();
+            if let Some(field_name) = &field.ident {
                 have_named_field = true;
                 ParamOrField::Field(FieldValue {
                     attrs: vec![],
@@ -180,12 +449,19 @@ fn gen_struct_or_variant(
                     colon_token: Some(Token![:](Span::call_site())),
                     expr,
                 })
+            } else {
+                ParamOrField::Param(expr)
             }
         })
         .collect::<Vec<ParamOrField>>();
 
     let construct_expr: syn::Expr = if let Some(variant_ident) = variant_ident {
-        if have_named_field {
+        if children_parsed.is_empty() {
+            // braces construct empty tuple and struct variants alike
+            syn::parse_quote! {
+                #containing_type::#variant_ident {}
+            }
+        } else if have_named_field {
             syn::parse_quote! {
                 #containing_type::#variant_ident {
                     #(#children_parsed),*
@@ -198,176 +474,1547 @@ fn gen_struct_or_variant(
                 )
             }
         }
-    } else {
+    } else if have_named_field {
         syn::parse_quote! {
             #containing_type {
                 #(#children_parsed),*
             }
         }
+    } else {
+        // tuple structs, such as a root that is just a list of items
+        syn::parse_quote! {
+            #containing_type(
+                #(#children_parsed),*
+            )
+        }
     };
 
-    out.push(syn::parse_quote! {
-        #[allow(non_snake_case)]
-        fn #extract_ident(node: rust_sitter::tree_sitter::Node, source: &[u8]) -> #containing_type {
-            let mut last_idx = node.start_byte();
+    let cursor_stmts: Vec<Stmt> = if unordered {
+        vec![]
+    } else {
+        syn::parse_quote! {
+            let mut last_idx = (node.start_byte(), rust_sitter::Point::from(node.start_position()));
             let mut parent_cursor = node.walk();
             let mut cursor = if parent_cursor.goto_first_child() {
                 Some(parent_cursor)
             } else {
                 None
             };
+        }
+    };
+
+    out.push(syn::parse_quote! {
+        #[allow(non_snake_case)]
+        fn #extract_ident #extract_generics(node: rust_sitter::tree_sitter::Node, source: &S) -> #containing_type #ty_generics {
+            rust_sitter::trace::enter_rule(#path, #type_path, &node);
+
+            #[cfg(debug_assertions)]
+            rust_sitter::check_fields(node, #path, &[#(#field_names),*], &[#(#required_field_names),*]);
+
+            #(#cursor_stmts)*
 
             #construct_expr
         }
     });
 }
 
-pub fn expand_grammar(input: ItemMod) -> ItemMod {
-    let grammar_name = input
-        .attrs
-        .iter()
-        .find_map(|a| {
-            if a.path == syn::parse_quote!(rust_sitter::grammar) {
-                let grammar_name_expr = a.parse_args_with(Expr::parse).ok();
-                if let Some(Expr::Lit(ExprLit {
-                    attrs: _,
-                    lit: Lit::Str(s),
-                })) = grammar_name_expr
-                {
-                    Some(s.value())
-                } else {
-                    panic!("Expected string literal for grammar name");
+/// Computes the parameter type and the field value of a generated constructor for a
+/// field of the given type, so that any `Box` and `Spanned` wrappers are filled in.
+fn constructor_param(ty: &Type, value: Expr) -> (Type, Expr) {
+    if let Type::Path(p) = ty {
+        let last = p.path.segments.last().unwrap();
+        if let PathArguments::AngleBracketed(args) = &last.arguments {
+            if let (1, Some(GenericArgument::Type(inner))) = (args.args.len(), args.args.first()) {
+                if last.ident == "Box" {
+                    let (inner_ty, inner_value) = constructor_param(inner, value);
+                    return (inner_ty, syn::parse_quote!(Box::new(#inner_value)));
+                } else if last.ident == "Spanned" {
+                    let (inner_ty, inner_value) = constructor_param(inner, value);
+                    return (
+                        inner_ty,
+                        syn::parse_quote!(rust_sitter::Spanned {
+                            value: #inner_value,
+                            span: (0, 0),
+                            points: Default::default()
+                        }),
+                    );
                 }
-            } else {
-                None
             }
-        })
-        .expect("Each grammar must have a name");
+        }
+    }
 
-    let (brace, new_contents) = input.content.unwrap();
+    (ty.clone(), value)
+}
 
-    let root_type = new_contents
+/// Computes the parameters of a generated constructor for the given fields, along with the
+/// value of each field. Leaves of type `()` and skipped fields are filled in automatically.
+fn constructor_params(fields: &Fields) -> (Vec<FnArg>, Vec<Expr>) {
+    let mut params: Vec<FnArg> = vec![];
+    let values = fields
         .iter()
-        .find_map(|item| match item {
-            Item::Enum(ItemEnum { ident, attrs, .. })
-            | Item::Struct(ItemStruct { ident, attrs, .. }) => {
-                if attrs
-                    .iter()
-                    .any(|attr| attr.path == syn::parse_quote!(rust_sitter::language))
-                {
-                    Some(ident.clone())
-                } else {
-                    None
-                }
+        .enumerate()
+        .map(|(i, field)| {
+            if let Some(skip_attr) = field
+                .attrs
+                .iter()
+                .find(|attr| attr.path == syn::parse_quote!(rust_sitter::skip))
+            {
+                return skip_attr.parse_args::<Expr>().unwrap();
             }
-            _ => None,
+
+            if field.ty == syn::parse_quote!(()) {
+                return syn::parse_quote!(());
+            }
+
+            let param_ident = field
+                .ident
+                .clone()
+                .unwrap_or_else(|| Ident::new(&format!("v{i}"), Span::call_site()));
+            let (param_ty, value) = constructor_param(&field.ty, syn::parse_quote!(#param_ident));
+            params.push(syn::parse_quote!(#param_ident: #param_ty));
+            value
         })
-        .expect("Each parser must have the root type annotated with `#[rust_sitter::language]`");
+        .collect::<Vec<Expr>>();
 
-    let mut transformed: Vec<Item> = new_contents
-        .iter()
-        .cloned()
-        .flat_map(|c| match c {
-            Item::Enum(mut e) => {
-                let mut impl_body = vec![];
-                e.variants.iter().for_each(|v| {
-                    gen_struct_or_variant(
-                        format!("{}_{}", e.ident, v.ident),
-                        v.fields.clone(),
-                        Some(v.ident.clone()),
-                        e.ident.clone(),
-                        &mut impl_body,
-                    )
-                });
+    (params, values)
+}
 
-                let match_cases: Vec<Arm> = e
-                    .variants
-                    .iter()
-                    .map(|v| {
-                        let variant_path = format!("{}_{}", e.ident, v.ident);
-                        let extract_ident =
-                            Ident::new(&format!("extract_{variant_path}"), Span::call_site());
-                        syn::parse_quote! {
-                            #variant_path => return #extract_ident(n, source)
-                        }
-                    })
-                    .collect();
+/// Generates a `new` constructor for a tuple struct, which takes only the fields that hold
+/// values, so that the positions of its `()` leaves do not have to be filled in by hand.
+fn gen_tuple_struct_constructor(s: &ItemStruct) -> Option<Item> {
+    if !matches!(s.fields, Fields::Unnamed(_)) {
+        return None;
+    }
 
-                e.attrs.retain(|a| !is_sitter_attr(a));
-                e.variants.iter_mut().for_each(|v| {
-                    v.attrs.retain(|a| !is_sitter_attr(a));
-                    v.fields.iter_mut().for_each(|f| {
-                        f.attrs.retain(|a| !is_sitter_attr(a));
-                    });
-                });
+    let (params, values) = constructor_params(&s.fields);
+    let struct_name = &s.ident;
+    let (impl_generics, ty_generics, where_clause) = s.generics.split_for_impl();
+    let doc = format!(" Constructs a [`{struct_name}`], with any boxes and spans filled in.");
+    Some(syn::parse_quote! {
+        impl #impl_generics #struct_name #ty_generics #where_clause {
+            #[doc = #doc]
+            #[allow(clippy::too_many_arguments)]
+            pub fn new(#(#params),*) -> Self {
+                #struct_name(#(#values),*)
+            }
+        }
+    })
+}
 
-                let enum_name = &e.ident;
-                let extract_impl: Item = syn::parse_quote! {
-                    impl rust_sitter::Extract<#enum_name> for #enum_name {
-                        type LeafFn = ();
+/// Returns the type wrapped by each variant of an enum marked with
+/// `#[rust_sitter::token_group(...)]`, along with the node kind of its rule, since the
+/// hidden rule of the group leaves the nodes of its members in the tree.
+fn token_group_members(
+    e: &ItemEnum,
+    rule_prefix: &str,
+    rule_case: RuleCase,
+) -> Option<Vec<(Type, String)>> {
+    if !e
+        .attrs
+        .iter()
+        .any(|a| a.path == syn::parse_quote!(rust_sitter::token_group))
+    {
+        return None;
+    }
 
-                        #[allow(non_snake_case)]
-                        fn extract(node: Option<rust_sitter::tree_sitter::Node>, source: &[u8], _last_idx: usize, _leaf_fn: Option<&Self::LeafFn>) -> Self {
-                            let node = node.unwrap();
-                            #(#impl_body)*
+    let mut skip_over = HashSet::new();
+    skip_over.insert("Spanned");
+    skip_over.insert("Box");
 
-                            let mut cursor = node.walk();
-                            assert!(cursor.goto_first_child());
-                            loop {
-                                let n = cursor.node();
-                                match n.kind() {
-                                    #(#match_cases),*,
-                                    _ => if !cursor.goto_next_sibling() {
-                                        panic!("Could not find a child corresponding to any enum branch")
-                                    }
-                                }
-                            }
+    Some(
+        e.variants
+            .iter()
+            .map(|v| match &v.fields {
+                Fields::Unnamed(f) if f.unnamed.len() == 1 => {
+                    let ty = f.unnamed[0].ty.clone();
+                    match filter_inner_type(&ty, &skip_over) {
+                        Type::Path(p) if p.path.segments.len() == 1 => {
+                            let kind = rule_case
+                                .apply(&format!("{rule_prefix}{}", p.path.segments[0].ident));
+                            (ty, kind)
                         }
+                        _ => panic!("`{}` is a token group, so each of its variants must wrap a single type, but `{}_{}` does not", e.ident, e.ident, v.ident),
                     }
-                };
+                }
+                _ => panic!("`{}` is a token group, so each of its variants must wrap a single type, but `{}_{}` does not", e.ident, e.ident, v.ident),
+            })
+            .collect(),
+    )
+}
+
+/// Generates a constructor for an enum variant, named after the variant in snake case,
+/// that takes the variant's fields without their boxes and spans. Leaves of type `()`
+/// and skipped fields are filled in automatically.
+fn gen_constructor(enum_name: &Ident, variant: &Variant) -> Option<ImplItem> {
+    if matches!(variant.fields, Fields::Unit) {
+        return None;
+    }
 
-                vec![Item::Enum(e), extract_impl]
+    let mut name = String::new();
+    variant
+        .ident
+        .to_string()
+        .chars()
+        .enumerate()
+        .for_each(|(i, c)| {
+            if c.is_uppercase() && i > 0 {
+                name.push('_');
             }
+            name.extend(c.to_lowercase());
+        });
 
-            Item::Struct(mut s) => {
-                let mut impl_body = vec![];
+    // the generated `kind` method takes precedence
+    if name == "kind" {
+        return None;
+    }
 
-                gen_struct_or_variant(
-                    s.ident.to_string(),
-                    s.fields.clone(),
-                    None,
-                    s.ident.clone(),
-                    &mut impl_body,
-                );
+    let fn_ident =
+        syn::parse_str::<Ident>(&name).unwrap_or_else(|_| Ident::new_raw(&name, Span::call_site()));
 
-                s.attrs.retain(|a| !is_sitter_attr(a));
-                s.fields.iter_mut().for_each(|f| {
-                    f.attrs.retain(|a| !is_sitter_attr(a));
-                });
+    let (params, values) = constructor_params(&variant.fields);
 
-                let struct_name = &s.ident;
-                let extract_ident =
-                    Ident::new(&format!("extract_{struct_name}"), Span::call_site());
+    let variant_ident = &variant.ident;
+    let construct_expr: Expr = match &variant.fields {
+        Fields::Named(named) => {
+            let field_idents = named.named.iter().map(|f| f.ident.as_ref().unwrap());
+            syn::parse_quote! {
+                #enum_name::#variant_ident {
+                    #(#field_idents: #values),*
+                }
+            }
+        }
+        _ => syn::parse_quote! {
+            #enum_name::#variant_ident(#(#values),*)
+        },
+    };
 
-                let extract_impl: Item = syn::parse_quote! {
-                    impl rust_sitter::Extract<#struct_name> for #struct_name {
-                        type LeafFn = ();
+    let doc = format!(
+        " Constructs an [`{enum_name}::{variant_ident}`], with any boxes and spans filled in."
+    );
+    Some(syn::parse_quote! {
+        #[doc = #doc]
+        #[allow(clippy::too_many_arguments)]
+        pub fn #fn_ident(#(#params),*) -> Self {
+            #construct_expr
+        }
+    })
+}
 
-                        #[allow(non_snake_case)]
-                        fn extract(node: Option<rust_sitter::tree_sitter::Node>, source: &[u8], last_idx: usize, _leaf_fn: Option<&Self::LeafFn>) -> Self {
-                            let node = node.unwrap();
-                            #(#impl_body)*
-                            #extract_ident(node, source)
-                        }
+fn has_alias_or_extra(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|a| {
+        a.path == syn::parse_quote!(rust_sitter::alias)
+            || a.path == syn::parse_quote!(rust_sitter::extra)
+    })
+}
+
+fn find_keyword_param(attrs: &[Attribute]) -> Option<String> {
+    attrs
+        .iter()
+        .find(|attr| attr.path == syn::parse_quote!(rust_sitter::keyword))?
+        .parse_args::<LitStr>()
+        .ok()
+        .map(|s| s.value())
+}
+
+/// Collects the rule names and literal texts of the `text` leaves in the given fields,
+/// following the same naming scheme as the generated grammar.
+fn collect_text_tokens(path: &str, fields: &Fields, out: &mut Vec<(String, String)>) {
+    fields.iter().enumerate().for_each(|(i, field)| {
+        let ident_str = field
+            .ident
+            .as_ref()
+            .map(|v| v.to_string())
+            .unwrap_or(format!("{i}"));
+        let field_path = format!("{path}_{ident_str}");
+
+        if let Some(text) = find_text_param(&field.attrs) {
+            out.push((field_path.clone(), text));
+        }
+
+        let delimiter = expand_repeat_separator(&field.attrs)
+            .into_iter()
+            .find(|attr| attr.path == syn::parse_quote!(rust_sitter::delimited))
+            .and_then(|a| a.parse_args_with(FieldThenParams::parse).ok());
+        if let Some(text) = delimiter.and_then(|d| find_text_param(&d.field.attrs)) {
+            out.push((format!("{field_path}_vec_delimiter"), text));
+        }
+    });
+}
+
+/// Reads the `trim`, `trim_start` and `trim_end` parameters of the leaf attribute into the
+/// characters to remove from the start and from the end of the text of the leaf.
+fn find_trim_chars(attrs: &[Attribute]) -> Option<(String, String)> {
+    let leaf_params = attrs
+        .iter()
+        .find(|attr| attr.path == syn::parse_quote!(rust_sitter::leaf))
+        .and_then(|a| {
+            a.parse_args_with(Punctuated::<NameValueExpr, Token![,]>::parse_terminated)
+                .ok()
+        })?;
+
+    let chars = |name: &str| {
+        leaf_params
+            .iter()
+            .find(|param| param.path == name)
+            .map(|p| match &p.expr {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(s), ..
+                }) => s.value(),
+                _ => panic!("Expected a string of the characters to remove for {name}"),
+            })
+    };
+    let (trim, trim_start, trim_end) = (chars("trim"), chars("trim_start"), chars("trim_end"));
+    if trim.is_none() && trim_start.is_none() && trim_end.is_none() {
+        return None;
+    }
+
+    let trim = trim.unwrap_or_default();
+    Some((
+        trim.clone() + &trim_start.unwrap_or_default(),
+        trim + &trim_end.unwrap_or_default(),
+    ))
+}
+
+/// Reads a boolean parameter of the leaf attribute, such as `unescape`, defaulting to `false`.
+fn find_bool_param(attrs: &[Attribute], name: &str) -> bool {
+    let leaf_params = attrs
+        .iter()
+        .find(|attr| attr.path == syn::parse_quote!(rust_sitter::leaf))
+        .and_then(|a| {
+            a.parse_args_with(Punctuated::<NameValueExpr, Token![,]>::parse_terminated)
+                .ok()
+        });
+
+    leaf_params
+        .iter()
+        .flatten()
+        .find(|param| param.path == name)
+        .map(|p| match &p.expr {
+            Expr::Lit(ExprLit {
+                lit: Lit::Bool(b), ..
+            }) => b.value,
+            _ => panic!("Expected boolean literal for {name}"),
+        })
+        .unwrap_or(false)
+}
+
+/// Generates a pattern binding the fields that are parsed from the input, each to an
+/// identifier made of the prefix and the field name, along with those identifiers.
+fn semantic_eq_pattern(fields: &Fields, prefix: &str) -> (proc_macro2::TokenStream, Vec<Ident>) {
+    let mut bound = vec![];
+    let mut bind = |i: usize, field: &Field| -> Option<Ident> {
+        if field
+            .attrs
+            .iter()
+            .any(|attr| attr.path == syn::parse_quote!(rust_sitter::skip))
+        {
+            return None;
+        }
+
+        let ident_str = field
+            .ident
+            .as_ref()
+            .map(|v| v.to_string())
+            .unwrap_or(format!("{i}"));
+        let ident = Ident::new(&format!("{prefix}_{ident_str}"), Span::call_site());
+        bound.push(ident.clone());
+        Some(ident)
+    };
+
+    let pattern = match fields {
+        Fields::Named(named) => {
+            let members = named
+                .named
+                .iter()
+                .enumerate()
+                .filter_map(|(i, field)| {
+                    let field_ident = field.ident.as_ref().unwrap();
+                    bind(i, field).map(|ident| quote::quote!(#field_ident: #ident))
+                })
+                .collect::<Vec<_>>();
+            quote::quote!({ #(#members,)* .. })
+        }
+        Fields::Unnamed(unnamed) => {
+            let members = unnamed
+                .unnamed
+                .iter()
+                .enumerate()
+                .map(|(i, field)| match bind(i, field) {
+                    Some(ident) => quote::quote!(#ident),
+                    None => quote::quote!(_),
+                })
+                .collect::<Vec<_>>();
+            quote::quote!((#(#members),*))
+        }
+        Fields::Unit => quote::quote!(),
+    };
+
+    (pattern, bound)
+}
+
+/// Compares each of the fields bound on one side with the same field on the other side.
+fn semantic_eq_comparison(bound_a: &[Ident], bound_b: &[Ident]) -> Expr {
+    if bound_a.is_empty() {
+        syn::parse_quote!(true)
+    } else {
+        syn::parse_quote!(#(rust_sitter::SemanticEq::semantic_eq(#bound_a, #bound_b))&&*)
+    }
+}
+
+/// Generates an implementation of `rust_sitter::SemanticEq` for a type of the grammar,
+/// which compares the fields parsed from the input and ignores skipped fields.
+fn gen_semantic_eq(item: &Item) -> Item {
+    match item {
+        Item::Struct(s) => {
+            let struct_name = &s.ident;
+            let (pattern_a, bound_a) = semantic_eq_pattern(&s.fields, "a");
+            let (pattern_b, bound_b) = semantic_eq_pattern(&s.fields, "b");
+            let comparison = semantic_eq_comparison(&bound_a, &bound_b);
+            let (impl_generics, ty_generics, where_clause) = s.generics.split_for_impl();
+            syn::parse_quote! {
+                impl #impl_generics rust_sitter::SemanticEq for #struct_name #ty_generics #where_clause {
+                    fn semantic_eq(&self, other: &Self) -> bool {
+                        let #struct_name #pattern_a = self;
+                        let #struct_name #pattern_b = other;
+                        #comparison
+                    }
+                }
+            }
+        }
+        Item::Enum(e) => {
+            let enum_name = &e.ident;
+            let arms = e
+                .variants
+                .iter()
+                .map(|v| {
+                    let variant_ident = &v.ident;
+                    let (pattern_a, bound_a) = semantic_eq_pattern(&v.fields, "a");
+                    let (pattern_b, bound_b) = semantic_eq_pattern(&v.fields, "b");
+                    let comparison = semantic_eq_comparison(&bound_a, &bound_b);
+                    syn::parse_quote! {
+                        (#enum_name::#variant_ident #pattern_a, #enum_name::#variant_ident #pattern_b) => #comparison,
+                    }
+                })
+                .collect::<Vec<Arm>>();
+
+            let (impl_generics, ty_generics, where_clause) = e.generics.split_for_impl();
+            syn::parse_quote! {
+                impl #impl_generics rust_sitter::SemanticEq for #enum_name #ty_generics #where_clause {
+                    fn semantic_eq(&self, other: &Self) -> bool {
+                        #[allow(unreachable_patterns)]
+                        match (self, other) {
+                            #(#arms)*
+                            _ => false,
+                        }
+                    }
+                }
+            }
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// Generates an implementation of `rust_sitter::Tagged` for an enum of the grammar, which
+/// numbers its variants in the order of their names.
+fn gen_tagged(e: &ItemEnum) -> Item {
+    let enum_name = &e.ident;
+    let mut names = e
+        .variants
+        .iter()
+        .map(|v| v.ident.to_string())
+        .collect::<Vec<_>>();
+    names.sort();
+    if names.len() > u16::MAX as usize + 1 {
+        panic!("`{enum_name}` has too many variants to give each a `u16` tag");
+    }
+    let tag_of = |v: &Variant| names.iter().position(|n| v.ident == n).unwrap() as u16;
+
+    let tags = names.iter().enumerate().map(|(i, name)| {
+        let tag = i as u16;
+        quote::quote! { (#name, #tag) }
+    });
+    let tag_arms = e.variants.iter().map(|v| {
+        let variant_ident = &v.ident;
+        let tag = tag_of(v);
+        quote::quote! { #enum_name::#variant_ident { .. } => #tag }
+    });
+    let from_tag_arms = e
+        .variants
+        .iter()
+        .filter(|v| matches!(v.fields, Fields::Unit))
+        .map(|v| {
+            let variant_ident = &v.ident;
+            let tag = tag_of(v);
+            quote::quote! { #tag => Some(#enum_name::#variant_ident) }
+        });
+
+    let (impl_generics, ty_generics, where_clause) = e.generics.split_for_impl();
+    syn::parse_quote! {
+        impl #impl_generics rust_sitter::Tagged for #enum_name #ty_generics #where_clause {
+            const TAGS: &'static [(&'static str, u16)] = &[#(#tags),*];
+
+            fn tag(&self) -> u16 {
+                match self {
+                    #(#tag_arms),*
+                }
+            }
+
+            fn from_tag(tag: u16) -> Option<Self> {
+                match tag {
+                    #(#from_tag_arms,)*
+                    _ => None,
+                }
+            }
+        }
+    }
+}
+
+/// Generates the construction of a struct with a leaf on the struct itself from the text
+/// of the leaf, filling each field with the named capture group of the same name.
+fn gen_captures_struct(s: &ItemStruct) -> Expr {
+    let pattern = s
+        .attrs
+        .iter()
+        .find(|a| a.path == syn::parse_quote!(rust_sitter::leaf))
+        .and_then(|a| {
+            a.parse_args_with(Punctuated::<NameValueExpr, Token![,]>::parse_terminated)
+                .ok()
+        })
+        .into_iter()
+        .flatten()
+        .find(|param| param.path == "pattern")
+        .and_then(|param| match param.expr {
+            Expr::Lit(ExprLit {
+                lit: Lit::Str(s), ..
+            }) => Some(s.value()),
+            _ => None,
+        })
+        .unwrap_or_else(|| {
+            panic!(
+                "`#[rust_sitter::leaf]` on the struct `{}` must have a string `pattern` with a named capture group for each field",
+                s.ident
+            )
+        });
+
+    let (_, group_names) = split_capture_groups(&pattern);
+    // the regex crate only accepts the `(?P<name>...)` syntax of named groups
+    let mut pattern = group_names.iter().fold(pattern, |pattern, name| {
+        pattern.replace(&format!("(?<{name}>"), &format!("(?P<{name}>"))
+    });
+    if find_bool_param(&s.attrs, "case_insensitive") {
+        pattern = format!("(?i){pattern}");
+    }
+
+    let fields = s
+        .fields
+        .iter()
+        .map(|field| {
+            let ident = field.ident.as_ref().unwrap_or_else(|| {
+                panic!(
+                    "`{}` is a leaf with capture groups, so its fields must be named after the groups",
+                    s.ident
+                )
+            });
+
+            let value: Expr = if let Some(skip_attr) = field
+                .attrs
+                .iter()
+                .find(|attr| attr.path == syn::parse_quote!(rust_sitter::skip))
+            {
+                skip_attr.parse_args::<syn::Expr>().unwrap()
+            } else {
+                let name = ident.to_string();
+                if !group_names.contains(&name) {
+                    panic!(
+                        "The field `{name}` of `{}` has no capture group of the same name in its pattern",
+                        s.ident
+                    );
+                }
+
+                let (_, is_option) = try_extract_inner_type(&field.ty, "Option", &HashSet::new());
+                if is_option {
+                    syn::parse_quote!(captures.get(#name))
+                } else {
+                    syn::parse_quote!(captures.require(#name))
+                }
+            };
+
+            quote::quote!(#ident: #value)
+        })
+        .collect::<Vec<_>>();
+
+    let struct_name = &s.ident;
+    syn::parse_quote!({
+        static PATTERN: rust_sitter::captures::CapturePattern =
+            rust_sitter::captures::CapturePattern::new(#pattern);
+        let captures = PATTERN.captures(&text);
+        #struct_name {
+            #(#fields),*
+        }
+    })
+}
+
+/// Collects the rule names of the leaves in the given fields whose escape sequences
+/// are processed, following the same naming scheme as the generated grammar.
+fn collect_unescape_leaves(path: &str, fields: &Fields, out: &mut Vec<String>) {
+    fields.iter().enumerate().for_each(|(i, field)| {
+        let ident_str = field
+            .ident
+            .as_ref()
+            .map(|v| v.to_string())
+            .unwrap_or(format!("{i}"));
+
+        if find_bool_param(&field.attrs, "unescape") {
+            out.push(format!("{path}_{ident_str}"));
+        }
+    });
+}
+
+fn is_unordered(attrs: &[Attribute]) -> bool {
+    attrs
+        .iter()
+        .any(|attr| attr.path == syn::parse_quote!(rust_sitter::unordered))
+}
+
+/// Collects the kind of the node of a `#[rust_sitter::unordered]` type or variant, along
+/// with its required fields and all of its fields, which are checked for after parsing.
+fn collect_unordered_fields(
+    kind: &str,
+    attrs: &[Attribute],
+    fields: &Fields,
+    out: &mut Vec<(String, Vec<String>, Vec<String>)>,
+) {
+    if !is_unordered(attrs) {
+        return;
+    }
+
+    let mut skip_over = HashSet::new();
+    skip_over.insert("Spanned");
+    skip_over.insert("Box");
+
+    let mut required = vec![];
+    let mut all = vec![];
+    fields
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| {
+            !field
+                .attrs
+                .iter()
+                .any(|attr| attr.path == syn::parse_quote!(rust_sitter::skip))
+        })
+        .for_each(|(i, field)| {
+            let field_name = ts_field_name(field, i);
+
+            if !try_extract_inner_type(&field.ty, "Option", &skip_over).1
+                && !is_optional_field(&field.attrs, &field.ty)
+            {
+                required.push(field_name.clone());
+            }
+            all.push(field_name);
+        });
+
+    out.push((kind.to_string(), required, all));
+}
+
+/// Collects the dialects that a type or variant is gated on with
+/// `#[rust_sitter::dialect(...)]`, paired with the kind of its node.
+fn collect_dialect_gates(kind: &str, attrs: &[Attribute], out: &mut Vec<(String, String)>) {
+    attrs
+        .iter()
+        .filter(|attr| attr.path == syn::parse_quote!(rust_sitter::dialect))
+        .for_each(|attr| {
+            let dialect = attr
+                .parse_args::<LitStr>()
+                .expect("Expected a string literal naming the dialect for `dialect`");
+            out.push((kind.to_string(), dialect.value()));
+        });
+}
+
+/// Collects the kinds of the overrun tokens of the leaves with `anchored = true` in the
+/// given fields, following the same naming scheme as the generated grammar.
+fn collect_anchored_overruns(path: &str, fields: &Fields, out: &mut Vec<String>) {
+    fields.iter().enumerate().for_each(|(i, field)| {
+        let ident_str = field
+            .ident
+            .as_ref()
+            .map(|v| v.to_string())
+            .unwrap_or(format!("{i}"));
+
+        if find_bool_param(&field.attrs, "anchored") {
+            out.push(anchored_overrun_rule_name(&format!("{path}_{ident_str}")));
+        }
+    });
+}
+
+/// Expands a grammar marked with `#[rust_sitter::untyped]`, whose types only define the
+/// grammar and are replaced by a `parse` function returning a dynamic syntax tree. With
+/// `keep_types`, as for `#[rust_sitter::debug_fast]`, the types are kept without their
+/// annotations, so that code naming them still compiles, but nothing is extracted into them.
+fn expand_untyped_grammar(input: ItemMod, grammar_name: &str, keep_types: bool) -> ItemMod {
+    let (brace, contents) = input.content.unwrap();
+
+    let mut transformed: Vec<Item> = contents
+        .into_iter()
+        .filter_map(|item| match item {
+            Item::Enum(_) | Item::Struct(_) | Item::Type(_) if !keep_types => None,
+            Item::Enum(mut e) => {
+                e.attrs.retain(|a| !is_sitter_attr(a));
+                e.variants.iter_mut().for_each(|v| {
+                    v.attrs.retain(|a| !is_sitter_attr(a));
+                    v.fields
+                        .iter_mut()
+                        .for_each(|f| f.attrs.retain(|a| !is_sitter_attr(a)));
+                });
+                Some(Item::Enum(e))
+            }
+            Item::Struct(mut s) => {
+                s.attrs.retain(|a| !is_sitter_attr(a));
+                s.fields
+                    .iter_mut()
+                    .for_each(|f| f.attrs.retain(|a| !is_sitter_attr(a)));
+                Some(Item::Struct(s))
+            }
+            Item::Type(mut t) => {
+                t.attrs.retain(|a| !is_sitter_attr(a));
+                Some(Item::Type(t))
+            }
+            item => Some(item),
+        })
+        .collect();
+
+    let tree_sitter_ident = Ident::new(&format!("tree_sitter_{grammar_name}"), Span::call_site());
+
+    transformed.push(syn::parse_quote! {
+        extern "C" {
+            fn #tree_sitter_ident() -> rust_sitter::tree_sitter::Language;
+        }
+    });
+
+    transformed.push(syn::parse_quote! {
+        pub fn language() -> rust_sitter::tree_sitter::Language {
+            unsafe { #tree_sitter_ident() }
+        }
+    });
+
+    transformed.push(syn::parse_quote! {
+        pub fn parse(input: &str) -> core::result::Result<rust_sitter::syntax::SyntaxNode, Vec<rust_sitter::errors::ParseError>> {
+            rust_sitter::syntax::parse(language(), input)
+        }
+    });
+
+    transformed.push(syn::parse_quote! {
+        pub fn parse_lossless(input: &str) -> core::result::Result<rust_sitter::lossless::LosslessNode, Vec<rust_sitter::errors::ParseError>> {
+            rust_sitter::lossless::parse(language(), input)
+        }
+    });
+
+    let mut filtered_attrs = input.attrs;
+    filtered_attrs.retain(|a| !is_sitter_attr(a));
+    ItemMod {
+        attrs: filtered_attrs,
+        vis: input.vis,
+        mod_token: input.mod_token,
+        ident: input.ident,
+        content: Some((brace, transformed)),
+        semi: input.semi,
+    }
+}
+
+pub fn expand_grammar(input: ItemMod) -> ItemMod {
+    let grammar_name = find_grammar_name(&input).expect("Each grammar must have a name");
+
+    if input
+        .attrs
+        .iter()
+        .any(|a| a.path == syn::parse_quote!(rust_sitter::tokenizer_only))
+    {
+        let mut expanded = expand_untyped_grammar(input, &grammar_name, false);
+        // the root of a tokenizer-only grammar repeats its tokens, which are its children
+        expanded.content.as_mut().unwrap().1.push(syn::parse_quote! {
+            pub fn tokenize(input: &str) -> core::result::Result<Vec<rust_sitter::syntax::SyntaxNode>, Vec<rust_sitter::errors::ParseError>> {
+                parse(input).map(|root| root.children)
+            }
+        });
+        return expanded;
+    }
+
+    if input
+        .attrs
+        .iter()
+        .any(|a| a.path == syn::parse_quote!(rust_sitter::untyped))
+    {
+        return expand_untyped_grammar(input, &grammar_name, false);
+    }
+
+    if input
+        .attrs
+        .iter()
+        .any(|a| a.path == syn::parse_quote!(rust_sitter::debug_fast))
+    {
+        return expand_untyped_grammar(input, &grammar_name, true);
+    }
+
+    // every rule name, and so every node kind, starts with the prefix of the grammar
+    let rule_prefix = find_grammar_params(&input.attrs)
+        .unwrap()
+        .rule_prefix(&input.ident);
+    let max_token_len = find_grammar_params(&input.attrs).unwrap().max_token_len();
+    let named_root = find_grammar_params(&input.attrs).unwrap().named_root();
+    let rule_case = RuleCase::find(&input.attrs);
+
+    let (brace, new_contents) = input.content.unwrap();
+
+    // several root types are extracted into the variants of a generated `SourceFile` enum,
+    // since `source_file` then matches any of them
+    let root_types = find_root_types(&new_contents);
+    let root_type = match root_types.as_slice() {
+        [] => {
+            panic!("Each parser must have the root type annotated with `#[rust_sitter::language]`")
+        }
+        [root_type] => root_type.clone(),
+        _ => Ident::new("SourceFile", Span::call_site()),
+    };
+    let multiple_roots = root_types.len() > 1;
+    if multiple_roots {
+        new_contents.iter().for_each(|item| match item {
+            Item::Enum(ItemEnum { ident, .. }) | Item::Struct(ItemStruct { ident, .. })
+                if *ident == root_type =>
+            {
+                panic!("The grammar has several types annotated with `#[rust_sitter::language]`, so it cannot have a type named `SourceFile`, which is generated for its root");
+            }
+            Item::Enum(ItemEnum {
+                ident, generics, ..
+            })
+            | Item::Struct(ItemStruct {
+                ident, generics, ..
+            }) if root_types.contains(ident) && generics.params.iter().next().is_some() => {
+                panic!("`{ident}` has lifetime parameters, but a grammar with several types annotated with `#[rust_sitter::language]` can only have root types without them");
+            }
+            _ => {}
+        });
+    }
+
+    // the hook on the root type runs once on the whole AST, rather than on every value of
+    // the type, so it is called by the parsing functions instead of by extraction
+    let post_parse = new_contents.iter().find_map(|item| match item {
+        Item::Enum(ItemEnum { ident, attrs, .. }) | Item::Struct(ItemStruct { ident, attrs, .. }) => {
+            attrs
+                .iter()
+                .find(|a| a.path == syn::parse_quote!(rust_sitter::post_parse))
+                .map(|a| {
+                    if !root_types.contains(ident) {
+                        panic!("`#[rust_sitter::post_parse]` can only be placed on the root type, which `{ident}` is not");
+                    }
+                    if multiple_roots {
+                        panic!("`#[rust_sitter::post_parse]` on `{ident}` needs a single type annotated with `#[rust_sitter::language]`");
+                    }
+                    a.parse_args::<Path>()
+                        .expect("Expected the path of a function such as `finalize` for `post_parse`")
+                })
+        }
+        _ => None,
+    });
+    // only a root type whose fields can all be empty has an empty AST, and the build warns
+    // about the others, since an attribute cannot make the macro warn
+    let default_impl: Option<Item> = new_contents.iter().find_map(|item| {
+        let (ident, attrs) = match item {
+            Item::Enum(e) => (&e.ident, &e.attrs),
+            Item::Struct(s) => (&s.ident, &s.attrs),
+            _ => return None,
+        };
+        if !attrs
+            .iter()
+            .any(|a| a.path == syn::parse_quote!(rust_sitter::default_empty))
+        {
+            return None;
+        }
+        if !root_types.contains(ident) {
+            panic!("`#[rust_sitter::default_empty]` can only be placed on a type annotated with `#[rust_sitter::language]`, which `{ident}` is not");
+        }
+
+        let Item::Struct(s) = item else {
+            return None;
+        };
+        let values = s
+            .fields
+            .iter()
+            .map(empty_field_value)
+            .collect::<Option<Vec<_>>>()?;
+        let construct = match &s.fields {
+            Fields::Named(_) => {
+                let idents = s.fields.iter().map(|f| f.ident.as_ref().unwrap());
+                quote::quote!(#ident { #(#idents: #values),* })
+            }
+            Fields::Unnamed(_) => quote::quote!(#ident(#(#values),*)),
+            Fields::Unit => quote::quote!(#ident),
+        };
+        let (impl_generics, ty_generics, where_clause) = s.generics.split_for_impl();
+        Some(syn::parse_quote! {
+            impl #impl_generics ::core::default::Default for #ident #ty_generics #where_clause {
+                fn default() -> Self {
+                    #construct
+                }
+            }
+        })
+    });
+
+    // the functions that extract fields only take the lifetimes of a type, such as the
+    // lifetime of an arena
+    new_contents.iter().for_each(|item| match item {
+        Item::Enum(ItemEnum {
+            ident, generics, ..
+        })
+        | Item::Struct(ItemStruct {
+            ident, generics, ..
+        }) if generics.type_params().next().is_some()
+            || generics.const_params().next().is_some() =>
+        {
+            panic!("`{ident}` has type or const parameters, but the types of a grammar can only have lifetime parameters");
+        }
+        _ => {}
+    });
+
+    let entry_types: Vec<Ident> = new_contents
+        .iter()
+        .filter_map(|item| match item {
+            Item::Enum(ItemEnum { ident, attrs, .. })
+            | Item::Struct(ItemStruct { ident, attrs, .. }) => attrs
+                .iter()
+                .any(|attr| attr.path == syn::parse_quote!(rust_sitter::entry))
+                .then(|| ident.clone()),
+            _ => None,
+        })
+        .collect();
+
+    // the node kinds of every AST type, for resolving nodes to typed references; extras are
+    // never extracted and aliased types have the kind of their alias, so both are left out
+    let mut typed_kinds: Vec<(String, Ident)> = vec![(
+        rule_case.apply(&format!("{rule_prefix}source_file")),
+        root_type.clone(),
+    )];
+    new_contents.iter().for_each(|item| match item {
+        // the members of a token group are types with kinds of their own
+        Item::Enum(e)
+            if !has_alias_or_extra(&e.attrs)
+                && token_group_members(e, &rule_prefix, rule_case).is_none() =>
+        {
+            e.variants.iter().for_each(|v| {
+                typed_kinds.push((
+                    rule_case.apply(&format!("{rule_prefix}{}_{}", e.ident, v.ident)),
+                    e.ident.clone(),
+                ));
+            })
+        }
+        Item::Struct(s) if !has_alias_or_extra(&s.attrs) && find_newtype_inner(s).is_none() => {
+            typed_kinds.push((
+                rule_case.apply(&format!("{rule_prefix}{}", s.ident)),
+                s.ident.clone(),
+            ));
+        }
+        _ => {}
+    });
+    let mut typed_types: Vec<Ident> = vec![];
+    typed_kinds.iter().for_each(|(_, ty)| {
+        if !typed_types.contains(ty) {
+            typed_types.push(ty.clone());
+        }
+    });
+
+    let mut text_tokens = vec![];
+    let mut unescape_leaves = vec![];
+    let mut anchored_overruns = vec![];
+    let mut dialect_gates = vec![];
+    let mut unordered_fields = vec![];
+    new_contents.iter().for_each(|item| match item {
+        Item::Enum(e) => e.variants.iter().for_each(|v| {
+            let variant_path = rule_case.apply(&format!("{rule_prefix}{}_{}", e.ident, v.ident));
+            collect_dialect_gates(&variant_path, &v.attrs, &mut dialect_gates);
+            collect_unordered_fields(&variant_path, &v.attrs, &v.fields, &mut unordered_fields);
+            if let Some(text) = find_text_param(&v.attrs)
+                .or_else(|| find_keyword_param(&v.attrs))
+                .or_else(|| {
+                    (is_fieldless(&v.fields)
+                        && !v
+                            .attrs
+                            .iter()
+                            .any(|attr| attr.path == syn::parse_quote!(rust_sitter::leaf)))
+                    .then(|| unit_variant_text(&v.ident, &e.attrs))
+                })
+            {
+                text_tokens.push((variant_path.clone(), text));
+            }
+            collect_text_tokens(&variant_path, &v.fields, &mut text_tokens);
+            collect_unescape_leaves(&variant_path, &v.fields, &mut unescape_leaves);
+            collect_anchored_overruns(&variant_path, &v.fields, &mut anchored_overruns);
+        }),
+        Item::Struct(s) => {
+            let struct_path = rule_case.apply(&format!("{rule_prefix}{}", s.ident));
+            collect_dialect_gates(&struct_path, &s.attrs, &mut dialect_gates);
+            collect_unordered_fields(&struct_path, &s.attrs, &s.fields, &mut unordered_fields);
+            let collected = (
+                text_tokens.len(),
+                unescape_leaves.len(),
+                anchored_overruns.len(),
+            );
+            collect_text_tokens(&struct_path, &s.fields, &mut text_tokens);
+            collect_unescape_leaves(&struct_path, &s.fields, &mut unescape_leaves);
+            collect_anchored_overruns(&struct_path, &s.fields, &mut anchored_overruns);
+
+            // the leaf of a token has the kind of the struct rather than of its field
+            if let Some(field) = find_token_field(s) {
+                let ident_str = field
+                    .ident
+                    .as_ref()
+                    .map(|v| v.to_string())
+                    .unwrap_or("0".to_string());
+                let field_path = rule_case.apply(&format!("{struct_path}_{ident_str}"));
+                let rename = |kind: &mut String| {
+                    if *kind == field_path {
+                        *kind = struct_path.clone();
+                    } else if *kind == anchored_overrun_rule_name(&field_path) {
+                        *kind = anchored_overrun_rule_name(&struct_path);
+                    }
+                };
+                text_tokens[collected.0..]
+                    .iter_mut()
+                    .for_each(|(kind, _)| rename(kind));
+                unescape_leaves[collected.1..].iter_mut().for_each(rename);
+                anchored_overruns[collected.2..].iter_mut().for_each(rename);
+            }
+        }
+        _ => {}
+    });
+
+    // a single root is copied into the `source_file` rule, whose node is then the one extracted
+    if let ([root_type], false) = (root_types.as_slice(), named_root) {
+        let root_kind = rule_case.apply(&format!("{rule_prefix}{root_type}"));
+        if let Some((_, required, all)) = unordered_fields
+            .iter()
+            .find(|(kind, ..)| *kind == root_kind)
+            .cloned()
+        {
+            let source_file = rule_case.apply(&format!("{rule_prefix}source_file"));
+            unordered_fields.push((source_file, required, all));
+        }
+    }
+
+    // the rules of fields are renamed along with the rules of their types
+    text_tokens
+        .iter_mut()
+        .for_each(|(kind, _)| *kind = rule_case.apply(kind));
+    unescape_leaves
+        .iter_mut()
+        .chain(anchored_overruns.iter_mut())
+        .for_each(|kind| *kind = rule_case.apply(kind));
+
+    // leaves are only unescaped during extraction, so invalid escapes are reported beforehand,
+    // along with anchored leaves that run on into more word characters, which Tree Sitter
+    // parses as their overrun tokens rather than failing, tokens longer than the
+    // `max_token_len` and unordered types with missing or repeated fields
+    let mut tree_checks: Vec<Stmt> = vec![];
+    if let Some(max_token_len) = max_token_len {
+        tree_checks.push(syn::parse_quote! {
+            rust_sitter::errors::collect_long_token_errors(
+                &root_node,
+                #max_token_len,
+                &mut errors,
+            );
+        });
+    }
+
+    if !anchored_overruns.is_empty() {
+        tree_checks.push(syn::parse_quote! {
+            rust_sitter::errors::collect_overrun_errors(
+                &root_node,
+                &[#(#anchored_overruns),*],
+                source,
+                &mut errors,
+            );
+        });
+    }
+
+    if !unescape_leaves.is_empty() {
+        tree_checks.push(syn::parse_quote! {
+            rust_sitter::unescape::collect_escape_errors(
+                &root_node,
+                &[#(#unescape_leaves),*],
+                source,
+                &mut errors,
+            );
+        });
+    }
+
+    if !unordered_fields.is_empty() {
+        let kinds = unordered_fields.iter().map(|(kind, ..)| kind);
+        let required = unordered_fields.iter().map(|(_, required, _)| required);
+        let all = unordered_fields.iter().map(|(.., all)| all);
+        tree_checks.push(syn::parse_quote! {
+            rust_sitter::errors::collect_unordered_errors(
+                &root_node,
+                &[#((#kinds, &[#(#required),*] as &[&str], &[#(#all),*] as &[&str])),*],
+                &mut errors,
+            );
+        });
+    }
+
+    // Tree Sitter parses the constructs of every dialect, so those of disabled dialects are
+    // rejected afterwards
+    if !dialect_gates.is_empty() {
+        let (gated_kinds, gated_dialects): (Vec<_>, Vec<_>) = dialect_gates.iter().cloned().unzip();
+        tree_checks.push(syn::parse_quote! {
+            if let Some(dialects) = options.dialects {
+                rust_sitter::dialect::collect_dialect_errors(
+                    &root_node,
+                    &[#((#gated_kinds, #gated_dialects)),*],
+                    dialects,
+                    &mut errors,
+                );
+            }
+        });
+    }
+
+    // `#[rust_sitter::serde]` on the root type makes every AST type serializable
+    let derive_serde = new_contents.iter().any(|c| match c {
+        Item::Enum(ItemEnum { attrs, .. }) | Item::Struct(ItemStruct { attrs, .. }) => {
+            attrs
+                .iter()
+                .any(|a| a.path == syn::parse_quote!(rust_sitter::language))
+                && attrs
+                    .iter()
+                    .any(|a| a.path == syn::parse_quote!(rust_sitter::serde))
+        }
+        _ => false,
+    });
+
+    let serde_attrs: Vec<Attribute> = if derive_serde {
+        vec![
+            syn::parse_quote!(#[derive(rust_sitter::serde::Serialize, rust_sitter::serde::Deserialize)]),
+            syn::parse_quote!(#[serde(crate = "rust_sitter::serde")]),
+        ]
+    } else {
+        vec![]
+    };
+
+    // `#[rust_sitter::semantic_eq]` on the root type makes every AST type comparable
+    // while ignoring spans
+    let derive_semantic_eq = new_contents.iter().any(|c| match c {
+        Item::Enum(ItemEnum { attrs, .. }) | Item::Struct(ItemStruct { attrs, .. }) => {
+            attrs
+                .iter()
+                .any(|a| a.path == syn::parse_quote!(rust_sitter::language))
+                && attrs
+                    .iter()
+                    .any(|a| a.path == syn::parse_quote!(rust_sitter::semantic_eq))
+        }
+        _ => false,
+    });
+
+    // `#[rust_sitter::tags]` on the root type gives the variants of every enum stable tags
+    let derive_tags = new_contents.iter().any(|c| match c {
+        Item::Enum(ItemEnum { attrs, .. }) | Item::Struct(ItemStruct { attrs, .. }) => {
+            attrs
+                .iter()
+                .any(|a| a.path == syn::parse_quote!(rust_sitter::language))
+                && attrs
+                    .iter()
+                    .any(|a| a.path == syn::parse_quote!(rust_sitter::tags))
+        }
+        _ => false,
+    });
+
+    let field_template = field_extract_template();
+    let mut transformed: Vec<Item> = new_contents
+        .iter()
+        .cloned()
+        .flat_map(|c| match c {
+            Item::Enum(mut e) => {
+                let semantic_eq_impl = derive_semantic_eq.then(|| gen_semantic_eq(&Item::Enum(e.clone())));
+                let tagged_impl = derive_tags.then(|| gen_tagged(&e));
+                let group_members = token_group_members(&e, &rule_prefix, rule_case);
+                let mut impl_body = vec![];
+                if group_members.is_none() {
+                    e.variants.iter().for_each(|v| {
+                        gen_struct_or_variant(
+                            rule_case.apply(&format!("{rule_prefix}{}_{}", e.ident, v.ident)),
+                            v.fields.clone(),
+                            Some(v.ident.clone()),
+                            e.ident.clone(),
+                            &e.generics,
+                            is_unordered(&v.attrs),
+                            rule_case,
+                            &field_template,
+                            &mut impl_body,
+                        )
+                    });
+                }
+
+                let enum_name = e.ident.clone();
+                let match_cases: Vec<Arm> = match &group_members {
+                    // the hidden rule of a token group leaves the node of its member
+                    Some(members) => e
+                        .variants
+                        .iter()
+                        .zip(members)
+                        .map(|(v, (ty, kind))| {
+                            let variant_ident = &v.ident;
+                            syn::parse_quote! {
+                                #kind => return #enum_name::#variant_ident(<#ty as rust_sitter::Extract<_>>::extract(Some(n), source, (n.start_byte(), rust_sitter::Point::from(n.start_position())), None))
+                            }
+                        })
+                        .collect(),
+                    None => e
+                        .variants
+                        .iter()
+                        .map(|v| {
+                            let variant_path = rule_case
+                                .apply(&format!("{rule_prefix}{}_{}", e.ident, v.ident));
+                            let extract_ident =
+                                Ident::new(&format!("extract_{variant_path}"), Span::call_site());
+                            syn::parse_quote! {
+                                #variant_path => return #extract_ident(n, source)
+                            }
+                        })
+                        .collect(),
+                };
+
+                let constructors = e
+                    .variants
+                    .iter()
+                    .filter_map(|v| gen_constructor(&e.ident, v))
+                    .collect::<Vec<_>>();
+
+                e.attrs.retain(|a| !is_sitter_attr(a));
+                e.attrs.extend(serde_attrs.iter().cloned());
+                e.variants.iter_mut().for_each(|v| {
+                    v.attrs.retain(|a| !is_sitter_attr(a));
+                    v.fields.iter_mut().for_each(|f| {
+                        f.attrs.retain(|a| !is_sitter_attr(a));
+                    });
+                });
+
+                let (impl_generics, ty_generics, where_clause) = e.generics.split_for_impl();
+                let extract_impl: ItemImpl = syn::parse_quote! {
+                    impl #impl_generics rust_sitter::Extract<#enum_name #ty_generics> for #enum_name #ty_generics #where_clause {
+                        type LeafFn = ();
+
+                        #[allow(non_snake_case)]
+                        fn extract<S: rust_sitter::Source + ?Sized>(node: Option<rust_sitter::tree_sitter::Node>, source: &S, _last_idx: (usize, rust_sitter::Point), _leaf_fn: Option<&Self::LeafFn>) -> Self {
+                            let node = node.unwrap();
+
+                            // the node of a supertype is hidden, leaving the node of its variant
+                            let n = node;
+                            match n.kind() {
+                                #(#match_cases),*,
+                                _ => {}
+                            }
+
+                            let mut cursor = node.walk();
+                            assert!(cursor.goto_first_child());
+                            loop {
+                                let n = cursor.node();
+                                match n.kind() {
+                                    #(#match_cases),*,
+                                    _ => if !cursor.goto_next_sibling() {
+                                        panic!("Could not find a child corresponding to any enum branch")
+                                    }
+                                }
+                            }
+                        }
+                    }
+                };
+                let extract_impl = with_extract_fns(extract_impl, impl_body);
+
+                let kind_arms: Vec<Arm> = e
+                    .variants
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| {
+                        let variant_ident = &v.ident;
+                        let variant_path = match &group_members {
+                            Some(members) => members[i].1.clone(),
+                            None => rule_case
+                                .apply(&format!("{rule_prefix}{}_{}", e.ident, v.ident)),
+                        };
+                        syn::parse_quote! {
+                            #enum_name::#variant_ident { .. } => #variant_path
+                        }
+                    })
+                    .collect();
+
+                let kind_impl: Item = syn::parse_quote! {
+                    impl #impl_generics #enum_name #ty_generics #where_clause {
+                        /// Returns the name of the Tree Sitter rule of the variant this value was parsed from.
+                        pub fn kind(&self) -> &'static str {
+                            match self {
+                                #(#kind_arms),*
+                            }
+                        }
+
+                        #(#constructors)*
+                    }
+                };
+
+                let mut items = vec![Item::Enum(e), extract_impl, kind_impl];
+                items.extend(semantic_eq_impl);
+                items.extend(tagged_impl);
+                items
+            }
+
+            // a token is extracted from the node of its leaf, which is the node of the struct
+            Item::Struct(mut s) if find_token_field(&s).is_some() => {
+                let semantic_eq_impl =
+                    derive_semantic_eq.then(|| gen_semantic_eq(&Item::Struct(s.clone())));
+                let field = find_token_field(&s).unwrap().clone();
+                let leaf_expr = gen_leaf_expr(&s.ident.to_string(), &field);
+
+                s.attrs.retain(|a| !is_sitter_attr(a));
+                s.attrs.extend(serde_attrs.iter().cloned());
+                s.fields.iter_mut().for_each(|f| {
+                    f.attrs.retain(|a| !is_sitter_attr(a));
+                });
+
+                let struct_name = &s.ident;
+                let construct: Expr = match &field.ident {
+                    Some(ident) => syn::parse_quote!(#struct_name { #ident: value }),
+                    None => syn::parse_quote!(#struct_name(value)),
+                };
+                let (impl_generics, ty_generics, where_clause) = s.generics.split_for_impl();
+                let extract_impl: Item = syn::parse_quote! {
+                    impl #impl_generics rust_sitter::Extract<#struct_name #ty_generics> for #struct_name #ty_generics #where_clause {
+                        type LeafFn = ();
+
+                        fn extract<S: rust_sitter::Source + ?Sized>(node: Option<rust_sitter::tree_sitter::Node>, source: &S, last_idx: (usize, rust_sitter::Point), _leaf_fn: Option<&Self::LeafFn>) -> Self {
+                            let last_idx = &last_idx;
+                            let value = #leaf_expr;
+                            #construct
+                        }
+                    }
+                };
+
+                let mut items = vec![Item::Struct(s), extract_impl];
+                items.extend(semantic_eq_impl);
+                items
+            }
+
+            Item::Struct(mut s)
+                if s
+                    .attrs
+                    .iter()
+                    .any(|a| a.path == syn::parse_quote!(rust_sitter::leaf)) =>
+            {
+                let semantic_eq_impl =
+                    derive_semantic_eq.then(|| gen_semantic_eq(&Item::Struct(s.clone())));
+                let construct = gen_captures_struct(&s);
+
+                s.attrs.retain(|a| !is_sitter_attr(a));
+                s.attrs.extend(serde_attrs.iter().cloned());
+                s.fields.iter_mut().for_each(|f| {
+                    f.attrs.retain(|a| !is_sitter_attr(a));
+                });
+
+                let struct_name = &s.ident;
+                let (impl_generics, ty_generics, where_clause) = s.generics.split_for_impl();
+                let extract_impl: Item = syn::parse_quote! {
+                    impl #impl_generics rust_sitter::Extract<#struct_name #ty_generics> for #struct_name #ty_generics #where_clause {
+                        type LeafFn = ();
+
+                        fn extract<S: rust_sitter::Source + ?Sized>(node: Option<rust_sitter::tree_sitter::Node>, source: &S, _last_idx: (usize, rust_sitter::Point), _leaf_fn: Option<&Self::LeafFn>) -> Self {
+                            let node = node.unwrap();
+                            let text = source.text(node.start_byte(), node.end_byte());
+                            #construct
+                        }
+                    }
+                };
+
+                let mut items = vec![Item::Struct(s), extract_impl];
+                items.extend(semantic_eq_impl);
+                items
+            }
+
+            // a newtype is not a rule, and is extracted from the text of a leaf without a
+            // transform by parsing it into the inner type
+            Item::Struct(mut s) if find_newtype_inner(&s).is_some() => {
+                let semantic_eq_impl =
+                    derive_semantic_eq.then(|| gen_semantic_eq(&Item::Struct(s.clone())));
+                s.attrs.extend(serde_attrs.iter().cloned());
+
+                let struct_name = &s.ident;
+                let inner_type = find_newtype_inner(&s).unwrap();
+                let expect_message = format!("Could not parse the text of a leaf into `{struct_name}`");
+                let extract_impl: Item = syn::parse_quote! {
+                    impl rust_sitter::Extract<#struct_name> for #struct_name {
+                        type LeafFn = ();
+
+                        fn extract<S: rust_sitter::Source + ?Sized>(node: Option<rust_sitter::tree_sitter::Node>, source: &S, _last_idx: (usize, rust_sitter::Point), _leaf_fn: Option<&Self::LeafFn>) -> Self {
+                            let node = node.unwrap();
+                            let text = source.text(node.start_byte(), node.end_byte());
+                            #struct_name(text.parse::<#inner_type>().expect(#expect_message))
+                        }
+                    }
+                };
+
+                let mut items = vec![Item::Struct(s), extract_impl];
+                items.extend(semantic_eq_impl);
+                items
+            }
+
+            Item::Struct(mut s) => {
+                // a rule without extras is a single token, so none of its fields have nodes
+                if has_no_extras(&s.attrs)
+                    && s.fields.iter().any(|f| {
+                        f.ty != syn::parse_quote!(())
+                            && !f
+                                .attrs
+                                .iter()
+                                .any(|a| a.path == syn::parse_quote!(rust_sitter::skip))
+                    })
+                {
+                    panic!(
+                        "`{}` has no extras, so it is matched as a single token and its fields must be `()` leaves or skipped. Use `Spanned<{}>` to get its text",
+                        s.ident, s.ident
+                    );
+                }
+
+                let semantic_eq_impl =
+                    derive_semantic_eq.then(|| gen_semantic_eq(&Item::Struct(s.clone())));
+                let mut impl_body = vec![];
+
+                let is_extra = s
+                    .attrs
+                    .iter()
+                    .any(|a| a.path == syn::parse_quote!(rust_sitter::extra));
+                let constructor = if is_extra {
+                    None
+                } else {
+                    gen_tuple_struct_constructor(&s)
+                };
+
+                let struct_path = rule_case.apply(&format!("{rule_prefix}{}", s.ident));
+                gen_struct_or_variant(
+                    struct_path.clone(),
+                    s.fields.clone(),
+                    None,
+                    s.ident.clone(),
+                    &s.generics,
+                    is_unordered(&s.attrs),
+                    rule_case,
+                    &field_template,
+                    &mut impl_body,
+                );
+
+                s.attrs.retain(|a| !is_sitter_attr(a));
+                if is_extra {
+                    // extras are skipped while parsing, so they are never constructed
+                    s.attrs.push(syn::parse_quote!(#[allow(dead_code)]));
+                } else {
+                    s.attrs.extend(serde_attrs.iter().cloned());
+                }
+                s.fields.iter_mut().for_each(|f| {
+                    f.attrs.retain(|a| !is_sitter_attr(a));
+                });
+
+                let struct_name = &s.ident;
+                let extract_ident =
+                    Ident::new(&format!("extract_{struct_path}"), Span::call_site());
+                let (impl_generics, ty_generics, where_clause) = s.generics.split_for_impl();
+
+                let extract_impl: ItemImpl = syn::parse_quote! {
+                    impl #impl_generics rust_sitter::Extract<#struct_name #ty_generics> for #struct_name #ty_generics #where_clause {
+                        type LeafFn = ();
+
+                        #[allow(non_snake_case)]
+                        fn extract<S: rust_sitter::Source + ?Sized>(node: Option<rust_sitter::tree_sitter::Node>, source: &S, last_idx: (usize, rust_sitter::Point), _leaf_fn: Option<&Self::LeafFn>) -> Self {
+                            let node = node.unwrap();
+                            #extract_ident(node, source)
+                        }
                     }
                 };
+                let extract_impl = with_extract_fns(extract_impl, impl_body);
 
-                vec![Item::Struct(s), extract_impl]
+                let mut items = vec![Item::Struct(s), extract_impl];
+                items.extend(constructor);
+                items.extend(semantic_eq_impl);
+                items
             }
 
             o => vec![o],
         })
         .collect();
+    transformed.extend(default_impl);
+
+    if multiple_roots {
+        // the enum derives the traits that every root type derives
+        let root_derives = root_types
+            .iter()
+            .map(|root| {
+                new_contents
+                    .iter()
+                    .find_map(|item| match item {
+                        Item::Enum(ItemEnum { ident, attrs, .. })
+                        | Item::Struct(ItemStruct { ident, attrs, .. })
+                            if ident == root =>
+                        {
+                            Some(attrs)
+                        }
+                        _ => None,
+                    })
+                    .unwrap()
+                    .iter()
+                    .filter(|attr| attr.path == syn::parse_quote!(derive))
+                    .flat_map(|attr| {
+                        attr.parse_args_with(Punctuated::<Path, Token![,]>::parse_terminated)
+                            .unwrap()
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let derives = root_derives[0]
+            .iter()
+            .filter(|d| root_derives.iter().all(|ds| ds.contains(d)))
+            .collect::<Vec<_>>();
+        let root_kinds = root_types
+            .iter()
+            .map(|root| rule_case.apply(&format!("{rule_prefix}{root}")))
+            .collect::<Vec<_>>();
+
+        let source_file: ItemEnum = syn::parse_quote! {
+            /// The root of a tree, which is one of the types annotated with
+            /// `#[rust_sitter::language]`.
+            #[derive(#(#derives),*)]
+            #(#serde_attrs)*
+            pub enum SourceFile {
+                #(#root_types(#root_types)),*
+            }
+        };
+        if derive_semantic_eq {
+            transformed.push(gen_semantic_eq(&Item::Enum(source_file.clone())));
+        }
+        if derive_tags {
+            transformed.push(gen_tagged(&source_file));
+        }
+        transformed.push(Item::Enum(source_file));
+        transformed.push(syn::parse_quote! {
+            impl rust_sitter::Extract<SourceFile> for SourceFile {
+                type LeafFn = ();
+
+                fn extract<S: rust_sitter::Source + ?Sized>(node: Option<rust_sitter::tree_sitter::Node>, source: &S, _last_idx: (usize, rust_sitter::Point), _leaf_fn: Option<&Self::LeafFn>) -> Self {
+                    let node = node.unwrap();
+                    let mut cursor = node.walk();
+                    assert!(cursor.goto_first_child());
+                    loop {
+                        let n = cursor.node();
+                        match n.kind() {
+                            #(#root_kinds => return SourceFile::#root_types(<#root_types as rust_sitter::Extract<_>>::extract(Some(n), source, (n.start_byte(), rust_sitter::Point::from(n.start_position())), None)),)*
+                            _ => if !cursor.goto_next_sibling() {
+                                panic!("Could not find a child corresponding to any root type")
+                            }
+                        }
+                    }
+                }
+            }
+        });
+    }
 
     let tree_sitter_ident = Ident::new(&format!("tree_sitter_{grammar_name}"), Span::call_site());
 
@@ -383,29 +2030,488 @@ pub fn expand_grammar(input: ItemMod) -> ItemMod {
         }
     });
 
+    let post_parse_method = post_parse.as_ref().map(|hook| {
+        quote::quote! {
+            fn post_parse(&mut self) {
+                #hook(self)
+            }
+        }
+    });
+
+    // when recovering, the AST is still extracted from a tree that fails these checks
+    let check_tree = (!tree_checks.is_empty()).then(|| {
+        quote::quote! {
+            #(#tree_checks)*
+            if !options.recovering && !errors.is_empty() {
+                return (None, errors);
+            }
+        }
+    });
+
+    // every parse function checks the tree and extracts the AST in the same way, and only
+    // differs in its options, in how it gives its input to Tree Sitter and in how it extracts
+    // the root
+    transformed.push(syn::parse_quote! {
+        fn run<T: rust_sitter::EntryPoint, S: rust_sitter::Source + ?Sized>(
+            tree: &rust_sitter::tree_sitter::Tree,
+            source: &S,
+            options: &rust_sitter::pipeline::ParseOptions,
+            extract: impl FnOnce(rust_sitter::tree_sitter::Node) -> core::result::Result<T, rust_sitter::errors::ParseError>,
+        ) -> (Option<T>, Vec<rust_sitter::errors::ParseError>) {
+            let root_node = tree.root_node();
+            if let Err(error) = rust_sitter::pipeline::check_limits(&root_node, options) {
+                return (None, vec![error]);
+            }
+
+            let mut errors = vec![];
+            if root_node.has_error() {
+                rust_sitter::errors::collect_parsing_errors(&root_node, source, &mut errors);
+                if !options.recovering {
+                    return (None, errors);
+                }
+            }
+
+            #check_tree
+            rust_sitter::pipeline::extract(&root_node, options, errors, || extract(root_node))
+        }
+    });
+
+    if input
+        .attrs
+        .iter()
+        .any(|a| a.path == syn::parse_quote!(rust_sitter::arena))
+    {
+        let root_generics = new_contents
+            .iter()
+            .find_map(|item| match item {
+                Item::Enum(ItemEnum {
+                    ident, generics, ..
+                })
+                | Item::Struct(ItemStruct {
+                    ident, generics, ..
+                }) if *ident == root_type => Some(generics.clone()),
+                _ => None,
+            })
+            .unwrap();
+
+        let arena_lifetime = match root_generics.lifetimes().collect::<Vec<_>>()[..] {
+            [lifetime] => lifetime.lifetime.clone(),
+            _ => panic!("The root type of an arena grammar, `{root_type}`, must have a single lifetime parameter, which is the lifetime of the arena"),
+        };
+
+        transformed.push(syn::parse_quote! {
+            impl<#arena_lifetime> rust_sitter::EntryPoint for #root_type<#arena_lifetime> {
+                fn language() -> rust_sitter::tree_sitter::Language {
+                    language()
+                }
+
+                #post_parse_method
+            }
+        });
+
+        // the AST borrows the arena, so its references are allocated there instead of in boxes,
+        // and the input, which its `Cow<str>` leaves borrow
+        transformed.push(syn::parse_quote! {
+          pub fn parse_in<#arena_lifetime>(input: &#arena_lifetime str, arena: &#arena_lifetime rust_sitter::arena::Bump) -> core::result::Result<#root_type<#arena_lifetime>, Vec<rust_sitter::errors::ParseError>> {
+              let options = Default::default();
+              let tree = rust_sitter::pipeline::parse_tree::<#root_type>(&options, input.len(), |parser| {
+                  parser.parse(input, None)
+              })
+              .map_err(|error| vec![error])?;
+
+              rust_sitter::pipeline::into_result(run(&tree, input.as_bytes(), &options, |root_node| {
+                  // SAFETY: the extracted references live for the borrows of the arena and
+                  // the input
+                  Ok(unsafe {
+                      rust_sitter::arena::provide_arena(arena, || {
+                          rust_sitter::unescape::provide_input(input, || {
+                              <#root_type<#arena_lifetime> as rust_sitter::Extract<_>>::extract(Some(root_node), input.as_bytes(), Default::default(), None)
+                          })
+                      })
+                  })
+              }))
+          }
+      });
+
+        let mut filtered_attrs = input.attrs;
+        filtered_attrs.retain(|a| !is_sitter_attr(a));
+        return ItemMod {
+            attrs: filtered_attrs,
+            vis: input.vis,
+            mod_token: input.mod_token,
+            ident: input.ident,
+            content: Some((brace, transformed)),
+            semi: input.semi,
+        };
+    }
+
+    transformed.push(syn::parse_quote! {
+        impl rust_sitter::EntryPoint for #root_type {
+            fn language() -> rust_sitter::tree_sitter::Language {
+                language()
+            }
+
+            #post_parse_method
+        }
+    });
+
+    // every entry type has its own parser, generated by the tool with the type as the root
+    entry_types.iter().for_each(|entry_type| {
+        let entry_tree_sitter_ident = Ident::new(
+            &format!(
+                "tree_sitter_{}",
+                sanitize_grammar_name(&format!("{grammar_name}_{entry_type}"))
+            ),
+            Span::call_site(),
+        );
+
+        transformed.push(syn::parse_quote! {
+            extern "C" {
+                fn #entry_tree_sitter_ident() -> rust_sitter::tree_sitter::Language;
+            }
+        });
+
+        transformed.push(syn::parse_quote! {
+            impl rust_sitter::EntryPoint for #entry_type {
+                fn language() -> rust_sitter::tree_sitter::Language {
+                    unsafe { #entry_tree_sitter_ident() }
+                }
+            }
+        });
+    });
+
+    // the parse functions that take the input as a string are shorthands for these, which
+    // combine any of their options
+    transformed.push(syn::parse_quote! {
+      pub fn parse_as_with_options<T: rust_sitter::EntryPoint>(input: &str, options: &rust_sitter::pipeline::ParseOptions) -> (Option<T>, Vec<rust_sitter::errors::ParseError>) {
+          match rust_sitter::pipeline::parse_tree::<T>(options, input.len(), |parser| parser.parse(input, None)) {
+              Ok(tree) => run(&tree, input.as_bytes(), options, |root_node| {
+                  Ok(<T as rust_sitter::Extract<_>>::extract(Some(root_node), input.as_bytes(), Default::default(), None))
+              }),
+              Err(error) => (None, vec![error]),
+          }
+      }
+  });
+
+    transformed.push(syn::parse_quote! {
+      pub fn parse_with_options(input: &str, options: &rust_sitter::pipeline::ParseOptions) -> (Option<#root_type>, Vec<rust_sitter::errors::ParseError>) {
+          parse_as_with_options::<#root_type>(input, options)
+      }
+  });
+
     transformed.push(syn::parse_quote! {
       pub fn parse(input: &str) -> core::result::Result<#root_type, Vec<rust_sitter::errors::ParseError>> {
-          let mut parser = rust_sitter::tree_sitter::Parser::new();
-          parser.set_language(language()).unwrap();
-          let tree = parser.parse(input, None).unwrap();
-          let root_node = tree.root_node();
-
-          if root_node.has_error() {
-              let mut errors = vec![];
-              rust_sitter::errors::collect_parsing_errors(
-                  &root_node,
-                  input.as_bytes(),
-                  &mut errors,
-              );
-
-              Err(errors)
-          } else {
-              use rust_sitter::Extract;
-              Ok(<#root_type as rust_sitter::Extract<_>>::extract(Some(root_node), input.as_bytes(), 0, None))
+          parse_as::<#root_type>(input)
+      }
+  });
+
+    // transforms with a second parameter borrow the context while the AST is extracted
+    transformed.push(syn::parse_quote! {
+      pub fn parse_with_context<C: 'static>(input: &str, context: &mut C) -> core::result::Result<#root_type, Vec<rust_sitter::errors::ParseError>> {
+          rust_sitter::context::provide_context(context, || parse(input))
+      }
+  });
+
+    transformed.push(syn::parse_quote! {
+      pub fn parse_with_max_depth(input: &str, max_depth: usize) -> core::result::Result<#root_type, Vec<rust_sitter::errors::ParseError>> {
+          parse_as_with_max_depth::<#root_type>(input, max_depth)
+      }
+  });
+
+    transformed.push(syn::parse_quote! {
+      pub fn parse_with_max_nodes(input: &str, max_nodes: usize) -> core::result::Result<#root_type, Vec<rust_sitter::errors::ParseError>> {
+          parse_as_with_max_nodes::<#root_type>(input, max_nodes)
+      }
+  });
+
+    transformed.push(syn::parse_quote! {
+      pub fn parse_as<T: rust_sitter::EntryPoint>(input: &str) -> core::result::Result<T, Vec<rust_sitter::errors::ParseError>> {
+          rust_sitter::pipeline::into_result(parse_as_with_options(input, &Default::default()))
+      }
+  });
+
+    transformed.push(syn::parse_quote! {
+      pub fn parse_as_with_max_depth<T: rust_sitter::EntryPoint>(input: &str, max_depth: usize) -> core::result::Result<T, Vec<rust_sitter::errors::ParseError>> {
+          let options = rust_sitter::pipeline::ParseOptions {
+              max_depth: Some(max_depth),
+              ..Default::default()
+          };
+          rust_sitter::pipeline::into_result(parse_as_with_options(input, &options))
+      }
+  });
+
+    transformed.push(syn::parse_quote! {
+      pub fn parse_as_with_max_nodes<T: rust_sitter::EntryPoint>(input: &str, max_nodes: usize) -> core::result::Result<T, Vec<rust_sitter::errors::ParseError>> {
+          let options = rust_sitter::pipeline::ParseOptions {
+              max_nodes: Some(max_nodes),
+              ..Default::default()
+          };
+          rust_sitter::pipeline::into_result(parse_as_with_options(input, &options))
+      }
+  });
+
+    // the stack is not one of the options, since extracting on another thread needs an AST
+    // that can be sent back, and there is no shorthand for the root type, which may not be
+    // `Send`
+    transformed.push(syn::parse_quote! {
+      pub fn parse_as_on_larger_stack<T: rust_sitter::EntryPoint + Send>(input: &str) -> core::result::Result<T, Vec<rust_sitter::errors::ParseError>> {
+          let options = Default::default();
+          let tree = rust_sitter::pipeline::parse_tree::<T>(&options, input.len(), |parser| {
+              parser.parse(input, None)
+          })
+          .map_err(|error| vec![error])?;
+
+          rust_sitter::pipeline::into_result(run(&tree, input.as_bytes(), &options, |_| {
+              rust_sitter::recursion::extract_on_larger_stack(&tree, |root_node| {
+                  <T as rust_sitter::Extract<_>>::extract(Some(root_node), input.as_bytes(), Default::default(), None)
+              })
+          }))
+      }
+  });
+
+    // strict parsing reports only the first error, rather than every error Tree Sitter
+    // recovered from
+    transformed.push(syn::parse_quote! {
+      pub fn parse_strict(input: &str) -> core::result::Result<#root_type, Vec<rust_sitter::errors::ParseError>> {
+          parse(input).map_err(|errors| {
+              let mut errors: Vec<_> = errors.into_iter().take(1).collect();
+              for error in &mut errors {
+                  if matches!(
+                      error.reason,
+                      rust_sitter::errors::ParseErrorReason::UnexpectedToken(_)
+                          | rust_sitter::errors::ParseErrorReason::FailedNode(_)
+                          | rust_sitter::errors::ParseErrorReason::MissingToken(_)
+                  ) {
+                      error.expected = expected_at(input, error.start);
+                  }
+              }
+              errors
+          })
+      }
+  });
+
+    // recovering parsing extracts what it can from a tree with errors, for editors where a
+    // partial result is better than none
+    transformed.push(syn::parse_quote! {
+      pub fn parse_recovering(input: &str) -> (Option<#root_type>, Vec<rust_sitter::errors::ParseError>) {
+          let options = rust_sitter::pipeline::ParseOptions {
+              recovering: true,
+              ..Default::default()
+          };
+          parse_with_options(input, &options)
+      }
+  });
+
+    transformed.push(syn::parse_quote! {
+      pub fn parse_with_metrics(input: &str) -> (core::result::Result<#root_type, Vec<rust_sitter::errors::ParseError>>, rust_sitter::metrics::ParseMetrics) {
+          let options = Default::default();
+          let start = std::time::Instant::now();
+          let tree = rust_sitter::pipeline::parse_tree::<#root_type>(&options, input.len(), |parser| {
+              parser.parse(input, None)
+          });
+          let parse_time = start.elapsed();
+
+          match tree {
+              Ok(tree) => {
+                  let metrics = rust_sitter::metrics::ParseMetrics::new(&tree, parse_time);
+                  let parsed = run(&tree, input.as_bytes(), &options, |root_node| {
+                      Ok(<#root_type as rust_sitter::Extract<_>>::extract(Some(root_node), input.as_bytes(), Default::default(), None))
+                  });
+                  (rust_sitter::pipeline::into_result(parsed), metrics)
+              }
+              // there is no tree to measure
+              Err(error) => (
+                  Err(vec![error]),
+                  rust_sitter::metrics::ParseMetrics {
+                      node_count: 0,
+                      error_count: 0,
+                      max_depth: 0,
+                      parse_time,
+                  },
+              ),
           }
       }
   });
 
+    // the length of the input is not known up front, so a parser that fails is reported at
+    // its start
+    transformed.push(syn::parse_quote! {
+      pub fn parse_with<'a, T: rust_sitter::Chunk<'a>, F: FnMut(usize) -> T>(callback: F) -> core::result::Result<#root_type, Vec<rust_sitter::errors::ParseError>> {
+          let source = rust_sitter::ChunkedSource::new(callback);
+          let options = Default::default();
+          let tree = rust_sitter::pipeline::parse_tree::<#root_type>(&options, 0, |parser| {
+              parser.parse_with(&mut |offset, _| source.chunk(offset), None)
+          })
+          .map_err(|error| vec![error])?;
+
+          rust_sitter::pipeline::into_result(run(&tree, &source, &options, |root_node| {
+              Ok(<#root_type as rust_sitter::Extract<_>>::extract(Some(root_node), &source, Default::default(), None))
+          }))
+      }
+  });
+
+    // bytes from outside the program are only parsed once they are known to be UTF-8
+    transformed.push(syn::parse_quote! {
+      pub fn parse_utf8_checked(input: &[u8]) -> core::result::Result<#root_type, Vec<rust_sitter::errors::ParseError>> {
+          parse(rust_sitter::errors::check_utf8(input).map_err(|error| vec![error])?)
+      }
+  });
+
+    // every byte is decoded to a character of its own, so that `bytes` leaves can match
+    // bytes that are not valid UTF-8
+    transformed.push(syn::parse_quote! {
+      pub fn parse_bytes(input: &[u8]) -> core::result::Result<#root_type, Vec<rust_sitter::errors::ParseError>> {
+          let text = rust_sitter::bytes::decode(input);
+          parse(&text).map_err(|errors| rust_sitter::bytes::to_byte_offsets(&text, errors))
+      }
+  });
+
+    // untrusted input, such as from a fuzzer, is reported as errors in every step that could
+    // otherwise panic or overflow the stack, which is kept safe by rejecting trees deeper
+    // than `max_depth` before extraction
+    transformed.push(syn::parse_quote! {
+      pub fn try_parse(input: &[u8], max_depth: usize) -> core::result::Result<#root_type, Vec<rust_sitter::errors::ParseError>> {
+          let input = rust_sitter::errors::check_utf8(input).map_err(|error| vec![error])?;
+          let options = rust_sitter::pipeline::ParseOptions {
+              max_depth: Some(max_depth),
+              catch_panics: true,
+              ..Default::default()
+          };
+          rust_sitter::pipeline::into_result(parse_with_options(input, &options))
+      }
+  });
+
+    // trees parsed elsewhere, such as by a C host, only go through extraction
+    transformed.push(syn::parse_quote! {
+      pub fn extract(tree: &rust_sitter::tree_sitter::Tree, source: &str) -> core::result::Result<#root_type, Vec<rust_sitter::errors::ParseError>> {
+          rust_sitter::pipeline::into_result(run(tree, source.as_bytes(), &Default::default(), |root_node| {
+              Ok(<#root_type as rust_sitter::Extract<_>>::extract(Some(root_node), source.as_bytes(), Default::default(), None))
+          }))
+      }
+  });
+
+    // extraction finds nodes by their kinds and field names, so any language with the same
+    // rule layout can stand in for the bundled one
+    transformed.push(syn::parse_quote! {
+      pub fn parse_with_language(input: &str, language: rust_sitter::tree_sitter::Language) -> core::result::Result<#root_type, Vec<rust_sitter::errors::ParseError>> {
+          let options = rust_sitter::pipeline::ParseOptions {
+              language: Some(language),
+              ..Default::default()
+          };
+          rust_sitter::pipeline::into_result(parse_with_options(input, &options))
+      }
+  });
+
+    // Tree Sitter gives up on a parse that runs out of time without a tree, so a timeout
+    // has no partial result to extract. The parser keeps its progress for the next parse,
+    // but resuming from it crashes in this version of Tree Sitter, so every call starts
+    // over with a parser of its own
+    transformed.push(syn::parse_quote! {
+      pub fn parse_with_timeout(input: &str, timeout_micros: u64) -> core::result::Result<#root_type, Vec<rust_sitter::errors::ParseError>> {
+          let options = rust_sitter::pipeline::ParseOptions {
+              timeout_micros,
+              ..Default::default()
+          };
+          rust_sitter::pipeline::into_result(parse_with_options(input, &options))
+      }
+  });
+
+    if !dialect_gates.is_empty() {
+        transformed.push(syn::parse_quote! {
+          pub fn parse_with_dialects(input: &str, dialects: &[&str]) -> core::result::Result<#root_type, Vec<rust_sitter::errors::ParseError>> {
+              let options = rust_sitter::pipeline::ParseOptions {
+                  dialects: Some(dialects),
+                  ..Default::default()
+              };
+              rust_sitter::pipeline::into_result(parse_with_options(input, &options))
+          }
+      });
+    }
+
+    transformed.push(syn::parse_quote! {
+        /// A reference to the Tree Sitter node of a value of one of the types of the grammar.
+        #[derive(Clone, Copy, Debug)]
+        pub enum NodeRef<'tree> {
+            #(#typed_types(rust_sitter::tree_sitter::Node<'tree>)),*
+        }
+    });
+
+    let (typed_node_kinds, typed_node_types): (Vec<String>, Vec<Ident>) =
+        typed_kinds.into_iter().unzip();
+    transformed.push(syn::parse_quote! {
+        impl<'tree> NodeRef<'tree> {
+            /// Returns the Tree Sitter node, which can be extracted as the referenced type.
+            pub fn node(&self) -> rust_sitter::tree_sitter::Node<'tree> {
+                match self {
+                    #(NodeRef::#typed_types(node))|* => *node
+                }
+            }
+
+            /// Resolves a Tree Sitter node to a reference of its type, or `None` if it is
+            /// not the node of one of the types of the grammar, such as a token.
+            pub fn from_node(node: rust_sitter::tree_sitter::Node<'tree>) -> Option<Self> {
+                match node.kind() {
+                    #(#typed_node_kinds => Some(NodeRef::#typed_node_types(node)),)*
+                    _ => None,
+                }
+            }
+        }
+    });
+
+    transformed.push(syn::parse_quote! {
+        pub fn node_at(tree: &rust_sitter::tree_sitter::Tree, offset: usize) -> Option<NodeRef<'_>> {
+            rust_sitter::typed_node_at(tree, offset, NodeRef::from_node)
+        }
+    });
+
+    transformed.push(syn::parse_quote! {
+        pub fn typed_cursor(tree: &rust_sitter::tree_sitter::Tree) -> rust_sitter::cursor::TypedCursor<'_, NodeRef<'_>> {
+            rust_sitter::cursor::TypedCursor::new(tree, NodeRef::from_node)
+        }
+    });
+
+    transformed.push(syn::parse_quote! {
+        pub fn parse_streaming(input: &str, on_event: impl FnMut(rust_sitter::streaming::NodeEvent)) -> core::result::Result<(), Vec<rust_sitter::errors::ParseError>> {
+            let mut parser = rust_sitter::tree_sitter::Parser::new();
+            parser.set_language(language()).unwrap();
+            let tree = parser.parse(input, None).unwrap();
+            let root_node = tree.root_node();
+
+            if root_node.has_error() {
+                let mut errors = vec![];
+                rust_sitter::errors::collect_parsing_errors(
+                    &root_node,
+                    input.as_bytes(),
+                    &mut errors,
+                );
+
+                Err(errors)
+            } else {
+                rust_sitter::streaming::walk_typed_nodes(&tree, |kind| matches!(kind, #(#typed_node_kinds)|*), on_event);
+                Ok(())
+            }
+        }
+    });
+
+    transformed.push(syn::parse_quote! {
+        pub fn parse_lossless(input: &str) -> core::result::Result<rust_sitter::lossless::LosslessNode, Vec<rust_sitter::errors::ParseError>> {
+            rust_sitter::lossless::parse(language(), input)
+        }
+    });
+
+    let (text_token_kinds, text_token_texts): (Vec<String>, Vec<String>) =
+        text_tokens.into_iter().unzip();
+    transformed.push(syn::parse_quote! {
+        pub fn expected_at(input: &str, byte_offset: usize) -> Vec<String> {
+            rust_sitter::completion::expected_at(
+                language(),
+                &[#((#text_token_kinds, #text_token_texts)),*],
+                input,
+                byte_offset,
+            )
+        }
+    });
+
     let mut filtered_attrs = input.attrs;
     filtered_attrs.retain(|a| !is_sitter_attr(a));
     ItemMod {
@@ -6,6 +6,8 @@ use expansion::*;
 
 #[proc_macro_attribute]
 /// Marks the top level AST node where parsing should start.
+/// Several types can be marked, in which case parsing returns a generated `SourceFile`
+/// enum with a variant for each of them.
 ///
 /// ## Example
 /// ```ignore
@@ -40,15 +42,860 @@ pub fn extra(
     item
 }
 
+#[proc_macro_attribute]
+/// Matches a struct as a single token, so that the extras of the grammar (such as
+/// whitespace and comments) are not skipped inside of it, as in a string literal. The
+/// fields of the struct must be leaves of type `()`, since the token has no children,
+/// and its text can be recovered by wrapping the type in `Spanned` where it is used.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::no_extras]
+/// pub struct Quoted {
+///     #[rust_sitter::leaf(text = "\"")]
+///     _open: (),
+///     #[rust_sitter::leaf(pattern = r#"[^"]*"#)]
+///     _contents: (),
+///     #[rust_sitter::leaf(text = "\"")]
+///     _close: (),
+/// }
+/// ```
+pub fn no_extras(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// Limits the extras of the grammar that can be skipped inside of a struct. This annotation
+/// takes a single, unnamed argument, which must currently be `none`, to skip no extras at
+/// all, as with [`no_extras`]: the struct is matched as a single token, so its fields must
+/// be leaves of type `()`.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::scoped_extras(none)]
+/// pub struct Qualified {
+///     #[rust_sitter::leaf(pattern = r"[a-z]+")]
+///     _module: (),
+///     #[rust_sitter::leaf(text = "::")]
+///     _separator: (),
+///     #[rust_sitter::leaf(pattern = r"[a-z]+")]
+///     _name: (),
+/// }
+/// ```
+pub fn scoped_extras(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// Matches the fields of a struct after the first as immediate tokens, so that the extras
+/// of the grammar cannot be skipped between them, as in a floating point literal. Unlike
+/// `no_extras`, the fields are still extracted as usual, but they must be leaves.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::immediate_seq]
+/// pub struct Float {
+///     #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.to_string())]
+///     integer: String,
+///     #[rust_sitter::leaf(text = ".")]
+///     _dot: (),
+///     #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.to_string())]
+///     fraction: String,
+/// }
+/// ```
+pub fn immediate_seq(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// Accepts the fields of a type in any order, such as the keys of a config file. Each field
+/// must appear exactly once, unless it is an `Option`, which is checked after parsing and
+/// reported as a `ParseErrorReason::MissingField` or `ParseErrorReason::DuplicateField`
+/// error. Fields can't be repeated, since it would be ambiguous where one ends.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::unordered]
+/// pub struct Config {
+///     name: NameKey,
+///     version: VersionKey,
+///     license: Option<LicenseKey>,
+/// }
+/// ```
+pub fn unordered(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// Gives the node of a single field the given kind in the Tree Sitter tree, without changing
+/// the rule it refers to, so that the same type can appear as different kinds depending on
+/// where it is used. Passing `named = true` makes the node named.
+///
+/// ## Example
+/// ```ignore
+/// pub struct Let {
+///     #[rust_sitter::alias_as("type_identifier", named = true)]
+///     ty: Identifier,
+///     #[rust_sitter::alias_as("variable", named = true)]
+///     name: Identifier,
+/// }
+/// ```
+pub fn alias_as(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// Gives the nodes of a type the given kind in the Tree Sitter tree, instead of the name of
+/// the type. Several types can share the same kind, so that they can be queried uniformly
+/// while staying separate types in Rust. Passing `named = true` makes the nodes named.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::alias("operator", named = true)]
+/// pub enum UnaryOp {
+///     ...
+/// }
+/// ```
+pub fn alias(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// Registers an enum as a supertype of the grammar, with the rules of its variants as its
+/// subtypes. Tree Sitter hides the node of the enum, so a query for the enum matches the node
+/// of any of its variants. Every variant must have fields.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::supertype]
+/// pub enum Expr {
+///     Number(Number),
+///     Negation(Negation),
+/// }
+/// ```
+pub fn supertype(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// Defines a struct with a single `leaf` field as a token, whose leaf is the rule of the
+/// struct itself rather than a node nested in it. Other rules refer to the token by its type,
+/// so a leaf such as an identifier is defined once and shared by name. The field cannot be
+/// optional or repeated.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::token]
+/// pub struct Identifier {
+///     #[rust_sitter::leaf(pattern = r"[a-z_]+", transform = |v| v.to_string())]
+///     name: String,
+/// }
+/// ```
+pub fn token(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// Collapses an enum whose variants each wrap a single type, such as the leaves of the
+/// literals of a language, into a hidden rule with the given name that chooses between the
+/// rules of those types. Wherever the enum is used, the node of the matched type appears
+/// directly, without a node for the enum or its variants. The name must start with `_`.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::token_group(_literal)]
+/// pub enum Literal {
+///     Number(Number),
+///     Boolean(Boolean),
+/// }
+/// ```
+pub fn token_group(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// Marks a type as an additional entry point for parsing, so that inputs consisting of just
+/// that type can be parsed with `parse_as::<Type>(...)`. This generates a separate parser
+/// that uses the type as its root, which is useful for testing parts of a grammar.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::entry]
+/// pub struct Statement {
+///     ...
+/// }
+/// ```
+pub fn entry(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// On the top level AST node, makes every type in the grammar derive `Serialize` and
+/// `Deserialize`. This requires the `serde` feature of `rust-sitter` to be enabled.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::language]
+/// #[rust_sitter::serde]
+/// pub enum Expr {
+///     ...
+/// }
+/// ```
+pub fn serde(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// On the top level AST node, calls the given function with a mutable reference to the whole
+/// AST once it is extracted, before the parsing functions of the grammar return it, such as to
+/// resolve references between nodes or to assign them identifiers.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::language]
+/// #[rust_sitter::post_parse(resolve_names)]
+/// pub struct Program {
+///     ...
+/// }
+///
+/// fn resolve_names(program: &mut Program) {
+///     ...
+/// }
+/// ```
+pub fn post_parse(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// On the top level AST node, implements `Default` to produce the empty AST, in which every
+/// repeated or optional field is empty. Roots with a field that cannot be empty, and enums,
+/// do not implement `Default`, and the build of the grammar warns about them.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::language]
+/// #[rust_sitter::default_empty]
+/// pub struct Program {
+///     pub statements: Vec<Statement>,
+/// }
+/// ```
+pub fn default_empty(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// On the top level AST node, makes every type in the grammar implement
+/// `rust_sitter::SemanticEq`, which compares trees while ignoring the spans of `Spanned`
+/// values, so that inputs which only differ in formatting compare equal.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::language]
+/// #[rust_sitter::semantic_eq]
+/// pub enum Expr {
+///     ...
+/// }
+/// ```
+pub fn semantic_eq(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// On the top level AST node, makes every enum in the grammar implement `rust_sitter::Tagged`,
+/// which gives each variant a `u16` tag, such as for a compact binary serialization. The tags
+/// number the variants in the order of their names, so they do not change when the variants are
+/// reordered.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::language]
+/// #[rust_sitter::tags]
+/// pub enum Expr {
+///     ...
+/// }
+/// ```
+pub fn tags(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// On a grammar module, lists the extras that can safely be skipped while parsing, in
+/// addition to any types marked with [`rust_sitter::extra`]. Each argument is either a
+/// string literal, which is used as a regular expression, or the name of a type in the grammar.
+///
+/// Without this annotation or any `extra` types, Tree Sitter skips whitespace by default.
+/// Passing an empty list disables this, so that every character of the input is significant.
+///
+/// This annotation can be repeated. The extras are emitted in source order, which matters
+/// for tokenization: the lists of each annotation in turn, followed by the `extra` types.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::grammar("layout")]
+/// #[rust_sitter::extras()]
+/// mod grammar {
+///     ...
+/// }
+/// ```
+pub fn extras(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// On a grammar module, skips generating the AST types, so that the types in the module
+/// only define the grammar. Instead, the module's `parse` function returns a
+/// dynamically typed `rust_sitter::syntax::SyntaxNode` tree.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::grammar("config")]
+/// #[rust_sitter::untyped]
+/// mod grammar {
+///     ...
+/// }
+/// ```
+pub fn untyped(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// On a grammar module, generates a grammar that only splits the input into tokens, for
+/// uses such as syntax highlighting that do not need the full parse. The root of the grammar
+/// repeats a choice of every leaf of the types in the module, which are otherwise ignored,
+/// and the module's `tokenize` function returns the token nodes as a list of dynamically
+/// typed `rust_sitter::syntax::SyntaxNode`s, as with `untyped`.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::grammar("tokens")]
+/// #[rust_sitter::tokenizer_only]
+/// mod grammar {
+///     ...
+/// }
+/// ```
+pub fn tokenizer_only(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// On a grammar module, skips generating the extraction of the AST types, including their
+/// transforms and spans, to compile as fast as possible while working on the shape of the
+/// grammar. The types are kept as they are written, but the module's `parse` function
+/// returns a dynamically typed `rust_sitter::syntax::SyntaxNode` tree, as with `untyped`.
+/// Removing the annotation restores the typed `parse` without changing the grammar.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::grammar("arithmetic")]
+/// #[rust_sitter::debug_fast]
+/// mod grammar {
+///     ...
+/// }
+/// ```
+pub fn debug_fast(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// On a grammar module, extracts the AST into an arena rather than allocating each boxed
+/// child on its own, which saves many small allocations on deeply recursive inputs. The AST
+/// types take a lifetime and refer to their children through `&'a` references instead of
+/// `Box`es, and the module has a `parse_in` function that takes the arena to allocate them
+/// in, a `rust_sitter::arena::Bump`. This requires the `arena` feature of `rust-sitter`.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::grammar("arithmetic")]
+/// #[rust_sitter::arena]
+/// mod grammar {
+///     #[rust_sitter::language]
+///     pub enum Expr<'a> {
+///         Number(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] i32),
+///         #[rust_sitter::prec_left(1)]
+///         Add(&'a Expr<'a>, #[rust_sitter::leaf(text = "+")] (), &'a Expr<'a>),
+///     }
+/// }
+///
+/// let arena = rust_sitter::arena::Bump::new();
+/// let expr = grammar::parse_in("1 + 2", &arena);
+/// ```
+pub fn arena(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// On a grammar module, lists the file extensions that the grammar applies to. These are
+/// not part of the Tree Sitter grammar, but are written by the build tool to a
+/// `<grammar name>.metadata.json` file in `OUT_DIR` for use by editor integrations.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::grammar("config")]
+/// #[rust_sitter::file_types("toml", "ini")]
+/// mod grammar {
+///     ...
+/// }
+/// ```
+pub fn file_types(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// Attaches `key = value` metadata to the rule of a struct, enum, or enum variant for
+/// tools built on top of the grammar. The metadata does not change the grammar, and is
+/// written to the metadata file of the grammar keyed by the rule name.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::meta(category = "declaration", scoped = true)]
+/// pub struct Function {
+///     ...
+/// }
+/// ```
+pub fn meta(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// Captures the nodes of a struct, enum, enum variant, or leaf with the given name in the
+/// `highlights.scm` query generated for the grammar, such as `"keyword"` or `"number"`.
+///
+/// ## Example
+/// ```ignore
+/// pub struct Let {
+///     #[rust_sitter::highlight("keyword")]
+///     #[rust_sitter::leaf(text = "let")]
+///     _let: (),
+///     ...
+/// }
+/// ```
+pub fn highlight(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// Marks the nodes of a struct, enum, or enum variant as scopes in the `locals.scm` query
+/// generated for the grammar, such as a block whose bindings are only visible inside it.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::scope]
+/// pub struct Block {
+///     ...
+/// }
+/// ```
+pub fn scope(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// Marks the given field of a struct or enum variant as a definition in the `locals.scm`
+/// query generated for the grammar, such as the name bound by a `let`.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::definition(field = "name")]
+/// pub struct Let {
+///     name: Identifier,
+///     ...
+/// }
+/// ```
+pub fn definition(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// Marks the nodes of a struct, enum, or enum variant as references in the `locals.scm`
+/// query generated for the grammar, which editors resolve to the definitions in scope.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::reference]
+/// pub struct Identifier {
+///     ...
+/// }
+/// ```
+pub fn reference(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// On a grammar module, matches the `text` leaves of fields and the delimiters of vectors
+/// as anonymous nodes, so that punctuation does not clutter the tree. Types and enum
+/// variants, including unit variants with a `text` leaf, keep their named nodes.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::grammar("calls")]
+/// #[rust_sitter::hide_anonymous]
+/// mod grammar {
+///     ...
+/// }
+/// ```
+pub fn hide_anonymous(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// On a grammar module, makes every `text` leaf match its text in any letter case, as
+/// is common for the keywords of SQL-like languages. Leaves defined with a `pattern` are
+/// unaffected unless they pass `case_insensitive = true` themselves.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::grammar("sql")]
+/// #[rust_sitter::case_insensitive_keywords]
+/// mod grammar {
+///     ...
+/// }
+/// ```
+pub fn case_insensitive_keywords(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// On a grammar module, allows the input to start with a byte-order mark and a shebang
+/// line (such as `#!/usr/bin/env lang`) before the root type. Both are optional and
+/// are skipped during extraction.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::grammar("script")]
+/// #[rust_sitter::allow_shebang]
+/// mod grammar {
+///     ...
+/// }
+/// ```
+pub fn allow_shebang(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// On a grammar module, requires the input to end with the given token, such as an end
+/// marker, after the root type. Input without it fails to parse, and the token is skipped
+/// during extraction.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::grammar("script")]
+/// #[rust_sitter::trailing_token("__END__")]
+/// mod grammar {
+///     ...
+/// }
+/// ```
+pub fn trailing_token(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// On a grammar module, requires the input to end with a newline, as with
+/// `#[rust_sitter::trailing_token("\n")]`. Newlines are then left out of the default
+/// whitespace extras, so the input ends at the first newline outside of the root type.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::grammar("script")]
+/// #[rust_sitter::require_trailing_newline]
+/// mod grammar {
+///     ...
+/// }
+/// ```
+pub fn require_trailing_newline(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// On a grammar module, uses the single leaf whose pattern looks like an identifier
+/// (such as `[a-zA-Z_][a-zA-Z0-9_]*`) as the word rule, as if it was marked with
+/// `#[rust_sitter::word]`. Nothing is inferred if several leaves look like identifiers.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::grammar("lang")]
+/// #[rust_sitter::infer_word]
+/// mod grammar {
+///     ...
+/// }
+/// ```
+pub fn infer_word(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// On a grammar module, declares groups of rules whose conflicts Tree Sitter should resolve
+/// at runtime by trying each of them, rather than failing to generate the parser. Each group
+/// is a list of rule names, which are the names of types or `Enum_Variant` for variants.
+/// With the `check_conflicts` feature, the build tool warns about groups that are never needed.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::grammar("calls")]
+/// #[rust_sitter::conflicts([Expr_Call])]
+/// mod grammar {
+///     #[rust_sitter::language]
+///     pub enum Expr {
+///         Number(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] i32),
+///         Call(Box<Expr>, Box<Expr>),
+///     }
+/// }
+/// ```
+pub fn conflicts(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// On a grammar module, declares an ordering of named precedence levels, from the level that
+/// binds most tightly to the one that binds least, which `prec`, `prec_left` and `prec_right`
+/// can then refer to by name instead of by an integer. Several orderings can be given in the
+/// list, and levels that do not share an ordering are not compared.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::grammar("named_precedences")]
+/// #[rust_sitter::precedences(["unary" > "binary"])]
+/// mod grammar {
+///     #[rust_sitter::language]
+///     pub enum Expr {
+///         Number(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] i32),
+///         #[rust_sitter::prec_left("unary")]
+///         Neg(#[rust_sitter::leaf(text = "-")] (), Box<Expr>),
+///         #[rust_sitter::prec_left("binary")]
+///         Sub(Box<Expr>, #[rust_sitter::leaf(text = "-")] (), Box<Expr>),
+///     }
+/// }
+/// ```
+pub fn precedences(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// On a grammar module, declares tokens that are recognized by an external scanner
+/// written in C instead of by a regular expression. The build tool compiles the scanner
+/// from `<grammar name>_scanner.c` next to the root file, and a stub for it can be
+/// generated with `rust_sitter_tool::generate_scanner_stubs`.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::grammar("python")]
+/// #[rust_sitter::external(Indent, Dedent)]
+/// mod grammar {
+///     ...
+/// }
+/// ```
+pub fn external(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// Matches one of the tokens declared with `#[rust_sitter::external(...)]` on a field of
+/// type `()`, or an optional one on a field of type `Option<()>`, taking the name of the
+/// token as its only argument. The token must be declared on the grammar module.
+///
+/// ## Example
+/// ```ignore
+/// pub struct Block {
+///     #[rust_sitter::external_token(Indent)]
+///     _indent: (),
+///     body: Vec<Statement>,
+///     #[rust_sitter::external_token(Dedent)]
+///     _dedent: (),
+/// }
+/// ```
+pub fn external_token(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// On a grammar module, merges in the rules of another grammar module, so that its types
+/// can be used in this grammar after importing them with `use`. The imported rules are
+/// renamed to avoid collisions with local rules, while their nodes keep their original kinds.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::grammar("assignments")]
+/// #[rust_sitter::use_rules(crate::common::grammar::*)]
+/// mod grammar {
+///     use crate::common::grammar::Identifier;
+///     ...
+/// }
+/// ```
+pub fn use_rules(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
 #[proc_macro_attribute]
 /// Defines a field which matches a specific token in the source string.
 /// The token can be defined by passing one of two arguments
-/// - `text`: a string literal that will be exactly matched
+/// - `text`: a string literal that will be exactly matched, or an array of string literals
+///   where any one of them will be matched
 /// - `pattern`: a regular expression that will be matched against the source string
 ///
 /// If the resulting token needs to be converted into a richer type at runtime,
 /// such as a number, then the `transform` argument can be used to specify a function
-/// that will be called with the token's text.
+/// that will be called with the token's text. This is either a closure or a path to a
+/// function, such as `parse_hex` or `String::from`.
+///
+/// A `transform` closure with a second parameter, such as
+/// `|v: &str, ctx: &mut Interner| ctx.intern(v)`, is given the context passed to the
+/// generated `parse_with_context` function.
+///
+/// The `validate` argument takes a function that checks the result of the `transform`,
+/// returning either a `bool` or a `Result<(), E>`, such as
+/// `|v: &u32| (0..=100).contains(v)`. A rejected value makes parsing fail with an error
+/// that spans the leaf.
+///
+/// To transform the text differently depending on which of several patterns matched, the
+/// `alternatives` argument takes an array of `(pattern, transform)` pairs instead, such as
+/// `[(r"0x[0-9a-f]+", parse_hex), (r"\d+", parse_dec)]`.
+///
+/// Passing `unescape = true` replaces the standard escape sequences (such as `\n` and
+/// `\uXXXX`) in the token's text before it is transformed, or produces the unescaped
+/// `String` directly if there is no `transform`. Without a `transform`, the field can also
+/// be a `Cow<'a, str>` in a grammar annotated with `#[rust_sitter::arena]`, which borrows the
+/// input unless the token has escape sequences.
+///
+/// Passing `trim = "..."` removes any of the given characters from both ends of the token's
+/// text before it is transformed, or produces the trimmed `String` directly if there is no
+/// `transform`, such as `trim = "\""` for the contents of a quoted string. `trim_start` and
+/// `trim_end` only remove characters from one end.
+///
+/// Passing `keep_raw = true` keeps the token's text alongside the transformed value, in a
+/// field of type `rust_sitter::WithRaw<T>`.
+///
+/// Passing `case_insensitive = true` makes the ASCII letters of a `pattern` match in
+/// either case.
+///
+/// Passing `any = true` instead of a `pattern` or `text` matches any single character,
+/// including a newline, which the regular expression `.` does not match.
+///
+/// Passing `bytes = [0x7F, 0x45, 0x4C, 0x46]` instead of a `text` matches that exact byte
+/// sequence, including bytes that are not valid UTF-8, when parsing with the generated
+/// `parse_bytes` function.
+///
+/// Passing `anchored = true` makes a `pattern` only match when its match ends at a word
+/// boundary, instead of matching a prefix of a longer run of letters, digits and
+/// underscores, which is reported as an unexpected token.
+///
+/// Passing `token_prec = N` gives the token a lexical precedence, so that it is preferred
+/// over other tokens that match the same text, such as `==` over a pattern that also
+/// matches `=`.
+///
+/// Passing `name = "..."` gives a `text` leaf a named node of that kind, so that it can be
+/// queried by name rather than by its text.
+///
+/// On a struct, the `pattern` makes the whole struct a single token, and each field is
+/// parsed from the named capture group (`(?P<name>...)`) of the same name.
 ///
 /// ## Example
 /// ```ignore
@@ -87,10 +934,13 @@ pub fn skip(
 #[proc_macro_attribute]
 /// Defines a precedence level for a non-terminal that has no associativity.
 ///
-/// This annotation takes a single, unnamed parameter, which specifies the precedence level.
+/// This annotation takes a single, unnamed parameter, which specifies the precedence level,
+/// either as an integer or as the name of a level declared with `#[rust_sitter::precedences(...)]`.
 /// This is used to resolve conflicts with other non-terminals, so that the one with the higher
 /// precedence will bind more tightly (appear lower in the parse tree).
 ///
+/// On an enum, the precedence applies to every variant without a precedence of its own.
+///
 /// ## Example
 /// ```ignore
 /// #[rust_sitter::prec(1)]
@@ -103,12 +953,30 @@ pub fn prec(
     item
 }
 
+#[proc_macro_attribute]
+/// Marks a struct or enum variant as a construct of the dialect with the given name.
+/// Tree Sitter always parses it, but `parse_with_dialects` rejects it with an error unless
+/// its dialect is enabled. With several of these annotations, any of the dialects enables it.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::dialect("extended")]
+/// Power(Box<Expr>, #[rust_sitter::leaf(text = "**")] (), Box<Expr>)
+/// ```
+pub fn dialect(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
 #[proc_macro_attribute]
 /// Defines a precedence level for a non-terminal that should be left-associative.
 /// For example, with subtraction we expect 1 - 2 - 3 to be parsed as (1 - 2) - 3,
 /// which corresponds to a left-associativity.
 ///
-/// This annotation takes a single, unnamed parameter, which specifies the precedence level.
+/// This annotation takes a single, unnamed parameter, which specifies the precedence level,
+/// either as an integer or as the name of a level declared with `#[rust_sitter::precedences(...)]`.
 /// This is used to resolve conflicts with other non-terminals, so that the one with the higher
 /// precedence will bind more tightly (appear lower in the parse tree).
 ///
@@ -129,7 +997,8 @@ pub fn prec_left(
 /// For example, with cons we could have 1 :: 2 :: 3 to be parsed as 1 :: (2 :: 3),
 /// which corresponds to a right-associativity.
 ///
-/// This annotation takes a single, unnamed parameter, which specifies the precedence level.
+/// This annotation takes a single, unnamed parameter, which specifies the precedence level,
+/// either as an integer or as the name of a level declared with `#[rust_sitter::precedences(...)]`.
 /// This is used to resolve conflicts with other non-terminals, so that the one with the higher
 /// precedence will bind more tightly (appear lower in the parse tree).
 ///
@@ -151,7 +1020,9 @@ pub fn prec_right(
 ///
 /// This annotation takes a single, unnamed argument, which specifies a field type to parse. This can
 /// either be a reference to another type, or can be defined as a `leaf` field. Generally, the argument
-/// is parsed using the same rules as an unnamed field of an enum variant.
+/// is parsed using the same rules as an unnamed field of an enum variant. It can be followed by a
+/// `sep_prec` argument with the precedence of the separator, which settles conflicts with the same
+/// token in the elements.
 ///
 /// ## Example
 /// ```ignore
@@ -160,6 +1031,13 @@ pub fn prec_right(
 ///     ()
 /// )]
 /// numbers: Vec<Number>
+///
+/// #[rust_sitter::delimited(
+///     #[rust_sitter::leaf(text = ",")]
+///     (),
+///     sep_prec = 2
+/// )]
+/// arguments: Vec<Expr>
 /// ```
 pub fn delimited(
     _attr: proc_macro::TokenStream,
@@ -168,15 +1046,77 @@ pub fn delimited(
     item
 }
 
+#[proc_macro_attribute]
+/// On `Vec<_>` typed fields, matches the elements with a right-recursive rule rather than a
+/// repetition, as for a cons-style list such as `a : b : c`, where each element is followed
+/// by the rest of the list. The elements are still extracted in the order they appear in,
+/// and the field can be combined with [`repeat`] and [`delimited`], but not with a fixed
+/// number of elements.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::right_recursive]
+/// #[rust_sitter::repeat(sep = ":", non_empty = true)]
+/// items: Vec<Item>
+/// ```
+pub fn right_recursive(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// Gives the Tree Sitter field holding a Rust field a name of its own, such as `type` for a
+/// field named `ty`, since `type` is a keyword in Rust. The name appears in the generated
+/// grammar and in queries, while the rules of the field keep following the Rust name.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::field("type")]
+/// ty: Type
+/// ```
+pub fn field(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// Makes a field optional in the grammar without changing its type to `Option<_>`. When the
+/// field is absent, the default value of its type is extracted, so the type must implement
+/// `Default`. This annotation cannot be used on a field that is already an `Option<_>`.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::optional]
+/// #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+/// count: u32
+/// ```
+pub fn optional(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
 #[proc_macro_attribute]
 /// On `Vec<_>` typed fields, specifies additional config for how the repeated elements should
 /// be parsed. In particular, this annotation takes the following named arguments:
 /// - `non_empty` - if this argument is `true`, then there must be at least one element parsed
+/// - `count` - the exact number of elements, which is the length of the array for fields of
+///   a fixed-size array type such as `[Octet; 6]`
+/// - `sep` - the text of a separator between the elements, as a shorthand for [`delimited`]
+/// - `sep_prec` - the precedence of the separator given by `sep`, as for [`delimited`]
 ///
 /// ## Example
 /// ```ignore
 /// #[rust_sitter::repeat(non_empty = true)]
 /// numbers: Vec<Number>
+///
+/// #[rust_sitter::repeat(sep = ":")]
+/// octets: [Octet; 6]
 /// ```
 pub fn repeat(
     _attr: proc_macro::TokenStream,
@@ -185,9 +1125,167 @@ pub fn repeat(
     item
 }
 
+#[proc_macro_attribute]
+/// On an enum, sets the case of the texts of its unit variants without a leaf, which are
+/// matched by their names in snake case by default. This annotation takes a single, unnamed
+/// argument with one of `"snake_case"`, `"kebab-case"`, `"SCREAMING_SNAKE_CASE"`,
+/// `"lowercase"`, `"UPPERCASE"` or `"PascalCase"`.
+///
+/// On a grammar module, sets the case of the names of all of its rules, and so of the kinds
+/// of its nodes, instead. The only case for rules is `"snake_case"`, which names the rule of
+/// `Expression::Number` `expression_number` rather than `Expression_Number`, following the
+/// convention of grammars written for Tree Sitter directly.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::rename_all("UPPERCASE")]
+/// pub enum Keyword {
+///     Select,
+///     From,
+/// }
+///
+/// #[rust_sitter::grammar("arithmetic")]
+/// #[rust_sitter::rename_all("snake_case")]
+/// mod grammar {
+///     // ...
+/// }
+/// ```
+pub fn rename_all(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// Makes a unit enum variant a keyword, which is only matched as a whole word rather than
+/// as the prefix of a longer identifier. This annotation takes a single, unnamed argument
+/// with the text of the keyword. The grammar needs a [`rust_sitter::word`] rule, which is
+/// inferred from the leaves like with `#[rust_sitter::infer_word]` if none is marked.
+///
+/// ## Example
+/// ```ignore
+/// pub enum LoopKind {
+///     #[rust_sitter::keyword("in")]
+///     In,
+///     #[rust_sitter::keyword("of")]
+///     Of,
+/// }
+/// ```
+pub fn keyword(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// Surrounds a field with an opening and a closing text, which are matched as anonymous
+/// tokens. This annotation takes the following named arguments:
+/// - `open` - the text that comes before the field
+/// - `close` - the text that comes after the field
+///
+/// ## Example
+/// ```ignore
+/// Paren(#[rust_sitter::bracketed(open = "(", close = ")")] Items)
+/// ```
+pub fn bracketed(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// Matches syntax-only leaves before and after a field, such as keywords and punctuation,
+/// which are anonymous tokens without a field of their own in the Rust type. This
+/// annotation takes the following named arguments, of which at least one must be given:
+/// - `before` - the text that comes before the field
+/// - `after` - the text that comes after the field
+///
+/// ## Example
+/// ```ignore
+/// pub struct Paren {
+///     #[rust_sitter::syntax(before = "(", after = ")")]
+///     inner: Box<Expr>,
+/// }
+/// ```
+pub fn syntax(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// Matches a newline on a field of type `()`, or an optional one on a field of type
+/// `Option<()>`, such as the end of a statement. Newlines are also added to the extras, so
+/// they are only significant in the parse states where the grammar expects one and are
+/// skipped everywhere else, such as between brackets. The grammar's other extras must not
+/// match newlines themselves.
+///
+/// ## Example
+/// ```ignore
+/// pub struct Statement {
+///     expr: Expr,
+///     #[rust_sitter::newline]
+///     _end: (),
+/// }
+/// ```
+pub fn newline(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// Defines a field which matches any one of a set of operators, taking a single, unnamed
+/// array of string literals. The operators are matched as a single token, so the longest
+/// one that matches always wins, such as `<<=` over `<<` followed by `=`. The field holds
+/// the text of the matched operator as a `String`, unless a `#[rust_sitter::leaf]` on the
+/// field gives a `transform`.
+///
+/// ## Example
+/// ```ignore
+/// Compare(
+///     Box<Expr>,
+///     #[rust_sitter::operators(["<", "<=", "<<", "<<="])]
+///     String,
+///     Box<Expr>,
+/// )
+/// ```
+pub fn operators(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
 /// Mark a module to be analyzed for a Rust Sitter grammar. Takes a single, unnamed argument, which
 /// specifies the name of the grammar. This name must be unique across all Rust Sitter grammars within
-/// a compilation unit.
+/// a compilation unit. Without it, the name of the module is used.
+///
+/// The name can be followed by named arguments that configure the generated grammar:
+/// - `name` - overrides the name of the grammar, which determines the symbols of the generated
+///   parser (such as `tree_sitter_<name>`). Characters that cannot appear in a C identifier are
+///   replaced by underscores
+/// - `named_root` - if this argument is `true`, the root type's rule is used directly as the start
+///   rule, so the root node of the parse tree has the root type's name instead of `source_file`
+/// - `prefix_rules` - if this argument is `true`, every rule name, and so every node kind, is
+///   prefixed with the grammar name and an underscore, such as `arithmetic_Expression_Number`
+/// - `merge_leaves` - if this argument is `true`, the rules of leaves with identical patterns are
+///   merged into one rule, the first of them by name, to shrink the generated parser
+/// - `max_token_len` - a number of bytes that no token may exceed, past which parsing fails with a
+///   `ParseErrorReason::TokenTooLong` error, to catch patterns such as `.*` that run away
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::grammar("arithmetic", named_root = true)]
+/// mod grammar {
+///     ...
+/// }
+/// ```
 #[proc_macro_attribute]
 pub fn grammar(
     attr: proc_macro::TokenStream,
@@ -334,6 +1432,163 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn enum_serde() {
+        insta::assert_display_snapshot!(rustfmt_code(
+            &expand_grammar(parse_quote! {
+                #[rust_sitter::grammar("test")]
+                mod grammar {
+                    #[rust_sitter::language]
+                    #[rust_sitter::serde]
+                    pub enum Expression {
+                        Number(
+                            #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] i32,
+                        ),
+                        Neg(
+                            #[rust_sitter::leaf(text = "-")] (),
+                            Box<Expression>,
+                        ),
+                    }
+
+                    #[rust_sitter::extra]
+                    struct Whitespace {
+                        #[rust_sitter::leaf(pattern = r"\s")]
+                        _whitespace: (),
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn enum_semantic_eq() {
+        insta::assert_display_snapshot!(rustfmt_code(
+            &expand_grammar(parse_quote! {
+                #[rust_sitter::grammar("test")]
+                mod grammar {
+                    use rust_sitter::Spanned;
+
+                    #[rust_sitter::language]
+                    #[rust_sitter::semantic_eq]
+                    pub enum Expression {
+                        Number(
+                            #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] Spanned<i32>,
+                        ),
+                        Neg {
+                            #[rust_sitter::leaf(text = "-")]
+                            _minus: (),
+                            value: Box<Expression>,
+                            #[rust_sitter::skip(false)]
+                            visited: bool,
+                        },
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn enum_leaf_variants() {
+        insta::assert_display_snapshot!(rustfmt_code(
+            &expand_grammar(parse_quote! {
+                #[rust_sitter::grammar("test")]
+                mod grammar {
+                    #[rust_sitter::language]
+                    pub enum Value {
+                        #[rust_sitter::leaf(text = "true")]
+                        True,
+                        Number(
+                            #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] i32,
+                        ),
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn grammar_untyped() {
+        insta::assert_display_snapshot!(rustfmt_code(
+            &expand_grammar(parse_quote! {
+                #[rust_sitter::grammar("test")]
+                #[rust_sitter::untyped]
+                mod grammar {
+                    #[rust_sitter::language]
+                    pub struct Assignment {
+                        #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                        key: (),
+                        #[rust_sitter::leaf(text = "=")]
+                        _eq: (),
+                        #[rust_sitter::leaf(pattern = r"\d+")]
+                        value: (),
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn grammar_debug_fast() {
+        insta::assert_display_snapshot!(rustfmt_code(
+            &expand_grammar(parse_quote! {
+                #[rust_sitter::grammar("test")]
+                #[rust_sitter::debug_fast]
+                mod grammar {
+                    #[rust_sitter::language]
+                    pub enum Expression {
+                        Number(
+                            #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                            i32
+                        ),
+                        #[rust_sitter::prec_left(1)]
+                        Sub(
+                            Box<Expression>,
+                            #[rust_sitter::leaf(text = "-")] (),
+                            Box<Expression>,
+                        ),
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn grammar_arena() {
+        insta::assert_display_snapshot!(rustfmt_code(
+            &expand_grammar(parse_quote! {
+                #[rust_sitter::grammar("test")]
+                #[rust_sitter::arena]
+                mod grammar {
+                    #[rust_sitter::language]
+                    pub enum Expression<'a> {
+                        Number(
+                            #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                            i32
+                        ),
+                        #[rust_sitter::prec_left(1)]
+                        Sub(
+                            &'a Expression<'a>,
+                            #[rust_sitter::leaf(text = "-")] (),
+                            &'a Expression<'a>,
+                        ),
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
     #[test]
     fn grammar_unboxed_field() {
         insta::assert_display_snapshot!(rustfmt_code(
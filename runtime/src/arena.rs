@@ -0,0 +1,49 @@
+use std::cell::Cell;
+
+use crate::{tree_sitter, Extract, Source};
+
+pub use bumpalo::Bump;
+
+thread_local! {
+    static ARENA: Cell<Option<*const Bump>> = const { Cell::new(None) };
+}
+
+/// Puts back the arena that was current before, even if extraction panics.
+struct Restore(Option<*const Bump>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        ARENA.with(|a| a.set(self.0));
+    }
+}
+
+/// Makes `arena` the allocator of the references extracted while `f` runs on the current
+/// thread. This is how the generated `parse_in` allocates the nodes of the AST.
+///
+/// # Safety
+/// Every reference extracted by `f` must not outlive `arena`, which holds for an AST whose
+/// lifetime is tied to the borrow of the arena, as the one returned by `parse_in` is.
+pub unsafe fn provide_arena<R>(arena: &Bump, f: impl FnOnce() -> R) -> R {
+    let _restore = Restore(ARENA.with(|a| a.replace(Some(arena as *const Bump))));
+    f()
+}
+
+/// A reference is extracted by allocating the extracted value in the arena given to the
+/// surrounding [`provide_arena`].
+impl<'a, T: Extract<U>, U: 'a> Extract<&'a U> for &'a T {
+    type LeafFn = T::LeafFn;
+    fn extract<S: Source + ?Sized>(
+        node: Option<tree_sitter::Node>,
+        source: &S,
+        last_idx: (usize, crate::Point),
+        leaf_fn: Option<&Self::LeafFn>,
+    ) -> &'a U {
+        let value = T::extract(node, source, last_idx, leaf_fn);
+        let arena = ARENA.with(|a| a.get()).unwrap_or_else(|| {
+            panic!("References in the AST can only be extracted with `parse_in`, which provides an arena")
+        });
+
+        // SAFETY: `provide_arena` requires the arena to outlive every extracted reference
+        unsafe { &*arena }.alloc(value)
+    }
+}
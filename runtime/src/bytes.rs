@@ -0,0 +1,41 @@
+use crate::errors::{ParseError, ParseErrorReason};
+
+/// Decodes bytes as Latin-1, giving each byte the character with the same code point, so
+/// that a Tree Sitter parser, which only reads characters, can match every byte on its own,
+/// including bytes that are not valid UTF-8.
+pub fn decode(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| b as char).collect()
+}
+
+/// Encodes the text of a leaf decoded with [`decode`] back into the bytes it was decoded
+/// from, such as in the `transform` of a leaf that should hold the raw bytes.
+pub fn encode(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| u8::try_from(c).expect("Expected a character decoded from a byte"))
+        .collect()
+}
+
+/// Converts an offset into text decoded with [`decode`] into the offset of the same
+/// position in the bytes it was decoded from.
+pub fn byte_offset(text: &str, offset: usize) -> usize {
+    text[..offset].chars().count()
+}
+
+/// Converts the offsets of errors from parsing text decoded with [`decode`] into offsets
+/// into the bytes it was decoded from, along with the offsets of their nested errors.
+pub fn to_byte_offsets(text: &str, errors: Vec<ParseError>) -> Vec<ParseError> {
+    errors
+        .into_iter()
+        .map(|error| ParseError {
+            reason: match error.reason {
+                ParseErrorReason::FailedNode(inner) => {
+                    ParseErrorReason::FailedNode(to_byte_offsets(text, inner))
+                }
+                reason => reason,
+            },
+            start: byte_offset(text, error.start),
+            end: byte_offset(text, error.end),
+            ..error
+        })
+        .collect()
+}
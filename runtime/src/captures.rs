@@ -0,0 +1,68 @@
+use std::{fmt::Debug, str::FromStr, sync::OnceLock};
+
+use regex::Regex;
+
+/// The pattern of a leaf on a struct, whose named capture groups fill the fields of the
+/// struct. The pattern is only compiled the first time a leaf is extracted.
+pub struct CapturePattern {
+    pattern: &'static str,
+    regex: OnceLock<Regex>,
+}
+
+impl CapturePattern {
+    pub const fn new(pattern: &'static str) -> Self {
+        CapturePattern {
+            pattern,
+            regex: OnceLock::new(),
+        }
+    }
+
+    /// Matches the whole text of a leaf against the pattern. Tree Sitter already matched
+    /// the text with the same pattern, so this only fails if the two regex engines
+    /// disagree on it.
+    pub fn captures<'t>(&self, text: &'t str) -> Captures<'t> {
+        let regex = self.regex.get_or_init(|| {
+            Regex::new(&format!("^(?:{})$", self.pattern))
+                .unwrap_or_else(|e| panic!("Invalid pattern `{}`: {e}", self.pattern))
+        });
+
+        Captures(regex.captures(text).unwrap_or_else(|| {
+            panic!(
+                "The text `{text}` does not match the pattern `{}`",
+                self.pattern
+            )
+        }))
+    }
+}
+
+/// The capture groups of the text of a leaf.
+pub struct Captures<'t>(regex::Captures<'t>);
+
+impl<'t> Captures<'t> {
+    /// Parses the text of the group with the given name, or returns `None` if the group
+    /// did not take part in the match.
+    pub fn get<T: FromStr>(&self, name: &str) -> Option<T>
+    where
+        T::Err: Debug,
+    {
+        self.0.name(name).map(|m| {
+            m.as_str().parse().unwrap_or_else(|e| {
+                panic!(
+                    "Failed to parse the capture group `{name}` from `{}`: {e:?}",
+                    m.as_str()
+                )
+            })
+        })
+    }
+
+    /// Parses the text of the group with the given name, which must have taken part in
+    /// the match.
+    pub fn require<T: FromStr>(&self, name: &str) -> T
+    where
+        T::Err: Debug,
+    {
+        self.get(name).unwrap_or_else(|| {
+            panic!("The capture group `{name}` did not match, so its field must be an `Option`")
+        })
+    }
+}
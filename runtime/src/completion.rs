@@ -0,0 +1,116 @@
+use crate::tree_sitter;
+
+/// Lists the tokens that could legally appear at `byte_offset` in `source`, which is
+/// useful for autocompletion on possibly incomplete input.
+///
+/// This is a best-effort analysis: the text before the offset is re-parsed with each
+/// candidate token appended, and a candidate is kept if Tree Sitter lexes it as that
+/// token without reporting an error around it. Candidates are the `text` leaves of the
+/// grammar (passed as pairs of rule name and literal text) and samples of every other
+/// token found in `source`, plus any token that Tree Sitter would insert to complete
+/// the text before the offset.
+///
+/// Tokens with a known literal text are returned as that text, while other tokens are
+/// returned as the name of their rule.
+pub fn expected_at(
+    language: tree_sitter::Language,
+    text_tokens: &[(&str, &str)],
+    source: &str,
+    byte_offset: usize,
+) -> Vec<String> {
+    let mut byte_offset = byte_offset.min(source.len());
+    while !source.is_char_boundary(byte_offset) {
+        byte_offset -= 1;
+    }
+    let prefix = &source[..byte_offset];
+
+    let mut parser = tree_sitter::Parser::new();
+    parser.set_language(language).unwrap();
+
+    let mut candidates: Vec<(String, String)> = text_tokens
+        .iter()
+        .map(|(kind, text)| (kind.to_string(), text.to_string()))
+        .collect();
+    let source_tree = parser.parse(source, None).unwrap();
+    collect_token_samples(&source_tree.root_node(), source.as_bytes(), &mut candidates);
+
+    let display_name = |kind: &str| {
+        text_tokens
+            .iter()
+            .find(|(k, _)| *k == kind)
+            .map(|(_, text)| text.to_string())
+            .unwrap_or_else(|| kind.to_string())
+    };
+
+    let mut expected = vec![];
+
+    let prefix_tree = parser.parse(prefix, None).unwrap();
+    collect_missing_at(&prefix_tree.root_node(), byte_offset, &mut |kind| {
+        expected.push(display_name(kind))
+    });
+
+    for (kind, text) in candidates {
+        let probe = format!("{prefix}{text}");
+        let tree = parser.parse(&probe, None).unwrap();
+        let accepted = tree
+            .root_node()
+            .descendant_for_byte_range(byte_offset, probe.len())
+            .map(|n| {
+                n.kind() == kind
+                    && n.start_byte() == byte_offset
+                    && n.end_byte() == probe.len()
+                    && !n.is_missing()
+                    && !has_error_ancestor(n)
+            })
+            .unwrap_or(false);
+
+        if accepted {
+            expected.push(display_name(&kind));
+        }
+    }
+
+    expected.sort();
+    expected.dedup();
+    expected
+}
+
+/// Records the text of every token in the tree, so that tokens defined by patterns
+/// can be probed with a sample that is known to match.
+fn collect_token_samples(node: &tree_sitter::Node, source: &[u8], out: &mut Vec<(String, String)>) {
+    if node.is_extra() {
+        // extras can appear anywhere, so they are not useful suggestions
+    } else if node.child_count() == 0 {
+        if !node.is_error() && !node.is_missing() && node.is_named() {
+            if let Ok(text) = node.utf8_text(source) {
+                if !text.is_empty() && !out.iter().any(|(kind, _)| kind == node.kind()) {
+                    out.push((node.kind().to_string(), text.to_string()));
+                }
+            }
+        }
+    } else {
+        let mut cursor = node.walk();
+        node.children(&mut cursor)
+            .for_each(|c| collect_token_samples(&c, source, out));
+    }
+}
+
+fn collect_missing_at(node: &tree_sitter::Node, byte_offset: usize, found: &mut impl FnMut(&str)) {
+    if node.is_missing() && node.start_byte() == byte_offset {
+        found(node.kind());
+    }
+
+    let mut cursor = node.walk();
+    node.children(&mut cursor)
+        .for_each(|c| collect_missing_at(&c, byte_offset, found));
+}
+
+fn has_error_ancestor(node: tree_sitter::Node) -> bool {
+    let mut current = Some(node);
+    while let Some(n) = current {
+        if n.is_error() {
+            return true;
+        }
+        current = n.parent();
+    }
+    false
+}
@@ -0,0 +1,79 @@
+use std::{
+    any::{type_name, TypeId},
+    cell::Cell,
+};
+
+/// The context given to `parse_with_context`, along with its type, so that transforms
+/// asking for a context of another type are caught.
+type Current = Option<(*mut (), TypeId, &'static str)>;
+
+thread_local! {
+    static CONTEXT: Cell<Current> = const { Cell::new(None) };
+}
+
+/// Puts back the context that was current before, even if extraction panics.
+struct Restore(Current);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        CONTEXT.with(|c| c.set(self.0));
+    }
+}
+
+/// Makes `context` available to the transforms of leaves, through [`with_context`], while
+/// `f` runs on the current thread. This is how `parse_with_context` threads a context such
+/// as a string interner into extraction.
+pub fn provide_context<C: 'static, R>(context: &mut C, f: impl FnOnce() -> R) -> R {
+    let current = (
+        context as *mut C as *mut (),
+        TypeId::of::<C>(),
+        type_name::<C>(),
+    );
+    let _restore = Restore(CONTEXT.with(|c| c.replace(Some(current))));
+    f()
+}
+
+/// Calls `f` with the context given to the surrounding [`provide_context`], for the
+/// transforms that take a context as their second parameter.
+pub fn with_context<C: 'static, R>(f: impl FnOnce(&mut C) -> R) -> R {
+    // the context is taken out while it is borrowed, so that it is never borrowed twice
+    let current = CONTEXT.with(|c| c.take());
+    let _restore = Restore(current);
+    let (context, type_id, provided) = current.unwrap_or_else(|| {
+        panic!(
+            "A transform needs a context of type `{}`, so the input must be parsed with `parse_with_context`",
+            type_name::<C>()
+        )
+    });
+
+    if type_id != TypeId::of::<C>() {
+        panic!(
+            "A transform needs a context of type `{}`, but `parse_with_context` was given a `{provided}`",
+            type_name::<C>()
+        );
+    }
+
+    // SAFETY: the pointer comes from the `&mut C` that `provide_context` borrows for as long
+    // as it is current, and it is not current while `f` runs, so it is not aliased
+    f(unsafe { &mut *(context as *mut C) })
+}
+
+/// The context that is current on one thread, carried over to another thread that runs while
+/// the first one waits for it.
+pub(crate) struct Carried(Current);
+
+// SAFETY: the context is only used on the other thread while the thread that provided it is
+// blocked, so it is never used from two threads at once
+unsafe impl Send for Carried {}
+
+/// Takes note of the context that is current on this thread, for [`provide_carried`].
+pub(crate) fn carry() -> Carried {
+    Carried(CONTEXT.with(|c| c.get()))
+}
+
+/// Makes a context carried over from another thread current while `f` runs, so that
+/// transforms see the context of `parse_with_context` from a thread extraction moved to.
+pub(crate) fn provide_carried<R>(carried: Carried, f: impl FnOnce() -> R) -> R {
+    let _restore = Restore(CONTEXT.with(|c| c.replace(carried.0)));
+    f()
+}
@@ -0,0 +1,76 @@
+use crate::tree_sitter;
+
+/// A cursor over a tree that resolves the node it is on to a typed reference, such as the
+/// `NodeRef` of a grammar, which is what the generated `typed_cursor` functions return.
+///
+/// As with a [`tree_sitter::TreeCursor`], which it wraps, the cursor visits every node of
+/// the tree, including tokens and the nodes of leaves, for which [`TypedCursor::typed`]
+/// returns `None`. Nothing is extracted until a typed node is, so a large tree can be
+/// navigated without materializing its AST.
+pub struct TypedCursor<'tree, T> {
+    cursor: tree_sitter::TreeCursor<'tree>,
+    typed: fn(tree_sitter::Node<'tree>) -> Option<T>,
+}
+
+impl<'tree, T> TypedCursor<'tree, T> {
+    /// Creates a cursor on the root node of a tree, which resolves nodes with `typed`.
+    pub fn new(
+        tree: &'tree tree_sitter::Tree,
+        typed: fn(tree_sitter::Node<'tree>) -> Option<T>,
+    ) -> Self {
+        TypedCursor {
+            cursor: tree.walk(),
+            typed,
+        }
+    }
+
+    /// Returns the Tree Sitter node the cursor is on.
+    pub fn node(&self) -> tree_sitter::Node<'tree> {
+        self.cursor.node()
+    }
+
+    /// Returns the typed reference to the node the cursor is on, or `None` if the node is
+    /// not of one of the types of the grammar.
+    pub fn typed(&self) -> Option<T> {
+        (self.typed)(self.cursor.node())
+    }
+
+    /// Returns the name of the field the node the cursor is on was matched for, if any.
+    pub fn field_name(&self) -> Option<&'static str> {
+        self.cursor.field_name()
+    }
+
+    /// Moves to the first child of the current node, returning `false` if it has none.
+    pub fn goto_first_child(&mut self) -> bool {
+        self.cursor.goto_first_child()
+    }
+
+    /// Moves to the next sibling of the current node, returning `false` if it has none.
+    pub fn goto_next_sibling(&mut self) -> bool {
+        self.cursor.goto_next_sibling()
+    }
+
+    /// Moves to the parent of the current node, returning `false` if it is the root.
+    pub fn goto_parent(&mut self) -> bool {
+        self.cursor.goto_parent()
+    }
+
+    /// Moves to the first child of the current node that was matched for the field with
+    /// the given name, returning `false` and staying on the current node if there is none.
+    pub fn goto_field(&mut self, name: &str) -> bool {
+        if !self.cursor.goto_first_child() {
+            return false;
+        }
+
+        loop {
+            if self.cursor.field_name() == Some(name) {
+                return true;
+            }
+
+            if !self.cursor.goto_next_sibling() {
+                self.cursor.goto_parent();
+                return false;
+            }
+        }
+    }
+}
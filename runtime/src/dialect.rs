@@ -0,0 +1,41 @@
+use crate::errors::{ParseError, ParseErrorReason};
+use crate::tree_sitter;
+
+/// The kind of a node that is only allowed in some dialect, along with the name of the
+/// dialect, as given by `#[rust_sitter::dialect(...)]` on its type or variant.
+pub type DialectGate<'a> = (&'a str, &'a str);
+
+/// Given the root node of a Tree Sitter parsing result, accumulates an error for every
+/// node whose kind is gated on dialects that are all disabled. A node with several gates
+/// is allowed when any of its dialects is enabled, and the nodes inside a rejected node
+/// are not checked.
+pub fn collect_dialect_errors(
+    node: &tree_sitter::Node,
+    gates: &[DialectGate],
+    enabled: &[&str],
+    errors: &mut Vec<ParseError>,
+) {
+    let mut dialects = gates
+        .iter()
+        .filter(|(kind, _)| *kind == node.kind())
+        .map(|(_, dialect)| *dialect)
+        .peekable();
+    if let Some(dialect) = dialects.peek().copied() {
+        if !dialects.any(|d| enabled.contains(&d)) {
+            errors.push(ParseError {
+                reason: ParseErrorReason::DisabledDialect {
+                    dialect: dialect.to_string(),
+                },
+                start: node.start_byte(),
+                end: node.end_byte(),
+                rule: Some(node.kind().to_string()),
+                expected: vec![],
+            });
+            return;
+        }
+    }
+
+    let mut cursor = node.walk();
+    node.children(&mut cursor)
+        .for_each(|c| collect_dialect_errors(&c, gates, enabled, errors));
+}
@@ -1,4 +1,9 @@
-use std::ops::Deref;
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    marker::PhantomData,
+    ops::{Deref, Range},
+};
 
 pub use rust_sitter_macro::*;
 
@@ -8,18 +13,276 @@ pub use tree_sitter_runtime_standard as tree_sitter;
 #[cfg(feature = "tree-sitter-c2rust")]
 pub use tree_sitter_runtime_c2rust as tree_sitter;
 
+#[cfg(feature = "serde")]
+pub use serde;
+
+#[cfg(feature = "smallvec")]
+pub use smallvec::SmallVec;
+
+#[cfg(feature = "arena")]
+pub mod arena;
+pub mod bytes;
+pub mod captures;
+pub mod completion;
+pub mod context;
+pub mod cursor;
+pub mod dialect;
+pub mod lossless;
+pub mod metrics;
+pub mod pipeline;
+pub mod recursion;
+pub mod streaming;
+pub mod syntax;
+pub mod trace;
+pub mod unescape;
+pub mod validation;
+
 /// Defines the logic used to convert a node in a Tree Sitter tree to
 /// the corresponding Rust type.
+///
+/// The `last_idx` is the byte offset that extraction last moved to, along with its position,
+/// which is where an absent `node` is taken to be, such as for the span of an empty
+/// `Spanned` value. The root is extracted with `Default::default()`, the start of the input.
+///
+/// The input is read through a [`Source`], so that it does not have to be held in memory
+/// as a whole. Before this, `extract` took the input as a `&[u8]`, so a hand-written
+/// implementation has to take a generic `S: Source + ?Sized` instead, and read the text of
+/// its node with [`Source::text`] rather than by slicing the input.
 pub trait Extract<Output> {
     type LeafFn: ?Sized;
-    fn extract(
+    fn extract<S: Source + ?Sized>(
         node: Option<tree_sitter::Node>,
-        source: &[u8],
-        last_idx: usize,
+        source: &S,
+        last_idx: (usize, Point),
         leaf_fn: Option<&Self::LeafFn>,
     ) -> Output;
 }
 
+/// A type that can be used as the root when parsing, which has a Tree Sitter
+/// parser where its rule is the start rule.
+pub trait EntryPoint: Extract<Self> + Sized {
+    fn language() -> tree_sitter::Language;
+
+    /// Runs once on the whole AST after it is extracted, before parsing returns it, which
+    /// calls the hook given with `#[rust_sitter::post_parse(...)]` on the root type.
+    fn post_parse(&mut self) {}
+}
+
+/// Provides the text of the input to extraction, which lets leaves be extracted
+/// without holding the whole input in memory.
+pub trait Source {
+    /// Returns the text between the inclusive byte offset `start` and the exclusive
+    /// byte offset `end`.
+    fn text(&self, start: usize, end: usize) -> Cow<'_, str>;
+}
+
+impl Source for [u8] {
+    fn text(&self, start: usize, end: usize) -> Cow<'_, str> {
+        // a tree that was not parsed from this input can have out of range nodes, and
+        // invalid UTF-8 is replaced so that extraction never fails on malformed input
+        String::from_utf8_lossy(self.get(start..end).unwrap_or_default())
+    }
+}
+
+/// A chunk of the input returned by the callback of a [`ChunkedSource`]. Chunks that are
+/// borrowed for `'a` lend their text to the leaves that lie within them, while the text
+/// of owned chunks is copied out of them.
+pub trait Chunk<'a>: AsRef<[u8]> {
+    /// Returns the text of the first `len` bytes of the chunk.
+    fn into_text(self, len: usize) -> Cow<'a, str>;
+}
+
+impl<'a> Chunk<'a> for &'a [u8] {
+    fn into_text(self, len: usize) -> Cow<'a, str> {
+        String::from_utf8_lossy(&self[..len])
+    }
+}
+
+impl<'a> Chunk<'a> for &'a str {
+    fn into_text(self, len: usize) -> Cow<'a, str> {
+        self.as_bytes().into_text(len)
+    }
+}
+
+impl<'a> Chunk<'a> for &'a Vec<u8> {
+    fn into_text(self, len: usize) -> Cow<'a, str> {
+        self.as_slice().into_text(len)
+    }
+}
+
+impl<'a> Chunk<'a> for &'a String {
+    fn into_text(self, len: usize) -> Cow<'a, str> {
+        self.as_bytes().into_text(len)
+    }
+}
+
+impl<'a> Chunk<'a> for Cow<'a, [u8]> {
+    fn into_text(self, len: usize) -> Cow<'a, str> {
+        match self {
+            Cow::Borrowed(chunk) => chunk.into_text(len),
+            Cow::Owned(chunk) => chunk.into_text(len),
+        }
+    }
+}
+
+impl<'a> Chunk<'a> for Vec<u8> {
+    fn into_text(mut self, len: usize) -> Cow<'a, str> {
+        self.truncate(len);
+        Cow::Owned(
+            String::from_utf8(self)
+                .unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned()),
+        )
+    }
+}
+
+impl<'a> Chunk<'a> for String {
+    fn into_text(self, len: usize) -> Cow<'a, str> {
+        self.into_bytes().into_text(len)
+    }
+}
+
+impl<'a> Chunk<'a> for Box<[u8]> {
+    fn into_text(self, len: usize) -> Cow<'a, str> {
+        self.into_vec().into_text(len)
+    }
+}
+
+/// A [`Source`] that fetches the input on demand from a callback, which is given
+/// a byte offset and returns a [`Chunk`] of the input starting at that offset. An
+/// empty chunk marks the end of the input.
+pub struct ChunkedSource<'a, F> {
+    callback: RefCell<F>,
+    _chunks: PhantomData<&'a [u8]>,
+}
+
+impl<'a, T: Chunk<'a>, F: FnMut(usize) -> T> ChunkedSource<'a, F> {
+    pub fn new(callback: F) -> Self {
+        ChunkedSource {
+            callback: RefCell::new(callback),
+            _chunks: PhantomData,
+        }
+    }
+
+    /// Returns the chunk of the input starting at the given byte offset.
+    pub fn chunk(&self, offset: usize) -> T {
+        (self.callback.borrow_mut())(offset)
+    }
+}
+
+impl<'a, T: Chunk<'a>, F: FnMut(usize) -> T> Source for ChunkedSource<'a, F> {
+    fn text(&self, start: usize, end: usize) -> Cow<'_, str> {
+        // text within a single chunk is taken from it, borrowing it if it is borrowed
+        let first = self.chunk(start);
+        if first.as_ref().len() >= end - start {
+            return first.into_text(end - start);
+        }
+
+        let mut bytes = Vec::with_capacity(end - start);
+        bytes.extend_from_slice(first.as_ref());
+        while start + bytes.len() < end {
+            let chunk = self.chunk(start + bytes.len());
+            let chunk = chunk.as_ref();
+            if chunk.is_empty() {
+                break;
+            }
+
+            let remaining = end - start - bytes.len();
+            bytes.extend_from_slice(&chunk[..remaining.min(chunk.len())]);
+        }
+
+        Cow::Owned(String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
+
+/// Returns the byte ranges whose syntactic structure differs between a tree and the
+/// tree produced by reparsing it after an edit. The `old` tree must have been edited
+/// with [`tree_sitter::Tree::edit`] to match the new input before it was reparsed.
+pub fn changed_ranges(old: &tree_sitter::Tree, new: &tree_sitter::Tree) -> Vec<Range<usize>> {
+    old.changed_ranges(new)
+        .map(|r| r.start_byte..r.end_byte)
+        .collect()
+}
+
+/// Finds the deepest node of a tree that contains the given byte offset, then walks up from
+/// it to the first node that `typed` maps to a value. The generated `node_at` functions use
+/// this to resolve an offset, such as the cursor in an editor, to the smallest AST node
+/// containing it.
+pub fn typed_node_at<'tree, T>(
+    tree: &'tree tree_sitter::Tree,
+    offset: usize,
+    typed: impl Fn(tree_sitter::Node<'tree>) -> Option<T>,
+) -> Option<T> {
+    let root = tree.root_node();
+    if offset < root.start_byte() || offset > root.end_byte() {
+        return None;
+    }
+
+    let mut node = root.descendant_for_byte_range(offset, offset);
+    while let Some(n) = node {
+        if let Some(typed) = typed(n) {
+            return Some(typed);
+        }
+
+        node = n.parent();
+    }
+
+    None
+}
+
+/// Formats a tree as an S-expression in the style of `tree-sitter parse`, which is handy
+/// for corpus-style snapshot tests. Only named nodes are shown, prefixed with the name of
+/// the field they were matched for, along with any missing nodes and the text of
+/// unexpected tokens in errors.
+pub fn to_sexp(tree: &tree_sitter::Tree, source: &str) -> String {
+    let mut out = String::new();
+    write_sexp(tree.root_node(), source, &mut out);
+    out
+}
+
+fn write_sexp(node: tree_sitter::Node, source: &str, out: &mut String) {
+    if node.is_missing() {
+        if node.is_named() {
+            out.push_str(&format!("(MISSING {})", node.kind()));
+        } else {
+            out.push_str(&format!("(MISSING {:?})", node.kind()));
+        }
+        return;
+    }
+
+    out.push('(');
+    out.push_str(node.kind());
+
+    if node.is_error() && node.child_count() == 0 {
+        out.push_str(&format!(
+            " (UNEXPECTED {:?})",
+            source
+                .get(node.start_byte()..node.end_byte())
+                .unwrap_or_default()
+        ));
+    }
+
+    let mut cursor = node.walk();
+    if cursor.goto_first_child() {
+        loop {
+            let child = cursor.node();
+            if child.is_named() || child.is_missing() {
+                out.push(' ');
+                if let Some(field) = cursor.field_name() {
+                    out.push_str(field);
+                    out.push_str(": ");
+                }
+                write_sexp(child, source, out);
+            }
+
+            if !cursor.goto_next_sibling() {
+                break;
+            }
+        }
+    }
+
+    out.push(')');
+}
+
 pub struct WithLeaf<L> {
     _phantom: std::marker::PhantomData<L>,
 }
@@ -27,35 +290,69 @@ pub struct WithLeaf<L> {
 impl<L> Extract<L> for WithLeaf<L> {
     type LeafFn = dyn Fn(&str) -> L;
 
-    fn extract(
+    fn extract<S: Source + ?Sized>(
         node: Option<tree_sitter::Node>,
-        source: &[u8],
-        _last_idx: usize,
+        source: &S,
+        _last_idx: (usize, Point),
         leaf_fn: Option<&Self::LeafFn>,
     ) -> L {
-        node.and_then(|n| n.utf8_text(source).ok())
-            .map(|s| leaf_fn.unwrap()(s))
-            .unwrap()
+        let n = node.unwrap();
+        let text = source.text(n.start_byte(), n.end_byte());
+        errors::transform_leaf(&n, || leaf_fn.unwrap()(&text))
+    }
+}
+
+/// Like [`WithLeaf`], but for leaves with `alternatives`, whose function is also given the
+/// kind of the matched node so that it can apply the transform of that alternative.
+pub struct WithAlternatives<L> {
+    _phantom: std::marker::PhantomData<L>,
+}
+
+impl<L> Extract<L> for WithAlternatives<L> {
+    type LeafFn = dyn Fn(&str, &str) -> L;
+
+    fn extract<S: Source + ?Sized>(
+        node: Option<tree_sitter::Node>,
+        source: &S,
+        _last_idx: (usize, Point),
+        leaf_fn: Option<&Self::LeafFn>,
+    ) -> L {
+        let n = node.unwrap();
+        let text = source.text(n.start_byte(), n.end_byte());
+        errors::transform_leaf(&n, || leaf_fn.unwrap()(n.kind(), &text))
     }
 }
 
 impl Extract<()> for () {
     type LeafFn = ();
-    fn extract(
+    fn extract<S: Source + ?Sized>(
         _node: Option<tree_sitter::Node>,
-        _source: &[u8],
-        _last_idx: usize,
+        _source: &S,
+        _last_idx: (usize, Point),
         _leaf_fn: Option<&Self::LeafFn>,
     ) {
     }
 }
 
+/// A `bool` text leaf is a flag, which is `true` if its optional text was matched.
+impl Extract<bool> for bool {
+    type LeafFn = ();
+    fn extract<S: Source + ?Sized>(
+        node: Option<tree_sitter::Node>,
+        _source: &S,
+        _last_idx: (usize, Point),
+        _leaf_fn: Option<&Self::LeafFn>,
+    ) -> bool {
+        node.is_some()
+    }
+}
+
 impl<T: Extract<U>, U> Extract<Option<U>> for Option<T> {
     type LeafFn = T::LeafFn;
-    fn extract(
+    fn extract<S: Source + ?Sized>(
         node: Option<tree_sitter::Node>,
-        source: &[u8],
-        last_idx: usize,
+        source: &S,
+        last_idx: (usize, Point),
         leaf_fn: Option<&Self::LeafFn>,
     ) -> Option<U> {
         node.map(|n| T::extract(Some(n), source, last_idx, leaf_fn))
@@ -64,49 +361,128 @@ impl<T: Extract<U>, U> Extract<Option<U>> for Option<T> {
 
 impl<T: Extract<U>, U> Extract<Box<U>> for Box<T> {
     type LeafFn = T::LeafFn;
-    fn extract(
+    fn extract<S: Source + ?Sized>(
         node: Option<tree_sitter::Node>,
-        source: &[u8],
-        last_idx: usize,
+        source: &S,
+        last_idx: (usize, Point),
         leaf_fn: Option<&Self::LeafFn>,
     ) -> Box<U> {
         Box::new(T::extract(node, source, last_idx, leaf_fn))
     }
 }
 
+/// Extracts the elements of a repeated field, which are the children of its node that are
+/// matched for a field, collecting them into any collection that can be built from them.
+fn extract_repeated<T: Extract<U>, U, C: FromIterator<U>, S: Source + ?Sized>(
+    node: Option<tree_sitter::Node>,
+    source: &S,
+    mut last_idx: (usize, Point),
+    leaf_fn: Option<&T::LeafFn>,
+) -> C {
+    let mut cursor = node.map(|node| node.walk());
+    let mut has_next = cursor
+        .as_mut()
+        .map(|c| c.goto_first_child())
+        .unwrap_or(false);
+
+    // the elements are extracted as they are collected, so that no intermediate vector is
+    // allocated for collections that store short lists inline
+    std::iter::from_fn(|| {
+        let cursor = cursor.as_mut()?;
+        while has_next {
+            let n = cursor.node();
+            let value = cursor
+                .field_name()
+                .map(|_| T::extract(Some(n), source, last_idx, leaf_fn));
+
+            last_idx = (n.end_byte(), n.end_position().into());
+            has_next = cursor.goto_next_sibling();
+
+            if value.is_some() {
+                return value;
+            }
+        }
+
+        None
+    })
+    .collect()
+}
+
 impl<T: Extract<U>, U> Extract<Vec<U>> for Vec<T> {
     type LeafFn = T::LeafFn;
-    fn extract(
+    fn extract<S: Source + ?Sized>(
         node: Option<tree_sitter::Node>,
-        source: &[u8],
-        mut last_idx: usize,
+        source: &S,
+        last_idx: (usize, Point),
         leaf_fn: Option<&Self::LeafFn>,
     ) -> Vec<U> {
-        node.map(|node| {
-            let mut cursor = node.walk();
-            let mut out = vec![];
-            if cursor.goto_first_child() {
-                loop {
-                    let n = cursor.node();
-                    if cursor.field_name().is_some() {
-                        out.push(T::extract(Some(n), source, last_idx, leaf_fn));
-                    }
+        extract_repeated::<T, U, _, S>(node, source, last_idx, leaf_fn)
+    }
+}
 
-                    last_idx = n.end_byte();
+/// The elements of a fixed-size array are matched exactly `N` times by the grammar.
+impl<T: Extract<U>, U, const N: usize> Extract<[U; N]> for [T; N] {
+    type LeafFn = T::LeafFn;
+    fn extract<S: Source + ?Sized>(
+        node: Option<tree_sitter::Node>,
+        source: &S,
+        last_idx: (usize, Point),
+        leaf_fn: Option<&Self::LeafFn>,
+    ) -> [U; N] {
+        let elements: Vec<U> = extract_repeated::<T, U, _, S>(node, source, last_idx, leaf_fn);
+        let len = elements.len();
+        // the grammar only matches `N` elements, so a tree with another count has errors,
+        // or was not parsed with the grammar
+        elements.try_into().unwrap_or_else(|_| {
+            let span = node.map_or(last_idx.0..last_idx.0, |n| n.byte_range());
+            errors::fail_extraction(errors::ParseError {
+                reason: errors::ParseErrorReason::ExtractionFailed(format!(
+                    "expected {N} elements, but found {len}"
+                )),
+                start: span.start,
+                end: span.end,
+                rule: None,
+                expected: vec![],
+            })
+        })
+    }
+}
 
-                    if !cursor.goto_next_sibling() {
-                        break;
-                    }
-                }
-            }
+#[cfg(feature = "smallvec")]
+impl<T: Extract<U>, U, const N: usize> Extract<SmallVec<[U; N]>> for SmallVec<[T; N]> {
+    type LeafFn = T::LeafFn;
+    fn extract<S: Source + ?Sized>(
+        node: Option<tree_sitter::Node>,
+        source: &S,
+        last_idx: (usize, Point),
+        leaf_fn: Option<&Self::LeafFn>,
+    ) -> SmallVec<[U; N]> {
+        extract_repeated::<T, U, _, S>(node, source, last_idx, leaf_fn)
+    }
+}
 
-            out
-        })
-        .unwrap_or_default()
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A position in the source, as a row and a column in bytes from the start of the row,
+/// both starting at zero, like the `Point` of Tree Sitter.
+pub struct Point {
+    /// The line of the position.
+    pub row: usize,
+    /// The byte offset of the position from the start of its line.
+    pub column: usize,
+}
+
+impl From<tree_sitter::Point> for Point {
+    fn from(point: tree_sitter::Point) -> Self {
+        Point {
+            row: point.row,
+            column: point.column,
+        }
     }
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// A wrapper around a value that also contains the span of the value in the source.
 pub struct Spanned<T> {
     /// The underlying parsed node.
@@ -114,6 +490,25 @@ pub struct Spanned<T> {
     /// The span of the node in the source. The first value is the inclusive start
     /// of the span, and the second value is the exclusive end of the span.
     pub span: (usize, usize),
+    /// The positions of the start and the end of the span, as rows and columns.
+    pub points: (Point, Point),
+}
+
+impl<T> Spanned<T> {
+    /// The span of the node in the source, as a range of byte offsets.
+    pub fn byte_range(&self) -> Range<usize> {
+        self.span.0..self.span.1
+    }
+
+    /// The position of the start of the node in the source.
+    pub fn start_point(&self) -> Point {
+        self.points.0
+    }
+
+    /// The position of the end of the node in the source.
+    pub fn end_point(&self) -> Point {
+        self.points.1
+    }
 }
 
 impl<T> Deref for Spanned<T> {
@@ -126,22 +521,245 @@ impl<T> Deref for Spanned<T> {
 
 impl<T: Extract<U>, U> Extract<Spanned<U>> for Spanned<T> {
     type LeafFn = T::LeafFn;
-    fn extract(
+    fn extract<S: Source + ?Sized>(
         node: Option<tree_sitter::Node>,
-        source: &[u8],
-        last_idx: usize,
+        source: &S,
+        last_idx: (usize, Point),
         leaf_fn: Option<&Self::LeafFn>,
     ) -> Spanned<U> {
         Spanned {
             value: T::extract(node, source, last_idx, leaf_fn),
             span: node
                 .map(|n| (n.start_byte(), n.end_byte()))
-                .unwrap_or((last_idx, last_idx)),
+                .unwrap_or((last_idx.0, last_idx.0)),
+            // an absent node has no position of its own, so it takes the position where
+            // extraction last moved to, which is the end of the node before it
+            points: node
+                .map(|n| (n.start_position().into(), n.end_position().into()))
+                .unwrap_or((last_idx.1, last_idx.1)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A wrapper around the transformed value of a leaf that also contains the text it was
+/// transformed from, produced by leaves with `keep_raw = true`.
+pub struct WithRaw<T> {
+    /// The transformed value of the leaf.
+    pub value: T,
+    /// The text of the leaf in the source.
+    pub raw: String,
+}
+
+impl<T> Deref for WithRaw<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+/// Wraps a leaf transform so that the text of the leaf is kept alongside its result.
+pub fn with_raw<L>(transform: impl Fn(&str) -> L) -> impl Fn(&str) -> WithRaw<L> {
+    move |text| WithRaw {
+        value: transform(text),
+        raw: text.to_string(),
+    }
+}
+
+/// Wraps a leaf transform so that it receives the text of the leaf without any of the
+/// characters in `start` at its beginning and any of the characters in `end` at its end.
+pub fn trimmed<L>(
+    start: &'static str,
+    end: &'static str,
+    transform: impl Fn(&str) -> L,
+) -> impl Fn(&str) -> L {
+    move |text| {
+        transform(
+            text.trim_start_matches(|c| start.contains(c))
+                .trim_end_matches(|c| end.contains(c)),
+        )
+    }
+}
+
+/// Compares values parsed from the input while ignoring where they were found, which is
+/// implemented for every type of a grammar whose root is marked with
+/// `#[rust_sitter::semantic_eq]`. The spans of [`Spanned`] values and the raw text of
+/// [`WithRaw`] values are ignored, so that inputs which only differ in formatting
+/// compare equal. Custom leaf types have to implement this trait themselves.
+pub trait SemanticEq {
+    fn semantic_eq(&self, other: &Self) -> bool;
+}
+
+macro_rules! semantic_eq_by_partial_eq {
+    ($($ty:ty),*) => {
+        $(impl SemanticEq for $ty {
+            fn semantic_eq(&self, other: &Self) -> bool {
+                self == other
+            }
+        })*
+    };
+}
+
+semantic_eq_by_partial_eq!(
+    (),
+    bool,
+    char,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    f32,
+    f64,
+    str,
+    String
+);
+
+impl<T: SemanticEq + ?Sized> SemanticEq for Box<T> {
+    fn semantic_eq(&self, other: &Self) -> bool {
+        T::semantic_eq(self, other)
+    }
+}
+
+impl<T: SemanticEq + ?Sized> SemanticEq for &T {
+    fn semantic_eq(&self, other: &Self) -> bool {
+        T::semantic_eq(self, other)
+    }
+}
+
+impl<T: SemanticEq> SemanticEq for Option<T> {
+    fn semantic_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Some(a), Some(b)) => a.semantic_eq(b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+impl<T: SemanticEq> SemanticEq for Vec<T> {
+    fn semantic_eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().zip(other).all(|(a, b)| a.semantic_eq(b))
+    }
+}
+
+#[cfg(feature = "smallvec")]
+impl<T: SemanticEq, const N: usize> SemanticEq for SmallVec<[T; N]> {
+    fn semantic_eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().zip(other).all(|(a, b)| a.semantic_eq(b))
+    }
+}
+
+impl<T: SemanticEq> SemanticEq for Spanned<T> {
+    fn semantic_eq(&self, other: &Self) -> bool {
+        self.value.semantic_eq(&other.value)
+    }
+}
+
+impl<T: SemanticEq> SemanticEq for WithRaw<T> {
+    fn semantic_eq(&self, other: &Self) -> bool {
+        self.value.semantic_eq(&other.value)
+    }
+}
+
+/// Identifies the variants of an enum with small integer tags, such as for a compact binary
+/// serialization, which is implemented for every enum of a grammar whose root is marked
+/// with `#[rust_sitter::tags]`. The tags number the variants in the order of their names,
+/// so they are stable across builds and reordering the variants keeps them, while adding,
+/// removing or renaming a variant shifts the tags of the variants after it.
+pub trait Tagged: Sized {
+    /// The names of the variants along with their tags, in the order of the tags.
+    const TAGS: &'static [(&'static str, u16)];
+
+    /// Returns the tag of the variant of this value.
+    fn tag(&self) -> u16;
+
+    /// Reconstructs the variant with the given tag if it has no fields, which is all that a
+    /// tag determines. Variants with fields, and tags of no variant, give `None`, so that a
+    /// decoder reads the fields of the variant named in [`Tagged::TAGS`] itself.
+    fn from_tag(tag: u16) -> Option<Self>;
+}
+
+/// Checks that the fields of a node are the ones expected by the type extracted
+/// from it, which the generated extraction code does in debug builds. The node must not
+/// have a field that the type does not expect, the language of the node must know every
+/// field that the type expects, and a node without syntax errors must have every field in
+/// `required`, which are the fields that the rule of the type always matches.
+///
+/// A mismatch means that the parser was generated from a different version of
+/// the grammar than the types, so fields would otherwise be silently left empty.
+pub fn check_fields(node: tree_sitter::Node, rule: &str, expected: &[&str], required: &[&str]) {
+    let mut cursor = node.walk();
+    if !cursor.goto_first_child() {
+        return;
+    }
+
+    let mut found: Vec<&'static str> = vec![];
+    loop {
+        if let Some(name) = cursor.field_name() {
+            if !found.contains(&name) {
+                found.push(name);
+            }
+        }
+
+        if !cursor.goto_next_sibling() {
+            break;
+        }
+    }
+
+    let language = node.language();
+    let unexpected = found.iter().any(|name| !expected.contains(name));
+    let unknown = expected
+        .iter()
+        .any(|name| language.field_id_for_name(name).is_none());
+    let missing = !node.has_error() && required.iter().any(|name| !found.contains(name));
+    if unexpected || unknown || missing {
+        panic!(
+            "The node for `{rule}` has {} fields ({}) but its type has {} ({}), so the parser is probably out of date with the grammar and should be rebuilt",
+            found.len(),
+            found.join(", "),
+            expected.len(),
+            expected.join(", ")
+        );
+    }
+}
+
+/// Creates a cursor on the first child of a node with the given field, which the generated
+/// extraction code of `#[rust_sitter::unordered]` types starts each field from, since their
+/// fields can appear in any order.
+pub fn field_cursor<'a>(
+    node: tree_sitter::Node<'a>,
+    field: &str,
+) -> Option<tree_sitter::TreeCursor<'a>> {
+    let mut cursor = node.walk();
+    if !cursor.goto_first_child() {
+        return None;
+    }
+
+    loop {
+        if cursor.field_name() == Some(field) {
+            return Some(cursor);
+        }
+
+        if !cursor.goto_next_sibling() {
+            return None;
         }
     }
 }
 
 pub mod errors {
+    use std::cell::Cell;
+    use std::ops::Range;
+
     #[cfg(feature = "tree-sitter-standard")]
     use tree_sitter_runtime_standard as tree_sitter;
 
@@ -158,6 +776,50 @@ pub mod errors {
         FailedNode(Vec<ParseError>),
         /// The parser expected a specific token, but it was not found.
         MissingToken(String),
+        /// A leaf with `unescape = true` contains an invalid escape sequence.
+        InvalidEscape(String),
+        /// The `validate` function of a leaf rejected the value transformed from its text,
+        /// with the explanation given by the function, if it returned one.
+        InvalidValue {
+            text: String,
+            message: Option<String>,
+        },
+        /// The Tree Sitter tree, which is usually somewhat deeper than the AST, is nested more
+        /// deeply than the maximum depth allowed when parsing. The error spans the first node
+        /// at the reported depth, or the whole tree when no thread could be given a stack deep
+        /// enough for it with `parse_as_on_larger_stack`.
+        TooDeep { depth: usize },
+        /// The Tree Sitter tree has more named nodes than the maximum allowed when parsing,
+        /// which only roughly bounds the size of the AST. The error spans the first node past
+        /// the limit.
+        TooManyNodes { limit: usize },
+        /// The input is not valid UTF-8. The error spans the first invalid sequence.
+        InvalidUtf8,
+        /// Tree Sitter did not produce a tree, for example because the parser was generated
+        /// for an incompatible version of the runtime.
+        ParserFailed,
+        /// Extracting the AST from the tree panicked, usually in a leaf transform. The
+        /// panic message is in the string, and the error spans the leaf whose transform
+        /// panicked, if it was one. A fixed-size array whose node has another number of
+        /// elements also fails with this error, which spans the node.
+        ExtractionFailed(String),
+        /// The input uses a construct of a dialect that was not enabled when parsing with
+        /// `parse_with_dialects`. The error spans the node of the construct.
+        DisabledDialect { dialect: String },
+        /// A token is longer than the `max_token_len` of the grammar, usually because a
+        /// pattern such as `.*` matched far more than intended. The error spans the token,
+        /// whose rule and length in bytes are given.
+        TokenTooLong { rule: String, len: usize },
+        /// A required field of a `#[rust_sitter::unordered]` type does not appear in its
+        /// node, which the error spans.
+        MissingField { field: String },
+        /// A field of a `#[rust_sitter::unordered]` type appears more than once in its node.
+        /// The error spans the repeated field.
+        DuplicateField { field: String },
+        /// Parsing with `parse_with_timeout` took longer than the given number of
+        /// microseconds. Tree Sitter does not produce a tree when it runs out of time, so the
+        /// error spans the whole input.
+        Timeout { micros: u64 },
     }
 
     #[derive(Debug)]
@@ -168,13 +830,409 @@ pub mod errors {
         pub start: usize,
         /// Exclusive end of the error.
         pub end: usize,
+        /// The kind of the node that was being parsed when a syntax error occurred, which is
+        /// the name of its rule in the grammar.
+        pub rule: Option<String>,
+        /// The tokens that could have appeared at the start of a syntax error, as listed by
+        /// `expected_at`. Only `parse_strict` fills this in, since it has to re-parse the
+        /// input for every candidate token.
+        pub expected: Vec<String>,
+    }
+
+    impl std::fmt::Display for ParseError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            if !self.expected.is_empty() {
+                let expected = self
+                    .expected
+                    .iter()
+                    .map(|e| format!("`{e}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "expected one of {expected}, ")?;
+            }
+
+            match &self.reason {
+                ParseErrorReason::UnexpectedToken(token) if self.expected.is_empty() => {
+                    write!(f, "unexpected `{token}`")
+                }
+                ParseErrorReason::UnexpectedToken(token) => write!(f, "found `{token}`"),
+                ParseErrorReason::FailedNode(_) if self.expected.is_empty() => {
+                    write!(f, "unexpected input")
+                }
+                ParseErrorReason::FailedNode(_) => write!(f, "found unexpected input"),
+                ParseErrorReason::MissingToken(token) => write!(f, "missing `{token}`"),
+                ParseErrorReason::InvalidEscape(escape) => {
+                    write!(f, "invalid escape sequence `{escape}`")
+                }
+                ParseErrorReason::InvalidValue { text, message } => {
+                    write!(f, "invalid value `{text}`")?;
+                    if let Some(message) = message {
+                        write!(f, ": {message}")?;
+                    }
+                    Ok(())
+                }
+                ParseErrorReason::TooDeep { depth } => {
+                    write!(f, "nested too deeply (at depth {depth})")
+                }
+                ParseErrorReason::TooManyNodes { limit } => {
+                    write!(f, "too many nodes (more than {limit})")
+                }
+                ParseErrorReason::InvalidUtf8 => write!(f, "invalid UTF-8"),
+                ParseErrorReason::ParserFailed => write!(f, "the parser did not produce a tree"),
+                ParseErrorReason::ExtractionFailed(message) => {
+                    write!(f, "failed to extract the AST: {message}")
+                }
+                ParseErrorReason::DisabledDialect { dialect } => {
+                    write!(f, "the `{dialect}` dialect is not enabled")
+                }
+                ParseErrorReason::TokenTooLong { rule, len } => {
+                    write!(f, "a `{rule}` token of {len} bytes is too long")
+                }
+                ParseErrorReason::MissingField { field } => {
+                    write!(f, "missing the `{field}` field")
+                }
+                ParseErrorReason::DuplicateField { field } => {
+                    write!(f, "the `{field}` field appears more than once")
+                }
+                ParseErrorReason::Timeout { micros } => {
+                    write!(f, "parsing took longer than {micros} microseconds")
+                }
+            }?;
+
+            if let Some(rule) = &self.rule {
+                write!(f, " in `{rule}`")?;
+            }
+
+            write!(f, " at {}..{}", self.start, self.end)
+        }
+    }
+
+    /// The kind of the closest ancestor of an error node that is not itself an error, which
+    /// is the rule that Tree Sitter was parsing when it ran into the error.
+    fn enclosing_rule(node: &tree_sitter::Node) -> Option<String> {
+        let mut parent = node.parent();
+        while let Some(p) = parent {
+            if !p.is_error() {
+                return Some(p.kind().to_string());
+            }
+            parent = p.parent();
+        }
+        None
+    }
+
+    thread_local! {
+        static LEAF: Cell<Option<Range<usize>>> = const { Cell::new(None) };
+        static CATCHING: Cell<bool> = const { Cell::new(false) };
+    }
+
+    /// Calls the transform of a leaf, noting where the leaf is so that a panic in the
+    /// transform is reported there by [`catch_extraction_panic`].
+    pub(crate) fn transform_leaf<L>(node: &tree_sitter::Node, transform: impl FnOnce() -> L) -> L {
+        let outer = LEAF.with(|l| l.replace(Some(node.byte_range())));
+        let value = transform();
+        LEAF.with(|l| l.set(outer));
+        value
+    }
+
+    /// Whether a panic on the current thread would be caught by [`catch_extraction_panic`],
+    /// which a panic hook can check so that it only reports the panics that are not turned
+    /// into errors.
+    pub fn catching_extraction_panic() -> bool {
+        CATCHING.with(|c| c.get())
+    }
+
+    /// An error that unwinds out of extraction, which cannot otherwise fail, such as an
+    /// array with the wrong number of elements, to be caught by [`catch_extraction_error`].
+    struct ExtractionError(ParseError);
+
+    /// Stops extraction with an error, without running the panic hook.
+    pub(crate) fn fail_extraction(error: ParseError) -> ! {
+        std::panic::resume_unwind(Box::new(ExtractionError(error)))
+    }
+
+    /// Runs extraction, returning the error that stopped it, if there was one. Other panics
+    /// are passed on. Errors unwind out of extraction, so with `panic = "abort"` they abort
+    /// the process.
+    pub fn catch_extraction_error<T>(extract: impl FnOnce() -> T) -> Result<T, ParseError> {
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(extract)).map_err(|payload| {
+            match payload.downcast::<ExtractionError>() {
+                Ok(error) => error.0,
+                Err(payload) => std::panic::resume_unwind(payload),
+            }
+        })
+    }
+
+    /// Runs extraction, turning a panic into a [`ParseErrorReason::ExtractionFailed`] error
+    /// that spans the leaf whose transform panicked, or the given node when the panic was not
+    /// in a transform, along with the errors caught by [`catch_extraction_error`]. Panics are
+    /// caught by unwinding, so with `panic = "abort"` they still abort the process.
+    pub fn catch_extraction_panic<T>(
+        node: &tree_sitter::Node,
+        extract: impl FnOnce() -> T,
+    ) -> Result<T, ParseError> {
+        let outer = (LEAF.with(|l| l.take()), CATCHING.with(|c| c.replace(true)));
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(extract));
+        let leaf = LEAF.with(|l| l.replace(outer.0));
+        CATCHING.with(|c| c.set(outer.1));
+
+        result.map_err(|payload| {
+            let payload = match payload.downcast::<ExtractionError>() {
+                Ok(error) => return error.0,
+                Err(payload) => payload,
+            };
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|m| m.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+            let span = leaf.unwrap_or_else(|| node.byte_range());
+
+            ParseError {
+                reason: ParseErrorReason::ExtractionFailed(message),
+                start: span.start,
+                end: span.end,
+                rule: None,
+                expected: vec![],
+            }
+        })
+    }
+
+    /// Checks that the input is valid UTF-8 before it is given to Tree Sitter, whose byte
+    /// offsets would otherwise not line up with the text that leaves are extracted from. The
+    /// error starts at the offset of the first invalid byte and spans its invalid sequence.
+    pub fn check_utf8(input: &[u8]) -> Result<&str, ParseError> {
+        std::str::from_utf8(input).map_err(|error| {
+            let start = error.valid_up_to();
+            ParseError {
+                reason: ParseErrorReason::InvalidUtf8,
+                start,
+                end: start + error.error_len().unwrap_or(input.len() - start),
+                rule: None,
+                expected: vec![],
+            }
+        })
+    }
+
+    /// Checks that the tree below the given node is at most `max_depth` nodes deep, counting
+    /// the node itself. The tree is walked without recursion, so that deeply nested inputs
+    /// can be rejected before they are walked recursively by extraction.
+    ///
+    /// This limits the Tree Sitter tree rather than the AST extracted from it. Every node
+    /// counts towards the depth, including tokens and the nodes of rules without a type of
+    /// their own, and the whole tree is walked to find the deepest node, on top of the walk
+    /// made by extraction.
+    pub fn check_depth(node: &tree_sitter::Node, max_depth: usize) -> Result<(), ParseError> {
+        let mut cursor = node.walk();
+        let mut depth = 1;
+        let mut deepest: Option<(usize, tree_sitter::Node)> = None;
+        loop {
+            if depth > max_depth && deepest.map(|(d, _)| depth > d).unwrap_or(true) {
+                deepest = Some((depth, cursor.node()));
+            }
+
+            if cursor.goto_first_child() {
+                depth += 1;
+                continue;
+            }
+
+            while !cursor.goto_next_sibling() {
+                if !cursor.goto_parent() {
+                    return match deepest {
+                        Some((depth, node)) => Err(ParseError {
+                            reason: ParseErrorReason::TooDeep { depth },
+                            start: node.start_byte(),
+                            end: node.end_byte(),
+                            rule: None,
+                            expected: vec![],
+                        }),
+                        None => Ok(()),
+                    };
+                }
+
+                depth -= 1;
+            }
+        }
+    }
+
+    /// Checks that the tree below the given node, including the node itself, has at most
+    /// `max_nodes` named nodes. The walk stops at the first node past the limit, so that wide
+    /// inputs are rejected without being walked in full, but inputs within the limit are
+    /// walked once before extraction walks them again.
+    ///
+    /// This counts the named nodes of the Tree Sitter tree rather than the values of the
+    /// AST. The two are usually close, since extraction builds a value for most named nodes,
+    /// but text tokens are not counted even though they are extracted, and fields such as
+    /// `Box` or `Vec` are values without nodes of their own.
+    pub fn check_node_count(node: &tree_sitter::Node, max_nodes: usize) -> Result<(), ParseError> {
+        let mut cursor = node.walk();
+        let mut count = 0;
+        loop {
+            if cursor.node().is_named() {
+                count += 1;
+                if count > max_nodes {
+                    let node = cursor.node();
+                    return Err(ParseError {
+                        reason: ParseErrorReason::TooManyNodes { limit: max_nodes },
+                        start: node.start_byte(),
+                        end: node.end_byte(),
+                        rule: None,
+                        expected: vec![],
+                    });
+                }
+            }
+
+            if cursor.goto_first_child() {
+                continue;
+            }
+
+            while !cursor.goto_next_sibling() {
+                if !cursor.goto_parent() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Given the root node of a Tree Sitter parsing result, accumulates a
+    /// [`ParseErrorReason::UnexpectedToken`] error for every node with one of the given
+    /// kinds, which match the leaves with `anchored = true` that run on into more word
+    /// characters.
+    pub fn collect_overrun_errors<S: crate::Source + ?Sized>(
+        node: &tree_sitter::Node,
+        kinds: &[&str],
+        source: &S,
+        errors: &mut Vec<ParseError>,
+    ) {
+        if kinds.contains(&node.kind()) {
+            errors.push(ParseError {
+                reason: ParseErrorReason::UnexpectedToken(
+                    source.text(node.start_byte(), node.end_byte()).to_string(),
+                ),
+                start: node.start_byte(),
+                end: node.end_byte(),
+                rule: enclosing_rule(node),
+                expected: vec![],
+            })
+        } else {
+            let mut cursor = node.walk();
+            node.children(&mut cursor)
+                .for_each(|c| collect_overrun_errors(&c, kinds, source, errors));
+        }
+    }
+
+    /// Given the root node of a Tree Sitter parsing result, accumulates an error for every
+    /// token that is longer than the given number of bytes.
+    pub fn collect_long_token_errors(
+        node: &tree_sitter::Node,
+        max_len: usize,
+        errors: &mut Vec<ParseError>,
+    ) {
+        if node.child_count() == 0 {
+            let len = node.end_byte() - node.start_byte();
+            if len > max_len {
+                errors.push(ParseError {
+                    reason: ParseErrorReason::TokenTooLong {
+                        rule: node.kind().to_string(),
+                        len,
+                    },
+                    start: node.start_byte(),
+                    end: node.end_byte(),
+                    rule: enclosing_rule(node),
+                    expected: vec![],
+                })
+            }
+        } else {
+            let mut cursor = node.walk();
+            node.children(&mut cursor)
+                .for_each(|c| collect_long_token_errors(&c, max_len, errors));
+        }
+    }
+
+    /// Given the root node of a Tree Sitter parsing result, accumulates an error for every
+    /// node of a `#[rust_sitter::unordered]` type that is missing one of its required fields
+    /// or has a field more than once, since its rule accepts the fields in any number. The
+    /// nodes are given by their kinds along with their required fields and all their fields.
+    pub fn collect_unordered_errors(
+        node: &tree_sitter::Node,
+        kinds: &[(&str, &[&str], &[&str])],
+        errors: &mut Vec<ParseError>,
+    ) {
+        if let Some((_, required, fields)) = kinds.iter().find(|(kind, ..)| *kind == node.kind()) {
+            let mut found: Vec<&str> = vec![];
+            let mut cursor = node.walk();
+            if cursor.goto_first_child() {
+                loop {
+                    if let Some(name) = cursor.field_name().filter(|n| fields.contains(n)) {
+                        if found.contains(&name) {
+                            let field = cursor.node();
+                            errors.push(ParseError {
+                                reason: ParseErrorReason::DuplicateField {
+                                    field: name.to_string(),
+                                },
+                                start: field.start_byte(),
+                                end: field.end_byte(),
+                                rule: Some(node.kind().to_string()),
+                                expected: vec![],
+                            });
+                        } else {
+                            found.push(name);
+                        }
+                    }
+
+                    if !cursor.goto_next_sibling() {
+                        break;
+                    }
+                }
+            }
+
+            required
+                .iter()
+                .filter(|field| !found.contains(field))
+                .for_each(|field| {
+                    errors.push(ParseError {
+                        reason: ParseErrorReason::MissingField {
+                            field: field.to_string(),
+                        },
+                        start: node.start_byte(),
+                        end: node.end_byte(),
+                        rule: Some(node.kind().to_string()),
+                        expected: vec![],
+                    })
+                });
+        }
+
+        let mut cursor = node.walk();
+        node.children(&mut cursor)
+            .for_each(|c| collect_unordered_errors(&c, kinds, errors));
+    }
+
+    /// Given the root node of a Tree Sitter parsing result, returns the first error in the
+    /// input, without walking the parts of the tree after it. The error is reported in the
+    /// same way as by [`collect_parsing_errors`].
+    pub fn first_parsing_error<S: crate::Source + ?Sized>(
+        node: &tree_sitter::Node,
+        source: &S,
+    ) -> Option<ParseError> {
+        if node.is_error() || node.is_missing() {
+            let mut errors = vec![];
+            collect_parsing_errors(node, source, &mut errors);
+            errors.into_iter().next()
+        } else if node.has_error() {
+            let mut cursor = node.walk();
+            let first = node
+                .children(&mut cursor)
+                .find_map(|c| first_parsing_error(&c, source));
+            first
+        } else {
+            None
+        }
     }
 
     /// Given the root node of a Tree Sitter parsing result, accumulates all
     /// errors that were emitted.
-    pub fn collect_parsing_errors(
+    pub fn collect_parsing_errors<S: crate::Source + ?Sized>(
         node: &tree_sitter::Node,
-        source: &[u8],
+        source: &S,
         errors: &mut Vec<ParseError>,
     ) {
         if node.is_error() {
@@ -189,20 +1247,26 @@ pub mod errors {
                     reason: ParseErrorReason::FailedNode(inner_errors),
                     start: node.start_byte(),
                     end: node.end_byte(),
+                    rule: enclosing_rule(node),
+                    expected: vec![],
                 })
             } else {
-                let contents = node.utf8_text(source).unwrap();
+                let contents = source.text(node.start_byte(), node.end_byte());
                 if !contents.is_empty() {
                     errors.push(ParseError {
                         reason: ParseErrorReason::UnexpectedToken(contents.to_string()),
                         start: node.start_byte(),
                         end: node.end_byte(),
+                        rule: enclosing_rule(node),
+                        expected: vec![],
                     })
                 } else {
                     errors.push(ParseError {
                         reason: ParseErrorReason::FailedNode(vec![]),
                         start: node.start_byte(),
                         end: node.end_byte(),
+                        rule: enclosing_rule(node),
+                        expected: vec![],
                     })
                 }
             }
@@ -211,6 +1275,8 @@ pub mod errors {
                 reason: ParseErrorReason::MissingToken(node.kind().to_string()),
                 start: node.start_byte(),
                 end: node.end_byte(),
+                rule: enclosing_rule(node),
+                expected: vec![],
             })
         } else if node.has_error() {
             let mut cursor = node.walk();
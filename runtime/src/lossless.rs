@@ -0,0 +1,138 @@
+use crate::errors::{collect_parsing_errors, ParseError};
+use crate::tree_sitter;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A node of a lossless syntax tree, produced by the generated `parse_lossless`, which keeps
+/// every byte of the input so that the tree can be edited and written back with the rest of
+/// the input unchanged.
+pub struct LosslessNode {
+    /// The name of the rule that matched this node.
+    pub kind: &'static str,
+    /// The name of the field this node was matched for in its parent, if any.
+    pub field: Option<&'static str>,
+    /// Whether this node is an extra, such as a comment, which belongs to no field.
+    pub is_extra: bool,
+    /// The text between the end of the previous sibling, or the start of the parent, and the
+    /// start of this node, such as whitespace that is not matched by an extra.
+    pub leading: String,
+    /// The child nodes, in source order, including extras.
+    pub children: Vec<LosslessNode>,
+    /// The text of the node if it has no children, such as the text of a token.
+    pub text: String,
+    /// The text between the end of the last child and the end of this node.
+    pub trailing: String,
+}
+
+impl LosslessNode {
+    /// Converts a node in a Tree Sitter tree, and all its descendants, where `pos` is the
+    /// end of the text already covered by the previous nodes.
+    fn from_node(
+        node: tree_sitter::Node,
+        field: Option<&'static str>,
+        source: &str,
+        pos: &mut usize,
+    ) -> Self {
+        let leading = source[*pos..node.start_byte()].to_string();
+        *pos = node.start_byte();
+
+        let mut children = vec![];
+        let mut cursor = node.walk();
+        if cursor.goto_first_child() {
+            loop {
+                children.push(LosslessNode::from_node(
+                    cursor.node(),
+                    cursor.field_name(),
+                    source,
+                    pos,
+                ));
+
+                if !cursor.goto_next_sibling() {
+                    break;
+                }
+            }
+        }
+
+        let (text, trailing) = if children.is_empty() {
+            (source[*pos..node.end_byte()].to_string(), String::new())
+        } else {
+            (String::new(), source[*pos..node.end_byte()].to_string())
+        };
+        *pos = node.end_byte();
+
+        LosslessNode {
+            kind: node.kind(),
+            field,
+            is_extra: node.is_extra(),
+            leading,
+            children,
+            text,
+            trailing,
+        }
+    }
+
+    /// Returns the first child that was matched for the field with the given name.
+    pub fn child_by_field(&self, field: &str) -> Option<&LosslessNode> {
+        self.children.iter().find(|c| c.field == Some(field))
+    }
+
+    /// Returns the first child that was matched for the field with the given name, so that
+    /// it can be edited.
+    pub fn child_by_field_mut(&mut self, field: &str) -> Option<&mut LosslessNode> {
+        self.children.iter_mut().find(|c| c.field == Some(field))
+    }
+
+    /// Replaces the text of this node and all its descendants, while keeping the text before
+    /// it, so that the node is written back as the given text.
+    pub fn set_text(&mut self, text: impl Into<String>) {
+        self.children.clear();
+        self.text = text.into();
+        self.trailing.clear();
+    }
+
+    /// Appends the text of this node, including the text before it, to `out`.
+    pub fn write_source(&self, out: &mut String) {
+        out.push_str(&self.leading);
+        if self.children.is_empty() {
+            out.push_str(&self.text);
+        } else {
+            self.children.iter().for_each(|c| c.write_source(out));
+            out.push_str(&self.trailing);
+        }
+    }
+
+    /// Writes the tree back to source, which is identical to the input for an unedited tree.
+    pub fn to_source(&self) -> String {
+        let mut out = String::new();
+        self.write_source(&mut out);
+        out
+    }
+}
+
+/// Parses the input with the given language into a [`LosslessNode`] tree, whose root also
+/// keeps the text before and after the root node of Tree Sitter.
+pub fn parse(
+    language: tree_sitter::Language,
+    input: &str,
+) -> Result<LosslessNode, Vec<ParseError>> {
+    let mut parser = tree_sitter::Parser::new();
+    parser.set_language(language).unwrap();
+    let tree = parser.parse(input, None).unwrap();
+    let root_node = tree.root_node();
+
+    if root_node.has_error() {
+        let mut errors = vec![];
+        collect_parsing_errors(&root_node, input.as_bytes(), &mut errors);
+
+        Err(errors)
+    } else {
+        let mut pos = 0;
+        let mut root = LosslessNode::from_node(root_node, None, input, &mut pos);
+        if root.children.is_empty() {
+            root.text.push_str(&input[pos..]);
+        } else {
+            root.trailing.push_str(&input[pos..]);
+        }
+
+        Ok(root)
+    }
+}
@@ -0,0 +1,53 @@
+use std::time::Duration;
+
+use crate::tree_sitter;
+
+/// Statistics about a single parse, as returned by the generated `parse_with_metrics`,
+/// which are useful for profiling a grammar on real inputs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseMetrics {
+    /// The number of nodes in the tree, including anonymous ones.
+    pub node_count: usize,
+    /// The number of nodes that are errors or that Tree Sitter inserted as missing.
+    pub error_count: usize,
+    /// How many nodes deep the tree is, counting the root node.
+    pub max_depth: usize,
+    /// How long Tree Sitter took to parse the input, not including extraction.
+    pub parse_time: Duration,
+}
+
+impl ParseMetrics {
+    /// Collects the metrics of a tree in a single walk, given the time spent parsing it.
+    pub fn new(tree: &tree_sitter::Tree, parse_time: Duration) -> ParseMetrics {
+        let mut metrics = ParseMetrics {
+            node_count: 0,
+            error_count: 0,
+            max_depth: 0,
+            parse_time,
+        };
+
+        let mut cursor = tree.walk();
+        let mut depth = 1;
+        loop {
+            let node = cursor.node();
+            metrics.node_count += 1;
+            if node.is_error() || node.is_missing() {
+                metrics.error_count += 1;
+            }
+            metrics.max_depth = metrics.max_depth.max(depth);
+
+            if cursor.goto_first_child() {
+                depth += 1;
+                continue;
+            }
+
+            while !cursor.goto_next_sibling() {
+                if !cursor.goto_parent() {
+                    return metrics;
+                }
+
+                depth -= 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use crate::errors::{self, ParseError, ParseErrorReason};
+use crate::{tree_sitter, validation, EntryPoint};
+
+/// How the generated `parse_with_options` parses an input, which the other generated parse
+/// functions are shorthands for. The default options parse as `parse` does, so options are
+/// usually set with struct update syntax, such as
+/// `ParseOptions { recovering: true, timeout_micros: 1000, ..Default::default() }`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ParseOptions<'a> {
+    /// The language to parse with instead of the one of the entry type, which must have the
+    /// same rule layout, as with `parse_with_language`.
+    pub language: Option<tree_sitter::Language>,
+    /// The number of microseconds that Tree Sitter may spend parsing, or `0` for no limit,
+    /// as with `parse_with_timeout`.
+    pub timeout_micros: u64,
+    /// The maximum depth of the tree, as with `parse_with_max_depth`.
+    pub max_depth: Option<usize>,
+    /// The maximum number of named nodes in the tree, as with `parse_with_max_nodes`.
+    pub max_nodes: Option<usize>,
+    /// The dialects that are enabled, as with `parse_with_dialects`, or `None` to allow the
+    /// constructs of every dialect.
+    pub dialects: Option<&'a [&'a str]>,
+    /// Whether to extract what can be extracted from a tree with errors, as with
+    /// `parse_recovering`, rather than only reporting the errors.
+    pub recovering: bool,
+    /// Whether to report a panic during extraction as an error, as `try_parse` does.
+    pub catch_panics: bool,
+}
+
+/// Parses an input with the parser of the entry type, or the language of the options, which
+/// the generated parse functions call with the way their input is given to Tree Sitter.
+/// A parser that does not produce a tree is reported as an error that spans the `len` bytes
+/// of the input.
+pub fn parse_tree<T: EntryPoint>(
+    options: &ParseOptions,
+    len: usize,
+    parse: impl FnOnce(&mut tree_sitter::Parser) -> Option<tree_sitter::Tree>,
+) -> Result<tree_sitter::Tree, ParseError> {
+    let failed = |reason| ParseError {
+        reason,
+        start: 0,
+        end: len,
+        rule: None,
+        expected: vec![],
+    };
+
+    let mut parser = tree_sitter::Parser::new();
+    // a language compiled elsewhere may have been built for another version of Tree Sitter
+    if parser
+        .set_language(options.language.unwrap_or_else(T::language))
+        .is_err()
+    {
+        return Err(failed(ParseErrorReason::ParserFailed));
+    }
+
+    parser.set_timeout_micros(options.timeout_micros);
+    parse(&mut parser).ok_or_else(|| match options.timeout_micros {
+        0 => failed(ParseErrorReason::ParserFailed),
+        micros => failed(ParseErrorReason::Timeout { micros }),
+    })
+}
+
+/// Checks the depth and the number of nodes of a tree against the limits of the options,
+/// which happens before anything else is done with the tree.
+pub fn check_limits(root: &tree_sitter::Node, options: &ParseOptions) -> Result<(), ParseError> {
+    if let Some(max_depth) = options.max_depth {
+        errors::check_depth(root, max_depth)?;
+    }
+
+    if let Some(max_nodes) = options.max_nodes {
+        errors::check_node_count(root, max_nodes)?;
+    }
+
+    Ok(())
+}
+
+/// Extracts the AST once the tree has been checked, given the errors found so far, which
+/// are only allowed when recovering. Values rejected by their `validate` function are
+/// reported along with them, which still gives an AST when recovering, and the `post_parse`
+/// hook of the entry type runs on an AST that is returned.
+pub fn extract<T: EntryPoint>(
+    root: &tree_sitter::Node,
+    options: &ParseOptions,
+    mut errors: Vec<ParseError>,
+    extract: impl FnOnce() -> Result<T, ParseError>,
+) -> (Option<T>, Vec<ParseError>) {
+    let extract = || validation::collect_validation_errors(extract);
+    let extracted = if options.recovering || options.catch_panics {
+        errors::catch_extraction_panic(root, extract)
+    } else {
+        errors::catch_extraction_error(extract)
+    };
+
+    match extracted {
+        Ok((Ok(mut ast), rejected)) if options.recovering || rejected.is_empty() => {
+            ast.post_parse();
+            errors.extend(rejected);
+            (Some(ast), errors)
+        }
+        Ok((Ok(_), rejected)) => {
+            errors.extend(rejected);
+            (None, errors)
+        }
+        Ok((Err(error), _)) => {
+            errors.push(error);
+            (None, errors)
+        }
+        // a failed extraction is only reported when no syntax error explains it
+        Err(error) if errors.is_empty() => (None, vec![error]),
+        Err(_) => (None, errors),
+    }
+}
+
+/// Turns the result of a parse into the result of the parse functions that do not recover,
+/// which only return an AST when there were no errors.
+pub fn into_result<T>((ast, errors): (Option<T>, Vec<ParseError>)) -> Result<T, Vec<ParseError>> {
+    match ast {
+        Some(ast) if errors.is_empty() => Ok(ast),
+        _ => Err(errors),
+    }
+}
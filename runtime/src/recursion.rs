@@ -0,0 +1,81 @@
+use crate::context;
+use crate::errors::{ParseError, ParseErrorReason};
+use crate::{tree_sitter, validation};
+
+/// The stack reserved for each level of the tree by [`extract_on_larger_stack`], which
+/// covers the few nested calls that extraction makes per node, even in debug builds.
+pub const STACK_PER_LEVEL: usize = 16 * 1024;
+
+/// The stack reserved by [`extract_on_larger_stack`] on top of the stack for each level of
+/// the tree, for the calls made by transforms and the extraction of leaves.
+pub const BASE_STACK: usize = 2 * 1024 * 1024;
+
+/// How many nodes deep the tree is, counting the root node, measured without recursion.
+fn depth(tree: &tree_sitter::Tree) -> usize {
+    let mut cursor = tree.walk();
+    let mut depth = 1;
+    let mut max_depth = 1;
+    loop {
+        if cursor.goto_first_child() {
+            depth += 1;
+            max_depth = max_depth.max(depth);
+            continue;
+        }
+
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                return max_depth;
+            }
+
+            depth -= 1;
+        }
+    }
+}
+
+/// Extracts from the root node of a tree on a thread whose stack is sized for the depth of
+/// the tree, which is measured with a walk that does not recurse, as used by the generated
+/// `parse_as_on_larger_stack`. Extraction still recurses, so nesting is bounded by the
+/// largest stack the platform gives a thread, and transforms that recurse deeply can use
+/// more than the [`STACK_PER_LEVEL`] reserved for each level. A thread that cannot be
+/// spawned, usually because the stack it needs is too large, is reported as a
+/// [`ParseErrorReason::TooDeep`] error that spans the whole tree.
+pub fn extract_on_larger_stack<T: Send>(
+    tree: &tree_sitter::Tree,
+    extract: impl FnOnce(tree_sitter::Node) -> T + Send,
+) -> Result<T, ParseError> {
+    let depth = depth(tree);
+    let stack_size = depth
+        .saturating_mul(STACK_PER_LEVEL)
+        .saturating_add(BASE_STACK);
+
+    // nodes cannot be sent to another thread, but the tree they belong to can, and
+    // the context of `parse_with_context` is used there while this thread waits. The
+    // values rejected there are reported back on this thread
+    let carried = context::carry();
+    std::thread::scope(|scope| {
+        let thread = std::thread::Builder::new()
+            .name("rust-sitter-extract".to_string())
+            .stack_size(stack_size)
+            .spawn_scoped(scope, || {
+                validation::collect_validation_errors(|| {
+                    context::provide_carried(carried, || extract(tree.root_node()))
+                })
+            })
+            .map_err(|_| {
+                let root = tree.root_node();
+                ParseError {
+                    reason: ParseErrorReason::TooDeep { depth },
+                    start: root.start_byte(),
+                    end: root.end_byte(),
+                    rule: None,
+                    expected: vec![],
+                }
+            })?;
+
+        let (ast, rejected) = thread
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+        validation::report_validation_errors(rejected);
+        Ok(ast)
+    })
+}
@@ -0,0 +1,60 @@
+use crate::tree_sitter;
+
+/// Whether a [`NodeEvent`] is for entering a node, before any of its descendants, or for
+/// leaving it, after all of them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodePhase {
+    Enter,
+    Leave,
+}
+
+/// A node of one of the types of a grammar that the generated `parse_streaming` passes
+/// through, which is reported once when it is entered and once when it is left.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NodeEvent {
+    /// The kind of the node, which is the name of its rule.
+    pub kind: &'static str,
+    /// The span of the node in the source, as in [`crate::Spanned`].
+    pub span: (usize, usize),
+    /// Whether the node is being entered or left.
+    pub phase: NodePhase,
+}
+
+/// Walks a tree in order, calling `on_event` when entering and leaving each node that
+/// `is_typed` accepts, so that consumers such as indexers can process the nodes of a large
+/// input without extracting them. The events of the other nodes are skipped, while their
+/// descendants are still visited.
+pub fn walk_typed_nodes(
+    tree: &tree_sitter::Tree,
+    is_typed: impl Fn(&str) -> bool,
+    mut on_event: impl FnMut(NodeEvent),
+) {
+    let mut emit = |node: tree_sitter::Node, phase: NodePhase| {
+        if is_typed(node.kind()) {
+            on_event(NodeEvent {
+                kind: node.kind(),
+                span: (node.start_byte(), node.end_byte()),
+                phase,
+            });
+        }
+    };
+
+    let mut cursor = tree.walk();
+    loop {
+        emit(cursor.node(), NodePhase::Enter);
+        if cursor.goto_first_child() {
+            continue;
+        }
+
+        loop {
+            emit(cursor.node(), NodePhase::Leave);
+            if cursor.goto_next_sibling() {
+                break;
+            }
+
+            if !cursor.goto_parent() {
+                return;
+            }
+        }
+    }
+}
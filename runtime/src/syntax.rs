@@ -0,0 +1,69 @@
+use crate::errors::{collect_parsing_errors, ParseError};
+use crate::tree_sitter;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A node of a dynamically typed syntax tree, produced by the `parse` function of
+/// grammars marked with `#[rust_sitter::untyped]`.
+pub struct SyntaxNode {
+    /// The name of the rule that matched this node.
+    pub kind: &'static str,
+    /// The name of the field this node was matched for in its parent, if any.
+    pub field: Option<&'static str>,
+    /// The child nodes, in source order. Extras such as whitespace are skipped.
+    pub children: Vec<SyntaxNode>,
+    /// The span of the node in the source. The first value is the inclusive start
+    /// of the span, and the second value is the exclusive end of the span.
+    pub span: (usize, usize),
+    /// The text of the source covered by this node.
+    pub text: String,
+}
+
+impl SyntaxNode {
+    /// Converts a node in a Tree Sitter tree, and all its descendants.
+    pub fn from_node(node: tree_sitter::Node, field: Option<&'static str>, source: &str) -> Self {
+        let mut children = vec![];
+        let mut cursor = node.walk();
+        if cursor.goto_first_child() {
+            loop {
+                let child = cursor.node();
+                if !child.is_extra() {
+                    children.push(SyntaxNode::from_node(child, cursor.field_name(), source));
+                }
+
+                if !cursor.goto_next_sibling() {
+                    break;
+                }
+            }
+        }
+
+        SyntaxNode {
+            kind: node.kind(),
+            field,
+            children,
+            span: (node.start_byte(), node.end_byte()),
+            text: source[node.start_byte()..node.end_byte()].to_string(),
+        }
+    }
+
+    /// Returns the first child that was matched for the field with the given name.
+    pub fn child_by_field(&self, field: &str) -> Option<&SyntaxNode> {
+        self.children.iter().find(|c| c.field == Some(field))
+    }
+}
+
+/// Parses the input with the given language into a [`SyntaxNode`] tree.
+pub fn parse(language: tree_sitter::Language, input: &str) -> Result<SyntaxNode, Vec<ParseError>> {
+    let mut parser = tree_sitter::Parser::new();
+    parser.set_language(language).unwrap();
+    let tree = parser.parse(input, None).unwrap();
+    let root_node = tree.root_node();
+
+    if root_node.has_error() {
+        let mut errors = vec![];
+        collect_parsing_errors(&root_node, input.as_bytes(), &mut errors);
+
+        Err(errors)
+    } else {
+        Ok(SyntaxNode::from_node(root_node, None, input))
+    }
+}
@@ -0,0 +1,36 @@
+use crate::tree_sitter;
+
+/// The target of the records logged while extracting, so that they can be filtered apart
+/// from the rest of the logs of an application.
+pub const TARGET: &str = "rust_sitter::extract";
+
+/// Logs, at the `trace` level, that extraction entered the node of a rule, which is
+/// extracted into the given Rust type or variant. This does nothing unless the `trace`
+/// feature is enabled.
+#[inline(always)]
+pub fn enter_rule(rule: &str, rust_path: &str, node: &tree_sitter::Node) {
+    #[cfg(feature = "trace")]
+    log::trace!(
+        target: TARGET,
+        "enter rule `{rule}` for `{rust_path}` at {}..{}",
+        node.start_byte(),
+        node.end_byte()
+    );
+    #[cfg(not(feature = "trace"))]
+    let _ = (rule, rust_path, node);
+}
+
+/// Logs, at the `trace` level, that extraction populated the given Rust field from a child
+/// of the node of a rule. This does nothing unless the `trace` feature is enabled.
+#[inline(always)]
+pub fn populate_field(rule: &str, rust_path: &str, node: &tree_sitter::Node) {
+    #[cfg(feature = "trace")]
+    log::trace!(
+        target: TARGET,
+        "populate field `{rust_path}` of rule `{rule}` from {}..{}",
+        node.start_byte(),
+        node.end_byte()
+    );
+    #[cfg(not(feature = "trace"))]
+    let _ = (rule, rust_path, node);
+}
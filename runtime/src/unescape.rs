@@ -0,0 +1,169 @@
+use std::borrow::Cow;
+use std::cell::Cell;
+
+use crate::errors::{ParseError, ParseErrorReason};
+use crate::{tree_sitter, Extract, Source};
+
+#[derive(Debug, PartialEq, Eq)]
+/// An escape sequence that could not be processed.
+pub struct InvalidEscape {
+    /// Inclusive start of the escape sequence in the escaped text.
+    pub start: usize,
+    /// Exclusive end of the escape sequence in the escaped text.
+    pub end: usize,
+}
+
+/// Replaces the standard escape sequences (`\n`, `\r`, `\t`, `\0`, `\\`, `\"`, `\'`
+/// and `\uXXXX`) in the given text with the characters they stand for.
+pub fn unescape(text: &str) -> Result<String, InvalidEscape> {
+    unescape_borrowed(text).map(Cow::into_owned)
+}
+
+/// Like [`unescape`], but borrows the text when it has no escape sequences, so that large
+/// leaves without any are not copied.
+pub fn unescape_borrowed(text: &str) -> Result<Cow<'_, str>, InvalidEscape> {
+    if !text.contains('\\') {
+        return Ok(Cow::Borrowed(text));
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.char_indices();
+    while let Some((start, c)) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some((_, 'n')) => out.push('\n'),
+            Some((_, 'r')) => out.push('\r'),
+            Some((_, 't')) => out.push('\t'),
+            Some((_, '0')) => out.push('\0'),
+            Some((_, c @ ('\\' | '"' | '\''))) => out.push(c),
+            Some((i, 'u')) => {
+                let digits = text[i + 1..]
+                    .chars()
+                    .take(4)
+                    .take_while(|c| c.is_ascii_hexdigit())
+                    .count();
+                let end = i + 1 + digits;
+
+                let unescaped = Some(&text[i + 1..end])
+                    .filter(|_| digits == 4)
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .and_then(char::from_u32)
+                    .ok_or(InvalidEscape { start, end })?;
+
+                out.push(unescaped);
+                chars.nth(3);
+            }
+            Some((i, c)) => {
+                return Err(InvalidEscape {
+                    start,
+                    end: i + c.len_utf8(),
+                })
+            }
+            None => {
+                return Err(InvalidEscape {
+                    start,
+                    end: text.len(),
+                })
+            }
+        }
+    }
+
+    Ok(Cow::Owned(out))
+}
+
+/// Unescapes the text of a leaf as it is extracted. Invalid escapes are reported by
+/// [`collect_escape_errors`] before extraction, so only parsing that recovers from errors
+/// extracts a leaf with one, whose text is then kept as it is.
+fn unescape_leaf(text: &str) -> Cow<'_, str> {
+    unescape_borrowed(text).unwrap_or(Cow::Borrowed(text))
+}
+
+/// Wraps a leaf transform so that it receives the text of the leaf with its
+/// escape sequences processed.
+pub fn unescaped<L>(transform: impl Fn(&str) -> L) -> impl Fn(&str) -> L {
+    move |text| transform(&unescape_leaf(text))
+}
+
+thread_local! {
+    static INPUT: Cell<Option<*const str>> = const { Cell::new(None) };
+}
+
+/// Puts back the input that was provided before, even if extraction panics.
+struct Restore(Option<*const str>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        INPUT.with(|i| i.set(self.0));
+    }
+}
+
+/// Makes `input` the text that the `Cow<str>` leaves extracted while `f` runs on the current
+/// thread borrow from. This is how the generated `parse_in` lets leaves borrow its input.
+///
+/// # Safety
+/// Every leaf extracted by `f` must not outlive `input`, which holds for an AST whose
+/// lifetime is tied to the borrow of the input, as the one returned by `parse_in` is.
+pub unsafe fn provide_input<R>(input: &str, f: impl FnOnce() -> R) -> R {
+    let _restore = Restore(INPUT.with(|i| i.replace(Some(input as *const str))));
+    f()
+}
+
+/// Extracts a leaf with `unescape = true` into a `Cow<str>`, which borrows the input given
+/// to the surrounding [`provide_input`] when the leaf has no escape sequences, and only
+/// allocates when they have to be processed. Without an input to borrow, such as in `parse`,
+/// whose AST cannot borrow its input, the leaf is always owned.
+pub struct WithUnescapedCow<L> {
+    _phantom: std::marker::PhantomData<L>,
+}
+
+impl<'a> Extract<Cow<'a, str>> for WithUnescapedCow<Cow<'a, str>> {
+    type LeafFn = ();
+    fn extract<S: Source + ?Sized>(
+        node: Option<tree_sitter::Node>,
+        source: &S,
+        _last_idx: (usize, crate::Point),
+        _leaf_fn: Option<&Self::LeafFn>,
+    ) -> Cow<'a, str> {
+        let n = node.unwrap();
+        match INPUT.with(|i| i.get()) {
+            Some(input) => {
+                // SAFETY: `provide_input` requires the input to outlive every extracted leaf
+                let input: &'a str = unsafe { &*input };
+                unescape_leaf(&input[n.start_byte()..n.end_byte()])
+            }
+            None => {
+                Cow::Owned(unescape_leaf(&source.text(n.start_byte(), n.end_byte())).into_owned())
+            }
+        }
+    }
+}
+
+/// Given the root node of a Tree Sitter parsing result, accumulates an error for
+/// every invalid escape sequence in the leaves with one of the given kinds.
+pub fn collect_escape_errors<S: Source + ?Sized>(
+    node: &tree_sitter::Node,
+    kinds: &[&str],
+    source: &S,
+    errors: &mut Vec<ParseError>,
+) {
+    if kinds.contains(&node.kind()) {
+        let text = source.text(node.start_byte(), node.end_byte());
+        if let Err(InvalidEscape { start, end }) = unescape_borrowed(&text) {
+            errors.push(ParseError {
+                reason: ParseErrorReason::InvalidEscape(text[start..end].to_string()),
+                start: node.start_byte() + start,
+                end: node.start_byte() + end,
+                rule: None,
+                expected: vec![],
+            })
+        }
+    } else {
+        let mut cursor = node.walk();
+        node.children(&mut cursor)
+            .for_each(|c| collect_escape_errors(&c, kinds, source, errors));
+    }
+}
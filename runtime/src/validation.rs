@@ -0,0 +1,109 @@
+use std::cell::RefCell;
+use std::fmt::Display;
+
+use crate::errors::{ParseError, ParseErrorReason};
+use crate::{tree_sitter, Extract, Source};
+
+/// The result of the `validate` function of a leaf, which is either a `bool` that is
+/// `false` when the value is rejected, or a `Result` whose error explains why it was.
+pub trait Validation {
+    /// Turns the result into an error with the explanation, if there is one.
+    fn into_result(self) -> Result<(), Option<String>>;
+}
+
+impl Validation for bool {
+    fn into_result(self) -> Result<(), Option<String>> {
+        if self {
+            Ok(())
+        } else {
+            Err(None)
+        }
+    }
+}
+
+impl<E: Display> Validation for Result<(), E> {
+    fn into_result(self) -> Result<(), Option<String>> {
+        self.map_err(|e| Some(e.to_string()))
+    }
+}
+
+/// Runs the `validate` function of a leaf on its transformed value.
+pub fn validate<T, V: Validation>(
+    value: &T,
+    validate: impl FnOnce(&T) -> V,
+) -> Result<(), Option<String>> {
+    validate(value).into_result()
+}
+
+thread_local! {
+    static ERRORS: RefCell<Option<Vec<ParseError>>> = const { RefCell::new(None) };
+}
+
+/// Puts back the errors that were being collected before, even if extraction panics.
+struct Restore(Option<Vec<ParseError>>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        ERRORS.with(|e| *e.borrow_mut() = self.0.take());
+    }
+}
+
+/// Runs the extraction `f`, collecting an error for every leaf whose value is rejected by
+/// its `validate` function. This is how the generated parse functions report such leaves,
+/// since extraction itself cannot fail.
+pub fn collect_validation_errors<R>(f: impl FnOnce() -> R) -> (R, Vec<ParseError>) {
+    let _restore = Restore(ERRORS.with(|e| e.borrow_mut().replace(vec![])));
+    let result = f();
+    let errors = ERRORS.with(|e| e.borrow_mut().take());
+    (result, errors.unwrap_or_default())
+}
+
+/// Adds errors collected elsewhere, such as on the thread that extracted the AST, to the
+/// surrounding [`collect_validation_errors`], if there is one.
+pub(crate) fn report_validation_errors(errors: Vec<ParseError>) {
+    ERRORS.with(|e| {
+        if let Some(collected) = e.borrow_mut().as_mut() {
+            collected.extend(errors);
+        }
+    });
+}
+
+/// Extracts a leaf with a `validate` function, whose function transforms the text of the
+/// leaf and validates the result. A rejected value is still extracted, and its error, which
+/// spans the leaf, goes to the surrounding [`collect_validation_errors`], if there is one.
+pub struct WithValidation<L> {
+    _phantom: std::marker::PhantomData<L>,
+}
+
+impl<L> Extract<L> for WithValidation<L> {
+    type LeafFn = dyn Fn(&str) -> (L, Result<(), Option<String>>);
+
+    fn extract<S: Source + ?Sized>(
+        node: Option<tree_sitter::Node>,
+        source: &S,
+        _last_idx: (usize, crate::Point),
+        leaf_fn: Option<&Self::LeafFn>,
+    ) -> L {
+        let n = node.unwrap();
+        let text = source.text(n.start_byte(), n.end_byte());
+        let (value, result) = crate::errors::transform_leaf(&n, || leaf_fn.unwrap()(&text));
+        if let Err(message) = result {
+            ERRORS.with(|e| {
+                if let Some(errors) = e.borrow_mut().as_mut() {
+                    errors.push(ParseError {
+                        reason: ParseErrorReason::InvalidValue {
+                            text: text.to_string(),
+                            message,
+                        },
+                        start: n.start_byte(),
+                        end: n.end_byte(),
+                        rule: None,
+                        expected: vec![],
+                    })
+                }
+            });
+        }
+
+        value
+    }
+}
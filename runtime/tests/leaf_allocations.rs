@@ -0,0 +1,64 @@
+//! Measures the allocations made while passing the text of a large leaf to its transform.
+//! This lives in its own test binary, since the counting allocator would otherwise also see
+//! other tests. Only the allocations of the measuring thread are counted, since the test
+//! harness may allocate on its own threads at the same time.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use rust_sitter::unescape::unescaped;
+use rust_sitter::Source;
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATED: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // the counter is gone while the thread is being torn down
+        let _ = ALLOCATED.try_with(|a| a.set(a.get() + layout.size()));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Returns the number of bytes allocated by this thread while running `f`.
+fn allocated_during<T>(f: impl FnOnce() -> T) -> usize {
+    let before = ALLOCATED.with(Cell::get);
+    std::hint::black_box(f());
+    ALLOCATED.with(Cell::get) - before
+}
+
+/// A string literal of about a megabyte, as found in inputs with embedded data.
+fn large_literal(escaped: bool) -> String {
+    let line = if escaped {
+        "lorem ipsum dolor sit amet\\n"
+    } else {
+        "lorem ipsum dolor sit amet "
+    };
+    format!("\"{}\"", line.repeat(40_000))
+}
+
+#[test]
+fn large_leaf_allocations() {
+    let input = large_literal(false);
+    let source = input.as_bytes();
+    let transform = unescaped(|v: &str| v.len());
+
+    // the text of a leaf borrows the input, and only escape sequences make it owned
+    let bytes = allocated_during(|| transform(&source.text(0, source.len())));
+    assert_eq!(bytes, 0);
+
+    let escaped = large_literal(true);
+    let source = escaped.as_bytes();
+    let bytes = allocated_during(|| transform(&source.text(0, source.len())));
+    assert!(bytes >= escaped.len() - 40_000, "{bytes}");
+}
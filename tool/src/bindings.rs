@@ -0,0 +1,237 @@
+use std::path::{Path, PathBuf};
+
+/// The placeholder for the grammar name in the templates, as in those of `tree-sitter generate`.
+const PARSER_NAME: &str = "PARSER_NAME";
+
+/// The placeholder for the grammar name with dashes instead of underscores, which is used for
+/// the names of packages.
+const PACKAGE_NAME: &str = "PACKAGE_NAME";
+
+const LIB_RS_TEMPLATE: &str = r#"//! This crate provides PARSER_NAME language support for the [tree-sitter][] parsing library.
+//!
+//! Typically, you will use the [language][language func] function to add this language to a
+//! tree-sitter [Parser][], and then use the parser to parse some code:
+//!
+//! ```
+//! let code = "";
+//! let mut parser = tree_sitter::Parser::new();
+//! parser.set_language(tree_sitter_PARSER_NAME::language()).expect("Error loading PARSER_NAME grammar");
+//! let tree = parser.parse(code, None).unwrap();
+//! ```
+//!
+//! [Language]: https://docs.rs/tree-sitter/*/tree_sitter/struct.Language.html
+//! [language func]: fn.language.html
+//! [Parser]: https://docs.rs/tree-sitter/*/tree_sitter/struct.Parser.html
+//! [tree-sitter]: https://tree-sitter.github.io/
+
+use tree_sitter::Language;
+
+extern "C" {
+    fn tree_sitter_PARSER_NAME() -> Language;
+}
+
+/// Get the tree-sitter [Language][] for this grammar.
+///
+/// [Language]: https://docs.rs/tree-sitter/*/tree_sitter/struct.Language.html
+pub fn language() -> Language {
+    unsafe { tree_sitter_PARSER_NAME() }
+}
+
+/// The content of the [`node-types.json`][] file for this grammar.
+///
+/// [`node-types.json`]: https://tree-sitter.github.io/tree-sitter/using-parsers#static-node-types
+pub const NODE_TYPES: &str = include_str!("../../src/node-types.json");
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_can_load_grammar() {
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(super::language())
+            .expect("Error loading PARSER_NAME language");
+    }
+}
+"#;
+
+const BUILD_RS_TEMPLATE: &str = r#"fn main() {
+    let src_dir = std::path::Path::new("src");
+
+    let mut c_config = cc::Build::new();
+    c_config.include(src_dir);
+    c_config
+        .flag_if_supported("-Wno-unused-parameter")
+        .flag_if_supported("-Wno-unused-but-set-variable")
+        .flag_if_supported("-Wno-trigraphs");
+
+    let parser_path = src_dir.join("parser.c");
+    c_config.file(&parser_path);
+    println!("cargo:rerun-if-changed={}", parser_path.to_str().unwrap());
+
+    // the external scanner of the grammar, if it has one
+    let scanner_path = src_dir.join("scanner.c");
+    if scanner_path.exists() {
+        c_config.file(&scanner_path);
+        println!("cargo:rerun-if-changed={}", scanner_path.to_str().unwrap());
+    }
+
+    c_config.compile("tree-sitter-PARSER_NAME");
+}
+"#;
+
+const CARGO_TOML_TEMPLATE: &str = r#"[package]
+name = "tree-sitter-PACKAGE_NAME"
+description = "PARSER_NAME grammar for the tree-sitter parsing library"
+version = "0.0.1"
+keywords = ["incremental", "parsing", "PARSER_NAME"]
+categories = ["parsing", "text-editors"]
+edition = "2018"
+license = "MIT"
+
+build = "bindings/rust/build.rs"
+include = [
+  "bindings/rust/*",
+  "queries/*",
+  "src/*",
+]
+
+[lib]
+path = "bindings/rust/lib.rs"
+
+[dependencies]
+tree-sitter = "~0.20.9"
+
+[build-dependencies]
+cc = "1.0"
+"#;
+
+const PACKAGE_JSON_TEMPLATE: &str = r#"{
+  "name": "tree-sitter-PACKAGE_NAME",
+  "version": "0.0.1",
+  "description": "PARSER_NAME grammar for tree-sitter",
+  "main": "bindings/node",
+  "keywords": [
+    "parsing",
+    "incremental"
+  ],
+  "dependencies": {
+    "nan": "^2.12.1"
+  }
+}
+"#;
+
+const BINDING_GYP_TEMPLATE: &str = r#"{
+  "targets": [
+    {
+      "target_name": "tree_sitter_PARSER_NAME_binding",
+      "include_dirs": [
+        "<!(node -e \"require('nan')\")",
+        "src"
+      ],
+      "sources": [
+        "bindings/node/binding.cc",
+        "src/parser.c",
+        # If your language uses an external scanner, add it here.
+      ],
+      "cflags_c": [
+        "-std=c99",
+      ]
+    }
+  ]
+}
+"#;
+
+const BINDING_CC_TEMPLATE: &str = r#"#include "tree_sitter/parser.h"
+#include <node.h>
+#include "nan.h"
+
+using namespace v8;
+
+extern "C" TSLanguage * tree_sitter_PARSER_NAME();
+
+namespace {
+
+NAN_METHOD(New) {}
+
+void Init(Local<Object> exports, Local<Object> module) {
+  Local<FunctionTemplate> tpl = Nan::New<FunctionTemplate>(New);
+  tpl->SetClassName(Nan::New("Language").ToLocalChecked());
+  tpl->InstanceTemplate()->SetInternalFieldCount(1);
+
+  Local<Function> constructor = Nan::GetFunction(tpl).ToLocalChecked();
+  Local<Object> instance = constructor->NewInstance(Nan::GetCurrentContext()).ToLocalChecked();
+  Nan::SetInternalFieldPointer(instance, 0, tree_sitter_PARSER_NAME());
+
+  Nan::Set(instance, Nan::New("name").ToLocalChecked(), Nan::New("PARSER_NAME").ToLocalChecked());
+  Nan::Set(module, Nan::New("exports").ToLocalChecked(), instance);
+}
+
+NODE_MODULE(tree_sitter_PARSER_NAME_binding, Init)
+
+}  // namespace
+"#;
+
+const INDEX_JS_TEMPLATE: &str = r#"try {
+  module.exports = require("../../build/Release/tree_sitter_PARSER_NAME_binding");
+} catch (error1) {
+  if (error1.code !== 'MODULE_NOT_FOUND') {
+    throw error1;
+  }
+  try {
+    module.exports = require("../../build/Debug/tree_sitter_PARSER_NAME_binding");
+  } catch (error2) {
+    if (error2.code !== 'MODULE_NOT_FOUND') {
+      throw error2;
+    }
+    throw error1
+  }
+}
+
+try {
+  module.exports.nodeTypeInfo = require("../../src/node-types.json");
+} catch (_) {}
+"#;
+
+/// The files written by [`generate_bindings`], relative to the output directory, along with
+/// their templates.
+const BINDING_FILES: &[(&str, &str)] = &[
+    ("bindings/rust/lib.rs", LIB_RS_TEMPLATE),
+    ("bindings/rust/build.rs", BUILD_RS_TEMPLATE),
+    ("Cargo.toml", CARGO_TOML_TEMPLATE),
+    ("bindings/node/index.js", INDEX_JS_TEMPLATE),
+    ("bindings/node/binding.cc", BINDING_CC_TEMPLATE),
+    ("binding.gyp", BINDING_GYP_TEMPLATE),
+    ("package.json", PACKAGE_JSON_TEMPLATE),
+];
+
+/// Writes the scaffolding that `tree-sitter generate` creates for publishing a grammar as a
+/// standard Tree Sitter package to the given directory, returning the paths of the files.
+///
+/// These are the Rust bindings (`bindings/rust/lib.rs`, `bindings/rust/build.rs` and
+/// `Cargo.toml`) and the Node bindings (`bindings/node/index.js`, `bindings/node/binding.cc`,
+/// `binding.gyp` and `package.json`) for the grammar with the given name, which expect the
+/// generated parser in `src/parser.c`, its `src/node-types.json`, and the external scanner,
+/// if there is one, in `src/scanner.c`. As with `tree-sitter generate`, files that already
+/// exist are left as they are, so that they can be customized, and are not returned.
+pub fn generate_bindings(grammar_name: &str, out_dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let package_name = grammar_name.replace('_', "-");
+
+    let mut paths = vec![];
+    for (file, template) in BINDING_FILES {
+        let path = out_dir.join(file);
+        if path.exists() {
+            continue;
+        }
+
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(
+            &path,
+            template
+                .replace(PACKAGE_NAME, &package_name)
+                .replace(PARSER_NAME, grammar_name),
+        )?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
@@ -0,0 +1,638 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use serde_json::{Map, Value};
+
+/// Parses the groups of declared conflicts that the Tree Sitter generator reports as
+/// unnecessary from its output, which lists each group on an indented line after the
+/// warning, with the names of its rules in backticks.
+pub fn parse_unused_conflicts(output: &str) -> Vec<Vec<String>> {
+    let mut out = vec![];
+    let mut in_report = false;
+    output.lines().for_each(|line| {
+        if line.contains("unnecessary conflicts") {
+            in_report = true;
+        } else if in_report && line.starts_with(char::is_whitespace) {
+            let names = line
+                .split('`')
+                .skip(1)
+                .step_by(2)
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>();
+            if !names.is_empty() {
+                out.push(names);
+            }
+        } else {
+            in_report = false;
+        }
+    });
+
+    out
+}
+
+#[cfg(feature = "check_conflicts")]
+/// Runs a grammar through the `tree-sitter` CLI, if it is on the `PATH`, and returns the
+/// groups of its declared conflicts that the generator never needed to resolve, so that
+/// they can be removed. Returns `None` when the CLI is not available, or when it fails to
+/// generate a parser for the grammar.
+///
+/// The generator embedded in Rust Sitter does not report unnecessary conflicts, which is
+/// why this invokes the external one.
+pub fn find_unused_conflicts(grammar: &Value) -> Option<Vec<Vec<String>>> {
+    if grammar.get("conflicts").is_none() {
+        return Some(vec![]);
+    }
+
+    let dir = tempfile::Builder::new()
+        .prefix("conflicts")
+        .tempdir()
+        .ok()?;
+    let grammar_file = dir.path().join("grammar.json");
+    std::fs::write(&grammar_file, grammar.to_string()).ok()?;
+
+    let output = std::process::Command::new("tree-sitter")
+        .arg("generate")
+        .arg(&grammar_file)
+        .current_dir(dir.path())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    Some(parse_unused_conflicts(&format!(
+        "{}\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    )))
+}
+
+/// A conflict between the rules of a grammar that is neither declared in its `conflicts` nor
+/// resolved by precedence or associativity, as found by [`find_undeclared_conflicts`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UndeclaredConflict {
+    /// The rules of the items in conflict, sorted by name, which are the group that would
+    /// declare the conflict with `#[rust_sitter::conflicts(...)]`.
+    pub rules: Vec<String>,
+    /// The token at which the parser cannot decide between the rules.
+    pub lookahead: String,
+}
+
+/// Looks for conflicts in the LALR(1) parse table of a grammar and returns the ones that are
+/// neither declared in its `conflicts` nor resolved by the precedence and associativity of
+/// its rules, so that they are reported before the grammar reaches the Tree Sitter generator.
+///
+/// This is a best-effort check rather than a reimplementation of the generator: lexical
+/// conflicts, extras and dynamic precedences are not considered, named precedences are only
+/// compared within the orderings of `precedences`, and merging states with the same items
+/// can report a few conflicts that the generator, which merges states more carefully, avoids.
+pub fn find_undeclared_conflicts(grammar: &Value) -> Vec<UndeclaredConflict> {
+    let rules = grammar["rules"].as_object().unwrap();
+    let orderings = grammar["precedences"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|ordering| {
+            ordering
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|entry| {
+                    entry["value"]
+                        .as_str()
+                        .or_else(|| entry["name"].as_str())
+                        .unwrap_or_default()
+                        .to_string()
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let declared = grammar["conflicts"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|group| {
+            group
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter_map(|name| name.as_str().map(str::to_string))
+                .collect::<HashSet<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let mut lowering = Lowering {
+        rules,
+        terminals: vec!["end of input".to_string()],
+        terminal_ids: HashMap::new(),
+        nonterminals: vec![],
+        nonterminal_ids: HashMap::new(),
+        token_uses: HashMap::new(),
+        productions: vec![],
+        pending: vec![],
+    };
+    rules
+        .values()
+        .for_each(|rule| count_token_uses(rule, &mut lowering.token_uses));
+
+    // the augmented start rule accepts the start rule of the grammar, which is its first rule
+    let start_rule = rules.keys().next().unwrap();
+    lowering.nonterminals.push(start_rule.clone());
+    let start = lowering.symbol(start_rule);
+    lowering.productions.push(Production {
+        lhs: 0,
+        steps: vec![Step {
+            symbol: start,
+            prec: Prec::Integer(0),
+            assoc: Assoc::None,
+        }],
+    });
+    while let Some((name, id)) = lowering.pending.pop() {
+        let rule = &lowering.rules[&name];
+        lowering
+            .flatten(rule, id, &Prec::Integer(0), Assoc::None)
+            .into_iter()
+            .for_each(|steps| lowering.productions.push(Production { lhs: id, steps }));
+    }
+
+    let table = Table::new(&lowering);
+    let compare = |a: &Prec, b: &Prec| match (a, b) {
+        (Prec::Integer(a), Prec::Integer(b)) => a.cmp(b),
+        (Prec::Name(a), Prec::Name(b)) => orderings
+            .iter()
+            .find_map(|ordering| {
+                let a = ordering.iter().position(|n| n == a)?;
+                let b = ordering.iter().position(|n| n == b)?;
+                // the orderings go from the level that binds most tightly
+                Some(b.cmp(&a))
+            })
+            .unwrap_or(Ordering::Equal),
+        _ => Ordering::Equal,
+    };
+
+    let mut conflicts: Vec<UndeclaredConflict> = vec![];
+    table.states.iter().for_each(|kernel| {
+        let items = table.closure(kernel);
+        (0..lowering.terminals.len()).for_each(|terminal| {
+            let reduces = items
+                .iter()
+                .filter(|((p, d), lookaheads)| {
+                    *p != 0
+                        && *d == lowering.productions[*p].steps.len()
+                        && lookaheads.contains(&terminal)
+                })
+                .map(|((p, _), _)| &lowering.productions[*p])
+                .collect::<Vec<_>>();
+            let shifts = items.keys().any(|(p, d)| {
+                lowering.productions[*p].steps.get(*d).map(|s| s.symbol)
+                    == Some(Symbol::Terminal(terminal))
+            });
+            if reduces.is_empty() || (reduces.len() == 1 && !shifts) {
+                return;
+            }
+
+            // the items that are partway through a rule that the token could continue
+            let shifting = items
+                .keys()
+                .filter(|(p, d)| {
+                    let steps = &lowering.productions[*p].steps;
+                    *d > 0 && *d < steps.len() && table.first(steps[*d].symbol).contains(&terminal)
+                })
+                .map(|(p, d)| (&lowering.productions[*p], *d))
+                .collect::<Vec<_>>();
+
+            // reductions with a lower precedence than another one give way to it
+            let reduces = reduces
+                .iter()
+                .filter(|r| {
+                    !reduces
+                        .iter()
+                        .any(|o| compare(&o.prec(), &r.prec()) == Ordering::Greater)
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+
+            let resolved = reduces.len() == 1
+                && (!shifts || {
+                    let reduce = reduces[0];
+                    let orders = shifting
+                        .iter()
+                        .map(|(p, d)| compare(&reduce.prec(), &p.steps[d - 1].prec))
+                        .collect::<Vec<_>>();
+                    if orders.iter().all(|o| *o == Ordering::Greater)
+                        || orders.iter().all(|o| *o == Ordering::Less) && !orders.is_empty()
+                    {
+                        true
+                    } else if orders.iter().all(|o| *o == Ordering::Equal) {
+                        reduce.assoc() != Assoc::None
+                    } else {
+                        false
+                    }
+                });
+            if resolved {
+                return;
+            }
+
+            let mut involved = reduces
+                .iter()
+                .map(|r| lowering.nonterminals[r.lhs].clone())
+                .chain(
+                    shifting
+                        .iter()
+                        .map(|(p, _)| lowering.nonterminals[p.lhs].clone()),
+                )
+                .collect::<Vec<_>>();
+            involved.sort();
+            involved.dedup();
+
+            let is_declared = declared
+                .iter()
+                .any(|group| involved.iter().all(|rule| group.contains(rule)));
+            if !is_declared && !conflicts.iter().any(|c| c.rules == involved) {
+                conflicts.push(UndeclaredConflict {
+                    rules: involved,
+                    lookahead: lowering.terminals[terminal].clone(),
+                });
+            }
+        });
+    });
+
+    conflicts
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+enum Symbol {
+    Terminal(usize),
+    NonTerminal(usize),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Assoc {
+    None,
+    Left,
+    Right,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Prec {
+    Integer(i64),
+    Name(String),
+}
+
+/// A symbol of a production, along with the precedence and associativity it was given by
+/// the `PREC` rules around it.
+#[derive(Clone)]
+struct Step {
+    symbol: Symbol,
+    prec: Prec,
+    assoc: Assoc,
+}
+
+struct Production {
+    lhs: usize,
+    steps: Vec<Step>,
+}
+
+impl Production {
+    /// The precedence of reducing the production, which is that of its last step.
+    fn prec(&self) -> Prec {
+        self.steps
+            .last()
+            .map(|step| step.prec.clone())
+            .unwrap_or(Prec::Integer(0))
+    }
+
+    fn assoc(&self) -> Assoc {
+        self.steps
+            .last()
+            .map(|step| step.assoc)
+            .unwrap_or(Assoc::None)
+    }
+}
+
+/// Lowers the rules of a grammar into productions, expanding the choices within each rule
+/// into separate productions like Tree Sitter does, and turning each repetition into a
+/// left-recursive auxiliary rule that is reported under the name of the rule it is part of.
+struct Lowering<'a> {
+    rules: &'a Map<String, Value>,
+    terminals: Vec<String>,
+    terminal_ids: HashMap<String, usize>,
+    nonterminals: Vec<String>,
+    nonterminal_ids: HashMap<String, usize>,
+    token_uses: HashMap<String, usize>,
+    productions: Vec<Production>,
+    pending: Vec<(String, usize)>,
+}
+
+impl Lowering<'_> {
+    fn terminal(&mut self, key: String, display: String) -> Symbol {
+        let next = self.terminals.len();
+        let id = *self.terminal_ids.entry(key).or_insert(next);
+        if id == next {
+            self.terminals.push(display);
+        }
+        Symbol::Terminal(id)
+    }
+
+    /// The symbol referring to a rule, which is a terminal for external tokens and for rules
+    /// that match a token no other rule matches. Like in Tree Sitter, a rule matching a token
+    /// that is shared with other rules stays a nonterminal wrapping that token.
+    fn symbol(&mut self, name: &str) -> Symbol {
+        match self.rules.get(name) {
+            Some(rule) if !is_lexical(rule) || self.token_uses[&token_key(rule)] > 1 => {
+                if let Some(id) = self.nonterminal_ids.get(name) {
+                    return Symbol::NonTerminal(*id);
+                }
+
+                let id = self.nonterminals.len();
+                self.nonterminals.push(name.to_string());
+                self.nonterminal_ids.insert(name.to_string(), id);
+                self.pending.push((name.to_string(), id));
+                Symbol::NonTerminal(id)
+            }
+            Some(rule) => self.terminal(format!("SYMBOL {name}"), token_display(rule)),
+            None => self.terminal(format!("SYMBOL {name}"), format!("`{name}`")),
+        }
+    }
+
+    /// Expands a rule into the sequences of steps it can match.
+    fn flatten(&mut self, rule: &Value, owner: usize, prec: &Prec, assoc: Assoc) -> Vec<Vec<Step>> {
+        let step = |symbol| Step {
+            symbol,
+            prec: prec.clone(),
+            assoc,
+        };
+
+        match rule["type"].as_str().unwrap() {
+            "SEQ" => rule["members"].as_array().unwrap().iter().fold(
+                vec![vec![]],
+                |sequences, member| {
+                    let alternatives = self.flatten(member, owner, prec, assoc);
+                    sequences
+                        .iter()
+                        .flat_map(|sequence: &Vec<Step>| {
+                            alternatives.iter().map(move |alternative| {
+                                sequence.iter().chain(alternative).cloned().collect()
+                            })
+                        })
+                        .collect()
+                },
+            ),
+            "CHOICE" => rule["members"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .flat_map(|member| self.flatten(member, owner, prec, assoc))
+                .collect(),
+            "BLANK" => vec![vec![]],
+            kind @ ("REPEAT" | "REPEAT1") => {
+                let id = self.nonterminals.len();
+                self.nonterminals.push(self.nonterminals[owner].clone());
+                self.flatten(&rule["content"], owner, prec, assoc)
+                    .into_iter()
+                    .for_each(|steps| {
+                        let repeated = std::iter::once(step(Symbol::NonTerminal(id)))
+                            .chain(steps.iter().cloned())
+                            .collect();
+                        self.productions.push(Production {
+                            lhs: id,
+                            steps: repeated,
+                        });
+                        self.productions.push(Production { lhs: id, steps });
+                    });
+
+                if kind == "REPEAT" {
+                    vec![vec![step(Symbol::NonTerminal(id))], vec![]]
+                } else {
+                    vec![vec![step(Symbol::NonTerminal(id))]]
+                }
+            }
+            "FIELD" | "ALIAS" | "PREC_DYNAMIC" => {
+                self.flatten(&rule["content"], owner, prec, assoc)
+            }
+            kind @ ("PREC" | "PREC_LEFT" | "PREC_RIGHT") => {
+                let prec = match &rule["value"] {
+                    Value::String(name) => Prec::Name(name.clone()),
+                    value => Prec::Integer(value.as_i64().unwrap_or_default()),
+                };
+                let assoc = match kind {
+                    "PREC_LEFT" => Assoc::Left,
+                    "PREC_RIGHT" => Assoc::Right,
+                    _ => Assoc::None,
+                };
+                self.flatten(&rule["content"], owner, &prec, assoc)
+            }
+            "SYMBOL" => {
+                let symbol = self.symbol(rule["name"].as_str().unwrap());
+                vec![vec![step(symbol)]]
+            }
+            _ => {
+                let symbol = self.terminal(token_key(rule), token_display(rule));
+                vec![vec![step(symbol)]]
+            }
+        }
+    }
+}
+
+/// Whether a rule matches a single token, which Tree Sitter turns into a terminal.
+fn is_lexical(rule: &Value) -> bool {
+    match rule["type"].as_str() {
+        Some("STRING" | "PATTERN" | "TOKEN" | "IMMEDIATE_TOKEN") => true,
+        Some("PREC" | "PREC_LEFT" | "PREC_RIGHT" | "PREC_DYNAMIC") => is_lexical(&rule["content"]),
+        _ => false,
+    }
+}
+
+/// Identifies the token matched by a lexical rule, ignoring its precedence, so that
+/// identical tokens in different rules are the same terminal.
+fn token_key(rule: &Value) -> String {
+    match rule["type"].as_str() {
+        Some("PREC" | "PREC_LEFT" | "PREC_RIGHT" | "PREC_DYNAMIC") => token_key(&rule["content"]),
+        _ => rule.to_string(),
+    }
+}
+
+/// Counts how many times each token appears across the rules of a grammar.
+fn count_token_uses(rule: &Value, uses: &mut HashMap<String, usize>) {
+    if is_lexical(rule) {
+        *uses.entry(token_key(rule)).or_default() += 1;
+    } else if let Some(members) = rule["members"].as_array() {
+        members
+            .iter()
+            .for_each(|member| count_token_uses(member, uses));
+    } else if let Some(content) = rule.get("content") {
+        count_token_uses(content, uses);
+    }
+}
+
+/// How a token appears in the reports of conflicts.
+fn token_display(rule: &Value) -> String {
+    match rule["type"].as_str() {
+        Some("STRING") => format!("`{}`", rule["value"].as_str().unwrap()),
+        Some("PATTERN") => format!("`/{}/`", rule["value"].as_str().unwrap()),
+        _ => match rule.get("content") {
+            Some(content) => token_display(content),
+            None => "a token".to_string(),
+        },
+    }
+}
+
+/// The items of a state, which are positions in productions, with the tokens that can
+/// follow each one.
+type Items = BTreeMap<(usize, usize), BTreeSet<usize>>;
+
+/// The LALR(1) states of the productions of a grammar, which are built like its LR(1)
+/// states, except that states with the same items are merged by combining their lookaheads.
+struct Table<'a> {
+    lowering: &'a Lowering<'a>,
+    by_lhs: Vec<Vec<usize>>,
+    nullable: Vec<bool>,
+    firsts: Vec<BTreeSet<usize>>,
+    /// The kernel items of each state, from which the rest of its items are derived.
+    states: Vec<Items>,
+}
+
+impl<'a> Table<'a> {
+    fn new(lowering: &'a Lowering<'a>) -> Self {
+        let count = lowering.nonterminals.len();
+        let mut by_lhs = vec![vec![]; count];
+        lowering
+            .productions
+            .iter()
+            .enumerate()
+            .for_each(|(i, p)| by_lhs[p.lhs].push(i));
+
+        let mut table = Table {
+            lowering,
+            by_lhs,
+            nullable: vec![false; count],
+            firsts: vec![BTreeSet::new(); count],
+            states: vec![],
+        };
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            lowering.productions.iter().for_each(|p| {
+                let (first, nullable) = table.first_of(&p.steps);
+                if nullable && !table.nullable[p.lhs] {
+                    table.nullable[p.lhs] = true;
+                    changed = true;
+                }
+
+                let before = table.firsts[p.lhs].len();
+                table.firsts[p.lhs].extend(first);
+                changed |= table.firsts[p.lhs].len() != before;
+            });
+        }
+
+        let mut index = HashMap::new();
+        index.insert(vec![(0, 0)], 0);
+        table
+            .states
+            .push(Items::from([((0, 0), BTreeSet::from([0]))]));
+        let mut queue = vec![0];
+        let mut queued = vec![true];
+        while let Some(i) = queue.pop() {
+            queued[i] = false;
+
+            let mut gotos: BTreeMap<Symbol, Items> = BTreeMap::new();
+            table
+                .closure(&table.states[i])
+                .into_iter()
+                .for_each(|((p, d), lookaheads)| {
+                    if let Some(step) = lowering.productions[p].steps.get(d) {
+                        gotos
+                            .entry(step.symbol)
+                            .or_default()
+                            .entry((p, d + 1))
+                            .or_default()
+                            .extend(lookaheads);
+                    }
+                });
+
+            gotos.into_values().for_each(|kernel| {
+                let core = kernel.keys().cloned().collect::<Vec<_>>();
+                match index.get(&core) {
+                    Some(&j) => {
+                        let mut changed = false;
+                        kernel.into_iter().for_each(|(item, lookaheads)| {
+                            let existing = table.states[j].get_mut(&item).unwrap();
+                            let before = existing.len();
+                            existing.extend(lookaheads);
+                            changed |= existing.len() != before;
+                        });
+
+                        if changed && !queued[j] {
+                            queued[j] = true;
+                            queue.push(j);
+                        }
+                    }
+                    None => {
+                        index.insert(core, table.states.len());
+                        queue.push(table.states.len());
+                        queued.push(true);
+                        table.states.push(kernel);
+                    }
+                }
+            });
+        }
+
+        table
+    }
+
+    /// The tokens that a sequence of steps can start with, and whether it can be empty.
+    fn first_of(&self, steps: &[Step]) -> (BTreeSet<usize>, bool) {
+        let mut first = BTreeSet::new();
+        for step in steps {
+            match step.symbol {
+                Symbol::Terminal(t) => {
+                    first.insert(t);
+                    return (first, false);
+                }
+                Symbol::NonTerminal(n) => {
+                    first.extend(&self.firsts[n]);
+                    if !self.nullable[n] {
+                        return (first, false);
+                    }
+                }
+            }
+        }
+
+        (first, true)
+    }
+
+    fn first(&self, symbol: Symbol) -> BTreeSet<usize> {
+        match symbol {
+            Symbol::Terminal(t) => BTreeSet::from([t]),
+            Symbol::NonTerminal(n) => self.firsts[n].clone(),
+        }
+    }
+
+    /// Adds the items predicted by the kernel items of a state, along with their lookaheads.
+    fn closure(&self, kernel: &Items) -> Items {
+        let mut items = kernel.clone();
+        let mut queue = items.keys().cloned().collect::<Vec<_>>();
+        while let Some((p, d)) = queue.pop() {
+            let steps = &self.lowering.productions[p].steps;
+            if let Some(Symbol::NonTerminal(n)) = steps.get(d).map(|step| step.symbol) {
+                let (mut lookaheads, nullable) = self.first_of(&steps[d + 1..]);
+                if nullable {
+                    lookaheads.extend(&items[&(p, d)]);
+                }
+
+                self.by_lhs[n].iter().for_each(|&q| {
+                    let is_new = !items.contains_key(&(q, 0));
+                    let existing = items.entry((q, 0)).or_default();
+                    let before = existing.len();
+                    existing.extend(&lookaheads);
+                    if is_new || existing.len() != before {
+                        queue.push((q, 0));
+                    }
+                });
+            }
+        }
+
+        items
+    }
+}
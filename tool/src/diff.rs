@@ -0,0 +1,212 @@
+use std::fmt;
+
+use serde_json::Value;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// The differences between two generated grammars, for reviewing how a change to the
+/// Rust definitions affects the Tree Sitter grammar without reading a diff of the JSON.
+pub struct GrammarDiff {
+    /// The names of the rules that are only in the new grammar.
+    pub added: Vec<String>,
+    /// The names of the rules that are only in the old grammar.
+    pub removed: Vec<String>,
+    /// The rules in both grammars whose definitions differ, along with a description of
+    /// each change and where in the rule it was made.
+    pub changed: Vec<(String, Vec<String>)>,
+    /// The other settings of the grammar, such as `extras` or `word`, that differ.
+    pub changed_settings: Vec<String>,
+}
+
+impl GrammarDiff {
+    /// Returns whether the two grammars have the same rules and settings.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+            && self.changed_settings.is_empty()
+    }
+}
+
+impl fmt::Display for GrammarDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for name in &self.added {
+            writeln!(f, "+ {name}")?;
+        }
+
+        for name in &self.removed {
+            writeln!(f, "- {name}")?;
+        }
+
+        for (name, changes) in &self.changed {
+            writeln!(f, "~ {name}")?;
+            for change in changes {
+                writeln!(f, "    {change}")?;
+            }
+        }
+
+        for setting in &self.changed_settings {
+            writeln!(f, "~ ({setting})")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Compares two grammars generated by Rust Sitter rule by rule.
+pub fn diff_grammars(old: &Value, new: &Value) -> GrammarDiff {
+    let empty = serde_json::Map::new();
+    let old_rules = old["rules"].as_object().unwrap_or(&empty);
+    let new_rules = new["rules"].as_object().unwrap_or(&empty);
+
+    let mut diff = GrammarDiff {
+        added: new_rules
+            .keys()
+            .filter(|name| !old_rules.contains_key(*name))
+            .cloned()
+            .collect(),
+        removed: old_rules
+            .keys()
+            .filter(|name| !new_rules.contains_key(*name))
+            .cloned()
+            .collect(),
+        ..Default::default()
+    };
+
+    old_rules.iter().for_each(|(name, old_rule)| {
+        if let Some(new_rule) = new_rules.get(name) {
+            let mut changes = vec![];
+            diff_rule(old_rule, new_rule, "", &mut changes);
+            if !changes.is_empty() {
+                diff.changed.push((name.clone(), changes));
+            }
+        }
+    });
+
+    old.as_object()
+        .into_iter()
+        .chain(new.as_object())
+        .flat_map(|o| o.keys())
+        .filter(|key| *key != "rules")
+        .for_each(|key| {
+            if old.get(key) != new.get(key) && !diff.changed_settings.contains(key) {
+                diff.changed_settings.push(key.clone());
+            }
+        });
+
+    diff
+}
+
+/// A short description of a rule, for reporting a change to it.
+fn describe(rule: &Value) -> String {
+    match rule["type"].as_str() {
+        Some("SYMBOL") => format!("`{}`", rule["name"].as_str().unwrap_or_default()),
+        Some("STRING") => format!("\"{}\"", rule["value"].as_str().unwrap_or_default()),
+        Some("PATTERN") => format!("/{}/", rule["value"].as_str().unwrap_or_default()),
+        Some("FIELD") => format!(
+            "field `{}` ({})",
+            rule["name"].as_str().unwrap_or_default(),
+            describe(&rule["content"])
+        ),
+        Some(ty) => ty.to_string(),
+        None => rule.to_string(),
+    }
+}
+
+fn diff_rule(old: &Value, new: &Value, at: &str, changes: &mut Vec<String>) {
+    if old == new {
+        return;
+    }
+
+    let location = at_location(at);
+
+    if old["type"] != new["type"] {
+        changes.push(format!(
+            "{} replaced with {}{location}",
+            describe(old),
+            describe(new)
+        ));
+        return;
+    }
+
+    match old["type"].as_str() {
+        Some("SEQ" | "CHOICE") => {
+            let old_members = old["members"].as_array().unwrap();
+            let new_members = new["members"].as_array().unwrap();
+
+            if old_members.len() == new_members.len() {
+                old_members
+                    .iter()
+                    .zip(new_members)
+                    .enumerate()
+                    .for_each(|(i, (o, n))| diff_rule(o, n, &format!("{at}/{i}"), changes));
+            } else {
+                // members are matched by equality, so that inserting one is reported once
+                // rather than as a change to every member after it
+                new_members
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, m)| !old_members.contains(m))
+                    .for_each(|(i, m)| {
+                        changes.push(format!(
+                            "added {}{}",
+                            describe(m),
+                            at_location(&format!("{at}/{i}"))
+                        ))
+                    });
+                old_members
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, m)| !new_members.contains(m))
+                    .for_each(|(i, m)| {
+                        changes.push(format!(
+                            "removed {}{}",
+                            describe(m),
+                            at_location(&format!("{at}/{i}"))
+                        ))
+                    });
+            }
+        }
+        _ => {
+            let old_content = old.get("content");
+            let new_content = new.get("content");
+            let old_rest = without_content(old);
+            if old_rest != without_content(new) {
+                changes.push(format!(
+                    "{} changed to {}{location}",
+                    describe_settings(&old_rest),
+                    describe_settings(&without_content(new))
+                ));
+            }
+
+            if let (Some(o), Some(n)) = (old_content, new_content) {
+                let ty = old["type"].as_str().unwrap_or_default();
+                diff_rule(o, n, &format!("{at}/{ty}"), changes);
+            }
+        }
+    }
+}
+
+/// Describes where in a rule a change was made, as the path of member indices and wrapper
+/// types from the top of the rule.
+fn at_location(at: &str) -> String {
+    if at.is_empty() {
+        String::new()
+    } else {
+        format!(" at {at}")
+    }
+}
+
+fn without_content(rule: &Value) -> Value {
+    let mut rule = rule.clone();
+    if let Some(o) = rule.as_object_mut() {
+        o.remove("content");
+    }
+    rule
+}
+
+fn describe_settings(rule: &Value) -> String {
+    match rule["type"].as_str() {
+        Some("SYMBOL" | "STRING" | "PATTERN") => describe(rule),
+        _ => rule.to_string(),
+    }
+}
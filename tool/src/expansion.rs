@@ -1,16 +1,453 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
+use quote::ToTokens;
 use rust_sitter_common::*;
 use serde_json::{json, Map, Value};
 use syn::{parse::Parse, punctuated::Punctuated, *};
 
+use crate::conflicts::find_undeclared_conflicts;
+
+/// Adds a generated rule to the grammar, panicking if a different rule was already
+/// generated with the same name (for example, a struct named like an enum variant's rule).
+fn insert_rule(out: &mut BTreeMap<String, Value>, name: String, rule: Value) {
+    // the start rule is reserved with an empty placeholder before any rules are generated
+    if out.get(&name).map(|r| *r != json!({})).unwrap_or(false) {
+        panic!("Multiple rules were generated with the name `{name}`, rename one of the types or fields to avoid the collision");
+    }
+
+    out.insert(name, rule);
+}
+
+/// The rule shared by the fields marked with `#[rust_sitter::newline]`.
+const NEWLINE_RULE: &str = "newline";
+
+/// The rule that matches nothing, which is the alternative that makes a rule optional.
+pub fn blank() -> Value {
+    json!({ "type": "BLANK" })
+}
+
+/// Makes a rule optional, as a choice between [`blank`] and the rule.
+pub fn optional(rule: Value) -> Value {
+    json!({
+        "type": "CHOICE",
+        "members": [blank(), rule]
+    })
+}
+
+/// Rewrites a regular expression so that its ASCII letters match in either case. The
+/// Tree Sitter generator does not support the `(?i)` flag, so every letter is expanded
+/// into a character class instead (`a` becomes `[aA]`, and `[a-f]` becomes `[a-fA-F]`).
+fn case_insensitive_pattern(pattern: &str) -> String {
+    let chars = pattern.chars().collect::<Vec<_>>();
+    let mut out = String::with_capacity(pattern.len());
+    let mut in_class = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\\' => {
+                // escapes are copied as is, including any braced or hex arguments
+                let end = match chars.get(i + 1) {
+                    Some('p' | 'P' | 'x' | 'u') if chars.get(i + 2) == Some(&'{') => chars[i..]
+                        .iter()
+                        .position(|c| *c == '}')
+                        .map(|p| i + p + 1)
+                        .unwrap_or(chars.len()),
+                    Some('x') => i + 4,
+                    Some('u') => i + 6,
+                    _ => i + 2,
+                };
+                let end = end.min(chars.len());
+                out.extend(&chars[i..end]);
+                i = end;
+                continue;
+            }
+            '[' if !in_class => {
+                in_class = true;
+                out.push(c);
+                // a leading `]` is a literal member of the class
+                if chars.get(i + 1) == Some(&']') {
+                    out.push(']');
+                    i += 1;
+                }
+            }
+            ']' if in_class => {
+                in_class = false;
+                out.push(c);
+            }
+            c if c.is_ascii_alphabetic() && in_class => {
+                let range_end = chars
+                    .get(i + 2)
+                    .filter(|e| chars[i + 1] == '-' && e.is_ascii_alphabetic());
+                if let Some(&e) = range_end {
+                    out.extend([c, '-', e]);
+                    if c.is_ascii_lowercase() == e.is_ascii_lowercase() {
+                        out.extend([swap_ascii_case(c), '-', swap_ascii_case(e)]);
+                    }
+                    i += 3;
+                    continue;
+                } else {
+                    out.extend([c, swap_ascii_case(c)]);
+                }
+            }
+            c if c.is_ascii_alphabetic() => {
+                out.extend(['[', c, swap_ascii_case(c), ']']);
+            }
+            c => out.push(c),
+        }
+        i += 1;
+    }
+
+    out
+}
+
+fn swap_ascii_case(c: char) -> char {
+    if c.is_ascii_lowercase() {
+        c.to_ascii_uppercase()
+    } else {
+        c.to_ascii_lowercase()
+    }
+}
+
+/// Splits a pattern that starts with a character class into the contents of the class
+/// and the rest of the pattern.
+fn split_class(pattern: &str) -> Option<(&str, &str)> {
+    let rest = pattern.strip_prefix('[')?;
+    let mut escaped = false;
+    for (i, c) in rest.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            ']' => return Some((&rest[..i], &rest[i + 1..])),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Checks whether a pattern looks like the identifiers of a typical language, which is a
+/// letter (or underscore) followed by any number of letters, digits or underscores, such
+/// as `[A-Za-z_][A-Za-z0-9_]*`.
+fn is_identifier_pattern(pattern: &str) -> bool {
+    let has_letters = |class: &str| class.contains("a-z") || class.contains("A-Z");
+    let has_digits = |class: &str| class.contains("0-9") || class.contains("\\d");
+
+    match split_class(pattern) {
+        Some((first, rest)) if has_letters(first) && !has_digits(first) => {
+            match split_class(rest) {
+                Some((next, "*")) => has_letters(next) && has_digits(next),
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+/// Finds the single leaf rule with a pattern that looks like an identifier, for grammars
+/// marked with `#[rust_sitter::infer_word]`. Nothing is inferred when there are several.
+fn infer_word_rule(rules: &BTreeMap<String, Value>) -> Option<String> {
+    let mut candidates = rules.iter().filter(|(_, rule)| {
+        rule["type"] == "PATTERN" && rule["value"].as_str().is_some_and(is_identifier_pattern)
+    });
+
+    match (candidates.next(), candidates.next()) {
+        (Some((name, _)), None) => Some(name.clone()),
+        _ => None,
+    }
+}
+
+/// Lowers the `STRING`s produced by `text` leaves into patterns that match them in any
+/// case, for grammars marked with `#[rust_sitter::case_insensitive_keywords]`. Strings
+/// without any letters are left as they are.
+fn lower_case_insensitive_text(rule: &mut Value) {
+    match rule["type"].as_str() {
+        Some("STRING") => {
+            let text = rule["value"].as_str().unwrap();
+            if text.chars().any(|c| c.is_ascii_alphabetic()) {
+                let escaped = text
+                    .chars()
+                    .map(|c| {
+                        if "\\.+*?()|[]{}^$-".contains(c) {
+                            format!("\\{c}")
+                        } else {
+                            c.to_string()
+                        }
+                    })
+                    .collect::<String>();
+
+                *rule = json!({
+                    "type": "PATTERN",
+                    "value": case_insensitive_pattern(&escaped),
+                });
+            }
+        }
+        Some("CHOICE" | "SEQ") => rule["members"]
+            .as_array_mut()
+            .unwrap()
+            .iter_mut()
+            .for_each(lower_case_insensitive_text),
+        _ => {
+            if let Some(content) = rule.get_mut("content") {
+                lower_case_insensitive_text(content);
+            }
+        }
+    }
+}
+
+/// Inlines the rules of `text` leaves into the rules that refer to them, for grammars
+/// marked with `#[rust_sitter::hide_anonymous]`, so that punctuation is matched by
+/// anonymous nodes. Fields and the elements of vectors are extracted by their field names,
+/// so they do not need named nodes. The rules of the types and enum variants in `keep`
+/// stay named, since an enum is extracted by the kind of its variant's node.
+fn hide_text_leaves(
+    rules: &mut BTreeMap<String, Value>,
+    keep: &HashSet<String>,
+    extras: &[Value],
+    word_rule: &Option<String>,
+) {
+    let text_rules = rules
+        .iter()
+        .filter(|(name, rule)| rule["type"] == "STRING" && !keep.contains(*name))
+        .map(|(name, rule)| (name.clone(), rule.clone()))
+        .collect::<HashMap<_, _>>();
+
+    fn inline(rule: &mut Value, text_rules: &HashMap<String, Value>) {
+        match rule["type"].as_str() {
+            Some("SYMBOL") => {
+                if let Some(text) = text_rules.get(rule["name"].as_str().unwrap()) {
+                    *rule = text.clone();
+                }
+            }
+            Some("CHOICE" | "SEQ") => rule["members"]
+                .as_array_mut()
+                .unwrap()
+                .iter_mut()
+                .for_each(|m| inline(m, text_rules)),
+            _ => {
+                if let Some(content) = rule.get_mut("content") {
+                    inline(content, text_rules);
+                }
+            }
+        }
+    }
+
+    rules
+        .values_mut()
+        .for_each(|rule| inline(rule, &text_rules));
+
+    // a rule that is still referenced, such as by an extra or as the word, is kept
+    let mut referenced = word_rule.iter().cloned().collect::<Vec<_>>();
+    extras
+        .iter()
+        .for_each(|extra| collect_symbols(extra, &mut referenced));
+    text_rules
+        .keys()
+        .filter(|name| !referenced.contains(name))
+        .for_each(|name| {
+            rules.remove(name);
+        });
+}
+
+/// Inlines the rules referenced from the content of a rule marked with
+/// `#[rust_sitter::no_extras]` or `#[rust_sitter::scoped_extras(none)]`, so that the rule
+/// can be matched as a single token inside which extras are not skipped. Fields are
+/// dropped, since tokens have no children.
+fn inline_token_content(
+    rule: &Value,
+    rules: &BTreeMap<String, Value>,
+    visiting: &mut Vec<String>,
+) -> Value {
+    match rule["type"].as_str() {
+        Some("SYMBOL") => {
+            let name = rule["name"].as_str().unwrap();
+            if visiting.iter().any(|v| v == name) {
+                panic!(
+                    "`{}` has no extras, so it is matched as a single token, but it refers to the recursive rule `{name}`",
+                    visiting[0]
+                );
+            }
+
+            let referenced = rules.get(name).unwrap_or_else(|| {
+                panic!(
+                    "`{}` has no extras, so it is matched as a single token, but it refers to `{name}`, which is not a rule of the grammar",
+                    visiting[0]
+                )
+            });
+
+            visiting.push(name.to_string());
+            let inlined = inline_token_content(referenced, rules, visiting);
+            visiting.pop();
+            inlined
+        }
+        Some("FIELD" | "TOKEN" | "IMMEDIATE_TOKEN") => {
+            inline_token_content(&rule["content"], rules, visiting)
+        }
+        Some("SEQ" | "CHOICE") => {
+            let mut rule = rule.clone();
+            rule["members"] = rule["members"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|m| inline_token_content(m, rules, visiting))
+                .collect();
+            rule
+        }
+        _ => {
+            let mut rule = rule.clone();
+            if let Some(content) = rule.get("content") {
+                rule["content"] = inline_token_content(content, rules, visiting);
+            }
+            rule
+        }
+    }
+}
+
+/// Makes every token matched by `rule` an immediate token, for the fields after the first of
+/// a struct marked with `#[rust_sitter::immediate_seq]`, so that no extras can be skipped
+/// before them. The generated rules of the struct's fields are rewritten in place, since
+/// they are only referenced from the struct.
+fn make_immediate(
+    rule: &mut Value,
+    path: &str,
+    rules: &mut BTreeMap<String, Value>,
+    visiting: &mut Vec<String>,
+) {
+    match rule["type"].as_str() {
+        Some("STRING" | "PATTERN") => {
+            *rule = json!({
+                "type": "IMMEDIATE_TOKEN",
+                "content": rule.clone()
+            });
+        }
+        Some("TOKEN") => {
+            rule["type"] = json!("IMMEDIATE_TOKEN");
+        }
+        Some("IMMEDIATE_TOKEN" | "BLANK") => {}
+        Some("SYMBOL") => {
+            let name = rule["name"].as_str().unwrap().to_string();
+            if !name.starts_with(&format!("{path}_")) {
+                panic!(
+                    "`{path}` is marked `immediate_seq`, but one of its fields refers to `{name}`, which is shared with other rules. Only leaves can be matched immediately"
+                );
+            }
+
+            if visiting.contains(&name) {
+                return;
+            }
+
+            visiting.push(name.clone());
+            let mut referenced = rules[&name].clone();
+            make_immediate(&mut referenced, path, rules, visiting);
+            rules.insert(name, referenced);
+        }
+        Some("SEQ" | "CHOICE") => rule["members"]
+            .as_array_mut()
+            .unwrap()
+            .iter_mut()
+            .for_each(|m| make_immediate(m, path, rules, visiting)),
+        _ => {
+            if let Some(content) = rule.get_mut("content") {
+                make_immediate(content, path, rules, visiting);
+            }
+        }
+    }
+}
+
+/// Wraps the rule of a leaf with `token_prec = N` in a token with that lexical precedence,
+/// which the lexer uses to choose between tokens that match at the same position before
+/// considering the length of the matches.
+fn with_token_prec(rule: Value, token_prec: Option<i64>) -> Value {
+    match token_prec {
+        Some(prec) => {
+            let content = if rule["type"] == "TOKEN" {
+                rule["content"].clone()
+            } else {
+                rule
+            };
+
+            json!({
+                "type": "TOKEN",
+                "content": {
+                    "type": "PREC",
+                    "value": prec,
+                    "content": content
+                }
+            })
+        }
+        None => rule,
+    }
+}
+
 fn gen_field(
     path: String,
     leaf_type: Type,
     leaf_attrs: Vec<Attribute>,
     word_rule: &mut Option<String>,
-    out: &mut Map<String, Value>,
+    out: &mut BTreeMap<String, Value>,
 ) -> (Value, bool) {
+    let leaf_type = strip_references(&leaf_type);
+    let leaf_attrs = expand_repeat_separator(&expand_operators(&leaf_attrs));
+    if is_optional_field(&leaf_attrs, &leaf_type) {
+        let leaf_attrs = leaf_attrs
+            .into_iter()
+            .filter(|attr| attr.path != syn::parse_quote!(rust_sitter::optional))
+            .collect();
+        let (field_json, _) = gen_field(path, leaf_type, leaf_attrs, word_rule, out);
+        return (field_json, true);
+    }
+
+    // every newline leaf is the same token, which is also an extra, so that it is only
+    // significant where the grammar expects one
+    if leaf_attrs
+        .iter()
+        .any(|attr| attr.path == syn::parse_quote!(rust_sitter::newline))
+    {
+        let is_option = if leaf_type == syn::parse_quote!(()) {
+            false
+        } else if leaf_type == syn::parse_quote!(Option<()>) {
+            true
+        } else {
+            panic!("A `#[rust_sitter::newline]` field must be of type `()` or `Option<()>`, but `{path}` is not");
+        };
+
+        let newline_rule = json!({
+            "type": "PATTERN",
+            "value": r"\r?\n"
+        });
+        if out.get(NEWLINE_RULE) != Some(&newline_rule) {
+            insert_rule(out, NEWLINE_RULE.to_string(), newline_rule);
+        }
+
+        return (
+            json!({
+                "type": "SYMBOL",
+                "name": NEWLINE_RULE
+            }),
+            is_option,
+        );
+    }
+
+    // the token is recognized by the external scanner, so it has no rule of its own; the
+    // grammar checks that it was declared (see `check_external_tokens`)
+    if let Some(external) = find_external_token(&leaf_attrs) {
+        let is_option = if leaf_type == syn::parse_quote!(()) {
+            false
+        } else if leaf_type == syn::parse_quote!(Option<()>) {
+            true
+        } else {
+            panic!("A `#[rust_sitter::external_token(...)]` field must be of type `()` or `Option<()>`, but `{path}` is not");
+        };
+
+        return (
+            json!({
+                "type": "SYMBOL",
+                "name": external
+            }),
+            is_option,
+        );
+    }
+
     let leaf_attr = leaf_attrs
         .iter()
         .find(|attr| attr.path == syn::parse_quote!(rust_sitter::leaf));
@@ -43,51 +480,273 @@ fn gen_field(
             .map(|p| p.expr.clone())
     });
 
+    // the bytes are matched as the characters they are decoded to by `parse_bytes`
+    let bytes_param = leaf_params.as_ref().and_then(|p| {
+        p.iter().find(|param| param.path == "bytes").map(|p| {
+            let text = LitStr::new(&bytes_leaf_text(&p.expr), spanned::Spanned::span(&p.expr));
+            Expr::Lit(ExprLit {
+                attrs: vec![],
+                lit: Lit::Str(text),
+            })
+        })
+    });
+    let text_param = match (text_param, bytes_param) {
+        (Some(_), Some(_)) => panic!(
+            "`bytes` cannot be combined with `text` on the leaf for `{path}`, since both give the token to match"
+        ),
+        (text_param, bytes_param) => text_param.or(bytes_param),
+    };
+
+    // `any = true` matches any single character, including a newline, which `.` does not
+    let any = leaf_params
+        .as_ref()
+        .and_then(|p| p.iter().find(|param| param.path == "any"))
+        .map(|p| match &p.expr {
+            Expr::Lit(ExprLit {
+                lit: Lit::Bool(b), ..
+            }) => b.value,
+            _ => panic!("Expected boolean literal for any"),
+        })
+        .unwrap_or(false);
+    let pattern_param = if any {
+        if let Some(param) = leaf_params.iter().flatten().find(|param| {
+            ["text", "bytes", "pattern", "alternatives"].contains(&param.path.to_string().as_str())
+        }) {
+            panic!(
+                "`any` cannot be combined with `{}` on the leaf for `{path}`, since it matches any character",
+                param.path
+            );
+        }
+        Some(syn::parse_quote!(r".|\n"))
+    } else {
+        pattern_param
+    };
+
+    let alternatives_param = leaf_params.as_ref().and_then(|p| {
+        p.iter()
+            .find(|param| param.path == "alternatives")
+            .map(|p| parse_leaf_alternatives(&p.expr))
+    });
+
+    // a named text leaf is aliased to a named node of the given kind, so that it can be
+    // queried by name rather than by its text
+    let leaf_name = find_leaf_name(&leaf_attrs);
+    if leaf_name.is_some() && text_param.is_none() {
+        panic!(
+            "`name` can only be given to a `text` leaf, but the leaf for `{path}` has no `text`"
+        );
+    }
+    let named_symbol = |name: &str| {
+        let symbol = json!({
+            "type": "SYMBOL",
+            "name": name
+        });
+        match &leaf_name {
+            Some(value) => json!({
+                "type": "ALIAS",
+                "content": symbol,
+                "named": true,
+                "value": value
+            }),
+            None => symbol,
+        }
+    };
+
+    // a `bool` text leaf without a transform is a flag, which is `true` if the text is present
+    let is_flag = text_param.is_some()
+        && leaf_type == syn::parse_quote!(bool)
+        && !leaf_params
+            .iter()
+            .flatten()
+            .any(|param| param.path == "transform");
+
+    let token_prec = leaf_params
+        .as_ref()
+        .and_then(|p| p.iter().find(|param| param.path == "token_prec"))
+        .map(|p| prec_integer(&p.expr, "token_prec"));
+
     let mut skip_over = HashSet::new();
     skip_over.insert("Spanned");
     skip_over.insert("Box");
 
-    let (inner_type_vec, is_vec) = try_extract_inner_type(&leaf_type, "Vec", &skip_over);
+    let (inner_type_vec, is_vec) = try_extract_repeated_type(&leaf_type, &skip_over);
     let (inner_type_option, is_option) = try_extract_inner_type(&leaf_type, "Option", &skip_over);
 
+    let case_insensitive = leaf_params
+        .as_ref()
+        .and_then(|p| p.iter().find(|param| param.path == "case_insensitive"))
+        .map(|p| match &p.expr {
+            Expr::Lit(ExprLit {
+                lit: Lit::Bool(b), ..
+            }) => b.value,
+            _ => panic!("Expected boolean literal for case_insensitive"),
+        })
+        .unwrap_or(false);
+
+    let anchored = leaf_params
+        .as_ref()
+        .and_then(|p| p.iter().find(|param| param.path == "anchored"))
+        .map(|p| match &p.expr {
+            Expr::Lit(ExprLit {
+                lit: Lit::Bool(b), ..
+            }) => b.value,
+            _ => panic!("Expected boolean literal for anchored"),
+        })
+        .unwrap_or(false);
+
     if !is_vec && !is_option {
-        if let Some(Expr::Lit(lit)) = pattern_param {
+        if let Some(alternatives) = alternatives_param {
+            // each pattern gets its own rule, so that extraction can tell from the kind of
+            // the matched node which transform to apply
+            let members = alternatives
+                .iter()
+                .enumerate()
+                .map(|(i, alternative)| {
+                    let pattern = if case_insensitive {
+                        case_insensitive_pattern(&alternative.pattern.value())
+                    } else {
+                        alternative.pattern.value()
+                    };
+
+                    let rule_name = LeafAlternative::rule_name(&path, i);
+                    insert_rule(
+                        out,
+                        rule_name.clone(),
+                        with_token_prec(
+                            json!({
+                                "type": "PATTERN",
+                                "value": pattern,
+                            }),
+                            token_prec,
+                        ),
+                    );
+
+                    json!({
+                        "type": "SYMBOL",
+                        "name": rule_name
+                    })
+                })
+                .collect::<Vec<Value>>();
+
+            (
+                json!({
+                    "type": "CHOICE",
+                    "members": members
+                }),
+                is_option,
+            )
+        } else if let Some(Expr::Lit(lit)) = pattern_param {
             if let Lit::Str(s) = &lit.lit {
-                out.insert(
+                let pattern = if case_insensitive {
+                    case_insensitive_pattern(&s.value())
+                } else {
+                    s.value()
+                };
+
+                insert_rule(
+                    out,
                     path.clone(),
-                    json!({
-                        "type": "PATTERN",
-                        "value": s.value(),
-                    }),
+                    with_token_prec(
+                        json!({
+                            "type": "PATTERN",
+                            "value": pattern,
+                        }),
+                        token_prec,
+                    ),
                 );
 
-                (
-                    json!({
-                        "type": "SYMBOL",
-                        "name": path
-                    }),
-                    is_option,
-                )
+                if anchored {
+                    // Tree Sitter takes the longest match, so a token that runs on into more
+                    // word characters wins over the leaf and is then reported as an error
+                    let overrun = anchored_overrun_rule_name(&path);
+                    insert_rule(
+                        out,
+                        overrun.clone(),
+                        json!({
+                            "type": "PATTERN",
+                            "value": format!("(?:{pattern})[a-zA-Z0-9_]+"),
+                        }),
+                    );
+
+                    (
+                        json!({
+                            "type": "CHOICE",
+                            "members": [
+                                {
+                                    "type": "SYMBOL",
+                                    "name": path
+                                },
+                                {
+                                    "type": "SYMBOL",
+                                    "name": overrun
+                                }
+                            ]
+                        }),
+                        is_option,
+                    )
+                } else {
+                    (
+                        json!({
+                            "type": "SYMBOL",
+                            "name": path
+                        }),
+                        is_option,
+                    )
+                }
             } else {
                 panic!("Expected string literal for pattern");
             }
-        } else if let Some(Expr::Lit(lit)) = text_param {
-            if let Lit::Str(s) = &lit.lit {
-                out.insert(
-                    path.clone(),
-                    json!({
+        } else if let Some(Expr::Array(array)) = text_param {
+            // any of the texts can be matched, as a single token
+            let members = array
+                .elems
+                .iter()
+                .map(|e| match e {
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Str(s), ..
+                    }) => json!({
                         "type": "STRING",
                         "value": s.value(),
                     }),
-                );
+                    _ => panic!("Expected string literals in text array"),
+                })
+                .collect::<Vec<Value>>();
+
+            if members.is_empty() {
+                panic!("Expected at least one string literal in text array");
+            }
 
-                (
+            insert_rule(
+                out,
+                path.clone(),
+                with_token_prec(
                     json!({
-                        "type": "SYMBOL",
-                        "name": path
+                        "type": "TOKEN",
+                        "content": {
+                            "type": "CHOICE",
+                            "members": members
+                        }
                     }),
-                    is_option,
-                )
+                    token_prec,
+                ),
+            );
+
+            (named_symbol(&path), is_option || is_flag)
+        } else if let Some(Expr::Lit(lit)) = text_param {
+            if let Lit::Str(s) = &lit.lit {
+                insert_rule(
+                    out,
+                    path.clone(),
+                    with_token_prec(
+                        json!({
+                            "type": "STRING",
+                            "value": s.value(),
+                        }),
+                        token_prec,
+                    ),
+                );
+
+                (named_symbol(&path), is_option || is_flag)
             } else {
                 panic!("Expected string literal for text");
             }
@@ -126,14 +785,38 @@ fn gen_field(
         let delimited_params =
             delimited_attr.and_then(|a| a.parse_args_with(FieldThenParams::parse).ok());
 
+        // the rule of the delimiter is given the precedence of `sep_prec`, which settles
+        // conflicts between the delimiter and the same token in the elements of the list
         let delimiter_json = delimited_params.map(|p| {
-            gen_field(
-                format!("{path}_vec_delimiter"),
+            let sep_prec = p
+                .params
+                .iter()
+                .find(|param| param.path == "sep_prec")
+                .map(|param| prec_value(&param.expr, "sep_prec"));
+            let delimiter_path = format!("{path}_vec_delimiter");
+            let (delimiter_json, delimiter_optional) = gen_field(
+                delimiter_path.clone(),
                 p.field.ty,
                 p.field.attrs,
                 word_rule,
                 out,
-            )
+            );
+            let with_sep_prec = |rule: Value| match &sep_prec {
+                Some(value) => json!({
+                    "type": "PREC",
+                    "value": value,
+                    "content": rule
+                }),
+                None => rule,
+            };
+            // a delimiter that refers to another type has no rule of its own
+            match out.remove(&delimiter_path) {
+                Some(rule) => {
+                    out.insert(delimiter_path, with_sep_prec(rule));
+                    (delimiter_json, delimiter_optional)
+                }
+                None => (with_sep_prec(delimiter_json), delimiter_optional),
+            }
         });
 
         let repeat_attr = leaf_attrs
@@ -161,30 +844,80 @@ fn gen_field(
         });
 
         let field_rule = if field_optional {
-            json!({
-                "type": "CHOICE",
-                "members": [
-                    {
-                        "type": "BLANK"
-                    },
-                    field_rule_non_optional
-                ]
-            })
+            optional(field_rule_non_optional)
         } else {
             field_rule_non_optional
         };
 
-        let vec_contents = if let Some((delimiter_json, delimiter_optional)) = delimiter_json {
+        let repeat_count = find_repeat_count(&leaf_attrs, &leaf_type);
+        let right_recursive = leaf_attrs
+            .iter()
+            .any(|attr| attr.path == syn::parse_quote!(rust_sitter::right_recursive));
+        if right_recursive && repeat_count.is_some() {
+            panic!("The field `{path}` has a fixed number of elements, so it cannot be `right_recursive`");
+        }
+
+        let vec_contents = if let Some(count) = repeat_count {
+            // Tree Sitter has no counted repetition, so the elements are spelled out
+            let delimiter = delimiter_json.map(|(delimiter_json, delimiter_optional)| {
+                if delimiter_optional {
+                    optional(delimiter_json)
+                } else {
+                    delimiter_json
+                }
+            });
+            let members = std::iter::once(field_rule.clone())
+                .chain((1..count).flat_map(|_| {
+                    delimiter
+                        .iter()
+                        .cloned()
+                        .chain(std::iter::once(field_rule.clone()))
+                }))
+                .collect::<Vec<_>>();
+
+            json!({
+                "type": "SEQ",
+                "members": members
+            })
+        } else if right_recursive {
+            let delimiter = delimiter_json.map(|(delimiter_json, delimiter_optional)| {
+                if delimiter_optional {
+                    optional(delimiter_json)
+                } else {
+                    delimiter_json
+                }
+            });
+            if field_optional && delimiter.is_none() {
+                panic!("The elements of the field `{path}` are optional, so it needs a delimiter to be `right_recursive`");
+            }
+
+            // the rest of the list is a hidden rule, so that its elements are children of the
+            // node of the list rather than of nested nodes, and they are extracted in order
+            let tail_ident = format!("_{path}_vec_tail");
+            let tail_symbol = json!({
+                "type": "SYMBOL",
+                "name": tail_ident,
+            });
+            let tail = json!({
+                "type": "PREC_RIGHT",
+                "value": 0,
+                "content": {
+                    "type": "SEQ",
+                    "members": delimiter
+                        .into_iter()
+                        .chain([field_rule.clone(), optional(tail_symbol.clone())])
+                        .collect::<Vec<_>>()
+                }
+            });
+            insert_rule(out, tail_ident, tail);
+
+            json!({
+                "type": "SEQ",
+                "members": [field_rule, optional(tail_symbol)]
+            })
+        } else if let Some((delimiter_json, delimiter_optional)) = delimiter_json {
             let delim_made_optional = if delimiter_optional {
-                json!({
-                    "type": "CHOICE",
-                    "members": [
-                        {
-                            "type": "BLANK"
-                        },
-                        delimiter_json
-                    ]
-                })
+                optional(delimiter_json)
             } else {
                 delimiter_json
             };
@@ -217,21 +950,24 @@ fn gen_field(
         };
 
         let contents_ident = format!("{path}_vec_contents");
-        out.insert(contents_ident.clone(), vec_contents);
+        insert_rule(out, contents_ident.clone(), vec_contents);
 
         (
             json!({
                 "type": "SYMBOL",
                 "name": contents_ident,
             }),
-            !repeat_non_empty,
+            !repeat_non_empty && repeat_count.is_none(),
         )
     } else {
         // is_option
+        let (_, is_option_of_vec) = try_extract_repeated_type(&inner_type_option, &skip_over);
         let (field_json, field_optional) =
             gen_field(path, inner_type_option, leaf_attrs, word_rule, out);
 
-        if field_optional {
+        // the contents of a vector always have an element, so an optional vector is only
+        // `None` when there are none, rather than being optional twice
+        if field_optional && !is_option_of_vec {
             panic!("Option<Option<_>> is not supported");
         }
 
@@ -239,13 +975,131 @@ fn gen_field(
     }
 }
 
-fn gen_struct_or_variant(
-    path: String,
-    attrs: Vec<Attribute>,
+/// Returns the texts that are matched before and after a field, given either by
+/// `#[rust_sitter::bracketed(open = "...", close = "...")]` or by the syntax-only leaves of
+/// `#[rust_sitter::syntax(before = "...", after = "...")]`, either of which can be left out.
+fn surrounding_texts(attrs: &[Attribute]) -> (Option<String>, Option<String>) {
+    let find_params = |name: Path| {
+        attrs.iter().find(|attr| attr.path == name).map(|attr| {
+            attr.parse_args_with(Punctuated::<NameValueExpr, Token![,]>::parse_terminated)
+                .unwrap()
+        })
+    };
+    let text = |params: &Punctuated<NameValueExpr, Token![,]>, name: &str| {
+        params
+            .iter()
+            .find(|param| param.path == name)
+            .map(|param| match &param.expr {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(s), ..
+                }) => s.value(),
+                _ => panic!("Expected string literal for {name}"),
+            })
+    };
+
+    match (
+        find_params(syn::parse_quote!(rust_sitter::bracketed)),
+        find_params(syn::parse_quote!(rust_sitter::syntax)),
+    ) {
+        (Some(_), Some(_)) => {
+            panic!("`#[rust_sitter::bracketed]` cannot be combined with `#[rust_sitter::syntax]` on the same field")
+        }
+        (Some(params), None) => match (text(&params, "open"), text(&params, "close")) {
+            (Some(open), Some(close)) => (Some(open), Some(close)),
+            _ => panic!("`#[rust_sitter::bracketed]` requires both `open` and `close`"),
+        },
+        (None, Some(params)) => match (text(&params, "before"), text(&params, "after")) {
+            (None, None) => {
+                panic!("`#[rust_sitter::syntax]` requires `before`, `after` or both")
+            }
+            texts => texts,
+        },
+        (None, None) => (None, None),
+    }
+}
+
+/// Removes the names of the capture groups from the pattern of a leaf on a struct, since
+/// Tree Sitter does not support named groups.
+fn without_capture_names(leaf_attr: &Attribute) -> Attribute {
+    let params = leaf_attr
+        .parse_args_with(Punctuated::<NameValueExpr, Token![,]>::parse_terminated)
+        .expect("Expected `key = value` parameters for `leaf`")
+        .into_iter()
+        .map(|param| {
+            let (path, mut expr) = (param.path, param.expr);
+            if path == "pattern" {
+                if let Expr::Lit(ExprLit {
+                    lit: Lit::Str(s), ..
+                }) = &expr
+                {
+                    let (pattern, _) = split_capture_groups(&s.value());
+                    expr = syn::parse_quote!(#pattern);
+                }
+            }
+            quote::quote!(#path = #expr)
+        })
+        .collect::<Vec<_>>();
+
+    syn::parse_quote!(#[rust_sitter::leaf(#(#params),*)])
+}
+
+/// Aliases the node of a single field marked with `#[rust_sitter::alias_as(...)]`, without
+/// changing the rule it refers to or the other places that rule is used.
+fn alias_field(contents: Value, attrs: &[Attribute], alias_as: &Attribute, path: &str) -> Value {
+    let params = alias_as
+        .parse_args_with(AliasThenParams::parse)
+        .expect("Expected a string literal for the node kind of `alias_as`");
+    let named = params
+        .params
+        .iter()
+        .find(|param| param.path == "named")
+        .map(|p| p.expr == syn::parse_quote!(true))
+        .unwrap_or(false);
+
+    if contents["type"] == "ALIAS" {
+        panic!("`#[rust_sitter::alias_as]` on `{path}` cannot be combined with a `name` on its leaf, which already gives it a node kind");
+    }
+
+    if contents["type"] != "SYMBOL" {
+        panic!("`#[rust_sitter::alias_as]` on `{path}` can only be used on a field that refers to a single rule, such as a type or a leaf");
+    }
+
+    // the runtime checks of these leaves find them by their node kind
+    let leaf_params = attrs
+        .iter()
+        .find(|attr| attr.path == syn::parse_quote!(rust_sitter::leaf))
+        .and_then(|leaf| {
+            leaf.parse_args_with(Punctuated::<NameValueExpr, Token![,]>::parse_terminated)
+                .ok()
+        })
+        .unwrap_or_default();
+    if leaf_params.iter().any(|param| {
+        (param.path == "unescape" || param.path == "anchored")
+            && param.expr == syn::parse_quote!(true)
+    }) {
+        panic!("`#[rust_sitter::alias_as]` cannot be combined with `unescape` or `anchored` on the leaf for `{path}`");
+    }
+
+    json!({
+        "type": "ALIAS",
+        "content": contents,
+        "named": named,
+        "value": params.value.value()
+    })
+}
+
+fn gen_struct_or_variant(
+    path: String,
+    attrs: Vec<Attribute>,
     fields: Fields,
-    out: &mut Map<String, Value>,
+    out: &mut BTreeMap<String, Value>,
     word_rule: &mut Option<String>,
 ) {
+    let unordered = attrs
+        .iter()
+        .any(|attr| attr.path == syn::parse_quote!(rust_sitter::unordered));
+    let mut any_required = false;
+
     let children = fields
         .iter()
         .enumerate()
@@ -263,37 +1117,144 @@ fn gen_struct_or_variant(
                     .map(|v| v.to_string())
                     .unwrap_or(format!("{i}"));
 
+                let field_path = format!("{}_{}", path.clone(), ident_str);
+                if unordered
+                    && try_extract_repeated_type(&field.ty, &HashSet::from(["Spanned", "Box"])).1
+                {
+                    panic!(
+                        "The field `{field_path}` of an unordered type can't be repeated, since each of its fields can only appear once"
+                    );
+                }
+
                 let (field_contents, is_option) = gen_field(
-                    format!("{}_{}", path.clone(), ident_str),
+                    field_path.clone(),
                     field.ty.clone(),
                     field.attrs.clone(),
                     word_rule,
                     out,
                 );
 
+                let field_contents = match field
+                    .attrs
+                    .iter()
+                    .find(|attr| attr.path == syn::parse_quote!(rust_sitter::alias_as))
+                {
+                    Some(alias_as) => {
+                        alias_field(field_contents, &field.attrs, alias_as, &field_path)
+                    }
+                    None => field_contents,
+                };
+
                 let core = json!({
                     "type": "FIELD",
-                    "name": ident_str,
+                    "name": ts_field_name(field, i),
                     "content": field_contents
                 });
 
-                if is_option {
-                    Some(json!({
-                        "type": "CHOICE",
-                        "members": [
-                            {
-                                "type": "BLANK"
-                            },
-                            core
-                        ]
-                    }))
+                // the fields of an unordered type are optional anyway, since its rule accepts
+                // any number of them, and they are checked for after parsing
+                any_required |= !is_option;
+                let field_rule = if is_option && !unordered {
+                    optional(core)
                 } else {
-                    Some(core)
-                }
+                    core
+                };
+
+                // the surrounding texts are anonymous tokens without fields, so the field is
+                // extracted as usual and they have no place in the Rust type
+                Some(match surrounding_texts(&field.attrs) {
+                    (None, None) => field_rule,
+                    (before, after) => {
+                        let text = |value: String| {
+                            json!({
+                                "type": "STRING",
+                                "value": value
+                            })
+                        };
+                        json!({
+                            "type": "SEQ",
+                            "members": before
+                                .map(text)
+                                .into_iter()
+                                .chain([field_rule])
+                                .chain(after.map(text))
+                                .collect::<Vec<_>>()
+                        })
+                    }
+                })
             }
         })
         .collect::<Vec<Value>>();
 
+    let seq_rule = if unordered {
+        json!({
+            "type": if any_required { "REPEAT1" } else { "REPEAT" },
+            "content": {
+                "type": "CHOICE",
+                "members": children
+            }
+        })
+    } else {
+        json!({
+            "type": "SEQ",
+            "members": children
+        })
+    };
+
+    insert_rule(out, path, with_prec(seq_rule, &attrs));
+}
+
+fn is_prec_attr(attr: &Attribute) -> bool {
+    attr.path == syn::parse_quote!(rust_sitter::prec)
+        || attr.path == syn::parse_quote!(rust_sitter::prec_left)
+        || attr.path == syn::parse_quote!(rust_sitter::prec_right)
+}
+
+/// Parses an integer precedence, which can be negative, as an `i64`, checking that it fits
+/// in the 32-bit precedences of Tree Sitter so that it is emitted as a plain JSON number.
+fn prec_integer(expr: &Expr, param: &str) -> i64 {
+    let (lit, negative) = match expr {
+        Expr::Unary(ExprUnary {
+            op: UnOp::Neg(_),
+            expr,
+            ..
+        }) => (expr.as_ref(), true),
+        expr => (expr, false),
+    };
+
+    match lit {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(i), ..
+        }) => i.base10_parse::<i64>().ok(),
+        _ => panic!(
+            "Expected an integer literal for `{param}`, but found `{}`",
+            expr.to_token_stream()
+        ),
+    }
+    .map(|value| if negative { -value } else { value })
+    .filter(|value| i32::try_from(*value).is_ok())
+    .unwrap_or_else(|| {
+        panic!(
+            "The precedence `{}` given for `{param}` is out of range, since Tree Sitter precedences must fit in an `i32`",
+            expr.to_token_stream()
+        )
+    })
+}
+
+/// The value of a precedence, which is either an integer level or the name of a level
+/// declared with `#[rust_sitter::precedences(...)]`.
+fn prec_value(expr: &Expr, param: &str) -> Value {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(s), ..
+        }) => json!(s.value()),
+        expr => json!(prec_integer(expr, param)),
+    }
+}
+
+/// Wraps a rule in the precedence given by a `prec`, `prec_left` or `prec_right` attribute,
+/// if there is one.
+fn with_prec(rule: Value, attrs: &[Attribute]) -> Value {
     let prec_attr = attrs
         .iter()
         .find(|attr| attr.path == syn::parse_quote!(rust_sitter::prec));
@@ -312,174 +1273,2648 @@ fn gen_struct_or_variant(
 
     let prec_right_param = prec_right_attr.and_then(|a| a.parse_args_with(Expr::parse).ok());
 
-    let seq_rule = json!({
-        "type": "SEQ",
-        "members": children
-    });
-
-    let rule = if let Some(Expr::Lit(lit)) = prec_param {
+    if let Some(expr) = prec_param {
         if prec_left_attr.is_some() || prec_right_attr.is_some() {
             panic!("only one of prec, prec_left, and prec_right can be specified");
         }
 
-        if let Lit::Int(i) = &lit.lit {
-            json!({
-                "type": "PREC",
-                "value": i.base10_parse::<u32>().unwrap(),
-                "content": seq_rule
-            })
-        } else {
-            panic!("Expected integer literal for precedence");
-        }
-    } else if let Some(Expr::Lit(lit)) = prec_left_param {
+        json!({
+            "type": "PREC",
+            "value": prec_value(&expr, "prec"),
+            "content": rule
+        })
+    } else if let Some(expr) = prec_left_param {
         if prec_right_attr.is_some() {
             panic!("only one of prec, prec_left, and prec_right can be specified");
         }
 
-        if let Lit::Int(i) = &lit.lit {
-            json!({
-                "type": "PREC_LEFT",
-                "value": i.base10_parse::<u32>().unwrap(),
-                "content": seq_rule
-            })
-        } else {
-            panic!("Expected integer literal for precedence");
-        }
-    } else if let Some(Expr::Lit(lit)) = prec_right_param {
-        if let Lit::Int(i) = &lit.lit {
-            json!({
-                "type": "PREC_RIGHT",
-                "value": i.base10_parse::<u32>().unwrap(),
-                "content": seq_rule
-            })
-        } else {
-            panic!("Expected integer literal for precedence");
-        }
+        json!({
+            "type": "PREC_LEFT",
+            "value": prec_value(&expr, "prec_left"),
+            "content": rule
+        })
+    } else if let Some(expr) = prec_right_param {
+        json!({
+            "type": "PREC_RIGHT",
+            "value": prec_value(&expr, "prec_right"),
+            "content": rule
+        })
     } else {
-        seq_rule
-    };
+        rule
+    }
+}
 
-    out.insert(path, rule);
+/// Computes the structure of a rule with the generated leaf rules of `path` inlined and
+/// field names dropped, so that two variants matching the same input have the same shape.
+fn rule_shape(rule: &Value, path: &str, rules: &BTreeMap<String, Value>) -> Value {
+    match rule {
+        Value::Object(o) => match o.get("type").and_then(Value::as_str) {
+            Some("FIELD") => rule_shape(&o["content"], path, rules),
+            Some("SYMBOL") => {
+                let name = o["name"].as_str().unwrap();
+                match rules.get(name) {
+                    Some(inner) if name.starts_with(&format!("{path}_")) => {
+                        rule_shape(inner, path, rules)
+                    }
+                    _ => rule.clone(),
+                }
+            }
+            _ => Value::Object(
+                o.iter()
+                    .map(|(k, v)| (k.clone(), rule_shape(v, path, rules)))
+                    .collect(),
+            ),
+        },
+        Value::Array(a) => Value::Array(a.iter().map(|v| rule_shape(v, path, rules)).collect()),
+        _ => rule.clone(),
+    }
 }
 
-pub fn generate_grammar(module: &ItemMod) -> Value {
-    let mut rules_map = Map::new();
-    // for some reason, source_file must be the first key for things to work
-    rules_map.insert("source_file".to_string(), json!({}));
+/// Finds variants of an enum that can never be selected, because another variant
+/// matches exactly the same input with a higher precedence. Returns a warning
+/// message for each dominated variant.
+pub fn dominated_variant_warnings(
+    variant_paths: &[String],
+    rules: &BTreeMap<String, Value>,
+) -> Vec<String> {
+    // named precedences are ordered by their declarations, so they are not compared here
+    let variants = variant_paths
+        .iter()
+        .filter_map(|path| {
+            let rule = &rules[path];
+            let (prec, content) = match rule["type"].as_str() {
+                Some("PREC" | "PREC_LEFT" | "PREC_RIGHT") => {
+                    (rule["value"].as_i64()?, &rule["content"])
+                }
+                _ => (0, rule),
+            };
 
-    let mut extras_list = vec![];
+            Some((path, prec, rule_shape(content, path, rules)))
+        })
+        .collect::<Vec<_>>();
 
-    let grammar_name = module
-        .attrs
+    variants
         .iter()
-        .find_map(|a| {
-            if a.path == syn::parse_quote!(rust_sitter::grammar) {
-                let grammar_name_expr = a.parse_args_with(Expr::parse).ok();
-                if let Some(Expr::Lit(ExprLit {
-                    attrs: _,
-                    lit: Lit::Str(s),
-                })) = grammar_name_expr
-                {
-                    Some(s.value())
-                } else {
-                    panic!("Expected string literal for grammar name");
+        .filter_map(|(path, prec, shape)| {
+            variants
+                .iter()
+                .filter(|(_, other_prec, other_shape)| other_prec > prec && other_shape == shape)
+                .max_by_key(|(_, other_prec, _)| *other_prec)
+                .map(|(other_path, other_prec, _)| {
+                    format!(
+                        "`{path}` can never be selected, because `{other_path}` matches the same input with a higher precedence ({other_prec} > {prec})"
+                    )
+                })
+        })
+        .collect()
+}
+
+/// Finds the leaf patterns in a grammar module written as non-raw string literals whose
+/// `\x` or `\u{...}` escapes produce a regex metacharacter. Both kinds of literals are
+/// read through their value, so `"\\d+"` and `r"\d+"` give the same pattern, but an escape
+/// such as `"\x2e"` becomes a `.` that matches any character, while `r"\x2e"` matches a
+/// literal dot. Returns a warning message for each such pattern.
+pub fn cooked_pattern_warnings(module: &ItemMod) -> Vec<String> {
+    fn check_attrs(attrs: &[Attribute], out: &mut Vec<String>) {
+        attrs.iter().for_each(|attr| {
+            if attr.path == syn::parse_quote!(rust_sitter::leaf) {
+                let params = attr
+                    .parse_args_with(Punctuated::<NameValueExpr, Token![,]>::parse_terminated)
+                    .into_iter()
+                    .flatten();
+                params.for_each(|param| match &param.expr {
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Str(pattern),
+                        ..
+                    }) if param.path == "pattern" => out.extend(cooked_pattern_warning(pattern)),
+                    expr if param.path == "alternatives" => parse_leaf_alternatives(expr)
+                        .iter()
+                        .for_each(|alternative| {
+                            out.extend(cooked_pattern_warning(&alternative.pattern))
+                        }),
+                    _ => {}
+                });
+            } else if attr.path == syn::parse_quote!(rust_sitter::delimited) {
+                if let Ok(delimiter) = attr.parse_args_with(FieldThenParams::parse) {
+                    check_attrs(&delimiter.field.attrs, out);
                 }
-            } else {
-                None
             }
-        })
-        .expect("Each grammar must have a name");
+        });
+    }
 
-    let (_, contents) = module.content.as_ref().unwrap();
+    let mut out = vec![];
+    if let Some((_, items)) = &module.content {
+        items.iter().for_each(|item| match item {
+            Item::Struct(s) => {
+                check_attrs(&s.attrs, &mut out);
+                s.fields
+                    .iter()
+                    .for_each(|f| check_attrs(&f.attrs, &mut out));
+            }
+            Item::Enum(e) => e.variants.iter().for_each(|v| {
+                check_attrs(&v.attrs, &mut out);
+                v.fields
+                    .iter()
+                    .for_each(|f| check_attrs(&f.attrs, &mut out));
+            }),
+            _ => {}
+        });
+    }
+
+    out
+}
 
-    let root_type = contents
+/// Finds the root types marked with `#[rust_sitter::default_empty]` that have no empty AST,
+/// which do not implement `Default`, such as an enum or a struct with a leaf that is always
+/// matched. Returns a warning message for each of them, naming the field that cannot be empty.
+pub fn default_empty_warnings(module: &ItemMod) -> Vec<String> {
+    let Some((_, items)) = &module.content else {
+        return vec![];
+    };
+
+    items
         .iter()
-        .find_map(|item| match item {
-            Item::Enum(ItemEnum { ident, attrs, .. })
-            | Item::Struct(ItemStruct { ident, attrs, .. }) => {
-                if attrs
-                    .iter()
-                    .any(|attr| attr.path == syn::parse_quote!(rust_sitter::language))
-                {
-                    Some(ident.clone())
-                } else {
-                    None
-                }
+        .filter_map(|item| {
+            let (ident, attrs) = match item {
+                Item::Enum(e) => (&e.ident, &e.attrs),
+                Item::Struct(s) => (&s.ident, &s.attrs),
+                _ => return None,
+            };
+            attrs
+                .iter()
+                .any(|a| a.path == syn::parse_quote!(rust_sitter::default_empty))
+                .then_some((ident, item))
+        })
+        .filter_map(|(ident, item)| match item {
+            Item::Struct(s) => s
+                .fields
+                .iter()
+                .enumerate()
+                .find(|(_, field)| empty_field_value(field).is_none())
+                .map(|(i, field)| {
+                    let field_name = field
+                        .ident
+                        .as_ref()
+                        .map(|v| v.to_string())
+                        .unwrap_or(format!("{i}"));
+                    format!("`{ident}` is marked with `default_empty`, but its field `{field_name}` cannot be empty, so it does not implement `Default`")
+                }),
+            _ => Some(format!("`{ident}` is marked with `default_empty`, but an enum has no empty value, so it does not implement `Default`")),
+        })
+        .collect()
+}
+
+fn cooked_pattern_warning(pattern: &LitStr) -> Option<String> {
+    let source = pattern.token().to_string();
+    if source.starts_with('r') {
+        return None;
+    }
+
+    let mut escapes = vec![];
+    let mut chars = source.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if c != '\\' {
+            continue;
+        }
+
+        let code = match chars.next() {
+            Some((_, 'x')) => {
+                let digits = (0..2).filter_map(|_| chars.next()).map(|(_, c)| c);
+                u32::from_str_radix(&digits.collect::<String>(), 16).ok()
+            }
+            Some((_, 'u')) => {
+                let digits = chars
+                    .by_ref()
+                    .map(|(_, c)| c)
+                    .take_while(|c| *c != '}')
+                    .filter(|c| *c != '{' && *c != '_');
+                u32::from_str_radix(&digits.collect::<String>(), 16).ok()
             }
             _ => None,
-        })
-        .expect("Each parser must have the root type annotated with `#[rust_sitter::language]`")
-        .to_string();
+        };
 
-    // Optionally locate the rule annotated with `#[rust_sitter::word]`.
-    let mut word_rule = None;
-    contents.iter().for_each(|c| {
-        let (symbol, attrs) = match c {
-            Item::Enum(e) => {
-                e.variants.iter().for_each(|v| {
-                    gen_struct_or_variant(
-                        format!("{}_{}", e.ident, v.ident),
-                        v.attrs.clone(),
-                        v.fields.clone(),
-                        &mut rules_map,
-                        &mut word_rule,
-                    )
-                });
+        if let Some(c) = code.and_then(char::from_u32) {
+            if "\\.^$|?*+()[]{}".contains(c) {
+                let end = chars.peek().map(|(i, _)| *i).unwrap_or(source.len());
+                escapes.push(format!("`{}`", &source[start..end]));
+            }
+        }
+    }
 
-                let mut members: Vec<Value> = vec![];
-                e.variants.iter().for_each(|v| {
-                    let variant_path = format!("{}_{}", e.ident.clone(), v.ident);
-                    members.push(json!({
-                        "type": "SYMBOL",
-                        "name": variant_path
-                    }))
-                });
+    if escapes.is_empty() {
+        None
+    } else {
+        Some(format!(
+            "the pattern {source} is not a raw string literal, so {} become regex metacharacters rather than matching literally; write it as a raw string literal, such as r\"...\", to keep the escapes",
+            escapes.join(", ")
+        ))
+    }
+}
 
-                let rule = json!({
-                    "type": "CHOICE",
-                    "members": members
-                });
+fn is_nullable(rule: &Value, nullable_rules: &HashSet<String>) -> bool {
+    match rule["type"].as_str() {
+        Some("BLANK" | "REPEAT") => true,
+        Some("SYMBOL") => nullable_rules.contains(rule["name"].as_str().unwrap()),
+        Some("SEQ") => rule["members"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|m| is_nullable(m, nullable_rules)),
+        Some("CHOICE") => rule["members"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|m| is_nullable(m, nullable_rules)),
+        Some("PATTERN") => pattern_matches_empty(rule["value"].as_str().unwrap()),
+        // strings are tokens, which always consume some input
+        _ => rule
+            .get("content")
+            .map(|c| is_nullable(c, nullable_rules))
+            .unwrap_or(false),
+    }
+}
 
-                rules_map.insert(e.ident.to_string(), rule);
+/// Checks whether a leaf pattern, such as `\d*` or `a?`, matches the empty string. Patterns
+/// that are not valid for the regex crate are assumed to consume some input, and are left
+/// for Tree Sitter to report.
+fn pattern_matches_empty(pattern: &str) -> bool {
+    regex::Regex::new(&format!("^(?:{pattern})$"))
+        .map(|r| r.is_match(""))
+        .unwrap_or(false)
+}
 
-                (e.ident.to_string(), e.attrs.clone())
-            }
+/// Follows a rule through fields, aliases, symbols and token wrappers to a pattern that
+/// matches the empty string, for reporting which leaf makes a repetition nullable.
+fn find_empty_pattern<'a>(rule: &'a Value, rules: &'a BTreeMap<String, Value>) -> Option<&'a str> {
+    match rule["type"].as_str() {
+        Some("PATTERN") => rule["value"]
+            .as_str()
+            .filter(|pattern| pattern_matches_empty(pattern)),
+        Some("SYMBOL") => rules
+            .get(rule["name"].as_str().unwrap())
+            .and_then(|r| find_empty_pattern(r, rules)),
+        Some("FIELD" | "ALIAS" | "TOKEN" | "IMMEDIATE_TOKEN" | "PREC") => {
+            find_empty_pattern(&rule["content"], rules)
+        }
+        _ => None,
+    }
+}
 
-            Item::Struct(s) => {
-                gen_struct_or_variant(
-                    s.ident.to_string(),
-                    s.attrs.clone(),
-                    s.fields.clone(),
-                    &mut rules_map,
-                    &mut word_rule,
-                );
+fn find_nullable_repeat<'a>(
+    rule: &'a Value,
+    nullable_rules: &HashSet<String>,
+) -> Option<&'a Value> {
+    match rule["type"].as_str() {
+        Some("REPEAT" | "REPEAT1") if is_nullable(&rule["content"], nullable_rules) => {
+            Some(&rule["content"])
+        }
+        Some("SEQ" | "CHOICE") => rule["members"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find_map(|m| find_nullable_repeat(m, nullable_rules)),
+        _ => rule
+            .get("content")
+            .and_then(|c| find_nullable_repeat(c, nullable_rules)),
+    }
+}
 
-                (s.ident.to_string(), s.attrs.clone())
-            }
+/// Finds the rules that can match the empty string.
+fn find_nullable_rules(rules: &BTreeMap<String, Value>) -> HashSet<String> {
+    // the nullable rules are found by iterating until no more rules become nullable
+    let mut nullable_rules = HashSet::new();
+    loop {
+        let newly_nullable = rules
+            .iter()
+            .filter(|(name, rule)| {
+                !nullable_rules.contains(*name) && is_nullable(rule, &nullable_rules)
+            })
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
 
-            _ => return,
-        };
+        if newly_nullable.is_empty() {
+            break;
+        }
 
-        if attrs
+        nullable_rules.extend(newly_nullable);
+    }
+
+    nullable_rules
+}
+
+/// Checks whether a rule can match some complete input, given the rules known to do so.
+/// Symbols that are not rules of the grammar, such as external tokens, are assumed to.
+fn is_productive(
+    rule: &Value,
+    productive_rules: &HashSet<String>,
+    rules: &BTreeMap<String, Value>,
+) -> bool {
+    match rule["type"].as_str() {
+        Some("SYMBOL") => {
+            let name = rule["name"].as_str().unwrap();
+            !rules.contains_key(name) || productive_rules.contains(name)
+        }
+        Some("SEQ") => rule["members"]
+            .as_array()
+            .unwrap()
             .iter()
-            .any(|a| a.path == syn::parse_quote!(rust_sitter::extra))
-        {
-            extras_list.push(json!({
-                "type": "SYMBOL",
-                "name": symbol
-            }));
+            .all(|m| is_productive(m, productive_rules, rules)),
+        Some("CHOICE") => rule["members"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|m| is_productive(m, productive_rules, rules)),
+        // a repetition can match nothing at all
+        Some("REPEAT") => true,
+        _ => rule
+            .get("content")
+            .map(|c| is_productive(c, productive_rules, rules))
+            .unwrap_or(true),
+    }
+}
+
+/// Finds the rules that can match some complete input, which are the rules that do not
+/// always contain themselves.
+fn find_productive_rules(rules: &BTreeMap<String, Value>) -> HashSet<String> {
+    // the productive rules are found by iterating until no more rules become productive
+    let mut productive_rules = HashSet::new();
+    loop {
+        let newly_productive = rules
+            .iter()
+            .filter(|(name, rule)| {
+                !productive_rules.contains(*name) && is_productive(rule, &productive_rules, rules)
+            })
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+
+        if newly_productive.is_empty() {
+            break;
         }
-    });
 
-    rules_map.insert(
-        "source_file".to_string(),
-        rules_map.get(&root_type).unwrap().clone(),
-    );
+        productive_rules.extend(newly_productive);
+    }
 
-    json!({
-        "name": grammar_name,
-        "word": word_rule,
-        "rules": rules_map,
-        "extras": extras_list
-    })
+    productive_rules
+}
+
+/// Names an alternative of a choice by the rule it refers to, looking through fields and
+/// other wrappers.
+fn describe_alternative(rule: &Value) -> String {
+    match rule["type"].as_str() {
+        Some("SYMBOL") => rule["name"].as_str().unwrap().to_string(),
+        _ => match rule.get("content") {
+            Some(content) => describe_alternative(content),
+            None => rule.to_string(),
+        },
+    }
+}
+
+/// Finds the alternatives of choices that can never match a complete input while another
+/// alternative can, such as a variant holding a type that always contains itself. Tree
+/// Sitter only builds the nodes of such an alternative while recovering from an error, so
+/// they usually point to a mistake in the grammar. Returns a warning message for each
+/// alternative, which is reported once even if it appears in several choices.
+pub fn error_only_alternative_warnings(rules: &BTreeMap<String, Value>) -> Vec<String> {
+    fn collect(
+        name: &str,
+        rule: &Value,
+        productive_rules: &HashSet<String>,
+        rules: &BTreeMap<String, Value>,
+        out: &mut Vec<(String, String)>,
+    ) {
+        match rule["type"].as_str() {
+            Some("CHOICE") => {
+                let members = rule["members"].as_array().unwrap();
+                if members
+                    .iter()
+                    .any(|m| is_productive(m, productive_rules, rules))
+                {
+                    members
+                        .iter()
+                        .filter(|m| !is_productive(m, productive_rules, rules))
+                        .for_each(|m| {
+                            let alternative = describe_alternative(m);
+                            if !out.iter().any(|(a, _)| *a == alternative) {
+                                out.push((alternative, name.to_string()));
+                            }
+                        });
+                }
+
+                members
+                    .iter()
+                    .for_each(|m| collect(name, m, productive_rules, rules, out));
+            }
+            Some("SEQ") => rule["members"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .for_each(|m| collect(name, m, productive_rules, rules, out)),
+            _ => {
+                if let Some(content) = rule.get("content") {
+                    collect(name, content, productive_rules, rules, out)
+                }
+            }
+        }
+    }
+
+    let productive_rules = find_productive_rules(rules);
+    let mut alternatives = vec![];
+    rules
+        .iter()
+        .for_each(|(name, rule)| collect(name, rule, &productive_rules, rules, &mut alternatives));
+
+    alternatives
+        .iter()
+        .map(|(alternative, name)| {
+            format!(
+                "`{alternative}` in `{name}` can only be matched while recovering from an error, because matching it always requires matching it again, so it never matches a complete input"
+            )
+        })
+        .collect()
+}
+
+/// Finds the rules that are referenced as named nodes in some places and as anonymous nodes
+/// in others, such as a type that is aliased with `named = false` in one field and used as
+/// is in another, which makes its nodes appear in the tree inconsistently. Aliasing a rule
+/// to different node kinds in different places is allowed, since that is what
+/// `#[rust_sitter::alias_as(...)]` is for. Returns a warning message for each such rule.
+pub fn inconsistent_alias_warnings(rules: &BTreeMap<String, Value>) -> Vec<String> {
+    // the first node kind each rule is referenced with as a named and an anonymous node,
+    // along with the rule in which that reference appears
+    type References = (Option<(String, String)>, Option<(String, String)>);
+
+    fn collect(name: &str, rule: &Value, out: &mut BTreeMap<String, References>) {
+        let (target, kind, named) = match rule["type"].as_str() {
+            Some("SYMBOL") => {
+                let target = rule["name"].as_str().unwrap();
+                (target, target, true)
+            }
+            Some("ALIAS") if rule["content"]["type"] == "SYMBOL" => (
+                rule["content"]["name"].as_str().unwrap(),
+                rule["value"].as_str().unwrap(),
+                rule["named"] == true,
+            ),
+            Some("CHOICE" | "SEQ") => {
+                rule["members"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .for_each(|m| collect(name, m, out));
+                return;
+            }
+            _ => {
+                if let Some(content) = rule.get("content") {
+                    collect(name, content, out);
+                }
+                return;
+            }
+        };
+
+        let references = out.entry(target.to_string()).or_default();
+        let reference = if named {
+            &mut references.0
+        } else {
+            &mut references.1
+        };
+        reference.get_or_insert_with(|| (kind.to_string(), name.to_string()));
+    }
+
+    let mut references = BTreeMap::new();
+    rules
+        .iter()
+        .for_each(|(name, rule)| collect(name, rule, &mut references));
+
+    references
+        .iter()
+        .filter_map(|(target, references)| match references {
+            (Some((named_kind, named_rule)), Some((anonymous_kind, anonymous_rule))) => Some(format!(
+                "The rule `{target}` is referenced as the named node `{named_kind}` in `{named_rule}` but as the anonymous node `{anonymous_kind}` in `{anonymous_rule}`, so its nodes are only named in some places. Give every alias of it the same `named` setting"
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Rejects extras that can match the empty string, such as a struct whose fields are all
+/// optional, since Tree Sitter could skip them forever. Extras that are rules, such as a
+/// comment made of a prefix and its contents, are otherwise allowed.
+fn check_nullable_extras(extras: &[Value], rules: &BTreeMap<String, Value>) {
+    let nullable_rules = find_nullable_rules(rules);
+    extras.iter().for_each(|extra| {
+        if is_nullable(extra, &nullable_rules) {
+            panic!(
+                "The extra {} can match the empty string. Tree Sitter does not allow this, so make sure every extra consumes some input",
+                describe_extra(extra)
+            );
+        }
+    });
+}
+
+fn describe_extra(extra: &Value) -> String {
+    match extra["type"].as_str() {
+        Some("SYMBOL") => format!("`{}`", extra["name"].as_str().unwrap()),
+        _ => extra.to_string(),
+    }
+}
+
+/// Rejects rules that repeat something that can match the empty string, such as a `Vec`
+/// whose elements only have optional fields, since Tree Sitter cannot generate a parser
+/// for them. Rules that match the empty string on their own are allowed.
+fn check_nullable_repeats(rules: &BTreeMap<String, Value>) {
+    let nullable_rules = find_nullable_rules(rules);
+
+    rules.iter().for_each(|(name, rule)| {
+        if let Some(content) = find_nullable_repeat(rule, &nullable_rules) {
+            if let Some(pattern) = find_empty_pattern(content, rules) {
+                panic!("The rule `{name}` repeats a leaf with the pattern `{pattern}`, which can match the empty string, so Tree Sitter would loop forever. Make the pattern consume some input, such as with `+` instead of `*`, and drop any `?` that makes all of it optional");
+            }
+
+            let repeated = match content["type"].as_str() {
+                Some("SYMBOL") => format!("`{}`", content["name"].as_str().unwrap()),
+                Some("FIELD") if content["content"]["type"] == "SYMBOL" => {
+                    format!("`{}`", content["content"]["name"].as_str().unwrap())
+                }
+                _ => "an element".to_string(),
+            };
+
+            panic!("The rule `{name}` repeats {repeated}, which can match the empty string. Tree Sitter does not allow this, so make sure every element of a `Vec` consumes some input");
+        }
+    });
+}
+
+fn resolve_type(ty: &Type, aliases: &HashMap<String, Type>) -> Type {
+    let mut ty = ty.clone();
+    if let Type::Path(p) = &mut ty {
+        if p.qself.is_none() && p.path.segments.len() == 1 {
+            let segment = &mut p.path.segments[0];
+            if segment.arguments.is_empty() {
+                if let Some(aliased) = aliases.get(&segment.ident.to_string()) {
+                    return resolve_type(aliased, aliases);
+                }
+            }
+        }
+
+        if let PathArguments::AngleBracketed(args) =
+            &mut p.path.segments.last_mut().unwrap().arguments
+        {
+            args.args.iter_mut().for_each(|a| {
+                if let GenericArgument::Type(t) = a {
+                    *t = resolve_type(t, aliases);
+                }
+            });
+        }
+    }
+
+    ty
+}
+
+/// Replaces the field types that refer to type aliases declared in the grammar module
+/// with the aliased types, so that they resolve to the rules of the underlying types. The
+/// newtypes that leaves are wrapped in are left out, since they are not rules.
+fn resolve_type_aliases(contents: &[Item]) -> Vec<Item> {
+    let aliases: HashMap<String, Type> = contents
+        .iter()
+        .filter_map(|item| match item {
+            Item::Type(t) if t.generics.params.is_empty() => {
+                Some((t.ident.to_string(), (*t.ty).clone()))
+            }
+            _ => None,
+        })
+        .collect();
+
+    let resolve_fields = |fields: &mut Fields| {
+        fields
+            .iter_mut()
+            .for_each(|f| f.ty = resolve_type(&f.ty, &aliases))
+    };
+
+    contents
+        .iter()
+        .filter(|item| !matches!(item, Item::Struct(s) if find_newtype_inner(s).is_some()))
+        .cloned()
+        .map(|mut item| {
+            match &mut item {
+                Item::Enum(e) => e
+                    .variants
+                    .iter_mut()
+                    .for_each(|v| resolve_fields(&mut v.fields)),
+                Item::Struct(s) => resolve_fields(&mut s.fields),
+                _ => {}
+            }
+
+            item
+        })
+        .collect()
+}
+
+pub fn generate_grammar(module: &ItemMod) -> Value {
+    generate_grammar_with_imports(module, &[])
+}
+
+/// Generates the grammar for a module, merging in the rules of the grammar modules it
+/// imports with `#[rust_sitter::use_rules(...)]`, in the order of the attributes.
+pub fn generate_grammar_with_imports(module: &ItemMod, imports: &[ItemMod]) -> Value {
+    generate_grammar_with_warnings(module, imports).0
+}
+
+/// Like [`generate_grammar_with_imports`], but also returns the warnings about the grammar,
+/// such as patterns whose escapes were probably meant for the regular expression, for the
+/// caller to report.
+pub fn generate_grammar_with_warnings(
+    module: &ItemMod,
+    imports: &[ItemMod],
+) -> (Value, Vec<String>) {
+    let mut warnings = vec![];
+    let grammar = generate_grammar_for_root(module, None, imports, &mut warnings);
+    (grammar, warnings)
+}
+
+/// Returns the paths of the grammar modules imported with `#[rust_sitter::use_rules(...)]`,
+/// such as `common::*` or `crate::common`, without any trailing glob.
+pub fn find_use_rules(module: &ItemMod) -> Vec<Vec<String>> {
+    module
+        .attrs
+        .iter()
+        .filter(|a| a.path == syn::parse_quote!(rust_sitter::use_rules))
+        .map(|a| {
+            let mut tree = a
+                .parse_args::<UseTree>()
+                .expect("Expected a module path for `use_rules`");
+            let mut path = vec![];
+            loop {
+                match tree {
+                    UseTree::Path(p) => {
+                        path.push(p.ident.to_string());
+                        tree = *p.tree;
+                    }
+                    UseTree::Name(n) => {
+                        path.push(n.ident.to_string());
+                        break;
+                    }
+                    UseTree::Glob(_) => break,
+                    _ => panic!("Expected a module path for `use_rules`"),
+                }
+            }
+
+            path
+        })
+        .collect()
+}
+
+/// Replaces every reference to a renamed rule with an alias of the renamed rule that
+/// keeps the original name, so that its nodes still have the original kind.
+fn alias_symbols(rule: &mut Value, rename: &impl Fn(&str) -> Option<String>) {
+    match rule["type"].as_str() {
+        Some("SYMBOL") => {
+            let name = rule["name"].as_str().unwrap().to_string();
+            if let Some(renamed) = rename(&name) {
+                *rule = json!({
+                    "type": "ALIAS",
+                    "content": {
+                        "type": "SYMBOL",
+                        "name": renamed
+                    },
+                    "named": true,
+                    "value": name
+                });
+            }
+        }
+        Some("CHOICE" | "SEQ") => rule["members"]
+            .as_array_mut()
+            .unwrap()
+            .iter_mut()
+            .for_each(|m| alias_symbols(m, rename)),
+        _ => {
+            if let Some(content) = rule.get_mut("content") {
+                alias_symbols(content, rename);
+            }
+        }
+    }
+}
+
+/// Renames every reference to the rules that `rename` gives a new name, such as the prefixed
+/// names of grammars with `prefix_rules = true`. Aliases keep their values, so imported and
+/// aliased nodes keep their kinds.
+fn rename_symbols(rule: &mut Value, rename: &impl Fn(&str) -> Option<String>) {
+    match rule["type"].as_str() {
+        Some("SYMBOL") => {
+            if let Some(renamed) = rename(rule["name"].as_str().unwrap()) {
+                rule["name"] = json!(renamed);
+            }
+        }
+        Some("CHOICE" | "SEQ") => rule["members"]
+            .as_array_mut()
+            .unwrap()
+            .iter_mut()
+            .for_each(|m| rename_symbols(m, rename)),
+        _ => {
+            if let Some(content) = rule.get_mut("content") {
+                rename_symbols(content, rename);
+            }
+        }
+    }
+}
+
+/// Renames the fields of a rule, with the same traversal as `rename_symbols`.
+fn rename_fields(rule: &mut Value, rename: &impl Fn(&str) -> Option<String>) {
+    match rule["type"].as_str() {
+        Some("CHOICE" | "SEQ") => rule["members"]
+            .as_array_mut()
+            .unwrap()
+            .iter_mut()
+            .for_each(|m| rename_fields(m, rename)),
+        _ => {
+            if rule["type"] == "FIELD" {
+                if let Some(renamed) = rename(rule["name"].as_str().unwrap()) {
+                    rule["name"] = json!(renamed);
+                }
+            }
+            if let Some(content) = rule.get_mut("content") {
+                rename_fields(content, rename);
+            }
+        }
+    }
+}
+
+/// Prefixes the name of a rule, keeping the leading underscore of a hidden rule in front so
+/// that it stays hidden.
+pub fn prefixed_rule_name(prefix: &str, name: &str) -> String {
+    match name.strip_prefix('_') {
+        Some(rest) => format!("_{prefix}{rest}"),
+        None => format!("{prefix}{name}"),
+    }
+}
+
+/// Whether a rule only matches a pattern, possibly with a token precedence, which is the
+/// rule of a leaf with a `pattern`.
+fn is_pattern_leaf(rule: &Value) -> bool {
+    match rule["type"].as_str() {
+        Some("PATTERN") => true,
+        Some("TOKEN" | "IMMEDIATE_TOKEN" | "PREC") => is_pattern_leaf(&rule["content"]),
+        _ => false,
+    }
+}
+
+/// The names of the rules whose node kinds are checked by the code generated for a grammar
+/// or by its queries, as the names of types and variants, and as the prefixes of the rules
+/// of leaves that are unescaped, anchored, have alternatives or are highlighted.
+fn kinds_in_use(contents: &[Item]) -> (HashSet<String>, HashSet<String>) {
+    let mut names = HashSet::new();
+    let mut leaf_prefixes = HashSet::new();
+    let mut add_fields = |path: &str, fields: &Fields| {
+        fields.iter().enumerate().for_each(|(i, field)| {
+            let leaf_params = field
+                .attrs
+                .iter()
+                .filter(|attr| attr.path == syn::parse_quote!(rust_sitter::leaf))
+                .flat_map(|leaf| {
+                    leaf.parse_args_with(Punctuated::<NameValueExpr, Token![,]>::parse_terminated)
+                        .unwrap_or_default()
+                })
+                .collect::<Vec<_>>();
+            let checked = field
+                .attrs
+                .iter()
+                .any(|attr| attr.path == syn::parse_quote!(rust_sitter::highlight))
+                || leaf_params.iter().any(|param| {
+                    param.path == "alternatives"
+                        || ((param.path == "unescape" || param.path == "anchored")
+                            && param.expr == syn::parse_quote!(true))
+                });
+            if checked {
+                let ident_str = field
+                    .ident
+                    .as_ref()
+                    .map(|v| v.to_string())
+                    .unwrap_or(format!("{i}"));
+                leaf_prefixes.insert(format!("{path}_{ident_str}"));
+            }
+        })
+    };
+
+    contents.iter().for_each(|item| match item {
+        Item::Enum(e) => {
+            names.insert(e.ident.to_string());
+            e.variants.iter().for_each(|v| {
+                let variant_path = format!("{}_{}", e.ident, v.ident);
+                add_fields(&variant_path, &v.fields);
+                names.insert(variant_path);
+            });
+        }
+        Item::Struct(s) => {
+            names.insert(s.ident.to_string());
+            add_fields(&s.ident.to_string(), &s.fields);
+        }
+        _ => {}
+    });
+
+    (names, leaf_prefixes)
+}
+
+/// Merges the rules of leaves with identical patterns into the first of them by name,
+/// pointing every reference at the merged rule, and returns the new name of each rule that
+/// was merged away. The rules whose kinds are in use (see [`kinds_in_use`]) keep their
+/// names, since their nodes are told apart by kind.
+fn merge_identical_leaves(
+    rules: &mut BTreeMap<String, Value>,
+    (names, leaf_prefixes): &(HashSet<String>, HashSet<String>),
+) -> HashMap<String, String> {
+    let in_use = |name: &str| {
+        // rules hidden by `hide_anonymous` are checked by the names they had before
+        let name = name.strip_prefix('_').unwrap_or(name);
+        names.contains(name)
+            || leaf_prefixes
+                .iter()
+                .any(|prefix| name == prefix || name.starts_with(&format!("{prefix}_")))
+    };
+
+    let mut first_with_rule: HashMap<String, String> = HashMap::new();
+    let mut merged = HashMap::new();
+    rules
+        .iter()
+        .filter(|(name, rule)| is_pattern_leaf(rule) && !in_use(name))
+        .for_each(
+            |(name, rule)| match first_with_rule.get(&rule.to_string()) {
+                Some(first) => {
+                    merged.insert(name.clone(), first.clone());
+                }
+                None => {
+                    first_with_rule.insert(rule.to_string(), name.clone());
+                }
+            },
+        );
+
+    merged.keys().for_each(|name| {
+        rules.remove(name);
+    });
+    rules
+        .values_mut()
+        .for_each(|rule| rename_symbols(rule, &|name| merged.get(name).cloned()));
+
+    merged
+}
+
+/// Replaces every reference to a rule of a type marked with `#[rust_sitter::alias(...)]`
+/// with an alias to its node kind. The rules themselves keep their names, so that several
+/// types can share a node kind without defining the same rule twice.
+fn alias_references(rule: &mut Value, aliases: &HashMap<String, (String, bool)>) {
+    match rule["type"].as_str() {
+        Some("SYMBOL") => {
+            let name = rule["name"].as_str().unwrap().to_string();
+            if let Some((value, named)) = aliases.get(&name) {
+                *rule = json!({
+                    "type": "ALIAS",
+                    "content": {
+                        "type": "SYMBOL",
+                        "name": name
+                    },
+                    "named": named,
+                    "value": value
+                });
+            }
+        }
+        Some("CHOICE" | "SEQ") => rule["members"]
+            .as_array_mut()
+            .unwrap()
+            .iter_mut()
+            .for_each(|m| alias_references(m, aliases)),
+        // a field marked with `#[rust_sitter::alias_as(...)]` already has its own node kind
+        Some("ALIAS") => {}
+        _ => {
+            if let Some(content) = rule.get_mut("content") {
+                alias_references(content, aliases);
+            }
+        }
+    }
+}
+
+/// Merges the rules of imported grammars into `rules`. Every imported rule is renamed to
+/// `<imported grammar name>_<rule>`, so that it never collides with a local rule, and every
+/// reference to it is aliased back to its original name, which is the node kind expected by
+/// the extraction logic of the imported types. A local reference is only resolved to an
+/// imported rule if there is no local rule with that name, and must not be ambiguous.
+fn merge_imported_rules(rules: &mut BTreeMap<String, Value>, imports: &[ItemMod]) {
+    // maps each imported name to its renamed rule, or `None` if several grammars define it
+    let mut imported: HashMap<String, Option<String>> = HashMap::new();
+    let mut imported_rules = HashMap::new();
+
+    imports.iter().for_each(|import| {
+        if !find_use_rules(import).is_empty() {
+            panic!("Grammars imported with `use_rules` cannot import rules themselves");
+        }
+
+        let grammar = generate_grammar(import);
+        let import_name = grammar["name"].as_str().unwrap().to_string();
+        let import_rules = grammar["rules"].as_object().unwrap();
+
+        import_rules
+            .iter()
+            .filter(|(name, _)| *name != "source_file")
+            .for_each(|(name, rule)| {
+                let mut rule = rule.clone();
+                alias_symbols(&mut rule, &|n| {
+                    Some(format!("{import_name}_{n}")).filter(|_| import_rules.contains_key(n))
+                });
+                imported_rules.insert(format!("{import_name}_{name}"), rule);
+
+                imported
+                    .entry(name.clone())
+                    .and_modify(|r| *r = None)
+                    .or_insert_with(|| Some(format!("{import_name}_{name}")));
+            });
+    });
+
+    let local_names = rules.keys().cloned().collect::<HashSet<_>>();
+    rules.values_mut().for_each(|rule| {
+        alias_symbols(rule, &|n| {
+            if local_names.contains(n) {
+                return None;
+            }
+
+            imported.get(n).map(|renamed| {
+                renamed.clone().unwrap_or_else(|| {
+                    panic!(
+                        "The rule `{n}` is defined by several grammars imported with `use_rules`"
+                    )
+                })
+            })
+        })
+    });
+
+    // only the imported rules that are used are merged in, which leaves out unused
+    // rules such as the extras of the imported grammars
+    let mut used = vec![];
+    rules
+        .values()
+        .for_each(|rule| collect_symbols(rule, &mut used));
+    let mut merged = HashSet::new();
+    while let Some(name) = used.pop() {
+        if let Some(rule) = imported_rules.get(&name) {
+            if merged.insert(name.clone()) {
+                collect_symbols(rule, &mut used);
+                insert_rule(rules, name, rule.clone());
+            }
+        }
+    }
+}
+
+fn collect_symbols(rule: &Value, out: &mut Vec<String>) {
+    match rule["type"].as_str() {
+        Some("SYMBOL") => out.push(rule["name"].as_str().unwrap().to_string()),
+        Some("CHOICE" | "SEQ") => rule["members"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .for_each(|m| collect_symbols(m, out)),
+        _ => {
+            if let Some(content) = rule.get("content") {
+                collect_symbols(content, out);
+            }
+        }
+    }
+}
+
+/// Replaces the rules of a grammar marked with `#[rust_sitter::tokenizer_only]` with a start
+/// rule that repeats a choice of every terminal rule and external token, so that Tree Sitter
+/// only splits the input into tokens. The texts and patterns inside of the other rules, such
+/// as those of `bracketed`, are added to the choice as anonymous tokens. The rules used by
+/// the extras are kept as they are, but left out of the choice, and so is a token identical
+/// to one that came before it, since it could never match.
+fn tokenizer_rules(
+    rules: BTreeMap<String, Value>,
+    start_rule: &str,
+    extras: &[Value],
+    externals: &[String],
+) -> BTreeMap<String, Value> {
+    let mut pending = vec![];
+    extras
+        .iter()
+        .for_each(|extra| collect_symbols(extra, &mut pending));
+    let mut extra_rules = HashSet::new();
+    while let Some(name) = pending.pop() {
+        if let Some(rule) = rules.get(&name) {
+            if extra_rules.insert(name) {
+                collect_symbols(rule, &mut pending);
+            }
+        }
+    }
+
+    let mut out = BTreeMap::new();
+    let mut tokens = vec![];
+    let mut anonymous = vec![];
+    for (name, rule) in rules {
+        if extra_rules.contains(&name) {
+            out.insert(name, rule);
+        } else if name == start_rule || !is_terminal(&rule) {
+            collect_inline_tokens(&rule, &mut anonymous);
+        } else if !out.values().any(|r| *r == rule) {
+            tokens.push(json!({ "type": "SYMBOL", "name": name }));
+            out.insert(name, rule);
+        }
+    }
+    anonymous.into_iter().for_each(|token| {
+        if !out.values().any(|r| *r == token) && !tokens.contains(&token) {
+            tokens.push(token);
+        }
+    });
+    tokens.extend(
+        externals
+            .iter()
+            .map(|name| json!({ "type": "SYMBOL", "name": name })),
+    );
+
+    out.insert(
+        start_rule.to_string(),
+        json!({
+            "type": "REPEAT",
+            "content": {
+                "type": "CHOICE",
+                "members": tokens
+            }
+        }),
+    );
+    out
+}
+
+/// Collects the tokens written inline in a rule rather than referred to by name.
+fn collect_inline_tokens(rule: &Value, out: &mut Vec<Value>) {
+    match rule["type"].as_str() {
+        Some("STRING" | "PATTERN" | "TOKEN" | "IMMEDIATE_TOKEN") => out.push(rule.clone()),
+        Some("CHOICE" | "SEQ") => rule["members"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .for_each(|m| collect_inline_tokens(m, out)),
+        _ => {
+            if let Some(content) = rule.get("content") {
+                collect_inline_tokens(content, out);
+            }
+        }
+    }
+}
+
+/// Generates an additional grammar for every type marked with `#[rust_sitter::entry]`,
+/// which is identical to the main grammar except that the entry type is used as the
+/// root. Each grammar is named `<grammar name>_<entry type>`. The grammars are generated
+/// lazily, so that only one of them is in memory at a time.
+pub fn generate_entry_grammars(
+    module: &ItemMod,
+    imports: Vec<ItemMod>,
+) -> impl Iterator<Item = Value> + '_ {
+    let (_, contents) = module.content.as_ref().unwrap();
+
+    contents
+        .iter()
+        .filter_map(|item| match item {
+            Item::Enum(ItemEnum { ident, attrs, .. })
+            | Item::Struct(ItemStruct { ident, attrs, .. }) => {
+                if attrs
+                    .iter()
+                    .any(|attr| attr.path == syn::parse_quote!(rust_sitter::entry))
+                {
+                    Some(ident.to_string())
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        })
+        .map(move |entry| generate_grammar_for_root(module, Some(entry), &imports, &mut vec![]))
+}
+
+fn generate_grammar_for_root(
+    module: &ItemMod,
+    entry: Option<String>,
+    imports: &[ItemMod],
+    warnings: &mut Vec<String>,
+) -> Value {
+    let grammar_params = find_grammar_params(&module.attrs).expect("Each grammar must have a name");
+    if find_use_rules(module).len() != imports.len() {
+        panic!(
+            "The grammar `{}` imports rules with `use_rules`, so it must be generated along with the rest of the crate, such as with `generate_grammars`",
+            grammar_params.grammar_name(&module.ident)
+        );
+    }
+
+    let grammar_name = match &entry {
+        Some(entry) => sanitize_grammar_name(&format!(
+            "{}_{entry}",
+            grammar_params.grammar_name(&module.ident)
+        )),
+        None => grammar_params.grammar_name(&module.ident),
+    };
+
+    // When the root is named, the root type's rule is the start rule of the grammar
+    // instead of being copied into an anonymous `source_file` rule.
+    let named_root = grammar_params.named_root();
+
+    // entry grammars share the leaves of the main grammar, which already warned
+    if entry.is_none() {
+        warnings.extend(cooked_pattern_warnings(module));
+        warnings.extend(default_empty_warnings(module));
+    }
+
+    let (_, contents) = module.content.as_ref().unwrap();
+    let contents = resolve_type_aliases(contents);
+
+    // An entry grammar starts at the entry type, and the main grammar at every root type,
+    // with `source_file` matching any of them when there are several.
+    let root_types = match &entry {
+        Some(entry) => vec![entry.clone()],
+        None => {
+            let root_types = find_root_types(&contents)
+                .iter()
+                .map(|ident| ident.to_string())
+                .collect::<Vec<_>>();
+            if root_types.is_empty() {
+                panic!(
+                    "Each parser must have the root type annotated with `#[rust_sitter::language]`"
+                );
+            }
+            root_types
+        }
+    };
+    if root_types.len() > 1 && named_root {
+        panic!("`named_root` makes the rule of the root type the start rule, so it cannot be combined with several `#[rust_sitter::language]` types");
+    }
+    let root_type = root_types[0].clone();
+
+    // Rules are collected in a sorted map so that the output does not depend on the order
+    // in which they are generated. The start rule is reserved with an empty placeholder.
+    let start_rule = if named_root {
+        root_type.clone()
+    } else {
+        "source_file".to_string()
+    };
+    let mut rules_map = BTreeMap::new();
+    rules_map.insert(start_rule.clone(), json!({}));
+
+    // Extras listed on the module with `#[rust_sitter::extras(...)]`, either as patterns or
+    // as references to rules. An explicit empty list disables the default whitespace extra.
+    // The order of extras is observable in tokenization, so they are kept in source order:
+    // the lists of each attribute in turn, followed by the types marked as `extra`.
+    let extras_attrs = module
+        .attrs
+        .iter()
+        .filter(|a| a.path == syn::parse_quote!(rust_sitter::extras))
+        .collect::<Vec<_>>();
+
+    let mut extras_list = extras_attrs
+        .iter()
+        .flat_map(|a| {
+            a.parse_args_with(Punctuated::<Expr, Token![,]>::parse_terminated)
+                .expect("Expected a list of patterns or rule types for `extras`")
+                .iter()
+                .map(|e| match e {
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Str(s), ..
+                    }) => json!({
+                        "type": "PATTERN",
+                        "value": s.value(),
+                    }),
+                    Expr::Path(p) if p.path.segments.len() == 1 => json!({
+                        "type": "SYMBOL",
+                        "name": p.path.segments[0].ident.to_string(),
+                    }),
+                    _ => panic!("Expected a string literal pattern or a rule type in `extras`"),
+                })
+                .collect::<Vec<Value>>()
+        })
+        .collect::<Vec<Value>>();
+
+    // Optionally locate the rule annotated with `#[rust_sitter::word]`.
+    let mut word_rule = None;
+    let mut no_extras_rules = vec![];
+    let mut immediate_seq_rules = vec![];
+    let mut aliases = HashMap::new();
+    let mut keyword_variants = vec![];
+    let mut supertypes = vec![];
+    let mut token_groups = HashMap::new();
+    contents.iter().for_each(|c| {
+        let (symbol, attrs) = match c {
+            Item::Enum(e) if find_token_group(&e.attrs).is_some() => {
+                let group = find_token_group(&e.attrs).unwrap();
+                if !group.starts_with('_') {
+                    panic!(
+                        "The token group `{group}` of `{}` must start with `_`, so that Tree Sitter hides it",
+                        e.ident
+                    );
+                }
+
+                // the members are referred to directly, so the group adds no node to the tree
+                let mut skip_over = HashSet::new();
+                skip_over.insert("Spanned");
+                skip_over.insert("Box");
+                let members = e
+                    .variants
+                    .iter()
+                    .map(|v| match token_group_member(&v.fields, &skip_over) {
+                        Some(member) => json!({
+                            "type": "SYMBOL",
+                            "name": member
+                        }),
+                        None => panic!(
+                            "`{}` is a token group, so each of its variants must wrap a single type, but `{}_{}` does not",
+                            e.ident, e.ident, v.ident
+                        ),
+                    })
+                    .collect::<Vec<_>>();
+
+                insert_rule(
+                    &mut rules_map,
+                    group.clone(),
+                    json!({
+                        "type": "CHOICE",
+                        "members": members
+                    }),
+                );
+                token_groups.insert(e.ident.to_string(), group.clone());
+
+                (group, e.attrs.clone())
+            }
+
+            Item::Enum(e) => {
+                e.variants.iter().for_each(|v| {
+                    let variant_path = format!("{}_{}", e.ident, v.ident);
+                    if let Some(keyword) = v
+                        .attrs
+                        .iter()
+                        .find(|attr| attr.path == syn::parse_quote!(rust_sitter::keyword))
+                    {
+                        if !matches!(v.fields, Fields::Unit) {
+                            panic!("`#[rust_sitter::keyword]` can only be placed on unit variants, but `{variant_path}` has fields");
+                        }
+
+                        let keyword = keyword
+                            .parse_args::<LitStr>()
+                            .expect("Expected a string literal for `keyword`")
+                            .value();
+                        if keyword.is_empty()
+                            || !keyword.chars().all(|c| c.is_alphanumeric() || c == '_')
+                        {
+                            panic!("The keyword `{keyword}` of `{variant_path}` must be a word, made of letters, digits and underscores");
+                        }
+
+                        // the word rule makes Tree Sitter only match the keyword as a whole word
+                        insert_rule(
+                            &mut rules_map,
+                            variant_path.clone(),
+                            json!({
+                                "type": "STRING",
+                                "value": keyword,
+                            }),
+                        );
+                        keyword_variants.push(variant_path);
+                    } else if v
+                        .attrs
+                        .iter()
+                        .any(|attr| attr.path == syn::parse_quote!(rust_sitter::leaf))
+                    {
+                        // a leaf on the variant itself makes the whole variant a single token
+                        if !matches!(v.fields, Fields::Unit) {
+                            panic!("`#[rust_sitter::leaf]` can only be placed on unit variants, but `{variant_path}` has fields");
+                        }
+
+                        gen_field(
+                            variant_path,
+                            syn::parse_quote!(()),
+                            v.attrs.clone(),
+                            &mut word_rule,
+                            &mut rules_map,
+                        );
+                    } else if is_fieldless(&v.fields) {
+                        // a variant without fields or a leaf is matched by its name
+                        insert_rule(
+                            &mut rules_map,
+                            variant_path,
+                            json!({
+                                "type": "STRING",
+                                "value": unit_variant_text(&v.ident, &e.attrs),
+                            }),
+                        );
+                    } else {
+                        // a variant without its own precedence takes the precedence of the
+                        // enum, since Tree Sitter only applies a precedence to the rule it is in
+                        let mut attrs = v.attrs.clone();
+                        if !attrs.iter().any(is_prec_attr) {
+                            attrs.extend(e.attrs.iter().filter(|a| is_prec_attr(a)).cloned());
+                        }
+
+                        gen_struct_or_variant(
+                            variant_path,
+                            attrs,
+                            v.fields.clone(),
+                            &mut rules_map,
+                            &mut word_rule,
+                        )
+                    }
+                });
+
+                let variant_paths = e
+                    .variants
+                    .iter()
+                    .map(|v| format!("{}_{}", e.ident.clone(), v.ident))
+                    .collect::<Vec<_>>();
+
+                // entry grammars share the rules of the main grammar, which already warned
+                if entry.is_none() {
+                    warnings.extend(dominated_variant_warnings(&variant_paths, &rules_map));
+                }
+
+                // the variants of a supertype become its subtypes, so they must be rules with
+                // nodes of their own rather than tokens
+                if e
+                    .attrs
+                    .iter()
+                    .any(|a| a.path == syn::parse_quote!(rust_sitter::supertype))
+                {
+                    if let Some(v) = e.variants.iter().find(|v| is_fieldless(&v.fields)) {
+                        panic!(
+                            "`{}` is a supertype, so its variants must have fields, but `{}_{}` is a token",
+                            e.ident, e.ident, v.ident
+                        );
+                    }
+
+                    supertypes.push(e.ident.to_string());
+                }
+
+                let mut members: Vec<Value> = vec![];
+                variant_paths.iter().for_each(|variant_path| {
+                    members.push(json!({
+                        "type": "SYMBOL",
+                        "name": variant_path
+                    }))
+                });
+
+                let rule = json!({
+                    "type": "CHOICE",
+                    "members": members
+                });
+
+                insert_rule(&mut rules_map, e.ident.to_string(), with_prec(rule, &e.attrs));
+
+                (e.ident.to_string(), e.attrs.clone())
+            }
+
+            Item::Struct(s) => {
+                if let Some(leaf_attr) = s
+                    .attrs
+                    .iter()
+                    .find(|attr| attr.path == syn::parse_quote!(rust_sitter::leaf))
+                {
+                    // a leaf on the struct itself makes the whole struct a single token, whose
+                    // fields are filled from the named groups of the pattern
+                    gen_field(
+                        s.ident.to_string(),
+                        syn::parse_quote!(()),
+                        vec![without_capture_names(leaf_attr)],
+                        &mut word_rule,
+                        &mut rules_map,
+                    );
+                } else if let Some(field) = find_token_field(s) {
+                    // the leaf of a token is the rule of the struct itself, so references to
+                    // the struct refer to the leaf without a node around it
+                    gen_field(
+                        s.ident.to_string(),
+                        syn::parse_quote!(()),
+                        field.attrs.clone(),
+                        &mut word_rule,
+                        &mut rules_map,
+                    );
+                } else {
+                    gen_struct_or_variant(
+                        s.ident.to_string(),
+                        s.attrs.clone(),
+                        s.fields.clone(),
+                        &mut rules_map,
+                        &mut word_rule,
+                    );
+                }
+
+                if has_no_extras(&s.attrs) {
+                    no_extras_rules.push(s.ident.to_string());
+                }
+
+                if s
+                    .attrs
+                    .iter()
+                    .any(|a| a.path == syn::parse_quote!(rust_sitter::immediate_seq))
+                {
+                    immediate_seq_rules.push(s.ident.to_string());
+                }
+
+                (s.ident.to_string(), s.attrs.clone())
+            }
+
+            _ => return,
+        };
+
+        if let Some(alias_attr) = attrs
+            .iter()
+            .find(|a| a.path == syn::parse_quote!(rust_sitter::alias))
+        {
+            let alias_params = alias_attr
+                .parse_args_with(AliasThenParams::parse)
+                .expect("Expected a string literal for the node kind of `alias`");
+            let named = alias_params
+                .params
+                .iter()
+                .find(|param| param.path == "named")
+                .map(|p| p.expr == syn::parse_quote!(true))
+                .unwrap_or(false);
+            aliases.insert(symbol.clone(), (alias_params.value.value(), named));
+        }
+
+        if attrs
+            .iter()
+            .any(|a| a.path == syn::parse_quote!(rust_sitter::extra))
+        {
+            extras_list.push(json!({
+                "type": "SYMBOL",
+                "name": symbol
+            }));
+        }
+    });
+
+    if word_rule.is_none()
+        && (!keyword_variants.is_empty()
+            || module
+                .attrs
+                .iter()
+                .any(|a| a.path == syn::parse_quote!(rust_sitter::infer_word)))
+    {
+        word_rule = infer_word_rule(&rules_map);
+    }
+
+    if let (Some(keyword_variant), None) = (keyword_variants.first(), &word_rule) {
+        panic!("`{keyword_variant}` is a keyword, which needs a word rule, but none could be inferred. Mark the leaf of identifiers with `#[rust_sitter::word]`");
+    }
+
+    if module
+        .attrs
+        .iter()
+        .any(|a| a.path == syn::parse_quote!(rust_sitter::case_insensitive_keywords))
+    {
+        rules_map.values_mut().for_each(lower_case_insensitive_text);
+    }
+
+    merge_imported_rules(&mut rules_map, imports);
+
+    // extras are never skipped inside of a token
+    no_extras_rules.iter().for_each(|name| {
+        let content =
+            inline_token_content(&rules_map[name], &rules_map, &mut vec![name.to_string()]);
+        rules_map.insert(
+            name.clone(),
+            json!({
+                "type": "TOKEN",
+                "content": content
+            }),
+        );
+    });
+
+    // the fields after the first must directly follow the previous field
+    immediate_seq_rules.iter().for_each(|name| {
+        let mut rule = rules_map[name].clone();
+        let mut seq = &mut rule;
+        while seq["type"] != "SEQ" {
+            seq = &mut seq["content"];
+        }
+
+        seq["members"]
+            .as_array_mut()
+            .unwrap()
+            .iter_mut()
+            .skip(1)
+            .for_each(|m| make_immediate(m, name, &mut rules_map, &mut vec![]));
+        rules_map.insert(name.clone(), rule);
+    });
+
+    // aliased types share a node kind, which must not be the name of another rule
+    aliases.iter().for_each(|(name, (value, _))| {
+        if rules_map.contains_key(value) {
+            panic!("`{name}` is aliased to `{value}`, which is already the name of a rule");
+        }
+    });
+    rules_map
+        .values_mut()
+        .for_each(|rule| alias_references(rule, &aliases));
+
+    // newlines are skipped as extras wherever the grammar does not expect one, such as
+    // inside of brackets, since Tree Sitter only skips an extra where it has no other use
+    if rules_map.contains_key(NEWLINE_RULE) {
+        extras_list.push(json!({
+            "type": "SYMBOL",
+            "name": NEWLINE_RULE
+        }));
+    }
+
+    check_nullable_repeats(&rules_map);
+    check_nullable_extras(&extras_list, &rules_map);
+
+    // entry grammars share the rules of the main grammar, which already warned
+    if entry.is_none() {
+        warnings.extend(error_only_alternative_warnings(&rules_map));
+        warnings.extend(inconsistent_alias_warnings(&rules_map));
+    }
+
+    if module
+        .attrs
+        .iter()
+        .any(|a| a.path == syn::parse_quote!(rust_sitter::hide_anonymous))
+    {
+        let keep = contents
+            .iter()
+            .flat_map(|item| match item {
+                Item::Enum(e) => std::iter::once(e.ident.to_string())
+                    .chain(find_token_group(&e.attrs))
+                    .chain(
+                        e.variants
+                            .iter()
+                            .map(|v| format!("{}_{}", e.ident, v.ident)),
+                    )
+                    .collect(),
+                Item::Struct(s) => vec![s.ident.to_string()],
+                _ => vec![],
+            })
+            .collect::<HashSet<_>>();
+        hide_text_leaves(&mut rules_map, &keep, &extras_list, &word_rule);
+    }
+
+    let allow_shebang = module
+        .attrs
+        .iter()
+        .any(|a| a.path == syn::parse_quote!(rust_sitter::allow_shebang));
+    if allow_shebang && named_root {
+        panic!("`allow_shebang` adds a prefix to the `source_file` rule, so it cannot be combined with `named_root`");
+    }
+
+    let trailing_tokens = module
+        .attrs
+        .iter()
+        .filter_map(|a| {
+            if a.path == syn::parse_quote!(rust_sitter::require_trailing_newline) {
+                Some("\n".to_string())
+            } else if a.path == syn::parse_quote!(rust_sitter::trailing_token) {
+                Some(
+                    a.parse_args::<LitStr>()
+                        .expect("Expected a string literal such as \";\" for `trailing_token`")
+                        .value(),
+                )
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    let trailing_token = match trailing_tokens.as_slice() {
+        [] => None,
+        [token] if token.is_empty() => panic!("The `trailing_token` of a grammar cannot be empty"),
+        [token] => Some(token.clone()),
+        _ => panic!("A grammar can only require one trailing token, from either `trailing_token` or `require_trailing_newline`"),
+    };
+    if trailing_token.is_some() && named_root {
+        panic!("A trailing token is added to the `source_file` rule, so it cannot be combined with `named_root`");
+    }
+
+    if !named_root {
+        let root_rule = if let [root_type] = root_types.as_slice() {
+            rules_map
+                .get(token_groups.get(root_type).unwrap_or(root_type))
+                .unwrap()
+                .clone()
+        } else {
+            // the node of each root is kept, so that its type can be told apart when extracting
+            json!({
+                "type": "CHOICE",
+                "members": root_types
+                    .iter()
+                    .map(|root_type| json!({
+                        "type": "SYMBOL",
+                        "name": token_groups.get(root_type).unwrap_or(root_type),
+                    }))
+                    .collect::<Vec<_>>()
+            })
+        };
+        let mut members = vec![];
+        if allow_shebang {
+            // the prefix is made of anonymous tokens, so the root is extracted as usual; the
+            // shebang takes precedence over extras such as `#` comments matching the same line
+            members.push(optional(json!({ "type": "PATTERN", "value": "\\x{feff}" })));
+            members.push(optional(with_token_prec(
+                json!({ "type": "PATTERN", "value": "#![^\\n]*" }),
+                Some(1),
+            )));
+        }
+        members.push(root_rule);
+        // the trailing token is anonymous too, so it is skipped during extraction
+        if let Some(token) = &trailing_token {
+            members.push(json!({ "type": "STRING", "value": token }));
+        }
+        let source_file = if members.len() == 1 {
+            members.pop().unwrap()
+        } else {
+            json!({
+                "type": "SEQ",
+                "members": members
+            })
+        };
+
+        rules_map.insert("source_file".to_string(), source_file);
+    }
+
+    // Without any configured extras, skip whitespace like the Tree Sitter DSL does by default,
+    // leaving out newlines when they are significant to the grammar, including when the input
+    // must end with one, since the lexer takes any newline where the input can end for it
+    let newline_symbol = json!({ "type": "SYMBOL", "name": NEWLINE_RULE });
+    if extras_attrs.is_empty() && extras_list.iter().all(|extra| extra == &newline_symbol) {
+        let whitespace = if extras_list.is_empty() && trailing_token.as_deref() != Some("\n") {
+            "\\s"
+        } else {
+            "[ \\t\\f\\v]"
+        };
+        extras_list.insert(
+            0,
+            json!({
+                "type": "PATTERN",
+                "value": whitespace,
+            }),
+        );
+    }
+
+    // references to the enum of a token group refer to its hidden rule instead
+    let group_rule = |name: &str| token_groups.get(name).cloned();
+    rules_map
+        .values_mut()
+        .for_each(|rule| rename_symbols(rule, &group_rule));
+    extras_list
+        .iter_mut()
+        .for_each(|extra| rename_symbols(extra, &group_rule));
+
+    let mut conflicts = find_conflicts(module);
+    conflicts.iter().flatten().for_each(|name| {
+        if !rules_map.contains_key(name) {
+            panic!("The conflict `{name}` does not name a rule of the grammar, use the name of a type or `Enum_Variant`");
+        }
+    });
+
+    let merge_leaves = grammar_params
+        .param("merge_leaves")
+        .map(|e| *e == syn::parse_quote!(true))
+        .unwrap_or(false);
+    let word_rule = if merge_leaves {
+        let merged = merge_identical_leaves(&mut rules_map, &kinds_in_use(&contents));
+        let merged_rule = |name: &str| merged.get(name).cloned();
+        extras_list
+            .iter_mut()
+            .for_each(|extra| rename_symbols(extra, &merged_rule));
+        conflicts
+            .iter_mut()
+            .flatten()
+            .for_each(|name| *name = merged_rule(name).unwrap_or(name.clone()));
+        word_rule.map(|word| merged_rule(&word).unwrap_or(word))
+    } else {
+        word_rule
+    };
+
+    let precedences = find_precedences(module)
+        .iter()
+        .map(|ordering| {
+            ordering
+                .iter()
+                .map(|name| {
+                    json!({
+                        "type": "STRING",
+                        "value": name
+                    })
+                })
+                .collect::<Value>()
+        })
+        .collect::<Vec<_>>();
+
+    let strict_conflicts = grammar_params
+        .param("strict_conflicts")
+        .map(|e| *e == syn::parse_quote!(true))
+        .unwrap_or(false);
+    if strict_conflicts {
+        let mut rules = Map::new();
+        rules.insert(start_rule.clone(), rules_map[&start_rule].clone());
+        rules.extend(rules_map.clone());
+        check_undeclared_conflicts(
+            &contents,
+            &json!({
+                "rules": rules,
+                "conflicts": conflicts,
+                "precedences": precedences,
+            }),
+        );
+    }
+
+    // the rules are renamed last, since the passes above refer to them by their names
+    let rule_prefix = grammar_params.rule_prefix(&module.ident);
+    let rule_case = RuleCase::find(&module.attrs);
+    let (start_rule, word_rule) = if rule_prefix.is_empty() && rule_case == RuleCase::Unchanged {
+        (start_rule, word_rule)
+    } else {
+        let renamed = |name: &str| rule_case.apply(&prefixed_rule_name(&rule_prefix, name));
+        let mut names = HashMap::new();
+        rules_map.keys().for_each(|name| {
+            if let Some(other) = names.insert(renamed(name), name) {
+                panic!(
+                    "The rules `{other}` and `{name}` are both renamed to `{}`, rename one of the types or fields to avoid the collision",
+                    renamed(name)
+                );
+            }
+        });
+        let names = rules_map.keys().cloned().collect::<HashSet<_>>();
+        let rename = |name: &str| names.contains(name).then(|| renamed(name));
+        // the fields of the elements of a repetition are named after its rule, so they take
+        // its case, but not its prefix, as the fields of a rule are already kept apart
+        let rename_field = |field: &str| {
+            field
+                .strip_suffix("_vec_element")
+                .filter(|path| names.contains(&format!("{path}_vec_contents")))
+                .map(|_| rule_case.apply(field))
+        };
+        rules_map = rules_map
+            .into_iter()
+            .map(|(name, mut rule)| {
+                rename_symbols(&mut rule, &rename);
+                rename_fields(&mut rule, &rename_field);
+                (renamed(&name), rule)
+            })
+            .collect();
+        extras_list
+            .iter_mut()
+            .for_each(|extra| rename_symbols(extra, &rename));
+        supertypes.iter_mut().for_each(|name| *name = renamed(name));
+        conflicts
+            .iter_mut()
+            .flatten()
+            .for_each(|name| *name = renamed(name));
+
+        (renamed(&start_rule), word_rule.map(|word| renamed(&word)))
+    };
+
+    let externals = find_externals(module);
+    check_external_tokens(&contents, &externals);
+    if module
+        .attrs
+        .iter()
+        .any(|a| a.path == syn::parse_quote!(rust_sitter::tokenizer_only))
+    {
+        rules_map = tokenizer_rules(rules_map, &start_rule, &extras_list, &externals);
+        supertypes.clear();
+        conflicts.clear();
+    }
+
+    // for some reason, the start rule must be the first key for things to work
+    let mut rules = Map::new();
+    rules.insert(start_rule.clone(), rules_map.remove(&start_rule).unwrap());
+    rules.extend(rules_map);
+
+    let mut grammar = json!({
+        "name": grammar_name,
+        "word": word_rule,
+        "rules": rules,
+        "extras": extras_list
+    });
+
+    if !supertypes.is_empty() {
+        grammar["supertypes"] = json!(supertypes);
+    }
+
+    if !conflicts.is_empty() {
+        grammar["conflicts"] = json!(conflicts);
+    }
+
+    if !precedences.is_empty() {
+        grammar["precedences"] = json!(precedences);
+    }
+
+    if !externals.is_empty() {
+        grammar["externals"] = externals
+            .iter()
+            .map(|name| {
+                json!({
+                    "type": "SYMBOL",
+                    "name": name
+                })
+            })
+            .collect();
+    }
+
+    grammar
+}
+
+/// Returns the external token referenced by a field with `#[rust_sitter::external_token(...)]`.
+fn find_external_token(attrs: &[Attribute]) -> Option<String> {
+    attrs
+        .iter()
+        .find(|a| a.path == syn::parse_quote!(rust_sitter::external_token))
+        .map(|a| {
+            a.parse_args::<Ident>()
+                .expect("Expected the name of an external token for `external_token`")
+                .to_string()
+        })
+}
+
+/// Checks that every external token referenced with `#[rust_sitter::external_token(...)]`
+/// was declared with `#[rust_sitter::external(...)]`, since Tree Sitter would otherwise
+/// report it as an undefined symbol without saying where it came from.
+fn check_external_tokens(contents: &[Item], externals: &[String]) {
+    let check_fields = |path: &str, fields: &Fields| {
+        fields.iter().enumerate().for_each(|(i, field)| {
+            if let Some(external) = find_external_token(&field.attrs) {
+                if !externals.contains(&external) {
+                    let ident_str = field
+                        .ident
+                        .as_ref()
+                        .map(|v| v.to_string())
+                        .unwrap_or(format!("{i}"));
+                    panic!("The external token `{external}` referenced by `{path}_{ident_str}` is not declared with `#[rust_sitter::external(...)]` on the grammar module");
+                }
+            }
+        })
+    };
+
+    contents.iter().for_each(|item| match item {
+        Item::Enum(e) => e
+            .variants
+            .iter()
+            .for_each(|v| check_fields(&format!("{}_{}", e.ident, v.ident), &v.fields)),
+        Item::Struct(s) => check_fields(&s.ident.to_string(), &s.fields),
+        _ => {}
+    });
+}
+
+/// Returns the names of the external tokens declared on a grammar module with
+/// `#[rust_sitter::external(...)]`, which are recognized by a scanner written in C.
+pub fn find_externals(module: &ItemMod) -> Vec<String> {
+    module
+        .attrs
+        .iter()
+        .find(|a| a.path == syn::parse_quote!(rust_sitter::external))
+        .map(|a| {
+            a.parse_args_with(Punctuated::<Ident, Token![,]>::parse_terminated)
+                .expect("Expected a list of token names for `external`")
+                .iter()
+                .map(|ident| ident.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns the name of the hidden rule of an enum marked with
+/// `#[rust_sitter::token_group(...)]`.
+fn find_token_group(attrs: &[Attribute]) -> Option<String> {
+    attrs
+        .iter()
+        .find(|a| a.path == syn::parse_quote!(rust_sitter::token_group))
+        .map(|a| {
+            a.parse_args::<Ident>()
+                .expect("Expected the name of a hidden rule such as `_literal` for `token_group`")
+                .to_string()
+        })
+}
+
+/// Returns the rule of the single type wrapped by a variant of a token group.
+fn token_group_member(fields: &Fields, skip_over: &HashSet<&str>) -> Option<String> {
+    match fields {
+        Fields::Unnamed(f) if f.unnamed.len() == 1 => {
+            match filter_inner_type(&f.unnamed[0].ty, skip_over) {
+                Type::Path(p) if p.path.segments.len() == 1 => {
+                    Some(p.path.segments[0].ident.to_string())
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Returns the groups of rules declared on a grammar module with
+/// `#[rust_sitter::conflicts(...)]`, whose conflicts Tree Sitter resolves at runtime.
+fn find_conflicts(module: &ItemMod) -> Vec<Vec<String>> {
+    module
+        .attrs
+        .iter()
+        .filter(|a| a.path == syn::parse_quote!(rust_sitter::conflicts))
+        .flat_map(|a| {
+            a.parse_args_with(Punctuated::<ExprArray, Token![,]>::parse_terminated)
+                .expect("Expected a list of rule groups such as `[Expr_Call, Expr_Index]` for `conflicts`")
+        })
+        .map(|group| {
+            group
+                .elems
+                .iter()
+                .map(|e| match e {
+                    Expr::Path(p) if p.path.segments.len() == 1 => {
+                        p.path.segments[0].ident.to_string()
+                    }
+                    _ => panic!("Expected the name of a rule in `conflicts`"),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// With `strict_conflicts = true`, rejects a grammar whose parse table has conflicts that are
+/// neither declared with `#[rust_sitter::conflicts(...)]` nor resolved by precedence, naming
+/// the types, variants and fields involved instead of leaving Tree Sitter to report them.
+fn check_undeclared_conflicts(contents: &[Item], grammar: &Value) {
+    let undeclared = find_undeclared_conflicts(grammar);
+    if undeclared.is_empty() {
+        return;
+    }
+
+    // the rules of types, variants and fields, with the Rust paths they were generated from
+    let field_sources = |rule: &str, path: &str, fields: &Fields| {
+        fields
+            .iter()
+            .enumerate()
+            .map(|(i, field)| {
+                let name = field
+                    .ident
+                    .as_ref()
+                    .map(|ident| ident.to_string())
+                    .unwrap_or(format!("{i}"));
+                (format!("{rule}_{name}"), format!("{path}::{name}"))
+            })
+            .collect::<Vec<_>>()
+    };
+    let sources = contents
+        .iter()
+        .flat_map(|item| match item {
+            Item::Struct(s) => {
+                let name = s.ident.to_string();
+                let mut out = field_sources(&name, &name, &s.fields);
+                out.push((name.clone(), name));
+                out
+            }
+            Item::Enum(e) => {
+                let mut out = e
+                    .variants
+                    .iter()
+                    .flat_map(|v| {
+                        let rule = format!("{}_{}", e.ident, v.ident);
+                        let path = format!("{}::{}", e.ident, v.ident);
+                        let mut out = field_sources(&rule, &path, &v.fields);
+                        out.push((rule, path));
+                        out
+                    })
+                    .collect::<Vec<_>>();
+                out.push((e.ident.to_string(), e.ident.to_string()));
+                out
+            }
+            _ => vec![],
+        })
+        .collect::<Vec<_>>();
+    // auxiliary rules such as the contents of a list are named after the field they are for
+    let source = |rule: &str| {
+        sources
+            .iter()
+            .filter(|(name, _)| {
+                rule == name
+                    || rule
+                        .strip_prefix(name.as_str())
+                        .is_some_and(|rest| rest.starts_with('_'))
+            })
+            .max_by_key(|(name, _)| name.len())
+            .filter(|(_, path)| path != rule)
+            .map(|(_, path)| format!("`{rule}` (`{path}`)"))
+            .unwrap_or(format!("`{rule}`"))
+    };
+
+    let report = undeclared
+        .iter()
+        .map(|conflict| {
+            format!(
+                "- between {} before {}, which can be declared with `#[rust_sitter::conflicts([{}])]`",
+                conflict
+                    .rules
+                    .iter()
+                    .map(|rule| source(rule))
+                    .collect::<Vec<_>>()
+                    .join(" and "),
+                conflict.lookahead,
+                conflict.rules.join(", ")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    panic!(
+        "The grammar has conflicts that are neither declared nor resolved by precedence:\n{report}\nDeclare the conflicts that Tree Sitter should resolve at runtime, or give the rules involved a precedence or associativity"
+    );
+}
+
+/// Returns the orderings of named precedences declared on a grammar module with
+/// `#[rust_sitter::precedences(...)]`, each from the level that binds most tightly to the
+/// one that binds least, which is the order Tree Sitter expects.
+fn find_precedences(module: &ItemMod) -> Vec<Vec<String>> {
+    /// Flattens a chain such as `"a" > "b" > "c"`, which parses as `("a" > "b") > "c"`.
+    fn flatten_ordering(expr: &Expr, out: &mut Vec<String>) {
+        match expr {
+            Expr::Binary(b) if matches!(b.op, BinOp::Gt(_)) => {
+                flatten_ordering(&b.left, out);
+                flatten_ordering(&b.right, out);
+            }
+            Expr::Lit(ExprLit {
+                lit: Lit::Str(s), ..
+            }) => {
+                let name = s.value();
+                if out.contains(&name) {
+                    panic!("The precedence `{name}` appears twice in the same ordering");
+                }
+                out.push(name);
+            }
+            _ => panic!("Expected precedence names ordered from highest to lowest, such as `\"unary\" > \"binary\"`, in `precedences`"),
+        }
+    }
+
+    module
+        .attrs
+        .iter()
+        .filter(|a| a.path == syn::parse_quote!(rust_sitter::precedences))
+        .flat_map(|a| {
+            a.parse_args::<ExprArray>()
+                .expect("Expected a list of orderings such as `[\"unary\" > \"binary\"]` for `precedences`")
+                .elems
+        })
+        .map(|ordering| {
+            let mut names = vec![];
+            flatten_ordering(&ordering, &mut names);
+            names
+        })
+        .collect()
+}
+
+/// Generates a stub of the external scanner for a grammar with external tokens, with the
+/// functions Tree Sitter expects and an enum of the tokens in declaration order, so that
+/// only the body of the `scan` function has to be filled in.
+pub fn generate_scanner_stub(module: &ItemMod) -> Option<String> {
+    let externals = find_externals(module);
+    if externals.is_empty() {
+        return None;
+    }
+
+    let grammar_name = find_grammar_name(module).expect("Each grammar must have a name");
+
+    let tokens = externals
+        .iter()
+        .map(|name| {
+            let mut token = String::new();
+            name.chars().enumerate().for_each(|(i, c)| {
+                if c.is_uppercase() && i > 0 {
+                    token.push('_');
+                }
+                token.push(c.to_ascii_uppercase());
+            });
+            format!("  {token},\n")
+        })
+        .collect::<String>();
+
+    Some(format!(
+        r#"#include <tree_sitter/parser.h>
+
+enum TokenType {{
+{tokens}}};
+
+void *tree_sitter_{grammar_name}_external_scanner_create(void) {{
+  return NULL;
+}}
+
+void tree_sitter_{grammar_name}_external_scanner_destroy(void *payload) {{}}
+
+unsigned tree_sitter_{grammar_name}_external_scanner_serialize(void *payload, char *buffer) {{
+  return 0;
+}}
+
+void tree_sitter_{grammar_name}_external_scanner_deserialize(void *payload, const char *buffer, unsigned length) {{}}
+
+bool tree_sitter_{grammar_name}_external_scanner_scan(void *payload, TSLexer *lexer, const bool *valid_symbols) {{
+  return false;
+}}
+"#
+    ))
+}
+
+/// Generates a C file that compiles the external scanner of a grammar again for one of its
+/// entry grammars, whose parser expects the scanner functions under its own name.
+#[cfg(feature = "build_parsers")]
+pub fn generate_entry_scanner(
+    grammar_name: &str,
+    entry_grammar_name: &str,
+    scanner: &str,
+) -> String {
+    let defines = ["create", "destroy", "serialize", "deserialize", "scan"]
+        .iter()
+        .map(|f| {
+            format!("#define tree_sitter_{grammar_name}_external_scanner_{f} tree_sitter_{entry_grammar_name}_external_scanner_{f}\n")
+        })
+        .collect::<String>();
+
+    format!("{defines}#include {scanner:?}\n")
+}
+
+/// Generates editor metadata for a grammar module, which is emitted next to the grammar
+/// since Tree Sitter grammars have no place for it. The metadata also has a stamp with the
+/// version of the tool and a hash of the module, to tell when a grammar is out of date.
+pub fn generate_metadata(module: &ItemMod) -> Value {
+    let grammar_name = find_grammar_name(module).expect("Each grammar must have a name");
+
+    let file_types: Vec<String> = module
+        .attrs
+        .iter()
+        .find(|a| a.path == syn::parse_quote!(rust_sitter::file_types))
+        .map(|a| {
+            a.parse_args_with(Punctuated::<LitStr, Token![,]>::parse_terminated)
+                .expect("Expected a list of string literals for `file_types`")
+                .into_iter()
+                .map(|ext| ext.value())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    json!({
+        "name": grammar_name,
+        "file_types": file_types,
+        "rules": rule_metadata(module),
+        "fields": field_order(module),
+        "stamp": {
+            "tool_version": env!("CARGO_PKG_VERSION"),
+            "source_hash": source_hash(module)
+        }
+    })
+}
+
+/// Collects the `meta` attributes of the types and variants in a grammar module, keyed by
+/// the name of the rule each one generates. Rules without metadata are left out.
+fn rule_metadata(module: &ItemMod) -> Map<String, Value> {
+    let rule_prefix = find_grammar_params(&module.attrs)
+        .expect("Each grammar must have a name")
+        .rule_prefix(&module.ident);
+    let rule_case = RuleCase::find(&module.attrs);
+
+    let mut out = Map::new();
+    let mut add = |name: String, attrs: &[Attribute]| {
+        let meta = attrs
+            .iter()
+            .filter(|a| a.path == syn::parse_quote!(rust_sitter::meta))
+            .flat_map(|a| {
+                a.parse_args_with(Punctuated::<NameValueExpr, Token![,]>::parse_terminated)
+                    .expect("Expected a list of `key = value` pairs for `meta`")
+            })
+            .map(|nv| {
+                let value = match &nv.expr {
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Str(s), ..
+                    }) => json!(s.value()),
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Bool(b), ..
+                    }) => json!(b.value),
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Int(i), ..
+                    }) => json!(i
+                        .base10_parse::<i64>()
+                        .expect("Expected an integer that fits in an i64")),
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Float(f), ..
+                    }) => json!(f.base10_parse::<f64>().expect("Expected a float")),
+                    _ => panic!(
+                        "Expected a string, boolean or number literal for the `meta` key `{}`",
+                        nv.path
+                    ),
+                };
+                (nv.path.to_string(), value)
+            })
+            .collect::<Map<_, _>>();
+
+        if !meta.is_empty() {
+            out.insert(
+                rule_case.apply(&format!("{rule_prefix}{name}")),
+                Value::Object(meta),
+            );
+        }
+    };
+
+    if let Some((_, items)) = &module.content {
+        items.iter().for_each(|item| match item {
+            Item::Enum(e) => {
+                add(e.ident.to_string(), &e.attrs);
+                e.variants
+                    .iter()
+                    .for_each(|v| add(format!("{}_{}", e.ident, v.ident), &v.attrs));
+            }
+            Item::Struct(s) => add(s.ident.to_string(), &s.attrs),
+            _ => {}
+        });
+    }
+
+    out
+}
+
+/// Lists the fields of the rule of each struct and variant in a grammar module in the order
+/// they are matched, with whether each one can be absent and whether it is repeated, so that
+/// tools such as formatters can lay out a node even when some of its fields are missing.
+fn field_order(module: &ItemMod) -> Map<String, Value> {
+    let rule_prefix = find_grammar_params(&module.attrs)
+        .expect("Each grammar must have a name")
+        .rule_prefix(&module.ident);
+    let rule_case = RuleCase::find(&module.attrs);
+
+    let mut skip_over = HashSet::new();
+    skip_over.insert("Spanned");
+    skip_over.insert("Box");
+
+    let descriptors = |fields: &Fields| {
+        fields
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| {
+                !field
+                    .attrs
+                    .iter()
+                    .any(|attr| attr.path == syn::parse_quote!(rust_sitter::skip))
+            })
+            .map(|(i, field)| {
+                let name = ts_field_name(field, i);
+
+                // mirrors `gen_field`, where a vector is optional unless it is `non_empty`
+                let (inner, is_option) = try_extract_inner_type(&field.ty, "Option", &skip_over);
+                let is_option = is_option || is_optional_field(&field.attrs, &field.ty);
+                let (_, is_vec) = try_extract_repeated_type(&inner, &skip_over);
+                let counted = find_repeat_count(&field.attrs, &inner).is_some();
+                let non_empty = field
+                    .attrs
+                    .iter()
+                    .find(|attr| attr.path == syn::parse_quote!(rust_sitter::repeat))
+                    .and_then(|a| {
+                        a.parse_args_with(Punctuated::<NameValueExpr, Token![,]>::parse_terminated)
+                            .ok()
+                    })
+                    .map(|p| {
+                        p.iter().any(|param| {
+                            param.path == "non_empty" && param.expr == syn::parse_quote!(true)
+                        })
+                    })
+                    .unwrap_or(false);
+
+                json!({
+                    "name": name,
+                    "optional": is_option || is_vec && !non_empty && !counted,
+                    "repeated": is_vec
+                })
+            })
+            .collect::<Vec<Value>>()
+    };
+
+    let mut out = Map::new();
+    if let Some((_, items)) = &module.content {
+        resolve_type_aliases(items)
+            .iter()
+            .for_each(|item| match item {
+                // the members of a token group are rules of their own
+                Item::Enum(e) if find_token_group(&e.attrs).is_some() => {}
+                Item::Enum(e) => e.variants.iter().for_each(|v| {
+                    let fields = descriptors(&v.fields);
+                    if !fields.is_empty() {
+                        out.insert(
+                            rule_case.apply(&format!("{rule_prefix}{}_{}", e.ident, v.ident)),
+                            Value::Array(fields),
+                        );
+                    }
+                }),
+                // the fields of a leaf struct are filled from its capture groups instead
+                Item::Struct(s)
+                    if !s
+                        .attrs
+                        .iter()
+                        .any(|attr| attr.path == syn::parse_quote!(rust_sitter::leaf)) =>
+                {
+                    let fields = descriptors(&s.fields);
+                    if !fields.is_empty() {
+                        out.insert(
+                            rule_case.apply(&format!("{rule_prefix}{}", s.ident)),
+                            Value::Array(fields),
+                        );
+                    }
+                }
+                _ => {}
+            });
+    }
+
+    out
+}
+
+/// Generates a `locals.scm` query from the `scope`, `definition` and `reference` annotations
+/// of a grammar module, or `None` if the grammar has none of them.
+pub fn generate_locals(module: &ItemMod) -> Option<String> {
+    let grammar_params = find_grammar_params(&module.attrs).expect("Each grammar must have a name");
+    let rule_prefix = grammar_params.rule_prefix(&module.ident);
+    let rule_case = RuleCase::find(&module.attrs);
+
+    let (mut scopes, mut definitions, mut references) = (vec![], vec![], vec![]);
+    let mut add = |kinds: Vec<String>, attrs: &[Attribute], fields: Option<&Fields>| {
+        let kind = &kinds[0];
+        if attrs
+            .iter()
+            .any(|attr| attr.path == syn::parse_quote!(rust_sitter::scope))
+        {
+            scopes.extend(kinds.iter().map(|kind| format!("({kind}) @local.scope")));
+        }
+
+        if let Some(definition) = attrs
+            .iter()
+            .find(|attr| attr.path == syn::parse_quote!(rust_sitter::definition))
+        {
+            let field = definition
+                .parse_args_with(Punctuated::<NameValueExpr, Token![,]>::parse_terminated)
+                .ok()
+                .and_then(|params| {
+                    params.into_iter().find_map(|param| match param.expr {
+                        Expr::Lit(ExprLit {
+                            lit: Lit::Str(s), ..
+                        }) if param.path == "field" => Some(s.value()),
+                        _ => None,
+                    })
+                })
+                .unwrap_or_else(|| {
+                    panic!("Expected `field = \"...\"` for the `definition` of `{kind}`")
+                });
+
+            // the field must be one that the rule of the type matches
+            let has_field = fields.into_iter().flatten().enumerate().any(|(i, f)| {
+                !f.attrs
+                    .iter()
+                    .any(|attr| attr.path == syn::parse_quote!(rust_sitter::skip))
+                    && ts_field_name(f, i) == field
+            });
+            if !has_field {
+                panic!("The `definition` of `{kind}` names the field `{field}`, which is not a field of its rule");
+            }
+
+            definitions.extend(
+                kinds
+                    .iter()
+                    .map(|kind| format!("({kind} {field}: (_) @local.definition)")),
+            );
+        }
+
+        if attrs
+            .iter()
+            .any(|attr| attr.path == syn::parse_quote!(rust_sitter::reference))
+        {
+            references.extend(
+                kinds
+                    .iter()
+                    .map(|kind| format!("({kind}) @local.reference")),
+            );
+        }
+    };
+
+    let kinds = |ident: &Ident, attrs: &[Attribute]| type_node_kinds(module, ident, attrs);
+
+    if let Some((_, items)) = &module.content {
+        items.iter().for_each(|item| match item {
+            Item::Enum(e) => {
+                add(kinds(&e.ident, &e.attrs), &e.attrs, None);
+                e.variants.iter().for_each(|v| {
+                    add(
+                        vec![rule_case.apply(&format!("{rule_prefix}{}_{}", e.ident, v.ident))],
+                        &v.attrs,
+                        Some(&v.fields),
+                    )
+                });
+            }
+            Item::Struct(s) => add(kinds(&s.ident, &s.attrs), &s.attrs, Some(&s.fields)),
+            _ => {}
+        });
+    }
+
+    let sections = [scopes, definitions, references]
+        .into_iter()
+        .filter(|patterns| !patterns.is_empty())
+        .map(|patterns| patterns.join("\n") + "\n")
+        .collect::<Vec<_>>();
+
+    if sections.is_empty() {
+        None
+    } else {
+        Some(sections.join("\n"))
+    }
+}
+
+/// The kinds of the nodes of a struct or enum, which is the kind of its alias if it has one,
+/// and otherwise the name of its rule. The root of the tree is a copy of the rule of the
+/// language type, unless it is named or there are several language types, so the language
+/// type also has the kind of the root.
+fn type_node_kinds(module: &ItemMod, ident: &Ident, attrs: &[Attribute]) -> Vec<String> {
+    let grammar_params = find_grammar_params(&module.attrs).expect("Each grammar must have a name");
+    let rule_prefix = grammar_params.rule_prefix(&module.ident);
+    let rule_case = RuleCase::find(&module.attrs);
+    let named_root = grammar_params.named_root();
+
+    let mut kinds = vec![attrs
+        .iter()
+        .find(|a| a.path == syn::parse_quote!(rust_sitter::alias))
+        .map(|a| {
+            a.parse_args_with(AliasThenParams::parse)
+                .expect("Expected a string literal for the node kind of `alias`")
+                .value
+                .value()
+        })
+        .unwrap_or(rule_case.apply(&format!("{rule_prefix}{ident}")))];
+
+    let (_, contents) = module.content.as_ref().unwrap();
+    if !named_root && find_root_types(contents) == [ident.clone()] {
+        kinds.insert(0, rule_case.apply(&format!("{rule_prefix}source_file")));
+    }
+
+    kinds
+}
+
+/// Whether a rule matches a token without referring to other rules, such as the `PATTERN`
+/// or `STRING` of a leaf, rather than being a `SEQ` or `CHOICE` of other rules.
+fn is_terminal(rule: &Value) -> bool {
+    match rule["type"].as_str() {
+        Some("SYMBOL") => false,
+        Some("CHOICE" | "SEQ") => rule["members"].as_array().unwrap().iter().all(is_terminal),
+        _ => rule.get("content").map(is_terminal).unwrap_or(true),
+    }
+}
+
+/// Collects the concrete node kinds that a rule of a supertype can match, going through the
+/// supertypes and hidden rules it refers to, which have no nodes of their own, so that a
+/// supertype of supertypes resolves to the kinds of all the nodes beneath it.
+fn collect_concrete_subtypes(
+    rule: &Value,
+    grammar: &Value,
+    visiting: &mut Vec<String>,
+    out: &mut Vec<String>,
+) {
+    match rule["type"].as_str() {
+        Some("SYMBOL") => {
+            let name = rule["name"].as_str().unwrap();
+            let is_supertype = grammar["supertypes"]
+                .as_array()
+                .map(|s| s.iter().any(|s| s == name))
+                .unwrap_or(false);
+            if is_supertype || name.starts_with('_') {
+                if !visiting.iter().any(|v| v == name) {
+                    visiting.push(name.to_string());
+                    collect_concrete_subtypes(&grammar["rules"][name], grammar, visiting, out);
+                    visiting.pop();
+                }
+            } else if !out.iter().any(|kind| kind == name) {
+                out.push(name.to_string());
+            }
+        }
+        Some("CHOICE" | "SEQ") => rule["members"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .for_each(|m| collect_concrete_subtypes(m, grammar, visiting, out)),
+        _ => {
+            if let Some(content) = rule.get("content") {
+                collect_concrete_subtypes(content, grammar, visiting, out);
+            }
+        }
+    }
+}
+
+/// Lists the named node types of a grammar in the form of the entries of `node-types.json`,
+/// with whether the rule of each one is terminal, so that tools can tell the nodes of leaves
+/// from those of composite rules without inspecting the rules. Hidden rules are left out,
+/// since they do not produce nodes. Each supertype lists the concrete node kinds it can be
+/// as its `subtypes`, with those of nested supertypes flattened into it.
+pub fn generate_node_types(grammar: &Value) -> Value {
+    let supertypes = grammar["supertypes"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    grammar["rules"]
+        .as_object()
+        .unwrap()
+        .iter()
+        .filter(|(name, _)| !name.starts_with('_'))
+        .map(|(name, rule)| {
+            let mut node_type = json!({
+                "type": name,
+                "named": true,
+                "is_terminal": is_terminal(rule)
+            });
+            if supertypes.iter().any(|s| s == name) {
+                let mut subtypes = vec![];
+                collect_concrete_subtypes(rule, grammar, &mut vec![name.clone()], &mut subtypes);
+                node_type["subtypes"] = subtypes
+                    .iter()
+                    .map(|kind| json!({ "type": kind, "named": true }))
+                    .collect();
+            }
+            node_type
+        })
+        .collect()
+}
+
+/// Generates a `highlights.scm` query from the `highlight` annotations of a grammar module,
+/// or `None` if the grammar has none of them. Annotated types and variants capture their
+/// nodes, and annotated leaves capture the nodes of their rules in the given grammar
+/// generated from the module, or their text if it is not matched by a rule of its own,
+/// such as with `hide_anonymous`.
+pub fn generate_highlights(module: &ItemMod, grammar: &Value) -> Option<String> {
+    let grammar_params = find_grammar_params(&module.attrs).expect("Each grammar must have a name");
+    let rule_prefix = grammar_params.rule_prefix(&module.ident);
+    let rule_case = RuleCase::find(&module.attrs);
+
+    let capture = |attrs: &[Attribute]| {
+        attrs
+            .iter()
+            .find(|attr| attr.path == syn::parse_quote!(rust_sitter::highlight))
+            .map(|a| {
+                a.parse_args::<LitStr>()
+                    .expect("Expected a string literal capture name such as \"keyword\" for `highlight`")
+                    .value()
+            })
+    };
+
+    // leaves are captured by the names their rules are actually given
+    let rules = grammar["rules"].as_object().unwrap();
+
+    let field_patterns = |path: String, fields: &Fields| {
+        fields
+            .iter()
+            .enumerate()
+            .filter_map(|(i, field)| {
+                let capture = capture(&field.attrs)?;
+                let ident_str = field
+                    .ident
+                    .as_ref()
+                    .map(|v| v.to_string())
+                    .unwrap_or(format!("{i}"));
+                let field_path = format!("{path}_{ident_str}");
+                let rule = rule_case.apply(&prefixed_rule_name(&rule_prefix, &field_path));
+                if let Some(name) = find_leaf_name(&field.attrs) {
+                    Some(format!("({name}) @{capture}"))
+                } else if rules.contains_key(&rule) && !rule.starts_with('_') {
+                    Some(format!("({rule}) @{capture}"))
+                } else if let Some(text) = find_text_param(&field.attrs) {
+                    Some(format!("{} @{capture}", json!(text)))
+                } else {
+                    panic!("The `highlight` of `{field_path}` is on a field without a node of its own, place it on a leaf instead");
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut patterns = vec![];
+    if let Some((_, items)) = &module.content {
+        items.iter().for_each(|item| match item {
+            Item::Enum(e) => {
+                if let Some(capture) = capture(&e.attrs) {
+                    type_node_kinds(module, &e.ident, &e.attrs)
+                        .iter()
+                        .for_each(|kind| patterns.push(format!("({kind}) @{capture}")));
+                }
+                e.variants.iter().for_each(|v| {
+                    let variant_path = format!("{}_{}", e.ident, v.ident);
+                    if let Some(capture) = capture(&v.attrs) {
+                        let kind = rule_case.apply(&format!("{rule_prefix}{variant_path}"));
+                        patterns.push(format!("({kind}) @{capture}"));
+                    }
+                    patterns.extend(field_patterns(variant_path, &v.fields));
+                });
+            }
+            Item::Struct(s) => {
+                if let Some(capture) = capture(&s.attrs) {
+                    type_node_kinds(module, &s.ident, &s.attrs)
+                        .iter()
+                        .for_each(|kind| patterns.push(format!("({kind}) @{capture}")));
+                }
+                patterns.extend(field_patterns(s.ident.to_string(), &s.fields));
+            }
+            _ => {}
+        });
+    }
+
+    if patterns.is_empty() {
+        None
+    } else {
+        Some(patterns.join("\n") + "\n")
+    }
+}
+
+/// Hashes the tokens of a grammar module with [`stable_hash`], so that a checked in grammar
+/// can be compared with its source in later builds.
+pub(crate) fn source_hash(module: &ItemMod) -> String {
+    stable_hash(&quote::ToTokens::to_token_stream(module).to_string())
+}
+
+/// Hashes text with 64-bit FNV-1a, which unlike the hashers of the standard library gives
+/// the same result across Rust versions, so the hash can be stored and compared later.
+pub(crate) fn stable_hash(text: &str) -> String {
+    let hash = text.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+
+    format!("{hash:016x}")
 }
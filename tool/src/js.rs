@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+
+use rust_sitter_common::*;
+use serde_json::Value;
+use syn::{Attribute, Fields, Item, ItemMod, Lit, Meta};
+
+use crate::expansion::prefixed_rule_name;
+use crate::GENERATED_MARKER;
+
+/// The width that the definitions of rules are wrapped at when they do not fit on one line.
+const LINE_WIDTH: usize = 100;
+
+/// Generates the `grammar.js` form of a grammar, which builds it with the functions of the
+/// Tree Sitter DSL, given its module and the JSON grammar generated from the module. The doc
+/// comments of the types, variants and fields of the module are emitted as `//` comments
+/// above the rules generated for them.
+pub fn generate_grammar_js(module: &ItemMod, grammar: &Value) -> String {
+    let docs = rule_docs(module);
+    let symbol = |name: &Value| format!("$.{}", name.as_str().unwrap());
+    let rule = |rule: &Value| flat(&to_js(rule));
+
+    let mut out = format!("// {GENERATED_MARKER}\n\nmodule.exports = grammar({{\n");
+    out.push_str(&format!("  name: {},\n", grammar["name"]));
+    if let Some(word) = grammar["word"].as_str() {
+        out.push_str(&format!("  word: $ => $.{word},\n"));
+    }
+
+    let mut list = |key: &str, item: &dyn Fn(&Value) -> String| {
+        if let Some(values) = grammar.get(key) {
+            out.push_str(&format!("  {key}: $ => {},\n", array(values, item)));
+        }
+    };
+    list("extras", &rule);
+    list("externals", &rule);
+    list("supertypes", &symbol);
+    list("conflicts", &|names| array(names, &symbol));
+    list("precedences", &|ordering| array(ordering, &rule));
+
+    out.push_str("  rules: {\n");
+    let rules = grammar["rules"].as_object().unwrap();
+    rules.iter().enumerate().for_each(|(i, (name, rule))| {
+        if i > 0 {
+            out.push('\n');
+        }
+
+        docs.get(name).into_iter().flatten().for_each(|line| {
+            if line.is_empty() {
+                out.push_str("    //\n");
+            } else {
+                out.push_str(&format!("    // {line}\n"));
+            }
+        });
+
+        let definition = format!("    {name}: $ => ");
+        let indent = definition.len();
+        out.push_str(&definition);
+        out.push_str(&render(&to_js(rule), indent, 4));
+        out.push_str(",\n");
+    });
+    out.push_str("  },\n});\n");
+
+    out
+}
+
+/// Writes an array of JSON values on a single line.
+fn array(values: &Value, item: &dyn Fn(&Value) -> String) -> String {
+    let items = values
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(item)
+        .collect::<Vec<_>>();
+    format!("[{}]", items.join(", "))
+}
+
+/// A JavaScript expression of the DSL, which is either a call of one of its functions or a
+/// plain value such as a string, a regular expression or a reference to a rule.
+enum Js {
+    Value(String),
+    Call(&'static str, Vec<Js>),
+}
+
+fn to_js(rule: &Value) -> Js {
+    let content = || to_js(&rule["content"]);
+    let members = || {
+        rule["members"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(to_js)
+            .collect()
+    };
+    let value = || Js::Value(rule["value"].to_string());
+
+    match rule["type"].as_str().unwrap() {
+        "BLANK" => Js::Call("blank", vec![]),
+        "STRING" => value(),
+        "PATTERN" => Js::Value(regex_literal(
+            rule["value"].as_str().unwrap(),
+            rule["flags"].as_str().unwrap_or_default(),
+        )),
+        "SYMBOL" => Js::Value(format!("$.{}", rule["name"].as_str().unwrap())),
+        "SEQ" => Js::Call("seq", members()),
+        "CHOICE" => Js::Call("choice", members()),
+        "REPEAT" => Js::Call("repeat", vec![content()]),
+        "REPEAT1" => Js::Call("repeat1", vec![content()]),
+        "FIELD" => Js::Call(
+            "field",
+            vec![Js::Value(rule["name"].to_string()), content()],
+        ),
+        "ALIAS" => {
+            let alias = if rule["named"].as_bool().unwrap_or(false) {
+                format!("$.{}", rule["value"].as_str().unwrap())
+            } else {
+                rule["value"].to_string()
+            };
+            Js::Call("alias", vec![content(), Js::Value(alias)])
+        }
+        "TOKEN" => Js::Call("token", vec![content()]),
+        "IMMEDIATE_TOKEN" => Js::Call("token.immediate", vec![content()]),
+        "PREC" => Js::Call("prec", vec![value(), content()]),
+        "PREC_LEFT" => Js::Call("prec.left", vec![value(), content()]),
+        "PREC_RIGHT" => Js::Call("prec.right", vec![value(), content()]),
+        "PREC_DYNAMIC" => Js::Call("prec.dynamic", vec![value(), content()]),
+        other => panic!("Rules of type `{other}` cannot be written in the DSL of `grammar.js`"),
+    }
+}
+
+/// Writes an expression on a single line.
+fn flat(js: &Js) -> String {
+    match js {
+        Js::Value(value) => value.clone(),
+        Js::Call(function, args) => format!(
+            "{function}({})",
+            args.iter().map(flat).collect::<Vec<_>>().join(", ")
+        ),
+    }
+}
+
+/// Writes an expression that starts at the given column, breaking the arguments of calls
+/// onto their own lines, indented one level deeper, when the call does not fit on the line.
+fn render(js: &Js, column: usize, indent: usize) -> String {
+    let line = flat(js);
+    match js {
+        Js::Call(function, args) if column + line.len() > LINE_WIDTH && !args.is_empty() => {
+            let inner = indent + 2;
+            let args = args
+                .iter()
+                .map(|arg| format!("{}{},\n", " ".repeat(inner), render(arg, inner, inner)))
+                .collect::<String>();
+            format!("{function}(\n{args}{})", " ".repeat(indent))
+        }
+        _ => line,
+    }
+}
+
+/// Writes a pattern as a regular expression literal, escaping the slashes that would
+/// otherwise end it.
+fn regex_literal(pattern: &str, flags: &str) -> String {
+    let mut out = String::from("/");
+    let mut escaped = false;
+    pattern.chars().for_each(|c| {
+        match c {
+            '/' if !escaped => out.push_str("\\/"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+        escaped = c == '\\' && !escaped;
+    });
+    out.push('/');
+    out.push_str(flags);
+    out
+}
+
+/// The lines of the doc comments in the given attributes.
+fn doc_lines(attrs: &[Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("doc"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::NameValue(meta)) => match meta.lit {
+                Lit::Str(s) => Some(s.value()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .flat_map(|doc| {
+            // split rather than `lines`, which drops the empty lines between paragraphs
+            doc.split('\n')
+                .map(|line| {
+                    line.strip_prefix(' ')
+                        .unwrap_or(line)
+                        .trim_end()
+                        .to_string()
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Finds the doc comments of the types, variants and fields of a grammar module, keyed by
+/// the names of the rules generated for them.
+fn rule_docs(module: &ItemMod) -> HashMap<String, Vec<String>> {
+    let grammar_params = find_grammar_params(&module.attrs).expect("Each grammar must have a name");
+    let rule_prefix = grammar_params.rule_prefix(&module.ident);
+    let rule_case = RuleCase::find(&module.attrs);
+
+    let mut documented: Vec<(String, &[Attribute])> = vec![];
+    if let Some((_, items)) = &module.content {
+        items.iter().for_each(|item| match item {
+            Item::Enum(e) => {
+                documented.push((e.ident.to_string(), &e.attrs));
+                e.variants.iter().for_each(|v| {
+                    let variant_path = format!("{}_{}", e.ident, v.ident);
+                    documented.extend(field_paths(&variant_path, &v.fields));
+                    documented.push((variant_path, &v.attrs));
+                });
+            }
+            Item::Struct(s) => {
+                documented.push((s.ident.to_string(), &s.attrs));
+                documented.extend(field_paths(&s.ident.to_string(), &s.fields));
+            }
+            _ => {}
+        });
+    }
+
+    documented
+        .into_iter()
+        .filter_map(|(path, attrs)| {
+            let lines = doc_lines(attrs);
+            (!lines.is_empty()).then(|| {
+                (
+                    rule_case.apply(&prefixed_rule_name(&rule_prefix, &path)),
+                    lines,
+                )
+            })
+        })
+        .collect()
+}
+
+/// The paths of the rules of the given fields, paired with the attributes of the fields.
+fn field_paths<'a>(path: &str, fields: &'a Fields) -> Vec<(String, &'a [Attribute])> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let ident_str = field
+                .ident
+                .as_ref()
+                .map(|v| v.to_string())
+                .unwrap_or(format!("{i}"));
+            (format!("{path}_{ident_str}"), field.attrs.as_slice())
+        })
+        .collect()
+}
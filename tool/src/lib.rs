@@ -1,127 +1,5540 @@
-use syn::{parse_quote, Item};
+use std::collections::HashMap;
+
+use rust_sitter_common::find_grammar_name;
+use serde_json::Value;
+use syn::{parse_quote, Item, ItemMod};
 
 mod expansion;
 use expansion::*;
 
+mod stats;
+pub use stats::{GrammarStats, LARGE_CHOICE_ARITY};
+
+mod diff;
+pub use diff::{diff_grammars, GrammarDiff};
+
+mod conflicts;
+#[cfg(feature = "check_conflicts")]
+pub use conflicts::find_unused_conflicts;
+pub use conflicts::{find_undeclared_conflicts, parse_unused_conflicts, UndeclaredConflict};
+
+mod schema;
+pub use schema::generate_json_schema;
+
+mod bindings;
+pub use bindings::generate_bindings;
+
+mod js;
+pub use js::generate_grammar_js;
+
 /// Generates JSON strings defining Tree Sitter grammars for every Rust Sitter
 /// grammar found in the given module and recursive submodules.
+///
+/// Each grammar is only generated once, even if its module is included from
+/// several places in the module tree. Grammars for the types marked with
+/// `#[rust_sitter::entry]` follow the main grammar of their module.
 pub fn generate_grammars(root_file: &Path) -> Vec<String> {
-    let root_file = syn_inline_mod::parse_and_inline_modules(root_file).items;
+    let modules = find_grammar_modules(root_file);
+    modules
+        .iter()
+        .flat_map(|(_, m)| generate_module_grammars(m, &modules))
+        .map(|g| g.to_string())
+        .collect()
+}
+
+/// Like [`generate_grammars`], but also returns the warnings about the grammars, such as
+/// patterns whose escapes were probably meant for the regular expression, which
+/// [`build_parsers`] reports to Cargo and other tools can report as they see fit.
+pub fn generate_grammars_with_warnings(root_file: &Path) -> (Vec<String>, Vec<String>) {
+    let modules = find_grammar_modules(root_file);
+    let mut warnings = vec![];
+    let grammars = modules
+        .iter()
+        .flat_map(|(_, m)| {
+            let (grammars, module_warnings) = generate_module_grammars_with_warnings(m, &modules);
+            warnings.extend(module_warnings);
+            grammars
+        })
+        .map(|g| g.to_string())
+        .collect();
+
+    (grammars, warnings)
+}
+
+/// Like [`generate_grammars`], but for code that was already parsed, such as the output of
+/// `cargo expand` when the grammar modules are themselves generated by another macro. The
+/// attributes of Rust Sitter only have to be present in the given code. Modules are not
+/// loaded from other files, so they must be inline, as they are in expanded code. A
+/// `proc_macro2::TokenStream` can be passed in after parsing it with
+/// `syn::parse2::<syn::File>(tokens)`.
+pub fn generate_grammars_from_file(file: &syn::File) -> Vec<String> {
+    let modules = find_grammar_modules_in(file);
+    modules
+        .iter()
+        .flat_map(|(_, m)| generate_module_grammars(m, &modules))
+        .map(|g| g.to_string())
+        .collect()
+}
+
+/// Like [`generate_grammars`], but combines the grammars into a single JSON document of
+/// the form `{ "grammars": { <grammar name>: <grammar>, ... } }`, in the same order, so that
+/// a project with several grammars can ship them as one artifact.
+pub fn generate_grammars_combined(root_file: &Path) -> String {
+    let modules = find_grammar_modules(root_file);
+    let grammars = modules
+        .iter()
+        .flat_map(|(_, m)| generate_module_grammars(m, &modules))
+        .map(|g| (g["name"].as_str().unwrap().to_string(), g))
+        .collect::<serde_json::Map<_, _>>();
+
+    serde_json::json!({ "grammars": grammars }).to_string()
+}
+
+/// Generates the JSON string of the main grammar with the given name, out of the Rust
+/// Sitter grammars found in the given module and recursive submodules, such as for a build
+/// script that only targets one language. A grammar can also be selected by the ident of
+/// its module. Only the selected grammar is generated, not its entry grammars or the
+/// other grammars of the file.
+pub fn generate_grammar_named(root_file: &Path, name: &str) -> Option<String> {
+    let modules = find_grammar_modules(root_file);
+    let (_, module) = modules
+        .iter()
+        .find(|(_, m)| find_grammar_name(m).as_deref() == Some(name))
+        .or_else(|| modules.iter().find(|(_, m)| m.ident == name))?;
+
+    let imports = resolve_imports(module, &modules);
+    Some(generate_grammar_with_imports(module, &imports).to_string())
+}
+
+/// Like [`generate_grammars`], but also computes statistics about each grammar.
+pub fn generate_grammars_with_stats(root_file: &Path) -> Vec<(String, GrammarStats)> {
+    let modules = find_grammar_modules(root_file);
+    modules
+        .iter()
+        .flat_map(|(_, m)| generate_module_grammars(m, &modules))
+        .map(|g| (g.to_string(), GrammarStats::from_grammar(&g)))
+        .collect()
+}
+
+/// Writes every grammar returned by [`generate_grammars`] to the given writer as
+/// pretty-printed JSON, one after the other, for inspecting the generated grammars
+/// such as from a build script.
+pub fn dump_grammars(root_file: &Path, writer: &mut impl std::io::Write) -> std::io::Result<()> {
+    let modules = find_grammar_modules(root_file);
+    for grammar in modules
+        .iter()
+        .flat_map(|(_, m)| generate_module_grammars(m, &modules))
+    {
+        serde_json::to_writer_pretty(&mut *writer, &grammar)?;
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+/// The marker at the start of every file written by Rust Sitter that is not meant to be
+/// customized, which tells reviewers and tools that the file is generated and should not be
+/// edited by hand.
+pub const GENERATED_MARKER: &str = "@generated by rust-sitter";
+
+/// Adds [`GENERATED_MARKER`] to a grammar as a `$comment` before all of its other keys,
+/// since JSON has no comments. Tree Sitter ignores the keys of a grammar that it does not
+/// know.
+fn mark_generated(grammar: Value) -> Value {
+    let mut marked = serde_json::Map::new();
+    marked.insert("$comment".to_string(), Value::from(GENERATED_MARKER));
+    if let Value::Object(keys) = grammar {
+        marked.extend(keys);
+    }
+
+    Value::Object(marked)
+}
+
+/// Writes every grammar returned by [`generate_grammars`] to `<grammar name>.json` in the
+/// given directory, returning the paths of the files. Each file starts with a `$comment`
+/// holding [`GENERATED_MARKER`].
+///
+/// Unlike [`generate_grammars`], the grammars are generated one at a time and serialized
+/// straight into their files, so neither the JSON strings nor more than one grammar are
+/// held in memory, which keeps the peak memory of build scripts down for large grammars.
+/// The rules of a single grammar are still all built before it is written, since passes
+/// such as word inference and aliasing look at every rule.
+pub fn write_grammars(root_file: &Path, out_dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let modules = find_grammar_modules(root_file);
+    let mut paths = vec![];
+    for grammar in modules
+        .iter()
+        .flat_map(|(_, m)| generate_module_grammars(m, &modules))
+    {
+        let path = out_dir.join(format!("{}.json", grammar["name"].as_str().unwrap()));
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(&path)?);
+        serde_json::to_writer(&mut writer, &mark_generated(grammar))?;
+        std::io::Write::flush(&mut writer)?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+/// The name of the file in which [`generate_if_changed`] records the hash of the grammar
+/// source and the grammar files written for it.
+const SOURCE_HASH_FILE: &str = "rust_sitter_source.hash";
+
+/// Like [`write_grammars`], but skips generating the grammars when their source has not
+/// changed since they were last written to the given directory, such as in a build script
+/// that is rerun for changes elsewhere in the crate. Returns whether the grammars were
+/// regenerated.
+///
+/// The source is hashed after parsing, so changes to comments and formatting outside of
+/// doc comments do not cause a regeneration, and upgrading Rust Sitter always does. The
+/// hash does not depend on the version of Rust, so a toolchain upgrade keeps the stored
+/// grammars. The hash is stored in the directory along with the names of the grammar
+/// files, which are also regenerated if any of them is missing.
+pub fn generate_if_changed(root_file: &Path, out_dir: &Path) -> std::io::Result<bool> {
+    let modules = find_grammar_modules(root_file);
+    let mut source = env!("CARGO_PKG_VERSION").to_string();
+    modules.iter().for_each(|(path, m)| {
+        source.push_str(&format!("\n{}\n{}", path.join("::"), source_hash(m)));
+    });
+    let hash = stable_hash(&source);
+
+    let hash_file = out_dir.join(SOURCE_HASH_FILE);
+    if let Ok(stored) = std::fs::read_to_string(&hash_file) {
+        let mut lines = stored.lines();
+        if lines.next() == Some(hash.as_str()) && lines.all(|name| out_dir.join(name).exists()) {
+            return Ok(false);
+        }
+    }
+
+    let paths = write_grammars(root_file, out_dir)?;
+    let names = paths
+        .iter()
+        .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    std::fs::write(&hash_file, format!("{hash}\n{}", names.join("\n")))?;
+
+    Ok(true)
+}
+
+#[derive(serde::Deserialize)]
+struct Manifest {
+    lib: Option<ManifestTarget>,
+    #[serde(default)]
+    bin: Vec<ManifestTarget>,
+}
+
+#[derive(serde::Deserialize)]
+struct ManifestTarget {
+    path: Option<String>,
+}
+
+/// Finds the root files of a crate from its `Cargo.toml`, following Cargo's conventions:
+/// the library root if the crate has one, and otherwise the roots of its binaries.
+fn find_crate_roots(manifest_dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let manifest = std::fs::read_to_string(manifest_dir.join("Cargo.toml"))?;
+    let manifest: Manifest = toml::from_str(&manifest)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+    let lib = match manifest.lib.and_then(|lib| lib.path) {
+        Some(path) => Some(manifest_dir.join(path)),
+        None => Some(manifest_dir.join("src/lib.rs")).filter(|p| p.exists()),
+    };
+
+    let roots = match lib {
+        Some(lib) => vec![lib],
+        None => {
+            let bins = manifest
+                .bin
+                .into_iter()
+                .filter_map(|bin| bin.path.map(|p| manifest_dir.join(p)))
+                .collect::<Vec<_>>();
+            if bins.is_empty() {
+                vec![manifest_dir.join("src/main.rs")]
+            } else {
+                bins
+            }
+        }
+    };
+
+    match roots.iter().find(|root| !root.exists()) {
+        Some(missing) => Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("The crate root `{}` does not exist", missing.display()),
+        )),
+        None => Ok(roots),
+    }
+}
+
+/// Like [`generate_grammars`], but finds the root file of the crate in the given directory
+/// from its `Cargo.toml`, so that the grammars of a crate can be generated from its source
+/// without a build script. The library root is used if the crate has one (`src/lib.rs` or
+/// the `path` of its `[lib]`), and otherwise the roots of its binaries.
+pub fn generate_from_crate(manifest_dir: &Path) -> std::io::Result<Vec<String>> {
+    Ok(find_crate_roots(manifest_dir)?
+        .iter()
+        .flat_map(|root| generate_grammars(root))
+        .collect())
+}
+
+/// Generates JSON strings with the editor metadata of every Rust Sitter grammar
+/// found in the given module and recursive submodules, in the same order as
+/// [`generate_grammars`]. Each entry contains the grammar `name` and the
+/// `file_types` declared with `#[rust_sitter::file_types(...)]`.
+pub fn generate_metadata_files(root_file: &Path) -> Vec<String> {
+    find_grammar_modules(root_file)
+        .iter()
+        .map(|(_, m)| generate_metadata(m).to_string())
+        .collect()
+}
+
+/// Generates a stub external scanner in C for every Rust Sitter grammar found in the
+/// given module and recursive submodules that declares external tokens with
+/// `#[rust_sitter::external(...)]`, paired with the name of its grammar.
+///
+/// [`build_parsers`] expects the completed scanner of each such grammar in a file named
+/// `<grammar name>_scanner.c` next to the root file.
+pub fn generate_scanner_stubs(root_file: &Path) -> Vec<(String, String)> {
+    find_grammar_modules(root_file)
+        .iter()
+        .filter_map(|(_, m)| {
+            generate_scanner_stub(m).map(|stub| {
+                let grammar_name = find_grammar_name(m).unwrap();
+                (grammar_name, stub)
+            })
+        })
+        .collect()
+}
+
+/// Generates a `locals.scm` query for every Rust Sitter grammar found in the given module
+/// and recursive submodules that marks scopes, definitions or references with
+/// `#[rust_sitter::scope]`, `#[rust_sitter::definition(...)]` or
+/// `#[rust_sitter::reference]`, paired with the name of its grammar.
+pub fn generate_locals_queries(root_file: &Path) -> Vec<(String, String)> {
+    find_grammar_modules(root_file)
+        .iter()
+        .filter_map(|(_, m)| {
+            generate_locals(m).map(|locals| {
+                let grammar_name = find_grammar_name(m).unwrap();
+                (grammar_name, locals)
+            })
+        })
+        .collect()
+}
+
+/// Generates a JSON Schema describing the serialized AST of every Rust Sitter grammar found
+/// in the given module and recursive submodules whose root type is marked with
+/// `#[rust_sitter::serde]`, paired with the name of its grammar.
+pub fn generate_json_schemas(root_file: &Path) -> Vec<(String, String)> {
+    find_grammar_modules(root_file)
+        .iter()
+        .filter_map(|(_, m)| {
+            generate_json_schema(m).map(|schema| {
+                let grammar_name = find_grammar_name(m).unwrap();
+                (grammar_name, serde_json::to_string_pretty(&schema).unwrap())
+            })
+        })
+        .collect()
+}
+
+/// Generates a `highlights.scm` query for every Rust Sitter grammar found in the given
+/// module and recursive submodules that annotates types, variants or leaves with
+/// `#[rust_sitter::highlight(...)]`, paired with the name of its grammar.
+pub fn generate_highlights_queries(root_file: &Path) -> Vec<(String, String)> {
+    let modules = find_grammar_modules(root_file);
+    modules
+        .iter()
+        .filter_map(|(_, m)| {
+            let grammar = generate_module_grammars(m, &modules).next().unwrap();
+            generate_highlights(m, &grammar).map(|highlights| {
+                let grammar_name = find_grammar_name(m).unwrap();
+                (grammar_name, highlights)
+            })
+        })
+        .collect()
+}
+
+/// Generates the `grammar.js` form of the main grammar of every Rust Sitter grammar found in
+/// the given module and recursive submodules, paired with the name of its grammar, for
+/// reading the grammars or developing them further with the Tree Sitter CLI. The doc
+/// comments of types, variants and fields are kept as comments above their rules.
+pub fn generate_grammars_js(root_file: &Path) -> Vec<(String, String)> {
+    let modules = find_grammar_modules(root_file);
+    modules
+        .iter()
+        .map(|(_, m)| {
+            let grammar = generate_module_grammars(m, &modules).next().unwrap();
+            let grammar_name = find_grammar_name(m).unwrap();
+            (grammar_name, generate_grammar_js(m, &grammar))
+        })
+        .collect()
+}
+
+/// Generates the named node types of every grammar returned by [`generate_grammars`] as a
+/// JSON string, paired with the name of its grammar. The node types are in the form of the
+/// entries of Tree Sitter's `node-types.json`, with an `is_terminal` flag for whether the
+/// rule of each one is a leaf that matches a token rather than a composite of other rules,
+/// and with the concrete node kinds of each supertype, including those of nested supertypes.
+pub fn generate_node_types_files(root_file: &Path) -> Vec<(String, String)> {
+    let modules = find_grammar_modules(root_file);
+    modules
+        .iter()
+        .flat_map(|(_, m)| generate_module_grammars(m, &modules))
+        .map(|grammar| {
+            (
+                grammar["name"].as_str().unwrap().to_string(),
+                generate_node_types(&grammar).to_string(),
+            )
+        })
+        .collect()
+}
+
+/// Generates the main grammar of a module followed by its entry grammars, merging in the
+/// rules of the grammar modules it imports with `#[rust_sitter::use_rules(...)]`.
+///
+/// The entry grammars are only generated as the iterator is consumed, so callers that
+/// handle one grammar at a time never hold all of them in memory.
+fn generate_module_grammars<'a>(
+    module: &'a ItemMod,
+    modules: &[(Vec<String>, ItemMod)],
+) -> impl Iterator<Item = Value> + 'a {
+    generate_module_grammars_with_warnings(module, modules).0
+}
+
+/// Like [`generate_module_grammars`], but also returns the warnings about the grammars,
+/// which are all found while generating the main grammar.
+fn generate_module_grammars_with_warnings<'a>(
+    module: &'a ItemMod,
+    modules: &[(Vec<String>, ItemMod)],
+) -> (impl Iterator<Item = Value> + 'a, Vec<String>) {
+    let imports = resolve_imports(module, modules);
+    let (grammar, warnings) = generate_grammar_with_warnings(module, &imports);
+    (
+        std::iter::once(grammar).chain(generate_entry_grammars(module, imports)),
+        warnings,
+    )
+}
+
+#[cfg(feature = "build_parsers")]
+/// Reports warnings about a grammar to Cargo from a build script.
+fn print_cargo_warnings(warnings: &[String]) {
+    warnings
+        .iter()
+        .for_each(|warning| println!("cargo:warning={warning}"));
+}
+
+/// Finds the grammar modules imported by a module, given the grammar modules of the crate
+/// and their paths. An import path matches the modules whose path ends with it, ignoring
+/// any leading `crate`, `self` or `super`, and must match exactly one module.
+fn resolve_imports(module: &ItemMod, modules: &[(Vec<String>, ItemMod)]) -> Vec<ItemMod> {
+    find_use_rules(module)
+        .iter()
+        .map(|import_path| {
+            let import_path = import_path
+                .iter()
+                .skip_while(|s| *s == "crate" || *s == "self" || *s == "super")
+                .cloned()
+                .collect::<Vec<_>>();
+
+            let matching = modules
+                .iter()
+                .filter(|(path, _)| path.ends_with(&import_path))
+                .collect::<Vec<_>>();
+
+            match matching.as_slice() {
+                [(_, m)] => m.clone(),
+                [] => panic!(
+                    "No grammar module matches `{}` in `use_rules`",
+                    import_path.join("::")
+                ),
+                _ => panic!(
+                    "Several grammar modules match `{}` in `use_rules`, use a longer path",
+                    import_path.join("::")
+                ),
+            }
+        })
+        .collect()
+}
+
+/// Finds the grammar modules in the given file and the modules it includes, along with
+/// their paths from the root of the file.
+fn find_grammar_modules(root_file: &Path) -> Vec<(Vec<String>, ItemMod)> {
+    find_grammar_modules_in(&syn_inline_mod::parse_and_inline_modules(root_file))
+}
+
+fn find_grammar_modules_in(file: &syn::File) -> Vec<(Vec<String>, ItemMod)> {
     let mut out = vec![];
-    root_file
+    let mut found = HashMap::new();
+    file.items
         .iter()
-        .for_each(|i| generate_all_grammars(i, &mut out));
+        .for_each(|i| find_all_grammar_modules(i, &mut vec![], &mut found, &mut out));
     out
 }
 
-fn generate_all_grammars(item: &Item, out: &mut Vec<String>) {
+fn find_all_grammar_modules(
+    item: &Item,
+    path: &mut Vec<String>,
+    found: &mut HashMap<String, ItemMod>,
+    out: &mut Vec<(Vec<String>, ItemMod)>,
+) {
     if let Item::Mod(m) = item {
-        m.content
-            .iter()
-            .for_each(|(_, items)| items.iter().for_each(|i| generate_all_grammars(i, out)));
+        path.push(m.ident.to_string());
+        m.content.iter().for_each(|(_, items)| {
+            items
+                .iter()
+                .for_each(|i| find_all_grammar_modules(i, path, found, out))
+        });
 
         if m.attrs
             .iter()
             .any(|a| a.path == parse_quote!(rust_sitter::grammar))
         {
-            out.push(generate_grammar(m).to_string())
+            let grammar_name = find_grammar_name(m).expect("Each grammar must have a name");
+
+            match found.get(&grammar_name) {
+                // the same module was included from several places, so it was already found
+                Some(existing) if existing.content == m.content => {}
+                Some(_) => panic!("Multiple different grammars are named `{grammar_name}`"),
+                None => {
+                    out.push((path.clone(), m.clone()));
+                    found.insert(grammar_name, m.clone());
+                }
+            }
         }
+
+        path.pop();
     }
 }
 
 #[cfg(feature = "build_parsers")]
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 #[cfg(feature = "build_parsers")]
 use tree_sitter_cli::generate;
 
+#[cfg(feature = "build_parsers")]
+#[derive(Debug)]
+/// An error while building the parsers of the grammars in a file with [`build_parser`].
+pub enum BuildError {
+    /// A grammar declares external tokens, but its external scanner is missing.
+    MissingScanner { grammar: String, path: PathBuf },
+    /// The Tree Sitter generator rejected a grammar, such as for an unresolved conflict.
+    Generate { grammar: String, message: String },
+    /// The C compiler failed to compile the parser of a grammar.
+    Compile { grammar: String, message: String },
+    /// An artifact could not be written.
+    Io(std::io::Error),
+}
+
+#[cfg(feature = "build_parsers")]
+impl std::fmt::Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildError::MissingScanner { grammar, path } => write!(
+                f,
+                "The grammar `{grammar}` declares external tokens, but its external scanner was not found at `{}`. A stub can be generated with `rust_sitter_tool::generate_scanner_stubs`",
+                path.display()
+            ),
+            BuildError::Generate { grammar, message } => write!(
+                f,
+                "Tree Sitter could not generate a parser for the grammar `{grammar}`: {message}"
+            ),
+            BuildError::Compile { grammar, message } => {
+                write!(f, "The parser of the grammar `{grammar}` failed to compile: {message}")
+            }
+            BuildError::Io(e) => write!(f, "Failed to write the parser: {e}"),
+        }
+    }
+}
+
+#[cfg(feature = "build_parsers")]
+impl std::error::Error for BuildError {}
+
+#[cfg(feature = "build_parsers")]
+impl From<std::io::Error> for BuildError {
+    fn from(e: std::io::Error) -> Self {
+        BuildError::Io(e)
+    }
+}
+
+#[cfg(feature = "build_parsers")]
+/// Finds the external scanner of a grammar module that declares external tokens, which is
+/// read from `<grammar name>_scanner.c` next to the root file.
+fn find_scanner_file(root_file: &Path, module: &ItemMod) -> Result<Option<PathBuf>, BuildError> {
+    if find_externals(module).is_empty() {
+        return Ok(None);
+    }
+
+    let grammar = find_grammar_name(module).unwrap();
+    let scanner_file = root_file
+        .parent()
+        .unwrap()
+        .join(format!("{grammar}_scanner.c"));
+    if !scanner_file.exists() {
+        return Err(BuildError::MissingScanner {
+            grammar,
+            path: scanner_file,
+        });
+    }
+
+    println!("cargo:rerun-if-changed={}", scanner_file.display());
+    Ok(Some(std::fs::canonicalize(scanner_file)?))
+}
+
+#[cfg(feature = "build_parsers")]
+/// Generates the C parser of a grammar into `src_dir`, along with the header it includes,
+/// and compiles it with the external scanner of its grammar module, if it has one, into a
+/// static library named after the grammar in `out_dir`, or in `OUT_DIR` if none is given.
+fn compile_parser(
+    grammar: &Value,
+    main_grammar_name: &str,
+    scanner_file: Option<&Path>,
+    src_dir: &Path,
+    out_dir: Option<&Path>,
+) -> Result<(), BuildError> {
+    use std::env;
+
+    let (grammar_name, grammar_c) = generate::generate_parser_for_grammar(&grammar.to_string())
+        .map_err(|e| BuildError::Generate {
+            grammar: grammar["name"].as_str().unwrap().to_string(),
+            message: e.to_string(),
+        })?;
+    std::fs::write(
+        src_dir.join("parser.c"),
+        format!("// {GENERATED_MARKER}\n{grammar_c}"),
+    )?;
+
+    let header_dir = src_dir.join("tree_sitter");
+    std::fs::create_dir_all(&header_dir)?;
+    let mut parser_file = std::fs::File::create(header_dir.join("parser.h"))?;
+    parser_file.write_all(tree_sitter::PARSER_HEADER.as_bytes())?;
+    drop(parser_file);
+
+    let sysroot_dir = src_dir.join("sysroot");
+    if env::var("TARGET")
+        .map(|target| target.starts_with("wasm32"))
+        .unwrap_or(false)
+    {
+        std::fs::create_dir_all(&sysroot_dir)?;
+        let mut stdint = std::fs::File::create(sysroot_dir.join("stdint.h"))?;
+        stdint.write_all(include_bytes!("wasm-sysroot/stdint.h"))?;
+        drop(stdint);
+
+        let mut stdlib = std::fs::File::create(sysroot_dir.join("stdlib.h"))?;
+        stdlib.write_all(include_bytes!("wasm-sysroot/stdlib.h"))?;
+        drop(stdlib);
+
+        let mut stdio = std::fs::File::create(sysroot_dir.join("stdio.h"))?;
+        stdio.write_all(include_bytes!("wasm-sysroot/stdio.h"))?;
+        drop(stdio);
+
+        let mut stdbool = std::fs::File::create(sysroot_dir.join("stdbool.h"))?;
+        stdbool.write_all(include_bytes!("wasm-sysroot/stdbool.h"))?;
+        drop(stdbool);
+    }
+
+    let mut build = cc::Build::new();
+    build
+        .include(src_dir)
+        .include(&sysroot_dir)
+        .flag_if_supported("-Wno-everthing")
+        .file(src_dir.join("parser.c"));
+    if let Some(out_dir) = out_dir {
+        build.out_dir(out_dir);
+    }
+
+    if let Some(scanner_file) = scanner_file {
+        if grammar_name == main_grammar_name {
+            build.file(scanner_file);
+        } else {
+            let entry_scanner_file = src_dir.join("scanner.c");
+            std::fs::write(
+                &entry_scanner_file,
+                format!(
+                    "// {GENERATED_MARKER}\n{}",
+                    generate_entry_scanner(
+                        main_grammar_name,
+                        &grammar_name,
+                        scanner_file.to_str().unwrap(),
+                    )
+                ),
+            )?;
+            build.file(entry_scanner_file);
+        }
+    }
+
+    build
+        .try_compile(&grammar_name)
+        .map_err(|e| BuildError::Compile {
+            grammar: grammar_name,
+            message: e.to_string(),
+        })
+}
+
+#[cfg(feature = "build_parsers")]
+/// Like [`build_parsers`], but keeps the artifacts of each grammar in the given directory
+/// and returns errors instead of panicking. For each grammar, `<grammar name>/src` in the
+/// directory gets the `grammar.json` along with the `parser.c` generated from it and the
+/// `tree_sitter/parser.h` header it includes, following the layout of Tree Sitter grammar
+/// repositories, where the grammar and the parser start with [`GENERATED_MARKER`], and the
+/// parser is compiled into a static library named after the grammar
+/// in the directory itself, which the crate of the build script links. Returns the `src`
+/// directory of each grammar.
+///
+/// The parsers are generated by the generator bundled with Rust Sitter, so the
+/// `tree-sitter` CLI does not need to be installed. Like `cc`, this expects the environment
+/// of a build script, such as the `TARGET` and `OPT_LEVEL` variables set by Cargo, and
+/// reports their absence as a [`BuildError::Compile`].
+pub fn build_parser(root_file: &Path, out_dir: &Path) -> Result<Vec<PathBuf>, BuildError> {
+    let modules = find_grammar_modules(root_file);
+    let mut src_dirs = vec![];
+    for (_, module) in &modules {
+        let main_grammar_name = find_grammar_name(module).unwrap();
+        let scanner_file = find_scanner_file(root_file, module)?;
+        let (grammars, warnings) = generate_module_grammars_with_warnings(module, &modules);
+        print_cargo_warnings(&warnings);
+        for grammar in grammars {
+            let src_dir = out_dir.join(grammar["name"].as_str().unwrap()).join("src");
+            std::fs::create_dir_all(&src_dir)?;
+            std::fs::write(
+                src_dir.join("grammar.json"),
+                mark_generated(grammar.clone()).to_string(),
+            )?;
+            compile_parser(
+                &grammar,
+                &main_grammar_name,
+                scanner_file.as_deref(),
+                &src_dir,
+                Some(out_dir),
+            )?;
+            src_dirs.push(src_dir);
+        }
+    }
+
+    Ok(src_dirs)
+}
+
 #[cfg(feature = "build_parsers")]
 /// Using the `cc` crate, generates and compiles a C parser with Tree Sitter
 /// for every Rust Sitter grammar found in the given module and recursive
 /// submodules.
+///
+/// The editor metadata of each grammar (see [`generate_metadata_files`]) is
+/// written to `$OUT_DIR/<grammar name>.metadata.json`, along with its `locals.scm`
+/// query, if it has one (see [`generate_locals_queries`]), to
+/// `$OUT_DIR/<grammar name>.locals.scm`, and its `highlights.scm` query, if it has one
+/// (see [`generate_highlights_queries`]), to `$OUT_DIR/<grammar name>.highlights.scm`.
+/// The named node types of each grammar, with whether each one is terminal (see
+/// [`generate_node_types_files`]), are written to `$OUT_DIR/<grammar name>.node-types.json`.
+/// Grammars whose root type is marked with `#[rust_sitter::serde]` also have the JSON
+/// Schema of their serialized AST (see [`generate_json_schemas`]) written to
+/// `$OUT_DIR/<grammar name>.schema.json`.
+///
+/// The warnings about each grammar (see [`generate_grammars_with_warnings`]) are printed
+/// for Cargo to show.
+///
+/// Grammars with external tokens are compiled along with their external scanner,
+/// which is read from `<grammar name>_scanner.c` next to the root file (see
+/// [`generate_scanner_stubs`]).
+///
+/// With the `check_conflicts` feature, the declared conflicts of each grammar that the
+/// `tree-sitter` CLI reports as unnecessary are printed as warnings (see
+/// [`find_unused_conflicts`]).
+///
+/// To keep the generated parsers, or to handle failures instead of panicking, use
+/// [`build_parser`].
 pub fn build_parsers(root_file: &Path) {
     use std::env;
 
-    generate_grammars(root_file).iter().for_each(|grammar| {
-        let dir = tempfile::Builder::new()
-            .prefix("grammar")
-            .tempdir()
-            .unwrap();
-        let grammar_file = dir.path().join("parser.c");
-        let mut f = std::fs::File::create(grammar_file).unwrap();
-
-        let (grammar_name, grammar_c) = generate::generate_parser_for_grammar(grammar).unwrap();
-        f.write_all(grammar_c.as_bytes()).unwrap();
-        drop(f);
-
-        let header_dir = dir.path().join("tree_sitter");
-        std::fs::create_dir(&header_dir).unwrap();
-        let mut parser_file = std::fs::File::create(header_dir.join("parser.h")).unwrap();
-        parser_file
-            .write_all(tree_sitter::PARSER_HEADER.as_bytes())
-            .unwrap();
-        drop(parser_file);
-
-        let sysroot_dir = dir.path().join("sysroot");
-        if env::var("TARGET").unwrap().starts_with("wasm32") {
-            std::fs::create_dir(&sysroot_dir).unwrap();
-            let mut stdint = std::fs::File::create(sysroot_dir.join("stdint.h")).unwrap();
-            stdint
-                .write_all(include_bytes!("wasm-sysroot/stdint.h"))
-                .unwrap();
-            drop(stdint);
+    let modules = find_grammar_modules(root_file);
+    modules.iter().for_each(|(_, module)| {
+        let main_grammar_name = find_grammar_name(module).unwrap();
+        let scanner_file = find_scanner_file(root_file, module).unwrap_or_else(|e| panic!("{e}"));
 
-            let mut stdlib = std::fs::File::create(sysroot_dir.join("stdlib.h")).unwrap();
-            stdlib
-                .write_all(include_bytes!("wasm-sysroot/stdlib.h"))
-                .unwrap();
-            drop(stdlib);
+        let metadata = generate_metadata(module);
+        let metadata_file = Path::new(&env::var("OUT_DIR").unwrap()).join(format!(
+            "{}.metadata.json",
+            metadata["name"].as_str().unwrap()
+        ));
+        std::fs::write(metadata_file, metadata.to_string()).unwrap();
 
-            let mut stdio = std::fs::File::create(sysroot_dir.join("stdio.h")).unwrap();
-            stdio
-                .write_all(include_bytes!("wasm-sysroot/stdio.h"))
-                .unwrap();
-            drop(stdio);
+        if let Some(locals) = generate_locals(module) {
+            let locals_file = Path::new(&env::var("OUT_DIR").unwrap())
+                .join(format!("{main_grammar_name}.locals.scm"));
+            std::fs::write(locals_file, locals).unwrap();
+        }
 
-            let mut stdbool = std::fs::File::create(sysroot_dir.join("stdbool.h")).unwrap();
-            stdbool
-                .write_all(include_bytes!("wasm-sysroot/stdbool.h"))
-                .unwrap();
-            drop(stdbool);
+        if let Some(schema) = generate_json_schema(module) {
+            let schema_file = Path::new(&env::var("OUT_DIR").unwrap())
+                .join(format!("{main_grammar_name}.schema.json"));
+            std::fs::write(schema_file, serde_json::to_string_pretty(&schema).unwrap()).unwrap();
         }
 
-        cc::Build::new()
-            .include(&dir)
-            .include(&sysroot_dir)
-            .flag_if_supported("-Wno-everthing")
-            .file(dir.path().join("parser.c"))
-            .compile(&grammar_name);
+        // the highlights query captures the rules of the main grammar, which comes first
+        let (grammars, warnings) = generate_module_grammars_with_warnings(module, &modules);
+        print_cargo_warnings(&warnings);
+        let mut grammars = grammars.peekable();
+        if let Some(highlights) = grammars
+            .peek()
+            .and_then(|grammar| generate_highlights(module, grammar))
+        {
+            let highlights_file = Path::new(&env::var("OUT_DIR").unwrap())
+                .join(format!("{main_grammar_name}.highlights.scm"));
+            std::fs::write(highlights_file, highlights).unwrap();
+        }
+
+        grammars.for_each(|grammar| {
+            #[cfg(feature = "check_conflicts")]
+            find_unused_conflicts(&grammar)
+                .unwrap_or_default()
+                .iter()
+                .for_each(|group| {
+                    println!(
+                        "cargo:warning=The conflict between `{}` in the grammar `{}` is never needed, so it can be removed from `conflicts`",
+                        group.join("`, `"),
+                        grammar["name"].as_str().unwrap()
+                    );
+                });
+
+            let node_types_file = Path::new(&env::var("OUT_DIR").unwrap()).join(format!(
+                "{}.node-types.json",
+                grammar["name"].as_str().unwrap()
+            ));
+            std::fs::write(node_types_file, generate_node_types(&grammar).to_string()).unwrap();
+
+            let dir = tempfile::Builder::new()
+                .prefix("grammar")
+                .tempdir()
+                .unwrap();
+            compile_parser(
+                &grammar,
+                &main_grammar_name,
+                scanner_file.as_deref(),
+                dir.path(),
+                None,
+            )
+            .unwrap_or_else(|e| panic!("{e}"));
+        });
     });
 }
 
 #[cfg(test)]
 mod tests {
+    use std::fs;
+
     use syn::parse_quote;
 
-    use super::generate_grammar;
+    use super::{
+        blank, default_empty_warnings, diff_grammars, dominated_variant_warnings, dump_grammars,
+        error_only_alternative_warnings, find_undeclared_conflicts, generate_bindings,
+        generate_entry_grammars, generate_from_crate, generate_grammar, generate_grammar_js,
+        generate_grammar_named, generate_grammar_with_warnings, generate_grammars,
+        generate_grammars_combined, generate_grammars_from_file, generate_highlights,
+        generate_if_changed, generate_json_schema, generate_locals, generate_metadata,
+        generate_metadata_files, generate_node_types, generate_scanner_stub,
+        inconsistent_alias_warnings, mark_generated, optional, parse_unused_conflicts,
+        write_grammars, GrammarStats, GENERATED_MARKER, LARGE_CHOICE_ARITY,
+    };
+
+    const FIXTURE_GRAMMAR: &str = r#"
+        #[rust_sitter::grammar("fixture")]
+        pub mod grammar {
+            #[rust_sitter::language]
+            pub struct Number {
+                #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                pub value: u32,
+            }
+        }
+    "#;
+
+    #[test]
+    fn generate_from_crate_lib() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"fixture\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        fs::create_dir_all(dir.path().join("src/grammars")).unwrap();
+        fs::write(
+            dir.path().join("src/lib.rs"),
+            "#[path = \"grammars/number.rs\"]\npub mod number;\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("src/grammars/number.rs"), FIXTURE_GRAMMAR).unwrap();
+        // the library is the root of the crate, so the grammars of the binary are not used
+        fs::write(
+            dir.path().join("src/main.rs"),
+            FIXTURE_GRAMMAR.replace("fixture", "unused"),
+        )
+        .unwrap();
+
+        let grammars = generate_from_crate(dir.path()).unwrap();
+        assert_eq!(grammars.len(), 1);
+        assert_eq!(
+            grammars[0],
+            generate_grammars(&dir.path().join("src/grammars/number.rs"))[0]
+        );
+    }
+
+    #[test]
+    fn write_grammars_marked() {
+        let dir = tempfile::tempdir().unwrap();
+        let root_file = dir.path().join("lib.rs");
+        fs::write(&root_file, FIXTURE_GRAMMAR).unwrap();
+
+        let paths = write_grammars(&root_file, dir.path()).unwrap();
+        let grammar = fs::read_to_string(&paths[0]).unwrap();
+        assert!(grammar.starts_with(r#"{"$comment":"@generated by rust-sitter","#));
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar).unwrap();
+    }
+
+    #[test]
+    fn generate_if_changed_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let root_file = dir.path().join("lib.rs");
+        let out_dir = dir.path().join("out");
+        fs::create_dir_all(&out_dir).unwrap();
+        fs::write(&root_file, FIXTURE_GRAMMAR).unwrap();
+
+        assert!(generate_if_changed(&root_file, &out_dir).unwrap());
+        let grammar_file = out_dir.join("fixture.json");
+        assert_eq!(
+            fs::read_to_string(&grammar_file).unwrap(),
+            mark_generated(serde_json::from_str(&generate_grammars(&root_file)[0]).unwrap())
+                .to_string()
+        );
+
+        // comments are not part of the hashed source
+        fs::write(&root_file, format!("// a comment\n{FIXTURE_GRAMMAR}")).unwrap();
+        fs::write(&grammar_file, "stale").unwrap();
+        assert!(!generate_if_changed(&root_file, &out_dir).unwrap());
+        assert_eq!(fs::read_to_string(&grammar_file).unwrap(), "stale");
+    }
+
+    #[test]
+    fn generate_if_changed_invalidated() {
+        let dir = tempfile::tempdir().unwrap();
+        let root_file = dir.path().join("lib.rs");
+        let out_dir = dir.path().join("out");
+        fs::create_dir_all(&out_dir).unwrap();
+        fs::write(&root_file, FIXTURE_GRAMMAR).unwrap();
+        assert!(generate_if_changed(&root_file, &out_dir).unwrap());
+
+        fs::write(&root_file, FIXTURE_GRAMMAR.replace(r"\d+", r"[0-9]+")).unwrap();
+        assert!(generate_if_changed(&root_file, &out_dir).unwrap());
+        assert!(fs::read_to_string(out_dir.join("fixture.json"))
+            .unwrap()
+            .contains("[0-9]+"));
+        assert!(!generate_if_changed(&root_file, &out_dir).unwrap());
+
+        // a missing grammar file is written again
+        fs::remove_file(out_dir.join("fixture.json")).unwrap();
+        assert!(generate_if_changed(&root_file, &out_dir).unwrap());
+        assert!(out_dir.join("fixture.json").exists());
+    }
+
+    #[test]
+    fn generate_bindings_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = generate_bindings("my_lang", dir.path()).unwrap();
+        assert_eq!(paths.len(), 7);
+
+        let lib_rs = fs::read_to_string(dir.path().join("bindings/rust/lib.rs")).unwrap();
+        assert!(lib_rs.contains("fn tree_sitter_my_lang() -> Language;"));
+        assert!(lib_rs.contains("unsafe { tree_sitter_my_lang() }"));
+        let build_rs = fs::read_to_string(dir.path().join("bindings/rust/build.rs")).unwrap();
+        assert!(build_rs.contains(r#"c_config.compile("tree-sitter-my_lang");"#));
+        let cargo_toml = fs::read_to_string(dir.path().join("Cargo.toml")).unwrap();
+        assert!(cargo_toml.contains(r#"name = "tree-sitter-my-lang""#));
+        let binding_cc = fs::read_to_string(dir.path().join("bindings/node/binding.cc")).unwrap();
+        assert!(binding_cc.contains("NODE_MODULE(tree_sitter_my_lang_binding, Init)"));
+        let package_json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(dir.path().join("package.json")).unwrap())
+                .unwrap();
+        assert_eq!(package_json["name"], "tree-sitter-my-lang");
+
+        // existing files are kept
+        fs::write(dir.path().join("package.json"), "{}").unwrap();
+        fs::remove_file(dir.path().join("Cargo.toml")).unwrap();
+        assert_eq!(
+            generate_bindings("my_lang", dir.path()).unwrap(),
+            vec![dir.path().join("Cargo.toml")]
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("package.json")).unwrap(),
+            "{}"
+        );
+    }
+
+    #[test]
+    fn generate_from_crate_custom_roots() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"fixture\"\nversion = \"0.1.0\"\n\n[[bin]]\nname = \"tool\"\npath = \"bin/tool.rs\"\n",
+        )
+        .unwrap();
+        fs::create_dir_all(dir.path().join("bin")).unwrap();
+        fs::write(dir.path().join("bin/tool.rs"), FIXTURE_GRAMMAR).unwrap();
+
+        let grammars = generate_from_crate(dir.path()).unwrap();
+        assert_eq!(grammars.len(), 1);
+        assert!(grammars[0].contains("\"name\":\"fixture\""));
+
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"fixture\"\nversion = \"0.1.0\"\n\n[lib]\npath = \"missing.rs\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            generate_from_crate(dir.path()).unwrap_err().kind(),
+            std::io::ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn grammars_from_expanded_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = r#"
+            macro_rules! number_grammar {
+                ($name:literal) => {
+                    #[rust_sitter::grammar($name)]
+                    pub mod grammar {
+                        #[rust_sitter::language]
+                        pub struct Number {
+                            #[rust_sitter::leaf(pattern = r"\d+")]
+                            _value: (),
+                        }
+                    }
+                };
+            }
+
+            number_grammar!("numbers");
+        "#;
+        fs::write(dir.path().join("main.rs"), source).unwrap();
+
+        // the grammar module only exists once the macro is expanded
+        assert!(generate_grammars(&dir.path().join("main.rs")).is_empty());
+
+        let expanded: syn::File = syn::parse2(quote::quote! {
+            #[rust_sitter::grammar("numbers")]
+            pub mod grammar {
+                #[rust_sitter::language]
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+")]
+                    _value: (),
+                }
+            }
+        })
+        .unwrap();
+        let grammars = generate_grammars_from_file(&expanded);
+        assert_eq!(grammars.len(), 1);
+        assert!(grammars[0].contains(r#""name":"numbers""#));
+    }
+
+    #[test]
+    fn grammars_combined() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("main.rs"),
+            r#"
+            #[rust_sitter::grammar("numbers")]
+            pub mod numbers {
+                #[rust_sitter::language]
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+")]
+                    _value: (),
+                }
+            }
+
+            #[rust_sitter::grammar("words")]
+            pub mod words {
+                #[rust_sitter::language]
+                pub struct Word {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                    _value: (),
+                }
+            }
+            "#,
+        )
+        .unwrap();
+
+        let combined: serde_json::Value =
+            serde_json::from_str(&generate_grammars_combined(&dir.path().join("main.rs"))).unwrap();
+        let grammars = combined["grammars"].as_object().unwrap();
+        assert_eq!(
+            grammars.keys().collect::<Vec<_>>(),
+            vec!["numbers", "words"]
+        );
+
+        // each grammar is the same as when it is generated on its own
+        let all = generate_grammars(&dir.path().join("main.rs"));
+        assert_eq!(grammars["numbers"].to_string(), all[0]);
+        assert_eq!(grammars["words"].to_string(), all[1]);
+    }
+
+    #[test]
+    fn grammar_named() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("main.rs"),
+            r#"
+            #[rust_sitter::grammar("numbers")]
+            pub mod numbers {
+                #[rust_sitter::language]
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+")]
+                    _value: (),
+                }
+            }
+
+            #[rust_sitter::grammar("words")]
+            pub mod word_grammar {
+                #[rust_sitter::language]
+                pub struct Word {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                    _value: (),
+                }
+            }
+            "#,
+        )
+        .unwrap();
+
+        let all = generate_grammars(&dir.path().join("main.rs"));
+        assert_eq!(all.len(), 2);
+
+        let words = generate_grammar_named(&dir.path().join("main.rs"), "words").unwrap();
+        assert_eq!(words, all[1]);
+        assert!(!words.contains("numbers"));
+
+        // the ident of the module also selects its grammar
+        assert_eq!(
+            generate_grammar_named(&dir.path().join("main.rs"), "word_grammar"),
+            Some(all[1].clone())
+        );
+        assert!(generate_grammar_named(&dir.path().join("main.rs"), "letters").is_none());
+    }
+
+    #[test]
+    fn grammar_use_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("main.rs"),
+            r#"
+            pub mod identifiers {
+                #[rust_sitter::grammar("identifiers")]
+                pub mod grammar {
+                    #[rust_sitter::language]
+                    pub enum Atom {
+                        Identifier(Identifier),
+                        Number(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] i32),
+                    }
+
+                    pub struct Identifier {
+                        #[rust_sitter::leaf(pattern = r"[a-z_]+", transform = String::from)]
+                        pub name: String,
+                    }
+
+                    #[rust_sitter::extra]
+                    struct Whitespace {
+                        #[rust_sitter::leaf(pattern = r"\s")]
+                        _whitespace: (),
+                    }
+                }
+            }
+
+            #[rust_sitter::grammar("assignments")]
+            #[rust_sitter::use_rules(crate::identifiers::grammar::*)]
+            pub mod grammar {
+                use crate::identifiers::grammar::{Atom, Identifier};
+
+                #[rust_sitter::language]
+                pub struct Assignment {
+                    pub target: Identifier,
+                    #[rust_sitter::leaf(text = "=")]
+                    _eq: (),
+                    pub value: Atom,
+                }
+
+                #[rust_sitter::extra]
+                struct Whitespace {
+                    #[rust_sitter::leaf(pattern = r"\s")]
+                    _whitespace: (),
+                }
+            }
+            "#,
+        )
+        .unwrap();
+
+        let grammars = generate_grammars(&dir.path().join("main.rs"));
+        assert_eq!(grammars.len(), 2);
+        let grammar: serde_json::Value = serde_json::from_str(&grammars[1]).unwrap();
+        let rules = grammar["rules"].as_object().unwrap();
+
+        // imported rules are renamed, but are aliased back to their original kinds
+        assert_eq!(
+            rules["Assignment"]["members"][0]["content"],
+            serde_json::json!({
+                "type": "ALIAS",
+                "content": { "type": "SYMBOL", "name": "identifiers_Identifier" },
+                "named": true,
+                "value": "Identifier"
+            })
+        );
+        assert!(rules.contains_key("identifiers_Atom_Number_0"));
+        // the local rule is kept, and the unused imported extra is left out
+        assert!(rules.contains_key("Whitespace"));
+        assert!(!rules.contains_key("identifiers_Whitespace"));
+        assert!(!rules.contains_key("Identifier"));
+
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammars[1]).unwrap();
+    }
+
+    #[test]
+    fn shared_module_generated_once() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("main.rs"), "mod a;\nmod b;\n").unwrap();
+        fs::create_dir(dir.path().join("a")).unwrap();
+        fs::write(
+            dir.path().join("a/mod.rs"),
+            "#[path = \"../shared.rs\"]\nmod shared;\n",
+        )
+        .unwrap();
+        fs::create_dir(dir.path().join("b")).unwrap();
+        fs::write(
+            dir.path().join("b/mod.rs"),
+            "#[path = \"../shared.rs\"]\nmod shared;\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("shared.rs"),
+            r#"
+            #[rust_sitter::grammar("shared")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    v: i32,
+                }
+            }
+            "#,
+        )
+        .unwrap();
+
+        let grammars = generate_grammars(&dir.path().join("main.rs"));
+        assert_eq!(grammars.len(), 1);
+        assert_eq!(
+            generate_metadata_files(&dir.path().join("main.rs")).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn dump_grammars_to_buffer() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("main.rs"),
+            r#"
+            #[rust_sitter::grammar("dumped")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    v: i32,
+                }
+            }
+            "#,
+        )
+        .unwrap();
+
+        let mut buffer = vec![];
+        dump_grammars(&dir.path().join("main.rs"), &mut buffer).unwrap();
+        let dumped = String::from_utf8(buffer).unwrap();
+
+        assert!(dumped.starts_with("{\n  \"name\": \"dumped\","));
+        assert!(dumped.ends_with("}\n"));
+        let grammar: serde_json::Value = serde_json::from_str(&dumped).unwrap();
+        assert_eq!(
+            grammar.to_string(),
+            generate_grammars(&dir.path().join("main.rs"))[0]
+        );
+    }
+
+    #[test]
+    fn enum_with_named_field() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expr {
+                    Number(
+                            #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                            u32
+                    ),
+                    Neg {
+                        #[rust_sitter::leaf(text = "!")]
+                        _bang: (),
+                        value: Box<Expr>,
+                    }
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn enum_transformed_fields() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expression {
+                    Number(
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v: &str| v.parse::<i32>().unwrap())]
+                        i32
+                    ),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn enum_recursive() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expression {
+                    Number(
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v: &str| v.parse::<i32>().unwrap())]
+                        i32
+                    ),
+                    Neg(
+                        #[rust_sitter::leaf(text = "-", transform = |v| ())]
+                        (),
+                        Box<Expression>
+                    ),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn enum_leaf_variants() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Value {
+                    #[rust_sitter::leaf(text = "true")]
+                    True,
+                    #[rust_sitter::leaf(pattern = r"nil|null")]
+                    Null,
+                    Number(
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                        u32
+                    ),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn enum_mixed_arity_variants() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Command {
+                    Reset,
+                    Clear(),
+                    Skip {},
+                    Ignored(#[rust_sitter::skip(0)] u32),
+                    Goto(
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                        u32
+                    ),
+                    Move {
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                        x: u32,
+                        #[rust_sitter::leaf(text = ",")]
+                        _comma: (),
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                        y: u32,
+                    },
+                }
+
+                #[rust_sitter::extra]
+                struct Whitespace {
+                    #[rust_sitter::leaf(pattern = r"\s")]
+                    _whitespace: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn enum_text_arrays() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Item {
+                    Function(
+                        #[rust_sitter::leaf(text = ["fn", "function"], transform = |v| v.to_string())]
+                        String,
+                        #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+                        String,
+                    ),
+                    #[rust_sitter::leaf(text = ["->", "→"])]
+                    Arrow,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_case_insensitive_keywords() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            #[rust_sitter::case_insensitive_keywords]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Statement {
+                    Select(
+                        #[rust_sitter::leaf(text = "select")]
+                        (),
+                        #[rust_sitter::leaf(pattern = r"[a-z_]+\d?", case_insensitive = true, transform = |v| v.to_string())]
+                        String,
+                        #[rust_sitter::leaf(text = ";")]
+                        (),
+                    ),
+                    #[rust_sitter::leaf(text = ["end", "end-all"])]
+                    End,
+                    Raw(
+                        #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+                        String,
+                    ),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_infer_word() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            #[rust_sitter::infer_word]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Statement {
+                    Let(
+                        #[rust_sitter::leaf(text = "let")]
+                        (),
+                        #[rust_sitter::leaf(pattern = r"[a-zA-Z_][a-zA-Z0-9_]*", transform = |v| v.to_string())]
+                        String,
+                    ),
+                    Number(
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                        u32,
+                    ),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        assert_eq!(grammar["word"], "Statement_Let_1");
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_infer_word_ambiguous() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            #[rust_sitter::infer_word]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Name {
+                    Lower(
+                        #[rust_sitter::leaf(pattern = r"[a-z_][a-z0-9_]*", transform = |v| v.to_string())]
+                        String,
+                    ),
+                    Upper(
+                        #[rust_sitter::leaf(pattern = r"\$[A-Z_][A-Z0-9_]*", transform = |v| v.to_string())]
+                        String,
+                    ),
+                    Label(
+                        #[rust_sitter::leaf(pattern = r"[A-Z][A-Za-z0-9]*", transform = |v| v.to_string())]
+                        String,
+                    ),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        assert_eq!(grammar["word"], serde_json::Value::Null);
+    }
+
+    #[test]
+    #[should_panic(expected = "can only be placed on unit variants")]
+    fn enum_leaf_variant_with_fields() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Value {
+                    #[rust_sitter::leaf(text = "true")]
+                    True(u32),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        generate_grammar(&m);
+    }
+
+    #[test]
+    fn enum_prec_left() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expression {
+                    Number(
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v: &str| v.parse::<i32>().unwrap())]
+                        i32
+                    ),
+                    #[rust_sitter::prec_left(1)]
+                    Sub(
+                        Box<Expression>,
+                        #[rust_sitter::leaf(text = "-", transform = |v| ())]
+                        (),
+                        Box<Expression>
+                    ),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn enum_negative_prec() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expression {
+                    Number(
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v: &str| v.parse::<i32>().unwrap())]
+                        i32
+                    ),
+                    #[rust_sitter::prec_left(-2)]
+                    Sub(
+                        Box<Expression>,
+                        #[rust_sitter::leaf(text = "-")]
+                        (),
+                        Box<Expression>
+                    ),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        assert_eq!(
+            grammar["rules"]["Expression_Sub"]["value"],
+            serde_json::json!(-2)
+        );
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected an integer literal for `prec_left`, but found `1.5`")]
+    fn enum_float_prec() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expression {
+                    Number(
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v: &str| v.parse::<i32>().unwrap())]
+                        i32
+                    ),
+                    #[rust_sitter::prec_left(1.5)]
+                    Sub(
+                        Box<Expression>,
+                        #[rust_sitter::leaf(text = "-")]
+                        (),
+                        Box<Expression>
+                    ),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        generate_grammar(&m);
+    }
+
+    #[test]
+    #[should_panic(expected = "The precedence `4294967296` given for `prec` is out of range")]
+    fn enum_out_of_range_prec() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expression {
+                    Number(
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v: &str| v.parse::<i32>().unwrap())]
+                        i32
+                    ),
+                    #[rust_sitter::prec(4294967296)]
+                    Sub(
+                        Box<Expression>,
+                        #[rust_sitter::leaf(text = "-")]
+                        (),
+                        Box<Expression>
+                    ),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        generate_grammar(&m);
+    }
+
+    #[test]
+    fn enum_level_prec() {
+        let with_prec = |prec: Option<syn::Attribute>| {
+            let prec = prec.into_iter();
+            let m = if let syn::Item::Mod(m) = parse_quote! {
+                #[rust_sitter::grammar("test")]
+                mod grammar {
+                    #[rust_sitter::language]
+                    pub struct Program {
+                        items: Vec<Item>,
+                    }
+
+                    pub enum Item {
+                        Expr(Expr),
+                        Pattern(Pattern),
+                    }
+
+                    #(#prec)*
+                    pub enum Expr {
+                        Ident(Ident),
+                        Neg(#[rust_sitter::leaf(text = "-")] (), Ident),
+                    }
+
+                    pub enum Pattern {
+                        Ident(Ident),
+                    }
+
+                    pub struct Ident {
+                        #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                        _name: (),
+                    }
+                }
+            } {
+                m
+            } else {
+                panic!()
+            };
+
+            generate_grammar(&m)
+        };
+
+        // both enums can be a lone identifier, which Tree Sitter cannot decide between
+        assert!(tree_sitter_cli::generate::generate_parser_for_grammar(
+            &with_prec(None).to_string()
+        )
+        .is_err());
+
+        let grammar = with_prec(Some(parse_quote!(#[rust_sitter::prec(1)])));
+        assert_eq!(grammar["rules"]["Expr"]["type"], "PREC");
+        assert_eq!(grammar["rules"]["Expr_Ident"]["type"], "PREC");
+        assert_eq!(grammar["rules"]["Pattern_Ident"]["type"], "SEQ");
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn named_precedences() {
+        let with_ordering = |ordering: syn::Attribute| {
+            let m = if let syn::Item::Mod(m) = parse_quote! {
+                #[rust_sitter::grammar("test")]
+                #ordering
+                mod grammar {
+                    #[rust_sitter::language]
+                    pub enum Expr {
+                        Number(
+                            #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                            u32
+                        ),
+                        #[rust_sitter::prec_left("unary")]
+                        Neg(#[rust_sitter::leaf(text = "-")] (), Box<Expr>),
+                        #[rust_sitter::prec_left("binary")]
+                        Sub(Box<Expr>, #[rust_sitter::leaf(text = "-")] (), Box<Expr>),
+                        #[rust_sitter::prec_left("postfix")]
+                        Fact(Box<Expr>, #[rust_sitter::leaf(text = "!")] ()),
+                    }
+                }
+            } {
+                m
+            } else {
+                panic!()
+            };
+
+            generate_grammar(&m)
+        };
+
+        let grammar = with_ordering(
+            parse_quote!(#[rust_sitter::precedences(["postfix" > "unary" > "binary"])]),
+        );
+        assert_eq!(grammar["rules"]["Expr_Neg"]["value"], "unary");
+        assert_eq!(
+            grammar["precedences"],
+            serde_json::json!([[
+                { "type": "STRING", "value": "postfix" },
+                { "type": "STRING", "value": "unary" },
+                { "type": "STRING", "value": "binary" }
+            ]])
+        );
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+
+        let reversed = with_ordering(
+            parse_quote!(#[rust_sitter::precedences(["binary" > "unary" > "postfix"])]),
+        );
+        assert_eq!(
+            reversed["precedences"],
+            serde_json::json!([[
+                { "type": "STRING", "value": "binary" },
+                { "type": "STRING", "value": "unary" },
+                { "type": "STRING", "value": "postfix" }
+            ]])
+        );
+        tree_sitter_cli::generate::generate_parser_for_grammar(&reversed.to_string()).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "The precedence `unary` appears twice in the same ordering")]
+    fn named_precedences_repeated() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            #[rust_sitter::precedences(["unary" > "binary" > "unary"])]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    v: i32,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        generate_grammar(&m);
+    }
+
+    #[test]
+    fn field_alias_as() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Let {
+                    #[rust_sitter::leaf(text = "let")]
+                    _let: (),
+                    #[rust_sitter::alias_as("variable", named = true)]
+                    name: Ident,
+                    #[rust_sitter::leaf(text = ":")]
+                    _colon: (),
+                    #[rust_sitter::alias_as("type_identifier", named = true)]
+                    ty: Option<Ident>,
+                    value: Ident,
+                }
+
+                pub struct Ident {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                    _name: (),
+                }
+
+                #[rust_sitter::extra]
+                struct Whitespace {
+                    #[rust_sitter::leaf(pattern = r"\s")]
+                    _whitespace: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        // the rule itself keeps its name, and is only aliased where it is used
+        assert!(grammar["rules"]["Ident"].is_object());
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn enum_same_type_variants() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expression {
+                    Number(
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v: &str| v.parse::<i32>().unwrap())]
+                        i32
+                    ),
+                    Paren(
+                        #[rust_sitter::leaf(text = "(")]
+                        (),
+                        Box<Expression>,
+                        #[rust_sitter::leaf(text = ")")]
+                        (),
+                    ),
+                    Group(
+                        #[rust_sitter::leaf(text = "[")]
+                        (),
+                        Box<Expression>,
+                        #[rust_sitter::leaf(text = "]")]
+                        (),
+                    ),
+                    #[rust_sitter::prec_left(1)]
+                    Pair(
+                        Box<Expression>,
+                        #[rust_sitter::leaf(text = ",")]
+                        (),
+                        Box<Expression>
+                    ),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn enum_reordered_same_type_fields() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expression {
+                    Range(
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v: &str| v.parse::<i32>().unwrap())]
+                        i32,
+                        #[rust_sitter::leaf(text = "..")]
+                        (),
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v: &str| v.parse::<i32>().unwrap())]
+                        i32
+                    ),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let reordered = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expression {
+                    Range(
+                        #[rust_sitter::leaf(text = "..")]
+                        (),
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v: &str| v.parse::<i32>().unwrap())]
+                        i32,
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v: &str| v.parse::<i32>().unwrap())]
+                        i32
+                    ),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        let reordered_grammar = generate_grammar(&reordered);
+
+        // rule names only depend on field positions, so reordering fields keeps the
+        // same set of names and only moves the leaf definitions between them
+        let rule_names = |g: &serde_json::Value| {
+            let mut names = g["rules"]
+                .as_object()
+                .unwrap()
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>();
+            names.sort();
+            names
+        };
+        assert_eq!(rule_names(&grammar), rule_names(&reordered_grammar));
+        assert_eq!(
+            grammar["rules"]["Expression_Range_0"],
+            reordered_grammar["rules"]["Expression_Range_1"]
+        );
+        assert_eq!(
+            grammar["rules"]["Expression_Range_1"],
+            reordered_grammar["rules"]["Expression_Range_0"]
+        );
+        assert_eq!(
+            grammar["rules"]["Expression_Range_2"],
+            reordered_grammar["rules"]["Expression_Range_2"]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Multiple rules were generated with the name `Expression_Number`")]
+    fn colliding_rule_names() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expression {
+                    Number(
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v: &str| v.parse::<i32>().unwrap())]
+                        i32
+                    ),
+                }
+
+                pub struct Expression_Number {
+                    #[rust_sitter::leaf(pattern = r"\w+")]
+                    _word: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        generate_grammar(&m);
+    }
+
+    #[test]
+    fn grammar_named_root() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test", named_root = true)]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expression {
+                    Number(
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v: &str| v.parse::<i32>().unwrap())]
+                        i32
+                    ),
+                    Neg(
+                        #[rust_sitter::leaf(text = "-")]
+                        (),
+                        Box<Expression>
+                    ),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        assert!(grammar["rules"].get("source_file").is_none());
+        assert_eq!(
+            grammar["rules"].as_object().unwrap().keys().next().unwrap(),
+            "Expression"
+        );
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_unnamed_root() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test", named_root = false)]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expression {
+                    Number(
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v: &str| v.parse::<i32>().unwrap())]
+                        i32
+                    ),
+                    Neg(
+                        #[rust_sitter::leaf(text = "-")]
+                        (),
+                        Box<Expression>
+                    ),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        assert_eq!(
+            grammar["rules"].as_object().unwrap().keys().next().unwrap(),
+            "source_file"
+        );
+        assert_eq!(
+            grammar["rules"]["source_file"],
+            grammar["rules"]["Expression"]
+        );
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected boolean literal for `named_root`")]
+    fn grammar_named_root_not_boolean() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test", named_root = "yes")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    v: i32,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        generate_grammar(&m);
+    }
+
+    #[test]
+    fn grammar_multiple_roots() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Module {
+                    #[rust_sitter::leaf(text = "module")]
+                    _module: (),
+                    #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                    name: (),
+                }
+
+                #[rust_sitter::language]
+                pub enum Expression {
+                    Number(#[rust_sitter::leaf(pattern = r"\d+")] ()),
+                    Neg(#[rust_sitter::leaf(text = "-")] (), Box<Expression>),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        assert_eq!(
+            grammar["rules"]["source_file"],
+            serde_json::json!({
+                "type": "CHOICE",
+                "members": [
+                    { "type": "SYMBOL", "name": "Module" },
+                    { "type": "SYMBOL", "name": "Expression" },
+                ]
+            })
+        );
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot be combined with several `#[rust_sitter::language]` types")]
+    fn grammar_multiple_named_roots() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test", named_root = true)]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Word {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                    word: (),
+                }
+
+                #[rust_sitter::language]
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+")]
+                    number: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        generate_grammar(&m);
+    }
+
+    #[test]
+    fn grammar_snake_case_rules() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test", prefix_rules = true)]
+            #[rust_sitter::rename_all("snake_case")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expression {
+                    Number(
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v: &str| v.parse::<i32>().unwrap())]
+                        i32
+                    ),
+                    NegatedValue(
+                        #[rust_sitter::leaf(text = "-")]
+                        (),
+                        Box<Expression>
+                    ),
+                    Call(FunctionCall),
+                }
+
+                pub struct FunctionCall {
+                    #[rust_sitter::word]
+                    #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+                    name: String,
+                    #[rust_sitter::leaf(text = "(")]
+                    _open: (),
+                    #[rust_sitter::delimited(
+                        #[rust_sitter::leaf(text = ",")]
+                        ()
+                    )]
+                    args: Vec<Expression>,
+                    #[rust_sitter::leaf(text = ")")]
+                    _close: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        fn symbols(rule: &serde_json::Value, out: &mut Vec<String>) {
+            match rule["type"].as_str() {
+                Some("SYMBOL") => out.push(rule["name"].as_str().unwrap().to_string()),
+                Some("CHOICE" | "SEQ") => rule["members"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .for_each(|m| symbols(m, out)),
+                _ => {
+                    if let Some(content) = rule.get("content") {
+                        symbols(content, out);
+                    }
+                }
+            }
+        }
+
+        let grammar = generate_grammar(&m);
+        let rules = grammar["rules"].as_object().unwrap();
+        assert_eq!(rules.keys().next().unwrap(), "test_source_file");
+        assert!(rules.contains_key("test_expression_negated_value"));
+        assert!(rules.contains_key("test_function_call_name"));
+        assert!(rules.keys().all(|name| !name.contains(char::is_uppercase)));
+        assert_eq!(grammar["word"], "test_function_call_name");
+        assert_eq!(
+            rules["test_function_call_args_vec_contents"]["members"][0]["name"],
+            "function_call_args_vec_element"
+        );
+
+        // every reference still points at a rule after renaming
+        let mut references = vec![];
+        rules
+            .values()
+            .for_each(|rule| symbols(rule, &mut references));
+        assert!(references.contains(&"test_function_call".to_string()));
+        references
+            .iter()
+            .for_each(|name| assert!(rules.contains_key(name), "`{name}` is not a rule"));
+
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "The rules `ExpressionNumber` and `Expression_Number` are both renamed to `expression_number`"
+    )]
+    fn snake_case_rule_collision() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            #[rust_sitter::rename_all("snake_case")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expression {
+                    Number(ExpressionNumber),
+                }
+
+                pub struct ExpressionNumber {
+                    #[rust_sitter::leaf(pattern = r"\d+")]
+                    _digits: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        generate_grammar(&m);
+    }
+
+    #[test]
+    fn grammar_small_vec() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct NumberList {
+                    #[rust_sitter::delimited(
+                        #[rust_sitter::leaf(text = ",")]
+                        ()
+                    )]
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    numbers: SmallVec<[Spanned<i32>; 4]>,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let vec_m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct NumberList {
+                    #[rust_sitter::delimited(
+                        #[rust_sitter::leaf(text = ",")]
+                        ()
+                    )]
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    numbers: Vec<Spanned<i32>>,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        // the grammar only depends on the field being repeated, not on its collection
+        let grammar = generate_grammar(&m);
+        assert_eq!(grammar, generate_grammar(&vec_m));
+        assert_eq!(
+            generate_metadata(&m)["fields"],
+            generate_metadata(&vec_m)["fields"]
+        );
+    }
+
+    #[test]
+    fn grammar_merge_leaves() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test", merge_leaves = true)]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expression {
+                    Number(
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                        i32
+                    ),
+                    Neg(
+                        #[rust_sitter::leaf(text = "-")]
+                        (),
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                        i32
+                    ),
+                    Hex(
+                        #[rust_sitter::leaf(pattern = r"0x[0-9a-f]+", transform = |v| i32::from_str_radix(&v[2..], 16).unwrap())]
+                        i32
+                    ),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        let rules = grammar["rules"].as_object().unwrap();
+        // the leaves are merged into the first of their rules by name
+        assert!(rules.contains_key("Expression_Neg_1"));
+        assert!(!rules.contains_key("Expression_Number_0"));
+        assert!(rules.contains_key("Expression_Hex_0"));
+        assert_eq!(
+            rules["Expression_Number"]["members"][0]["content"],
+            serde_json::json!({ "type": "SYMBOL", "name": "Expression_Neg_1" })
+        );
+
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_node_types() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expression {
+                    Number(
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                        i32
+                    ),
+                    #[rust_sitter::prec_left(1)]
+                    Sub(
+                        Box<Expression>,
+                        #[rust_sitter::leaf(text = "-")]
+                        (),
+                        Box<Expression>
+                    ),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let node_types = generate_node_types(&generate_grammar(&m));
+        let is_terminal = |kind: &str| {
+            node_types
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|node_type| node_type["type"] == kind)
+                .unwrap()["is_terminal"]
+                .clone()
+        };
+        assert_eq!(is_terminal("Expression_Number_0"), true);
+        assert_eq!(is_terminal("Expression"), false);
+        assert_eq!(is_terminal("Expression_Sub"), false);
+        assert_eq!(is_terminal("source_file"), false);
+    }
+
+    #[test]
+    fn node_types_nested_supertypes() {
+        let symbol = |name: &str| serde_json::json!({ "type": "SYMBOL", "name": name });
+        let choice = |members: Vec<serde_json::Value>| serde_json::json!({ "type": "CHOICE", "members": members });
+        let grammar = serde_json::json!({
+            "name": "test",
+            "rules": {
+                "source_file": { "type": "REPEAT", "content": symbol("Statement") },
+                "Statement": choice(vec![symbol("Expr"), symbol("Declaration")]),
+                "Declaration": {
+                    "type": "SEQ",
+                    "members": [{ "type": "STRING", "value": "let" }, symbol("Name")]
+                },
+                "Expr": choice(vec![symbol("_literal"), symbol("Negation")]),
+                "_literal": choice(vec![symbol("Number"), symbol("Name")]),
+                "Negation": {
+                    "type": "SEQ",
+                    "members": [{ "type": "STRING", "value": "-" }, symbol("Expr")]
+                },
+                "Number": { "type": "PATTERN", "value": "\\d+" },
+                "Name": { "type": "PATTERN", "value": "[a-z]+" }
+            },
+            "supertypes": ["Statement", "Expr"]
+        });
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+
+        // the kinds of the nested supertype and of its hidden rule take their places
+        let node_types = generate_node_types(&grammar);
+        let subtypes = |kind: &str| {
+            node_types
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|node_type| node_type["type"] == kind)
+                .unwrap()["subtypes"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|subtype| subtype["type"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(subtypes("Expr"), vec!["Number", "Name", "Negation"]);
+        assert_eq!(
+            subtypes("Statement"),
+            vec!["Number", "Name", "Negation", "Declaration"]
+        );
+        assert!(node_types
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|node_type| node_type["type"] == "Number")
+            .all(|node_type| node_type.get("subtypes").is_none()));
+    }
+
+    #[test]
+    fn grammar_operators() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Shift {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    left: i32,
+                    #[rust_sitter::operators(["<", "<=", "<<", "<<="])]
+                    op: String,
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    right: i32,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        // the operators form a single token, listed from the longest to the shortest
+        let grammar = generate_grammar(&m);
+        assert_eq!(
+            grammar["rules"]["Shift_op"],
+            serde_json::json!({
+                "type": "TOKEN",
+                "content": {
+                    "type": "CHOICE",
+                    "members": [
+                        { "type": "STRING", "value": "<<=" },
+                        { "type": "STRING", "value": "<<" },
+                        { "type": "STRING", "value": "<=" },
+                        { "type": "STRING", "value": "<" }
+                    ]
+                }
+            })
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The operator `<=` is listed more than once")]
+    fn grammar_duplicate_operators() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Compare {
+                    #[rust_sitter::operators(["<=", "<", "<="])]
+                    op: String,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        generate_grammar(&m);
+    }
+
+    #[test]
+    fn grammar_optional_vec_of_boxes() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Tree {
+                    #[rust_sitter::leaf(text = "x")]
+                    Atom,
+                    Block(
+                        #[rust_sitter::leaf(text = "{")]
+                        (),
+                        Option<Box<Vec<Box<Tree>>>>,
+                        #[rust_sitter::leaf(text = "}")]
+                        ()
+                    ),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        // the vector is only absent when it has no elements, which refer back to `Tree`
+        let grammar = generate_grammar(&m);
+        assert_eq!(
+            grammar["rules"]["Tree_Block"]["members"][1],
+            serde_json::json!({
+                "type": "CHOICE",
+                "members": [
+                    { "type": "BLANK" },
+                    {
+                        "type": "FIELD",
+                        "name": "1",
+                        "content": { "type": "SYMBOL", "name": "Tree_Block_1_vec_contents" }
+                    }
+                ]
+            })
+        );
+        assert_eq!(
+            grammar["rules"]["Tree_Block_1_vec_contents"]["content"]["content"],
+            serde_json::json!({ "type": "SYMBOL", "name": "Tree" })
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected `none` for `scoped_extras`")]
+    fn scoped_extras_unsupported_scope() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                #[rust_sitter::scoped_extras(whitespace)]
+                pub struct Name {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                    _name: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        generate_grammar(&m);
+    }
+
+    #[test]
+    fn grammar_optional_attribute() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Version {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    major: u32,
+                    #[rust_sitter::optional]
+                    #[rust_sitter::leaf(pattern = r"\.\d+", transform = |v| v[1..].parse().unwrap())]
+                    minor: u32,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        // the field keeps its type, but is matched as if it were an `Option<u32>`
+        let grammar = generate_grammar(&m);
+        assert_eq!(
+            grammar["rules"]["Version"]["members"][1],
+            serde_json::json!({
+                "type": "CHOICE",
+                "members": [
+                    { "type": "BLANK" },
+                    {
+                        "type": "FIELD",
+                        "name": "minor",
+                        "content": { "type": "SYMBOL", "name": "Version_minor" }
+                    }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "`optional` cannot be combined with a field of type `Option<_>`")]
+    fn optional_attribute_on_option() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Version {
+                    #[rust_sitter::optional]
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    major: Option<u32>,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        generate_grammar(&m);
+    }
+
+    #[test]
+    fn grammar_named_text_leaf() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            #[rust_sitter::hide_anonymous]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Mapping {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                    _from: (),
+                    #[rust_sitter::leaf(text = "->", name = "arrow")]
+                    _arrow: (),
+                    #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                    _to: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        assert_eq!(
+            grammar["rules"]["Mapping"]["members"][1]["content"],
+            serde_json::json!({
+                "type": "ALIAS",
+                "content": { "type": "STRING", "value": "->" },
+                "named": true,
+                "value": "arrow"
+            })
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "`name` can only be given to a `text` leaf")]
+    fn named_pattern_leaf() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Name {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+", name = "word")]
+                    _name: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        generate_grammar(&m);
+    }
+
+    #[test]
+    fn grammar_separator_prec() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expr {
+                    Number(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] u32),
+                    #[rust_sitter::prec_left(1)]
+                    Pair(Box<Expr>, #[rust_sitter::leaf(text = ",")] (), Box<Expr>),
+                    Call(
+                        #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())] String,
+                        #[rust_sitter::leaf(text = "(")] (),
+                        #[rust_sitter::repeat(sep = ",", sep_prec = 2)]
+                        Vec<Expr>,
+                        #[rust_sitter::leaf(text = ")")] (),
+                    ),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        // without the precedence, a `,` after an argument could also be that of a `Pair`
+        let grammar = generate_grammar(&m);
+        assert_eq!(
+            grammar["rules"]["Expr_Call_2_vec_delimiter"],
+            serde_json::json!({
+                "type": "PREC",
+                "value": 2,
+                "content": { "type": "STRING", "value": "," }
+            })
+        );
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_any_leaf() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Escape {
+                    #[rust_sitter::leaf(text = "\\")]
+                    _backslash: (),
+                    #[rust_sitter::leaf(any = true, transform = |v| v.chars().next().unwrap())]
+                    escaped: char,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        assert_eq!(
+            grammar["rules"]["Escape_escaped"],
+            serde_json::json!({ "type": "PATTERN", "value": r".|\n" })
+        );
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "`any` cannot be combined with `pattern`")]
+    fn grammar_any_leaf_with_pattern() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Char {
+                    #[rust_sitter::leaf(any = true, pattern = r"\w")]
+                    c: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        generate_grammar(&m);
+    }
+
+    #[test]
+    fn grammar_bytes_leaf() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Header {
+                    #[rust_sitter::leaf(bytes = [0x7F, 0x45, 0x4C, 0x46])]
+                    _magic: (),
+                    #[rust_sitter::leaf(bytes = [0xCA, 0xFE])]
+                    _marker: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        // each byte is matched as the character with the same code point
+        let grammar = generate_grammar(&m);
+        assert_eq!(
+            grammar["rules"]["Header__magic"],
+            serde_json::json!({ "type": "STRING", "value": "\u{7f}ELF" })
+        );
+        assert_eq!(
+            grammar["rules"]["Header__marker"],
+            serde_json::json!({ "type": "STRING", "value": "\u{ca}\u{fe}" })
+        );
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "`256` in `bytes` is not a byte")]
+    fn grammar_bytes_leaf_out_of_range() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Header {
+                    #[rust_sitter::leaf(bytes = [0x7F, 256])]
+                    _magic: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        generate_grammar(&m);
+    }
+
+    #[test]
+    fn grammar_coupled_optional_fields() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Import {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+                    name: String,
+                    alias: Option<AsClause>,
+                }
+
+                pub struct AsClause {
+                    #[rust_sitter::leaf(text = "as")]
+                    _as: (),
+                    #[rust_sitter::leaf(pattern = r"[A-Z][a-z]*", transform = |v| v.to_string())]
+                    ty: String,
+                    default: Option<DefaultClause>,
+                }
+
+                pub struct DefaultClause {
+                    #[rust_sitter::leaf(text = "=")]
+                    _eq: (),
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    value: u32,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        // the fields of an optional struct are present or absent together, as an optional
+        // sequence, and the same holds for an optional struct within it
+        let grammar = generate_grammar(&m);
+        let field = |name: &str, rule: &str| {
+            serde_json::json!({
+                "type": "FIELD",
+                "name": name,
+                "content": { "type": "SYMBOL", "name": rule }
+            })
+        };
+        assert_eq!(
+            grammar["rules"]["Import"]["members"][1],
+            optional(field("alias", "AsClause"))
+        );
+        assert_eq!(
+            grammar["rules"]["AsClause"],
+            serde_json::json!({
+                "type": "SEQ",
+                "members": [
+                    field("_as", "AsClause__as"),
+                    field("ty", "AsClause_ty"),
+                    optional(field("default", "DefaultClause")),
+                ]
+            })
+        );
+        assert_eq!(
+            grammar["rules"]["DefaultClause"],
+            serde_json::json!({
+                "type": "SEQ",
+                "members": [
+                    field("_eq", "DefaultClause__eq"),
+                    field("value", "DefaultClause_value"),
+                ]
+            })
+        );
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_counted_repeat() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Triple {
+                    #[rust_sitter::repeat(sep = ",")]
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    numbers: [u32; 3],
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        // the elements are spelled out with the separator between them, and are required
+        let grammar = generate_grammar(&m);
+        let element = serde_json::json!({
+            "type": "FIELD",
+            "name": "Triple_numbers_vec_element",
+            "content": { "type": "SYMBOL", "name": "Triple_numbers" }
+        });
+        let separator = serde_json::json!({
+            "type": "SYMBOL",
+            "name": "Triple_numbers_vec_delimiter"
+        });
+        assert_eq!(
+            grammar["rules"]["Triple_numbers_vec_contents"],
+            serde_json::json!({
+                "type": "SEQ",
+                "members": [element, separator, element, separator, element]
+            })
+        );
+        assert_eq!(grammar["rules"]["Triple"]["members"][0]["type"], "FIELD");
+    }
+
+    #[test]
+    fn grammar_right_recursive_repeat() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Cons {
+                    #[rust_sitter::right_recursive]
+                    #[rust_sitter::repeat(sep = ":")]
+                    #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+                    items: Vec<String>,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        // the rest of the list is hidden, so its elements are children of the list itself
+        let grammar = generate_grammar(&m);
+        let element = serde_json::json!({
+            "type": "FIELD",
+            "name": "Cons_items_vec_element",
+            "content": { "type": "SYMBOL", "name": "Cons_items" }
+        });
+        let tail = optional(serde_json::json!({
+            "type": "SYMBOL",
+            "name": "_Cons_items_vec_tail"
+        }));
+        assert_eq!(
+            grammar["rules"]["Cons_items_vec_contents"],
+            serde_json::json!({
+                "type": "SEQ",
+                "members": [element, tail]
+            })
+        );
+        assert_eq!(
+            grammar["rules"]["_Cons_items_vec_tail"],
+            serde_json::json!({
+                "type": "PREC_RIGHT",
+                "value": 0,
+                "content": {
+                    "type": "SEQ",
+                    "members": [
+                        { "type": "SYMBOL", "name": "Cons_items_vec_delimiter" },
+                        element,
+                        tail
+                    ]
+                }
+            })
+        );
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_token_rule() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Statement {
+                    Call(
+                        Identifier,
+                        #[rust_sitter::leaf(text = "(")] (),
+                        Identifier,
+                        #[rust_sitter::leaf(text = ")")] (),
+                    ),
+                    Assign(Identifier, #[rust_sitter::leaf(text = "=")] (), Identifier),
+                }
+
+                #[rust_sitter::token]
+                pub struct Identifier {
+                    #[rust_sitter::leaf(pattern = r"[a-z_]+", transform = |v| v.to_string())]
+                    name: String,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        // the leaf is the rule itself, which both variants refer to by name
+        let grammar = generate_grammar(&m);
+        assert_eq!(
+            grammar["rules"]["Identifier"],
+            serde_json::json!({ "type": "PATTERN", "value": "[a-z_]+" })
+        );
+        assert!(grammar["rules"].get("Identifier_name").is_none());
+        let identifier = serde_json::json!({ "type": "SYMBOL", "name": "Identifier" });
+        for variant in ["Statement_Call", "Statement_Assign"] {
+            let members = &grammar["rules"][variant]["members"];
+            assert_eq!(members[0]["content"], identifier);
+            assert_eq!(members[2]["content"], identifier);
+        }
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_renamed_field() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Parameter {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+                    name: String,
+                    #[rust_sitter::leaf(text = ":")]
+                    _colon: (),
+                    #[rust_sitter::field("type")]
+                    #[rust_sitter::leaf(pattern = r"[A-Z][a-z]*", transform = |v| v.to_string())]
+                    ty: String,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        // the field takes the given name, while its rule keeps following the Rust name
+        let grammar = generate_grammar(&m);
+        let members = &grammar["rules"]["Parameter"]["members"];
+        assert_eq!(members[0]["name"], "name");
+        assert_eq!(
+            members[2],
+            serde_json::json!({
+                "type": "FIELD",
+                "name": "type",
+                "content": { "type": "SYMBOL", "name": "Parameter_ty" }
+            })
+        );
+        assert!(grammar["rules"].get("Parameter_ty").is_some());
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "`count = 4` does not match the length of the array, which is 3")]
+    fn counted_repeat_length_mismatch() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Triple {
+                    #[rust_sitter::repeat(count = 4)]
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    numbers: [u32; 3],
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        generate_grammar(&m);
+    }
+
+    #[test]
+    fn grammar_struct_root() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Program {
+                    header: Header,
+                    body: Vec<Statement>,
+                }
+
+                pub struct Header {
+                    #[rust_sitter::leaf(text = "program")]
+                    _program: (),
+                    #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+                    name: String,
+                }
+
+                pub struct Statement {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    value: i32,
+                    #[rust_sitter::leaf(text = ";")]
+                    _semicolon: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        assert_eq!(
+            grammar["rules"].as_object().unwrap().keys().next().unwrap(),
+            "source_file"
+        );
+        assert_eq!(grammar["rules"]["source_file"]["type"], "SEQ");
+        assert_eq!(grammar["rules"]["source_file"], grammar["rules"]["Program"]);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_with_extras() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expression {
+                    Number(
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v: &str| v.parse::<i32>().unwrap())]
+                        i32
+                    ),
+                }
+
+                #[rust_sitter::extra]
+                struct Whitespace {
+                    #[rust_sitter::leaf(pattern = r"\s", transform = |_v| ())]
+                    _whitespace: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_empty_extras() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            #[rust_sitter::extras()]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expression {
+                    Number(
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v: &str| v.parse::<i32>().unwrap())]
+                        i32
+                    ),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        assert_eq!(grammar["extras"], serde_json::json!([]));
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_default_extras() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expression {
+                    Number(
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v: &str| v.parse::<i32>().unwrap())]
+                        i32
+                    ),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        assert_eq!(
+            grammar["extras"],
+            serde_json::json!([{ "type": "PATTERN", "value": "\\s" }])
+        );
+    }
+
+    #[test]
+    fn grammar_module_extras() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            #[rust_sitter::extras(r"\s", Comment)]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expression {
+                    Number(
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v: &str| v.parse::<i32>().unwrap())]
+                        i32
+                    ),
+                }
+
+                pub struct Comment {
+                    #[rust_sitter::leaf(pattern = r"#.*")]
+                    _comment: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_extras_order() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            #[rust_sitter::extras(LineComment, r"\s")]
+            #[rust_sitter::extras(BlockComment)]
+            mod grammar {
+                #[rust_sitter::extra]
+                struct Newline {
+                    #[rust_sitter::leaf(text = "\n")]
+                    _newline: (),
+                }
+
+                #[rust_sitter::language]
+                pub enum Expression {
+                    Number(
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v: &str| v.parse::<i32>().unwrap())]
+                        i32
+                    ),
+                }
+
+                pub struct LineComment {
+                    #[rust_sitter::leaf(pattern = r"//.*")]
+                    _comment: (),
+                }
+
+                pub struct BlockComment {
+                    #[rust_sitter::leaf(pattern = r"/\*[^*]*\*/")]
+                    _comment: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        assert_eq!(
+            grammar["extras"],
+            serde_json::json!([
+                { "type": "SYMBOL", "name": "LineComment" },
+                { "type": "PATTERN", "value": "\\s" },
+                { "type": "SYMBOL", "name": "BlockComment" },
+                { "type": "SYMBOL", "name": "Newline" },
+            ])
+        );
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_unboxed_field() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Language {
+                    e: Expression,
+                }
+
+                pub enum Expression {
+                    Number(
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v: &str| v.parse::<i32>().unwrap())]
+                        i32
+                    ),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_leaf_alternatives() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Number {
+                    #[rust_sitter::leaf(alternatives = [(r"0x[0-9a-f]+", parse_hex), (r"\d+", parse_dec)])]
+                    value: i64,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_no_extras() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Language {
+                    q: Quoted,
+                }
+
+                #[rust_sitter::no_extras]
+                pub struct Quoted {
+                    #[rust_sitter::leaf(text = "\"")]
+                    _open: (),
+                    #[rust_sitter::leaf(pattern = r#"[^"]*"#)]
+                    _contents: (),
+                    #[rust_sitter::leaf(text = "\"")]
+                    _close: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_immediate_seq() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                #[rust_sitter::immediate_seq]
+                pub struct Float {
+                    #[rust_sitter::leaf(pattern = r"\d+")]
+                    _integer: (),
+                    #[rust_sitter::leaf(text = ".")]
+                    _dot: (),
+                    #[rust_sitter::leaf(pattern = r"\d+")]
+                    _fraction: (),
+                    #[rust_sitter::leaf(pattern = r"[eE][+-]?\d+")]
+                    _exponent: Option<()>,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "which is shared with other rules")]
+    fn immediate_seq_of_non_leaf() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                #[rust_sitter::immediate_seq]
+                pub struct Pair {
+                    #[rust_sitter::leaf(pattern = r"\d+")]
+                    _first: (),
+                    second: Number,
+                }
+
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+")]
+                    _value: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        generate_grammar(&m);
+    }
+
+    #[test]
+    fn grammar_supertype() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Program {
+                    expr: Expr,
+                }
+
+                #[rust_sitter::supertype]
+                pub enum Expr {
+                    Number(Number),
+                    Negation(
+                        #[rust_sitter::leaf(text = "-")] (),
+                        Box<Expr>,
+                    ),
+                }
+
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+")]
+                    _value: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_token_group() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test", prefix_rules = true)]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Program {
+                    value: Literal,
+                }
+
+                #[rust_sitter::token_group(_literal)]
+                pub enum Literal {
+                    Number(Number),
+                    Boolean(Box<Boolean>),
+                }
+
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+")]
+                    _value: (),
+                }
+
+                pub struct Boolean {
+                    #[rust_sitter::leaf(pattern = r"true|false")]
+                    _value: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        insta::assert_display_snapshot!(grammar);
+        assert!(grammar["rules"].get("test__literal").is_none());
+        assert_eq!(
+            grammar["rules"]["_test_literal"]["members"],
+            serde_json::json!([
+                { "type": "SYMBOL", "name": "test_Number" },
+                { "type": "SYMBOL", "name": "test_Boolean" },
+            ])
+        );
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "The token group `literal` of `Literal` must start with `_`")]
+    fn token_group_without_underscore() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                #[rust_sitter::token_group(literal)]
+                pub enum Literal {
+                    Number(Number),
+                }
+
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+")]
+                    _value: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        generate_grammar(&m);
+    }
+
+    #[test]
+    #[should_panic(expected = "is a supertype, so its variants must have fields")]
+    fn supertype_with_token_variant() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                #[rust_sitter::supertype]
+                pub enum Expr {
+                    Number(Number),
+                    #[rust_sitter::leaf(text = "nil")]
+                    Nil,
+                }
+
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+")]
+                    _value: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        generate_grammar(&m);
+    }
+
+    #[test]
+    fn grammar_allow_shebang() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            #[rust_sitter::allow_shebang]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Word {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                    _word: (),
+                }
+
+                #[rust_sitter::extra]
+                struct Comment {
+                    #[rust_sitter::leaf(pattern = r"#[^\n]*")]
+                    _comment: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_trailing_token() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            #[rust_sitter::trailing_token("__END__")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Words {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                    words: Vec<()>,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        let members = grammar["rules"]["source_file"]["members"]
+            .as_array()
+            .unwrap();
+        assert_eq!(
+            members.last().unwrap(),
+            &serde_json::json!({ "type": "STRING", "value": "__END__" })
+        );
+        // only a trailing newline makes newlines significant
+        assert_eq!(
+            grammar["extras"],
+            serde_json::json!([{ "type": "PATTERN", "value": "\\s" }])
+        );
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "A grammar can only require one trailing token")]
+    fn grammar_several_trailing_tokens() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            #[rust_sitter::trailing_token(";")]
+            #[rust_sitter::require_trailing_newline]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Word {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                    _word: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        generate_grammar(&m);
+    }
+
+    #[test]
+    fn grammar_repeat_of_enum() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Body {
+                    #[rust_sitter::repeat(non_empty = true)]
+                    statements: Vec<Statement>,
+                }
+
+                pub enum Statement {
+                    Print(
+                        #[rust_sitter::leaf(text = "print")] (),
+                        #[rust_sitter::leaf(pattern = r"\d+")] (),
+                    ),
+                    Pass(#[rust_sitter::leaf(text = "pass")] ()),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        // the elements refer to the enum as a single symbol, rather than repeating a copy of
+        // its choice, so that every statement keeps its `Statement` node
+        let contents = &grammar["rules"]["Body_statements_vec_contents"];
+        assert_eq!(contents["type"], "REPEAT1");
+        assert_eq!(
+            contents["content"]["content"],
+            serde_json::json!({ "type": "SYMBOL", "name": "Statement" })
+        );
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_prefix_rules() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("calc", prefix_rules = true)]
+            mod grammar {
+                #[rust_sitter::language]
+                #[rust_sitter::supertype]
+                pub enum Expr {
+                    Number(Number),
+                    Sum(
+                        #[rust_sitter::leaf(text = "sum")] (),
+                        #[rust_sitter::repeat(non_empty = true)]
+                        #[rust_sitter::delimited(
+                            #[rust_sitter::leaf(text = "+")]
+                            ()
+                        )]
+                        Vec<Number>,
+                    ),
+                }
+
+                pub struct Number {
+                    #[rust_sitter::word]
+                    #[rust_sitter::leaf(pattern = r"\d+")]
+                    _value: (),
+                }
+
+                #[rust_sitter::extra]
+                struct Whitespace {
+                    #[rust_sitter::leaf(pattern = r"\s")]
+                    _whitespace: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        fn symbols(rule: &serde_json::Value, out: &mut Vec<String>) {
+            match rule {
+                serde_json::Value::Object(o) => {
+                    if o.get("type").and_then(|t| t.as_str()) == Some("SYMBOL") {
+                        out.push(o["name"].as_str().unwrap().to_string());
+                    }
+                    o.values().for_each(|v| symbols(v, out));
+                }
+                serde_json::Value::Array(a) => a.iter().for_each(|v| symbols(v, out)),
+                _ => {}
+            }
+        }
+
+        let grammar = generate_grammar(&m);
+        let rules = grammar["rules"].as_object().unwrap();
+        assert_eq!(rules.keys().next().unwrap(), "calc_source_file");
+        assert!(rules.keys().all(|name| name.starts_with("calc_")));
+
+        // every reference, including the extras, the word and the supertypes, is to a rule
+        let mut references = vec![];
+        symbols(&grammar["rules"], &mut references);
+        symbols(&grammar["extras"], &mut references);
+        references.push(grammar["word"].as_str().unwrap().to_string());
+        references.extend(
+            grammar["supertypes"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|s| s.as_str().unwrap().to_string()),
+        );
+        assert!(references.contains(&"calc_Number".to_string()));
+        references
+            .iter()
+            .for_each(|name| assert!(rules.contains_key(name), "`{name}` is not a rule"));
+
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_token_prec() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Statement {
+                    Comparison(
+                        #[rust_sitter::leaf(pattern = r"[a-z]+")] (),
+                        #[rust_sitter::leaf(text = "==", token_prec = 1)] (),
+                        #[rust_sitter::leaf(pattern = r"[a-z]+")] (),
+                    ),
+                    Assignment(
+                        #[rust_sitter::leaf(pattern = r"[a-z]+")] (),
+                        #[rust_sitter::leaf(pattern = r"[:=]?=", token_prec = -1)] (),
+                        #[rust_sitter::leaf(pattern = r"[a-z]+")] (),
+                    ),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_bracketed() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Group {
+                    Paren(#[rust_sitter::bracketed(open = "(", close = ")")] Number),
+                    Square(#[rust_sitter::bracketed(open = "[", close = "]")] Option<Number>),
+                }
+
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    v: i32,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_syntax_only_leaves() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expr {
+                    Number(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] i32),
+                    Paren(Paren),
+                    Neg(#[rust_sitter::syntax(before = "-")] Box<Expr>),
+                }
+
+                pub struct Paren {
+                    #[rust_sitter::syntax(before = "(", after = ")")]
+                    inner: Box<Expr>,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        assert_eq!(
+            grammar["rules"]["Paren"],
+            serde_json::json!({
+                "type": "SEQ",
+                "members": [
+                    {
+                        "type": "SEQ",
+                        "members": [
+                            { "type": "STRING", "value": "(" },
+                            {
+                                "type": "FIELD",
+                                "name": "inner",
+                                "content": { "type": "SYMBOL", "name": "Expr" }
+                            },
+                            { "type": "STRING", "value": ")" }
+                        ]
+                    }
+                ]
+            })
+        );
+        assert_eq!(
+            grammar["rules"]["Expr_Neg"]["members"][0]["members"][0],
+            serde_json::json!({ "type": "STRING", "value": "-" })
+        );
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot be combined with `#[rust_sitter::syntax]`")]
+    fn grammar_syntax_with_bracketed() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Paren {
+                    #[rust_sitter::bracketed(open = "(", close = ")")]
+                    #[rust_sitter::syntax(before = "-")]
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    inner: i32,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        generate_grammar(&m);
+    }
+
+    #[test]
+    fn grammar_external_token_references() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            #[rust_sitter::external(INDENT, DEDENT)]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Block {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                    name: (),
+                    #[rust_sitter::leaf(text = ":")]
+                    _colon: (),
+                    #[rust_sitter::external_token(INDENT)]
+                    _indent: (),
+                    #[rust_sitter::repeat(non_empty = true)]
+                    body: Vec<Statement>,
+                    #[rust_sitter::external_token(DEDENT)]
+                    _dedent: Option<()>,
+                }
+
+                pub struct Statement {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                    name: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        let members = grammar["rules"]["Block"]["members"].as_array().unwrap();
+        assert_eq!(
+            members[2],
+            serde_json::json!({
+                "type": "FIELD",
+                "name": "_indent",
+                "content": { "type": "SYMBOL", "name": "INDENT" }
+            })
+        );
+        assert_eq!(
+            members[4]["members"][1]["content"],
+            serde_json::json!({ "type": "SYMBOL", "name": "DEDENT" })
+        );
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "The external token `DEDENT` referenced by `Block__dedent` is not declared"
+    )]
+    fn grammar_undeclared_external_token() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            #[rust_sitter::external(INDENT)]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Block {
+                    #[rust_sitter::external_token(INDENT)]
+                    _indent: (),
+                    #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                    name: (),
+                    #[rust_sitter::external_token(DEDENT)]
+                    _dedent: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        generate_grammar(&m);
+    }
+
+    #[test]
+    fn grammar_unordered_fields() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                #[rust_sitter::unordered]
+                pub struct Config {
+                    #[rust_sitter::syntax(before = "name")]
+                    #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                    name: (),
+                    #[rust_sitter::syntax(before = "debug")]
+                    #[rust_sitter::leaf(pattern = r"true|false")]
+                    debug: Option<()>,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        let rule = &grammar["rules"]["Config"];
+        assert_eq!(rule["type"], "REPEAT1");
+        assert_eq!(rule["content"]["type"], "CHOICE");
+
+        // the optional field is not wrapped, since the repetition already allows leaving it out
+        let members = rule["content"]["members"].as_array().unwrap();
+        assert_eq!(
+            members[1]["members"][1],
+            serde_json::json!({
+                "type": "FIELD",
+                "name": "debug",
+                "content": { "type": "SYMBOL", "name": "Config_debug" }
+            })
+        );
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "The field `Config_values` of an unordered type can't be repeated")]
+    fn grammar_unordered_repeated_field() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                #[rust_sitter::unordered]
+                pub struct Config {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                    name: (),
+                    #[rust_sitter::leaf(pattern = r"\d+")]
+                    values: Vec<()>,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        generate_grammar(&m);
+    }
+
+    #[test]
+    fn grammar_js_doc_comments() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                /// A list of numbers.
+                ///
+                /// Numbers are separated by commas.
+                #[rust_sitter::language]
+                pub struct Numbers {
+                    #[rust_sitter::repeat(non_empty = true)]
+                    #[rust_sitter::delimited(#[rust_sitter::leaf(text = ",")] ())]
+                    numbers: Vec<Number>,
+                }
+
+                pub struct Number {
+                    /// The digits of a number, such as `42`.
+                    #[rust_sitter::leaf(pattern = r"\d+|0x/[0-9a-f]+")]
+                    digits: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        let js = generate_grammar_js(&m, &grammar);
+        assert!(js.starts_with(&format!("// {GENERATED_MARKER}\n")));
+        assert!(js.contains(
+            "    // A list of numbers.\n    //\n    // Numbers are separated by commas.\n    Numbers: $ => "
+        ));
+        assert!(js.contains(
+            "    // The digits of a number, such as `42`.\n    Number_digits: $ => /\\d+|0x\\/[0-9a-f]+/,\n"
+        ));
+
+        // rules without doc comments directly follow the blank line after the previous rule
+        assert!(js.contains(",\n\n    Number: $ => seq(field(\"digits\", $.Number_digits)),\n"));
+    }
+
+    #[test]
+    fn grammar_tokenizer_only() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            #[rust_sitter::tokenizer_only]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expr {
+                    Number(#[rust_sitter::leaf(pattern = r"\d+")] ()),
+                    Digits(#[rust_sitter::leaf(pattern = r"\d+")] ()),
+                    Paren(#[rust_sitter::bracketed(open = "(", close = ")")] Box<Expr>),
+                }
+
+                #[rust_sitter::extra]
+                struct Whitespace {
+                    #[rust_sitter::leaf(pattern = r"\s")]
+                    _whitespace: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        // the second `\d+` leaf could never match, and the extra is skipped as usual
+        assert_eq!(
+            grammar["rules"]["source_file"],
+            serde_json::json!({
+                "type": "REPEAT",
+                "content": {
+                    "type": "CHOICE",
+                    "members": [
+                        { "type": "SYMBOL", "name": "Expr_Digits_0" },
+                        { "type": "STRING", "value": "(" },
+                        { "type": "STRING", "value": ")" }
+                    ]
+                }
+            })
+        );
+        assert!(grammar["rules"].get("Expr").is_none());
+        assert!(grammar["rules"].get("Whitespace").is_some());
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_newline_leaves() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Program {
+                    statements: Vec<Statement>,
+                }
+
+                pub struct Statement {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    v: i32,
+                    #[rust_sitter::newline]
+                    _end: (),
+                    #[rust_sitter::newline]
+                    _blank_line: Option<()>,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        assert_eq!(
+            grammar["rules"]["newline"],
+            serde_json::json!({ "type": "PATTERN", "value": r"\r?\n" })
+        );
+        assert_eq!(
+            grammar["extras"],
+            serde_json::json!([
+                { "type": "PATTERN", "value": r"[ \t\f\v]" },
+                { "type": "SYMBOL", "name": "newline" }
+            ])
+        );
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "must be of type `()` or `Option<()>`")]
+    fn grammar_newline_wrong_type() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Statement {
+                    #[rust_sitter::newline]
+                    end: String,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        generate_grammar(&m);
+    }
+
+    #[test]
+    fn grammar_anchored() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap(), anchored = true)]
+                    v: i32,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_name_override() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test", name = "my-lang.v2")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    v: i32,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        assert_eq!(grammar["name"], "my_lang_v2");
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar]
+            mod my_grammar {
+                #[rust_sitter::language]
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    v: i32,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        assert_eq!(generate_grammar(&m)["name"], "my_grammar");
+    }
+
+    #[test]
+    fn grammar_name_sanitized() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar]
+            mod MyLang {
+                #[rust_sitter::language]
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    v: i32,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        assert_eq!(grammar["name"], "my_lang");
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_newtype_leaf() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("newtype")]
+            mod grammar {
+                pub struct LineNo(pub u32);
+
+                #[rust_sitter::language]
+                pub struct Goto {
+                    #[rust_sitter::leaf(pattern = r"\d+")]
+                    line: LineNo,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        assert!(grammar["rules"].get("LineNo").is_none());
+        assert_eq!(
+            grammar["rules"]["Goto_line"],
+            serde_json::json!({ "type": "PATTERN", "value": "\\d+" })
+        );
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "The grammar name `2d-shapes` becomes `2d_shapes`, but Tree Sitter grammar names must start with a letter"
+    )]
+    fn grammar_name_invalid() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("2d-shapes")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    v: i32,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        generate_grammar(&m);
+    }
+
+    #[test]
+    #[should_panic(expected = "`Op` is aliased to `Language`, which is already the name of a rule")]
+    fn alias_colliding_with_rule() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Language {
+                    op: Op,
+                }
+
+                #[rust_sitter::alias("Language")]
+                pub enum Op {
+                    #[rust_sitter::leaf(text = "+")]
+                    Add,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        generate_grammar(&m);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "`Kind_In` is a keyword, which needs a word rule, but none could be inferred"
+    )]
+    fn keyword_without_word() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Language {
+                    kind: Kind,
+                    #[rust_sitter::leaf(pattern = r"\d+")]
+                    _number: (),
+                }
+
+                pub enum Kind {
+                    #[rust_sitter::keyword("in")]
+                    In,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        generate_grammar(&m);
+    }
+
+    #[test]
+    fn grammar_repeat() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            pub mod grammar {
+                #[rust_sitter::language]
+                pub struct NumberList {
+                    #[rust_sitter::delimited(
+                        #[rust_sitter::leaf(text = ",")]
+                        ()
+                    )]
+                    numbers: Vec<Number>,
+                }
+
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    v: i32,
+                }
+
+                #[rust_sitter::extra]
+                struct Whitespace {
+                    #[rust_sitter::leaf(pattern = r"\s")]
+                    _whitespace: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_repeat_no_delimiter() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            pub mod grammar {
+                #[rust_sitter::language]
+                pub struct NumberList {
+                    numbers: Vec<Number>,
+                }
+
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    v: i32,
+                }
+
+                #[rust_sitter::extra]
+                struct Whitespace {
+                    #[rust_sitter::leaf(pattern = r"\s")]
+                    _whitespace: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "The rule `NumberList_numbers_vec_contents` repeats `Number`, which can match the empty string"
+    )]
+    fn grammar_repeat_nullable_element() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            pub mod grammar {
+                #[rust_sitter::language]
+                pub struct NumberList {
+                    #[rust_sitter::repeat(non_empty = true)]
+                    numbers: Vec<Number>,
+                }
+
+                pub struct Number {
+                    #[rust_sitter::leaf(text = "-")]
+                    sign: Option<()>,
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    v: Option<i32>,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        generate_grammar(&m);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "The rule `NumberList_numbers_vec_contents` repeats a leaf with the pattern `\\d*`, which can match the empty string"
+    )]
+    fn grammar_repeat_empty_pattern() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            pub mod grammar {
+                #[rust_sitter::language]
+                pub struct NumberList {
+                    #[rust_sitter::leaf(pattern = r"\d*", transform = |v| v.to_string())]
+                    numbers: Vec<String>,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        generate_grammar(&m);
+    }
+
+    #[test]
+    #[should_panic(expected = "The extra `Comment` can match the empty string")]
+    fn grammar_nullable_extra() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            pub mod grammar {
+                #[rust_sitter::language]
+                pub struct Word {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                    _word: (),
+                }
+
+                #[rust_sitter::extra]
+                struct Comment {
+                    #[rust_sitter::leaf(text = "//")]
+                    _prefix: Option<()>,
+                    #[rust_sitter::leaf(pattern = r"[^\n]+")]
+                    _contents: Option<()>,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        generate_grammar(&m);
+    }
+
+    #[test]
+    fn grammar_repeat1() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            pub mod grammar {
+                #[rust_sitter::language]
+                pub struct NumberList {
+                    #[rust_sitter::repeat(non_empty = true)]
+                    #[rust_sitter::delimited(
+                        #[rust_sitter::leaf(text = ",")]
+                        ()
+                    )]
+                    numbers: Vec<Number>,
+                }
+
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    v: i32,
+                }
+
+                #[rust_sitter::extra]
+                struct Whitespace {
+                    #[rust_sitter::leaf(pattern = r"\s")]
+                    _whitespace: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn struct_optional() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Language {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    v: Option<i32>,
+                    #[rust_sitter::leaf(pattern = r" ", transform = |v| ())]
+                    space: (),
+                    t: Option<Number>,
+                }
+
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    v: i32
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_entry_points() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Program {
+                    #[rust_sitter::repeat(non_empty = true)]
+                    statements: Vec<Statement>,
+                }
+
+                #[rust_sitter::entry]
+                pub struct Statement {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    value: u32,
+                    #[rust_sitter::leaf(text = ";")]
+                    _semicolon: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let entry_grammars = generate_entry_grammars(&m, vec![]).collect::<Vec<_>>();
+        assert_eq!(entry_grammars.len(), 1);
+        assert_eq!(entry_grammars[0]["name"], "test_statement");
+        assert_eq!(
+            entry_grammars[0]["rules"]["source_file"],
+            entry_grammars[0]["rules"]["Statement"]
+        );
+        tree_sitter_cli::generate::generate_parser_for_grammar(&entry_grammars[0].to_string())
+            .unwrap();
+    }
+
+    #[test]
+    fn grammar_type_aliases() {
+        let aliased = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                type Stmt = Statement;
+                type Value = Number;
+                type Ident = String;
+
+                #[rust_sitter::language]
+                pub struct Program {
+                    #[rust_sitter::repeat(non_empty = true)]
+                    statements: Vec<Stmt>,
+                }
+
+                pub struct Statement {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+                    name: Ident,
+                    #[rust_sitter::leaf(text = "=")]
+                    _eq: (),
+                    value: Box<Value>,
+                }
+
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    v: u32,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let unaliased = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Program {
+                    #[rust_sitter::repeat(non_empty = true)]
+                    statements: Vec<Statement>,
+                }
+
+                pub struct Statement {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+                    name: String,
+                    #[rust_sitter::leaf(text = "=")]
+                    _eq: (),
+                    value: Box<Number>,
+                }
+
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    v: u32,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&aliased);
+        assert_eq!(grammar, generate_grammar(&unaliased));
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_deterministic_order() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Program {
+                    statement: Statement,
+                    #[rust_sitter::leaf(text = ";")]
+                    _semicolon: (),
+                }
+
+                pub struct Statement {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+                    name: String,
+                    number: Number,
+                }
+
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    v: u32,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let reordered = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    v: u32,
+                }
+
+                pub struct Statement {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+                    name: String,
+                    number: Number,
+                }
+
+                #[rust_sitter::language]
+                pub struct Program {
+                    statement: Statement,
+                    #[rust_sitter::leaf(text = ";")]
+                    _semicolon: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m).to_string();
+        assert_eq!(grammar, generate_grammar(&m).to_string());
+        assert_eq!(grammar, generate_grammar(&reordered).to_string());
+        assert!(grammar.contains(r#""rules":{"source_file":"#));
+    }
+
+    #[test]
+    fn grammar_externals() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            #[rust_sitter::external(Indent, Dedent, StringContent)]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    v: i32,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        assert_eq!(
+            grammar["externals"],
+            serde_json::json!([
+                { "type": "SYMBOL", "name": "Indent" },
+                { "type": "SYMBOL", "name": "Dedent" },
+                { "type": "SYMBOL", "name": "StringContent" },
+            ])
+        );
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+
+        let stub = generate_scanner_stub(&m).unwrap();
+        assert!(stub.contains("enum TokenType {\n  INDENT,\n  DEDENT,\n  STRING_CONTENT,\n};"));
+        insta::assert_snapshot!(stub);
+    }
+
+    #[test]
+    fn grammar_without_externals() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    v: i32,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        assert!(generate_grammar(&m).get("externals").is_none());
+        assert!(generate_scanner_stub(&m).is_none());
+    }
+
+    #[test]
+    fn grammar_stats() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expr {
+                    Number(
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                        u32
+                    ),
+                    #[rust_sitter::prec_left(1)]
+                    Sub(
+                        Box<Expr>,
+                        #[rust_sitter::leaf(text = "-")] (),
+                        Box<Expr>
+                    ),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        // source_file, Expr, Expr_Number and Expr_Sub refer to other rules, while
+        // Expr_Number_0 and Expr_Sub_1 are tokens
+        assert_eq!(
+            GrammarStats::from_grammar(&generate_grammar(&m)),
+            GrammarStats {
+                rules: 6,
+                terminals: 2,
+                nonterminals: 4,
+                max_fan_out: 2,
+                large_choices: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn grammar_diff_added_variant() {
+        let old = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expr {
+                    Number(
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                        u32
+                    ),
+                    #[rust_sitter::prec_left(1)]
+                    Sub(
+                        Box<Expr>,
+                        #[rust_sitter::leaf(text = "-")] (),
+                        Box<Expr>
+                    ),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let new = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expr {
+                    Number(
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                        u32
+                    ),
+                    #[rust_sitter::prec_left(1)]
+                    Sub(
+                        Box<Expr>,
+                        #[rust_sitter::leaf(text = "-")] (),
+                        Box<Expr>
+                    ),
+                    #[rust_sitter::prec_left(2)]
+                    Mul(
+                        Box<Expr>,
+                        #[rust_sitter::leaf(text = "*")] (),
+                        Box<Expr>
+                    ),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let old = generate_grammar(&old);
+        let new = generate_grammar(&new);
+        assert!(diff_grammars(&old, &old).is_empty());
+
+        let diff = diff_grammars(&old, &new);
+        assert_eq!(diff.added, vec!["Expr_Mul", "Expr_Mul_1"]);
+        assert!(diff.removed.is_empty());
+        assert!(diff.changed_settings.is_empty());
+        assert_eq!(
+            diff.changed,
+            vec![
+                (
+                    "source_file".to_string(),
+                    vec!["added `Expr_Mul` at /2".to_string()]
+                ),
+                (
+                    "Expr".to_string(),
+                    vec!["added `Expr_Mul` at /2".to_string()]
+                ),
+            ]
+        );
+
+        let reverse = diff_grammars(&new, &old);
+        assert_eq!(reverse.removed, vec!["Expr_Mul", "Expr_Mul_1"]);
+        assert!(reverse.added.is_empty());
+        insta::assert_display_snapshot!(reverse);
+    }
+
+    #[test]
+    fn grammar_stats_large_choice() {
+        let variants = (0..LARGE_CHOICE_ARITY).map(|i| {
+            syn::parse_str::<syn::Variant>(&format!(
+                "K{i}(#[rust_sitter::leaf(text = \"k{i}\")] ())"
+            ))
+            .unwrap()
+        });
+
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Keyword {
+                    #(#variants),*
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let stats = GrammarStats::from_grammar(&generate_grammar(&m));
+        assert_eq!(stats.max_fan_out, LARGE_CHOICE_ARITY);
+        assert_eq!(
+            stats.large_choices,
+            vec![
+                ("source_file".to_string(), LARGE_CHOICE_ARITY),
+                ("Keyword".to_string(), LARGE_CHOICE_ARITY)
+            ]
+        );
+    }
+
+    #[test]
+    fn grammar_file_types() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("config")]
+            #[rust_sitter::file_types("toml", "ini")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Key {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+                    name: String,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let metadata: serde_json::Value =
+            serde_json::from_str(&generate_metadata(&m).to_string()).unwrap();
+        assert_eq!(metadata["name"], "config");
+        assert_eq!(metadata["file_types"], serde_json::json!(["toml", "ini"]));
+
+        // the extensions are only part of the metadata, not the grammar itself
+        let grammar = generate_grammar(&m);
+        assert!(grammar.get("file_types").is_none());
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_field_order() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Function {
+                    #[rust_sitter::leaf(text = "pub")]
+                    public: Option<()>,
+                    #[rust_sitter::leaf(text = "fn")]
+                    _fn: (),
+                    name: Ident,
+                    #[rust_sitter::delimited(
+                        #[rust_sitter::leaf(text = ",")]
+                        ()
+                    )]
+                    params: Vec<Ident>,
+                    #[rust_sitter::repeat(non_empty = true)]
+                    body: Vec<Statement>,
+                    #[rust_sitter::skip(None)]
+                    doc: Option<String>,
+                }
+
+                pub enum Statement {
+                    Return(#[rust_sitter::leaf(text = "return")] (), Option<Ident>),
+                    #[rust_sitter::leaf(text = ";")]
+                    Empty,
+                }
+
+                pub struct Ident {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                    _name: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let fields = &generate_metadata(&m)["fields"];
+        assert_eq!(
+            fields["Function"],
+            serde_json::json!([
+                { "name": "public", "optional": true, "repeated": false },
+                { "name": "_fn", "optional": false, "repeated": false },
+                { "name": "name", "optional": false, "repeated": false },
+                { "name": "params", "optional": true, "repeated": true },
+                { "name": "body", "optional": false, "repeated": true }
+            ])
+        );
+        assert_eq!(
+            fields["Statement_Return"],
+            serde_json::json!([
+                { "name": "0", "optional": false, "repeated": false },
+                { "name": "1", "optional": true, "repeated": false }
+            ])
+        );
+        assert!(fields.get("Statement_Empty").is_none());
+        tree_sitter_cli::generate::generate_parser_for_grammar(&generate_grammar(&m).to_string())
+            .unwrap();
+    }
+
+    #[test]
+    fn grammar_locals() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                #[rust_sitter::scope]
+                pub struct Block {
+                    #[rust_sitter::leaf(text = "{")]
+                    _open: (),
+                    statements: Vec<Statement>,
+                    #[rust_sitter::leaf(text = "}")]
+                    _close: (),
+                }
+
+                pub enum Statement {
+                    #[rust_sitter::definition(field = "name")]
+                    Let {
+                        #[rust_sitter::leaf(text = "let")]
+                        _let: (),
+                        name: Identifier,
+                        #[rust_sitter::leaf(text = "=")]
+                        _eq: (),
+                        value: Identifier,
+                    },
+                    Block(Block),
+                }
+
+                #[rust_sitter::reference]
+                pub struct Identifier {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                    _name: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        assert_eq!(
+            generate_locals(&m).unwrap(),
+            "(source_file) @local.scope\n(Block) @local.scope\n\n(Statement_Let name: (_) @local.definition)\n\n(Identifier) @local.reference\n"
+        );
+        tree_sitter_cli::generate::generate_parser_for_grammar(&generate_grammar(&m).to_string())
+            .unwrap();
+    }
+
+    #[test]
+    fn grammar_highlights() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Let {
+                    #[rust_sitter::highlight("keyword")]
+                    #[rust_sitter::leaf(text = "let")]
+                    _let: (),
+                    name: Identifier,
+                    #[rust_sitter::leaf(text = "=")]
+                    _eq: (),
+                    #[rust_sitter::highlight("number")]
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    value: i32,
+                }
+
+                #[rust_sitter::highlight("variable")]
+                pub struct Identifier {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                    _name: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        let highlights = generate_highlights(&m, &grammar).unwrap();
+        assert_eq!(
+            highlights,
+            "(Let__let) @keyword\n(Let_value) @number\n(Identifier) @variable\n"
+        );
+        // every captured node kind is a rule of the grammar
+        ["Let__let", "Let_value", "Identifier"]
+            .iter()
+            .for_each(|kind| assert!(grammar["rules"].get(*kind).is_some()));
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_highlights_renamed() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            #[rust_sitter::hide_anonymous]
+            #[rust_sitter::rename_all("snake_case")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct LetStatement {
+                    #[rust_sitter::highlight("keyword")]
+                    #[rust_sitter::leaf(text = "let")]
+                    _let: (),
+                    #[rust_sitter::highlight("number")]
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    value: i32,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        // the keyword is matched by its text rather than by a rule of its own
+        let grammar = generate_grammar(&m);
+        assert_eq!(
+            generate_highlights(&m, &grammar).unwrap(),
+            "\"let\" @keyword\n(let_statement_value) @number\n"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "names the field `name`, which is not a field of its rule")]
+    fn grammar_locals_unknown_field() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                #[rust_sitter::definition(field = "name")]
+                pub struct Let {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                    ident: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        generate_locals(&m);
+    }
+
+    #[test]
+    fn grammar_without_file_types() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("config")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Key {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+                    name: String,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        assert_eq!(generate_metadata(&m)["file_types"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn grammar_leaf_captures() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                #[rust_sitter::leaf(pattern = r"(?P<y>\d{4})-(?<m>\d{2})-(?P<d>\d{2})(?<=\d)")]
+                pub struct Date {
+                    y: u16,
+                    m: u8,
+                    d: u8,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        assert_eq!(
+            grammar["rules"]["Date"],
+            serde_json::json!({
+                "type": "PATTERN",
+                "value": r"(\d{4})-(\d{2})-(\d{2})(?<=\d)"
+            })
+        );
+    }
+
+    #[test]
+    fn grammar_hide_anonymous() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            #[rust_sitter::hide_anonymous]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expr {
+                    Number(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] u32),
+                    Group(
+                        #[rust_sitter::leaf(text = "(")] (),
+                        Box<Expr>,
+                        #[rust_sitter::leaf(text = ")")] (),
+                    ),
+                    #[rust_sitter::leaf(text = "nil")]
+                    Nil,
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        let rules = grammar["rules"].as_object().unwrap();
+        assert!(!rules.contains_key("Expr_Group_0"));
+        assert!(!rules.contains_key("Expr_Group_2"));
+        assert!(rules.contains_key("Expr_Nil"));
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_rule_metadata() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                #[rust_sitter::meta(category = "expression")]
+                pub enum Expr {
+                    #[rust_sitter::meta(category = "literal", weight = 2, pure = true)]
+                    Number(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] u32),
+                    Neg(#[rust_sitter::leaf(text = "-")] (), Box<Expr>),
+                }
+
+                #[rust_sitter::extra]
+                #[rust_sitter::meta(category = "trivia", ratio = 0.5)]
+                struct Whitespace {
+                    #[rust_sitter::leaf(pattern = r"\s")]
+                    _whitespace: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let metadata: serde_json::Value =
+            serde_json::from_str(&generate_metadata(&m).to_string()).unwrap();
+        assert_eq!(
+            metadata["rules"],
+            serde_json::json!({
+                "Expr": { "category": "expression" },
+                "Expr_Number": { "category": "literal", "weight": 2, "pure": true },
+                "Whitespace": { "category": "trivia", "ratio": 0.5 },
+            })
+        );
+
+        // the metadata is only in the sidecar, and the grammar is the same without it
+        let grammar = generate_grammar(&m);
+        assert!(!grammar.to_string().contains("category"));
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn metadata_stamp() {
+        let with_pattern = |pattern: &str| {
+            let m: syn::ItemMod = syn::parse_str(&format!(
+                r#"
+                #[rust_sitter::grammar("config")]
+                mod grammar {{
+                    #[rust_sitter::language]
+                    pub struct Key {{
+                        #[rust_sitter::leaf(pattern = r"{pattern}", transform = |v| v.to_string())]
+                        name: String,
+                    }}
+                }}
+                "#
+            ))
+            .unwrap();
+            generate_metadata(&m)["stamp"].clone()
+        };
+
+        let stamp = with_pattern("[a-z]+");
+        assert_eq!(stamp["tool_version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(stamp["source_hash"], with_pattern("[a-z]+")["source_hash"]);
+        assert_ne!(stamp["source_hash"], with_pattern("[a-z_]+")["source_hash"]);
+    }
+
+    #[test]
+    fn enum_dominated_variant() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expr {
+                    Number(
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                        u32
+                    ),
+                    #[rust_sitter::prec_left(2)]
+                    Sub(
+                        Box<Expr>,
+                        #[rust_sitter::leaf(text = "-")] (),
+                        Box<Expr>
+                    ),
+                    #[rust_sitter::prec_left(1)]
+                    Minus(
+                        Box<Expr>,
+                        #[rust_sitter::leaf(text = "-")] (),
+                        Box<Expr>
+                    ),
+                    #[rust_sitter::prec_left(1)]
+                    Mul(
+                        Box<Expr>,
+                        #[rust_sitter::leaf(text = "*")] (),
+                        Box<Expr>
+                    ),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        let variant_paths =
+            ["Expr_Number", "Expr_Sub", "Expr_Minus", "Expr_Mul"].map(|p| p.to_string());
+        assert_eq!(
+            dominated_variant_warnings(
+                &variant_paths,
+                &grammar["rules"]
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into_iter()
+                    .collect()
+            ),
+            vec!["`Expr_Minus` can never be selected, because `Expr_Sub` matches the same input with a higher precedence (2 > 1)"]
+        );
+    }
+
+    #[test]
+    fn inconsistent_aliases() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Let {
+                    #[rust_sitter::alias_as("variable", named = true)]
+                    name: Ident,
+                    #[rust_sitter::leaf(text = "=")]
+                    _eq: (),
+                    value: Ident,
+                    #[rust_sitter::alias_as("label")]
+                    label: Option<Ident>,
+                }
+
+                pub struct Ident {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                    _name: (),
+                }
+
+                #[rust_sitter::extra]
+                struct Whitespace {
+                    #[rust_sitter::leaf(pattern = r"\s")]
+                    _whitespace: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        let rules = grammar["rules"]
+            .as_object()
+            .unwrap()
+            .clone()
+            .into_iter()
+            .collect();
+
+        // aliasing `Ident` to other named nodes is fine, but not to an anonymous one
+        assert_eq!(
+            inconsistent_alias_warnings(&rules),
+            vec![
+                "The rule `Ident` is referenced as the named node `variable` in `Let` but as the anonymous node `label` in `Let`, so its nodes are only named in some places. Give every alias of it the same `named` setting",
+            ]
+        );
+
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Let {
+                    #[rust_sitter::alias_as("variable", named = true)]
+                    name: Ident,
+                    #[rust_sitter::leaf(text = "=")]
+                    _eq: (),
+                    value: Ident,
+                }
+
+                pub struct Ident {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                    _name: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+        let grammar = generate_grammar(&m);
+        let rules = grammar["rules"]
+            .as_object()
+            .unwrap()
+            .clone()
+            .into_iter()
+            .collect();
+        assert!(inconsistent_alias_warnings(&rules).is_empty());
+    }
 
     #[test]
-    fn enum_with_named_field() {
+    fn error_only_alternatives() {
         let m = if let syn::Item::Mod(m) = parse_quote! {
             #[rust_sitter::grammar("test")]
             mod grammar {
                 #[rust_sitter::language]
                 pub enum Expr {
                     Number(
-                            #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
-                            u32
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                        u32
                     ),
-                    Neg {
-                        #[rust_sitter::leaf(text = "!")]
-                        _bang: (),
-                        value: Box<Expr>,
-                    }
+                    Neg(#[rust_sitter::leaf(text = "-")] (), Box<Expr>),
+                    Cycle(#[rust_sitter::leaf(text = "@")] (), Box<Cycle>),
+                    List(#[rust_sitter::leaf(text = "[")] (), Vec<Cycle>),
+                }
+
+                // every `Cycle` holds another, so no input is ever a complete `Cycle`
+                pub struct Cycle {
+                    #[rust_sitter::leaf(text = "(")]
+                    _open: (),
+                    inner: Box<Cycle>,
                 }
             }
         } {
@@ -131,21 +5544,32 @@ mod tests {
         };
 
         let grammar = generate_grammar(&m);
-        insta::assert_display_snapshot!(grammar);
-        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
-    }
+        let rules = grammar["rules"]
+            .as_object()
+            .unwrap()
+            .clone()
+            .into_iter()
+            .collect();
+
+        // an empty list is complete, so only its elements are flagged rather than the variant
+        assert_eq!(
+            error_only_alternative_warnings(&rules),
+            vec![
+                "`Expr_Cycle` in `Expr` can only be matched while recovering from an error, because matching it always requires matching it again, so it never matches a complete input",
+                "`Expr_List_1_vec_contents` in `Expr_List` can only be matched while recovering from an error, because matching it always requires matching it again, so it never matches a complete input",
+            ]
+        );
 
-    #[test]
-    fn enum_transformed_fields() {
         let m = if let syn::Item::Mod(m) = parse_quote! {
             #[rust_sitter::grammar("test")]
             mod grammar {
                 #[rust_sitter::language]
-                pub enum Expression {
+                pub enum Expr {
                     Number(
-                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v: &str| v.parse::<i32>().unwrap())]
-                        i32
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                        u32
                     ),
+                    Neg(#[rust_sitter::leaf(text = "-")] (), Box<Expr>),
                 }
             }
         } {
@@ -153,28 +5577,32 @@ mod tests {
         } else {
             panic!()
         };
-
         let grammar = generate_grammar(&m);
-        insta::assert_display_snapshot!(grammar);
-        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+        let rules = grammar["rules"]
+            .as_object()
+            .unwrap()
+            .clone()
+            .into_iter()
+            .collect();
+        assert!(error_only_alternative_warnings(&rules).is_empty());
     }
 
     #[test]
-    fn enum_recursive() {
+    fn enum_with_unamed_vector() {
         let m = if let syn::Item::Mod(m) = parse_quote! {
             #[rust_sitter::grammar("test")]
             mod grammar {
+                pub struct Number {
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                        value: u32
+                }
+
                 #[rust_sitter::language]
-                pub enum Expression {
-                    Number(
-                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v: &str| v.parse::<i32>().unwrap())]
-                        i32
-                    ),
-                    Neg(
-                        #[rust_sitter::leaf(text = "-", transform = |v| ())]
-                        (),
-                        Box<Expression>
-                    ),
+                pub enum Expr {
+                    Numbers(
+                        #[rust_sitter::repeat(non_empty = true)]
+                        Vec<Number>
+                    )
                 }
             }
         } {
@@ -189,23 +5617,16 @@ mod tests {
     }
 
     #[test]
-    fn enum_prec_left() {
+    fn grammar_flag_leaf() {
         let m = if let syn::Item::Mod(m) = parse_quote! {
             #[rust_sitter::grammar("test")]
             mod grammar {
                 #[rust_sitter::language]
-                pub enum Expression {
-                    Number(
-                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v: &str| v.parse::<i32>().unwrap())]
-                        i32
-                    ),
-                    #[rust_sitter::prec_left(1)]
-                    Sub(
-                        Box<Expression>,
-                        #[rust_sitter::leaf(text = "-", transform = |v| ())]
-                        (),
-                        Box<Expression>
-                    ),
+                pub struct Function {
+                    #[rust_sitter::leaf(text = "pub")]
+                    public: bool,
+                    #[rust_sitter::leaf(text = "fn")]
+                    _fn: (),
                 }
             }
         } {
@@ -214,27 +5635,165 @@ mod tests {
             panic!()
         };
 
+        // the text of a flag is optional, unlike the text of a `()` leaf
         let grammar = generate_grammar(&m);
+        let members = &grammar["rules"]["Function"]["members"];
+        assert_eq!(members[0]["type"], "CHOICE");
+        assert_eq!(members[0]["members"][1]["name"], "public");
+        assert_eq!(members[1]["type"], "FIELD");
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn raw_and_cooked_patterns() {
+        let generate_with_pattern = |pattern: syn::LitStr| {
+            let m = if let syn::Item::Mod(m) = parse_quote! {
+                #[rust_sitter::grammar("test")]
+                mod grammar {
+                    #[rust_sitter::language]
+                    pub struct Number {
+                        #[rust_sitter::leaf(pattern = #pattern)]
+                        _digits: (),
+                    }
+                }
+            } {
+                m
+            } else {
+                panic!()
+            };
+
+            generate_grammar_with_warnings(&m, &[])
+        };
+
+        let (raw, raw_warnings) = generate_with_pattern(parse_quote!(r"\d+\x2e"));
+        let (cooked, cooked_warnings) = generate_with_pattern(parse_quote!("\\d+\\x2e"));
+        assert_eq!(raw, cooked);
+        assert_eq!(raw["rules"]["Number__digits"]["value"], r"\d+\x2e");
+        assert!(raw_warnings.is_empty());
+        assert!(cooked_warnings.is_empty());
+
+        // the escape is resolved by Rust, so the pattern matches any character
+        let (_, warnings) = generate_with_pattern(parse_quote!("\\d+\x2e\u{2a}\n"));
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("`\\x2e`, `\\u{2a}`"));
+    }
+
+    #[test]
+    fn default_empty_warnings_for_required_fields() {
+        let generate_with_root = |root: syn::Item| {
+            let m = if let syn::Item::Mod(m) = parse_quote! {
+                #[rust_sitter::grammar("test")]
+                mod grammar {
+                    #root
+                }
+            } {
+                m
+            } else {
+                panic!()
+            };
+
+            default_empty_warnings(&m)
+        };
+
+        assert!(generate_with_root(parse_quote! {
+            #[rust_sitter::language]
+            #[rust_sitter::default_empty]
+            pub struct Program {
+                #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                numbers: Vec<i32>,
+                #[rust_sitter::skip(0)]
+                count: usize,
+            }
+        })
+        .is_empty());
+
+        let warnings = generate_with_root(parse_quote! {
+            #[rust_sitter::language]
+            #[rust_sitter::default_empty]
+            pub struct Program {
+                #[rust_sitter::leaf(text = "begin")]
+                _begin: (),
+                #[rust_sitter::repeat(non_empty = true)]
+                #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                numbers: Vec<i32>,
+            }
+        });
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("its field `numbers` cannot be empty"));
+
+        let warnings = generate_with_root(parse_quote! {
+            #[rust_sitter::language]
+            #[rust_sitter::default_empty]
+            pub enum Expr {
+                Number(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] i32),
+            }
+        });
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("an enum has no empty value"));
+    }
+
+    #[test]
+    fn grammar_boxed_repetitions() {
+        let generate_with_field_type = |ty: syn::Type| {
+            let m = if let syn::Item::Mod(m) = parse_quote! {
+                #[rust_sitter::grammar("test")]
+                mod grammar {
+                    #[rust_sitter::language]
+                    pub enum Expr {
+                        Number(
+                            #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                            u32
+                        ),
+                        List(
+                            #[rust_sitter::leaf(text = "[")] (),
+                            #[rust_sitter::delimited(
+                                #[rust_sitter::leaf(text = ",")]
+                                ()
+                            )]
+                            #ty,
+                            #[rust_sitter::leaf(text = "]")] (),
+                        )
+                    }
+                }
+            } {
+                m
+            } else {
+                panic!()
+            };
+
+            generate_grammar(&m)
+        };
+
+        let grammar = generate_with_field_type(parse_quote!(Vec<Expr>));
         insta::assert_display_snapshot!(grammar);
         tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+
+        assert_eq!(
+            generate_with_field_type(parse_quote!(Vec<Box<Expr>>)),
+            grammar
+        );
+        assert_eq!(
+            generate_with_field_type(parse_quote!(Box<Vec<Expr>>)),
+            grammar
+        );
     }
 
     #[test]
-    fn grammar_with_extras() {
+    fn spanned_in_vec() {
         let m = if let syn::Item::Mod(m) = parse_quote! {
             #[rust_sitter::grammar("test")]
             mod grammar {
+                use rust_sitter::Spanned;
+
                 #[rust_sitter::language]
-                pub enum Expression {
-                    Number(
-                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v: &str| v.parse::<i32>().unwrap())]
-                        i32
-                    ),
+                pub struct NumberList {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    numbers: Vec<Spanned<i32>>,
                 }
 
                 #[rust_sitter::extra]
                 struct Whitespace {
-                    #[rust_sitter::leaf(pattern = r"\s", transform = |_v| ())]
+                    #[rust_sitter::leaf(pattern = r"\s")]
                     _whitespace: (),
                 }
             }
@@ -250,20 +5809,33 @@ mod tests {
     }
 
     #[test]
-    fn grammar_unboxed_field() {
+    fn blank_and_optional_rules() {
+        assert_eq!(blank().to_string(), r#"{"type":"BLANK"}"#);
+        assert_eq!(
+            optional(serde_json::json!({ "type": "SYMBOL", "name": "Number" })).to_string(),
+            r#"{"type":"CHOICE","members":[{"type":"BLANK"},{"type":"SYMBOL","name":"Number"}]}"#
+        );
+
+        // the generator accepts an optional rule, which matches the empty input
+        let grammar = serde_json::json!({
+            "name": "test",
+            "rules": {
+                "source_file": optional(serde_json::json!({ "type": "STRING", "value": "a" }))
+            }
+        });
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_conflicts() {
         let m = if let syn::Item::Mod(m) = parse_quote! {
             #[rust_sitter::grammar("test")]
+            #[rust_sitter::conflicts([Expr_Call])]
             mod grammar {
                 #[rust_sitter::language]
-                pub struct Language {
-                    e: Expression,
-                }
-
-                pub enum Expression {
-                    Number(
-                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v: &str| v.parse::<i32>().unwrap())]
-                        i32
-                    ),
+                pub enum Expr {
+                    Number(#[rust_sitter::leaf(pattern = r"\d+")] ()),
+                    Call(Box<Expr>, Box<Expr>),
                 }
             }
         } {
@@ -273,33 +5845,31 @@ mod tests {
         };
 
         let grammar = generate_grammar(&m);
-        insta::assert_display_snapshot!(grammar);
+        assert_eq!(grammar["conflicts"], serde_json::json!([["Expr_Call"]]));
         tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+
+        let mut without_conflicts = grammar.clone();
+        without_conflicts
+            .as_object_mut()
+            .unwrap()
+            .remove("conflicts");
+        assert!(tree_sitter_cli::generate::generate_parser_for_grammar(
+            &without_conflicts.to_string()
+        )
+        .is_err());
     }
 
     #[test]
-    fn grammar_repeat() {
+    #[should_panic(expected = "The conflict `Expr_Index` does not name a rule of the grammar")]
+    fn grammar_conflicts_unknown_rule() {
         let m = if let syn::Item::Mod(m) = parse_quote! {
             #[rust_sitter::grammar("test")]
-            pub mod grammar {
+            #[rust_sitter::conflicts([Expr_Call, Expr_Index])]
+            mod grammar {
                 #[rust_sitter::language]
-                pub struct NumberList {
-                    #[rust_sitter::delimited(
-                        #[rust_sitter::leaf(text = ",")]
-                        ()
-                    )]
-                    numbers: Vec<Number>,
-                }
-
-                pub struct Number {
-                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
-                    v: i32,
-                }
-
-                #[rust_sitter::extra]
-                struct Whitespace {
-                    #[rust_sitter::leaf(pattern = r"\s")]
-                    _whitespace: (),
+                pub enum Expr {
+                    Number(#[rust_sitter::leaf(pattern = r"\d+")] ()),
+                    Call(Box<Expr>, Box<Expr>),
                 }
             }
         } {
@@ -308,30 +5878,34 @@ mod tests {
             panic!()
         };
 
-        let grammar = generate_grammar(&m);
-        insta::assert_display_snapshot!(grammar);
-        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+        generate_grammar(&m);
     }
 
     #[test]
-    fn grammar_repeat_no_delimiter() {
+    fn unused_conflicts_output() {
+        let output = "Warning: unnecessary conflicts\n  `expr_call`, `expr_index`\n  `statement`\nDone\n  `not_reported`\n";
+        assert_eq!(
+            parse_unused_conflicts(output),
+            vec![
+                vec!["expr_call".to_string(), "expr_index".to_string()],
+                vec!["statement".to_string()],
+            ]
+        );
+        assert!(parse_unused_conflicts("").is_empty());
+    }
+
+    #[cfg(feature = "check_conflicts")]
+    #[test]
+    fn unused_conflicts_with_cli() {
         let m = if let syn::Item::Mod(m) = parse_quote! {
             #[rust_sitter::grammar("test")]
-            pub mod grammar {
+            #[rust_sitter::conflicts([Expr_Call], [Expr_Negation])]
+            mod grammar {
                 #[rust_sitter::language]
-                pub struct NumberList {
-                    numbers: Vec<Number>,
-                }
-
-                pub struct Number {
-                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
-                    v: i32,
-                }
-
-                #[rust_sitter::extra]
-                struct Whitespace {
-                    #[rust_sitter::leaf(pattern = r"\s")]
-                    _whitespace: (),
+                pub enum Expr {
+                    Number(#[rust_sitter::leaf(pattern = r"\d+")] ()),
+                    Negation(#[rust_sitter::leaf(text = "-")] (), Box<Expr>),
+                    Call(Box<Expr>, Box<Expr>),
                 }
             }
         } {
@@ -340,35 +5914,37 @@ mod tests {
             panic!()
         };
 
-        let grammar = generate_grammar(&m);
-        insta::assert_display_snapshot!(grammar);
-        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+        // the check is skipped where the `tree-sitter` CLI is not installed
+        if let Some(unused) = super::find_unused_conflicts(&generate_grammar(&m)) {
+            assert!(!unused.contains(&vec!["Expr_Call".to_string()]));
+        }
     }
 
     #[test]
-    fn grammar_repeat1() {
+    #[should_panic(
+        expected = "- between `If` before `else`, which can be declared with `#[rust_sitter::conflicts([If])]`"
+    )]
+    fn grammar_strict_conflicts_dangling_else() {
         let m = if let syn::Item::Mod(m) = parse_quote! {
-            #[rust_sitter::grammar("test")]
-            pub mod grammar {
+            #[rust_sitter::grammar("test", strict_conflicts = true)]
+            mod grammar {
                 #[rust_sitter::language]
-                pub struct NumberList {
-                    #[rust_sitter::repeat(non_empty = true)]
-                    #[rust_sitter::delimited(
-                        #[rust_sitter::leaf(text = ",")]
-                        ()
-                    )]
-                    numbers: Vec<Number>,
+                pub enum Statement {
+                    Expr(#[rust_sitter::leaf(pattern = r"[a-z]+")] ()),
+                    If(If),
                 }
 
-                pub struct Number {
-                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
-                    v: i32,
+                pub struct If {
+                    #[rust_sitter::leaf(text = "if")]
+                    _if: (),
+                    then: Box<Statement>,
+                    otherwise: Option<Else>,
                 }
 
-                #[rust_sitter::extra]
-                struct Whitespace {
-                    #[rust_sitter::leaf(pattern = r"\s")]
-                    _whitespace: (),
+                pub struct Else {
+                    #[rust_sitter::leaf(text = "else")]
+                    _else: (),
+                    body: Box<Statement>,
                 }
             }
         } {
@@ -377,28 +5953,33 @@ mod tests {
             panic!()
         };
 
-        let grammar = generate_grammar(&m);
-        insta::assert_display_snapshot!(grammar);
-        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+        generate_grammar(&m);
     }
 
     #[test]
-    fn struct_optional() {
+    fn grammar_strict_conflicts_resolved() {
         let m = if let syn::Item::Mod(m) = parse_quote! {
-            #[rust_sitter::grammar("test")]
+            #[rust_sitter::grammar("test", strict_conflicts = true)]
             mod grammar {
                 #[rust_sitter::language]
-                pub struct Language {
-                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
-                    v: Option<i32>,
-                    #[rust_sitter::leaf(pattern = r" ", transform = |v| ())]
-                    space: (),
-                    t: Option<Number>,
+                pub enum Statement {
+                    Expr(#[rust_sitter::leaf(pattern = r"[a-z]+")] ()),
+                    If(If),
                 }
 
-                pub struct Number {
-                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
-                    v: i32
+                // an `else` belongs to the innermost `if`
+                #[rust_sitter::prec_right(1)]
+                pub struct If {
+                    #[rust_sitter::leaf(text = "if")]
+                    _if: (),
+                    then: Box<Statement>,
+                    otherwise: Option<Else>,
+                }
+
+                pub struct Else {
+                    #[rust_sitter::leaf(text = "else")]
+                    _else: (),
+                    body: Box<Statement>,
                 }
             }
         } {
@@ -408,26 +5989,16 @@ mod tests {
         };
 
         let grammar = generate_grammar(&m);
-        insta::assert_display_snapshot!(grammar);
         tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
-    }
 
-    #[test]
-    fn enum_with_unamed_vector() {
         let m = if let syn::Item::Mod(m) = parse_quote! {
-            #[rust_sitter::grammar("test")]
+            #[rust_sitter::grammar("test", strict_conflicts = true)]
+            #[rust_sitter::conflicts([Expr_Call])]
             mod grammar {
-                pub struct Number {
-                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
-                        value: u32
-                }
-
                 #[rust_sitter::language]
                 pub enum Expr {
-                    Numbers(
-                        #[rust_sitter::repeat(non_empty = true)]
-                        Vec<Number>
-                    )
+                    Number(#[rust_sitter::leaf(pattern = r"\d+")] ()),
+                    Call(Box<Expr>, Box<Expr>),
                 }
             }
         } {
@@ -437,21 +6008,43 @@ mod tests {
         };
 
         let grammar = generate_grammar(&m);
-        insta::assert_display_snapshot!(grammar);
         tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+        let mut without_conflicts = grammar.clone();
+        without_conflicts
+            .as_object_mut()
+            .unwrap()
+            .remove("conflicts");
+        let undeclared = find_undeclared_conflicts(&without_conflicts);
+        assert_eq!(undeclared.len(), 1);
+        assert_eq!(undeclared[0].rules, vec!["Expr_Call".to_string()]);
     }
 
     #[test]
-    fn spanned_in_vec() {
+    fn json_schema() {
         let m = if let syn::Item::Mod(m) = parse_quote! {
             #[rust_sitter::grammar("test")]
             mod grammar {
-                use rust_sitter::Spanned;
-
                 #[rust_sitter::language]
-                pub struct NumberList {
-                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
-                    numbers: Vec<Spanned<i32>>,
+                #[rust_sitter::serde]
+                pub enum Expr {
+                    Number(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] u32),
+                    Neg(#[rust_sitter::leaf(text = "-")] (), Box<Expr>),
+                    Call {
+                        name: Spanned<Ident>,
+                        #[rust_sitter::leaf(text = "(")]
+                        _open: (),
+                        args: Vec<Expr>,
+                        #[rust_sitter::leaf(text = ")")]
+                        _close: (),
+                        rest: Option<Box<Expr>>,
+                    },
+                    #[rust_sitter::leaf(text = "nil")]
+                    Nil,
+                }
+
+                pub struct Ident {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                    name: String,
                 }
 
                 #[rust_sitter::extra]
@@ -466,8 +6059,26 @@ mod tests {
             panic!()
         };
 
-        let grammar = generate_grammar(&m);
-        insta::assert_display_snapshot!(grammar);
-        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+        let schema = generate_json_schema(&m).unwrap();
+        assert_eq!(schema["$ref"], "#/definitions/Expr");
+        assert!(schema["definitions"].get("Whitespace").is_none());
+        insta::assert_snapshot!(serde_json::to_string_pretty(&schema).unwrap());
+
+        // grammars whose AST is not serializable have no schema
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+")]
+                    v: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+        assert!(generate_json_schema(&m).is_none());
     }
 }
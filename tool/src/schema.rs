@@ -0,0 +1,253 @@
+use rust_sitter_common::find_root_types;
+use serde_json::{json, Map, Value};
+use syn::{Fields, GenericArgument, Item, ItemMod, PathArguments, Type};
+
+/// Generates a JSON Schema (draft-07) describing the JSON that `serde_json` produces for the
+/// AST of a grammar whose root type is marked with `#[rust_sitter::serde]`, or `None` for
+/// grammars that are not serializable.
+///
+/// Each type of the grammar other than extras has a definition. Structs are objects with
+/// all of their fields, enums are unions of their variants in serde's externally tagged
+/// representation, and `Option` fields accept `null`. Types that are not part of the
+/// grammar, such as the results of transforms, are not described, so any value is accepted
+/// for them.
+pub fn generate_json_schema(module: &ItemMod) -> Option<Value> {
+    let (_, contents) = module.content.as_ref()?;
+
+    let has_attr = |attrs: &[syn::Attribute], attr: syn::Path| attrs.iter().any(|a| a.path == attr);
+    let root_types = find_root_types(contents);
+    let serializable = contents.iter().any(|item| match item {
+        Item::Enum(syn::ItemEnum { ident, attrs, .. })
+        | Item::Struct(syn::ItemStruct { ident, attrs, .. }) => {
+            root_types.contains(ident) && has_attr(attrs, syn::parse_quote!(rust_sitter::serde))
+        }
+        _ => false,
+    });
+    if !serializable {
+        return None;
+    }
+
+    let local_types = contents
+        .iter()
+        .filter_map(|item| match item {
+            Item::Enum(e) => Some(e.ident.to_string()),
+            Item::Struct(s) if !has_attr(&s.attrs, syn::parse_quote!(rust_sitter::extra)) => {
+                Some(s.ident.to_string())
+            }
+            Item::Type(t) => Some(t.ident.to_string()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let mut definitions = Map::new();
+    contents.iter().for_each(|item| match item {
+        Item::Enum(e) => {
+            let variants = e
+                .variants
+                .iter()
+                .map(|v| {
+                    let name = v.ident.to_string();
+                    match &v.fields {
+                        Fields::Unit => json!({ "const": name }),
+                        fields => object_schema(vec![(name, fields_schema(fields, &local_types))]),
+                    }
+                })
+                .collect::<Vec<_>>();
+            definitions.insert(e.ident.to_string(), json!({ "oneOf": variants }));
+        }
+        Item::Struct(s) if local_types.contains(&s.ident.to_string()) => {
+            definitions.insert(s.ident.to_string(), fields_schema(&s.fields, &local_types));
+        }
+        Item::Type(t) => {
+            definitions.insert(t.ident.to_string(), type_schema(&t.ty, &local_types));
+        }
+        _ => {}
+    });
+
+    // several root types are extracted into the variants of a generated `SourceFile` enum
+    let root = if let [root_type] = root_types.as_slice() {
+        json!({ "$ref": format!("#/definitions/{root_type}") })
+    } else {
+        json!({
+            "oneOf": root_types
+                .iter()
+                .map(|root_type| object_schema(vec![(
+                    root_type.to_string(),
+                    json!({ "$ref": format!("#/definitions/{root_type}") }),
+                )]))
+                .collect::<Vec<_>>()
+        })
+    };
+
+    let mut schema = json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": module.ident.to_string(),
+    });
+    schema
+        .as_object_mut()
+        .unwrap()
+        .extend(root.as_object().unwrap().clone());
+    schema["definitions"] = Value::Object(definitions);
+    Some(schema)
+}
+
+/// The schema of the fields of a struct or variant, which serde writes as an object for
+/// named fields, as the value of the field for a single unnamed field, and as an array for
+/// any other number of unnamed fields.
+fn fields_schema(fields: &Fields, local_types: &[String]) -> Value {
+    match fields {
+        Fields::Named(named) => object_schema(
+            named
+                .named
+                .iter()
+                .map(|f| {
+                    (
+                        f.ident.as_ref().unwrap().to_string(),
+                        type_schema(&f.ty, local_types),
+                    )
+                })
+                .collect(),
+        ),
+        Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 => {
+            type_schema(&unnamed.unnamed[0].ty, local_types)
+        }
+        Fields::Unnamed(unnamed) => tuple_schema(
+            unnamed
+                .unnamed
+                .iter()
+                .map(|f| type_schema(&f.ty, local_types))
+                .collect(),
+        ),
+        Fields::Unit => json!({ "type": "null" }),
+    }
+}
+
+/// The schema of an object with exactly the given properties, all of which are required.
+fn object_schema(properties: Vec<(String, Value)>) -> Value {
+    let required = properties
+        .iter()
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<_>>();
+    json!({
+        "type": "object",
+        "properties": properties.into_iter().collect::<Map<_, _>>(),
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+/// The schema of an array with an element of each of the given schemas, in order.
+fn tuple_schema(items: Vec<Value>) -> Value {
+    let len = items.len();
+    json!({
+        "type": "array",
+        "items": items,
+        "minItems": len,
+        "maxItems": len,
+    })
+}
+
+/// The schema of a `rust_sitter::Point`.
+fn point() -> Value {
+    object_schema(vec![
+        (
+            "row".to_string(),
+            json!({ "type": "integer", "minimum": 0 }),
+        ),
+        (
+            "column".to_string(),
+            json!({ "type": "integer", "minimum": 0 }),
+        ),
+    ])
+}
+
+/// The schema of the values of a field type, referring to the definitions of the types of
+/// the grammar.
+fn type_schema(ty: &Type, local_types: &[String]) -> Value {
+    match ty {
+        Type::Tuple(tuple) if tuple.elems.is_empty() => json!({ "type": "null" }),
+        Type::Tuple(tuple) => tuple_schema(
+            tuple
+                .elems
+                .iter()
+                .map(|t| type_schema(t, local_types))
+                .collect(),
+        ),
+        Type::Array(array) => {
+            let mut schema = tuple_schema(vec![]);
+            schema["items"] = type_schema(&array.elem, local_types);
+            if let syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Int(len),
+                ..
+            }) = &array.len
+            {
+                let len = len.base10_parse::<usize>().unwrap();
+                schema["minItems"] = json!(len);
+                schema["maxItems"] = json!(len);
+            } else {
+                schema.as_object_mut().unwrap().remove("minItems");
+                schema.as_object_mut().unwrap().remove("maxItems");
+            }
+            schema
+        }
+        Type::Reference(reference) => type_schema(&reference.elem, local_types),
+        Type::Paren(paren) => type_schema(&paren.elem, local_types),
+        Type::Path(path) => {
+            let last = path.path.segments.last().unwrap();
+            let name = last.ident.to_string();
+            let inner = match &last.arguments {
+                PathArguments::AngleBracketed(args) => args.args.iter().find_map(|a| match a {
+                    GenericArgument::Type(t) => Some(t),
+                    _ => None,
+                }),
+                _ => None,
+            };
+
+            match (name.as_str(), inner) {
+                ("Box", Some(inner)) => type_schema(inner, local_types),
+                ("Option", Some(inner)) => json!({
+                    "anyOf": [type_schema(inner, local_types), { "type": "null" }]
+                }),
+                ("Vec", Some(inner)) => json!({
+                    "type": "array",
+                    "items": type_schema(inner, local_types),
+                }),
+                // the elements of a `SmallVec` are given as an array type with its capacity
+                ("SmallVec", Some(Type::Array(array))) => json!({
+                    "type": "array",
+                    "items": type_schema(&array.elem, local_types),
+                }),
+                ("Spanned", Some(inner)) => object_schema(vec![
+                    ("value".to_string(), type_schema(inner, local_types)),
+                    (
+                        "span".to_string(),
+                        tuple_schema(vec![
+                            json!({ "type": "integer", "minimum": 0 }),
+                            json!({ "type": "integer", "minimum": 0 }),
+                        ]),
+                    ),
+                    ("points".to_string(), tuple_schema(vec![point(), point()])),
+                ]),
+                ("WithRaw", Some(inner)) => object_schema(vec![
+                    ("value".to_string(), type_schema(inner, local_types)),
+                    ("raw".to_string(), json!({ "type": "string" })),
+                ]),
+                ("bool", None) => json!({ "type": "boolean" }),
+                ("u8" | "u16" | "u32" | "u64" | "u128" | "usize", None) => {
+                    json!({ "type": "integer", "minimum": 0 })
+                }
+                ("i8" | "i16" | "i32" | "i64" | "i128" | "isize", None) => {
+                    json!({ "type": "integer" })
+                }
+                ("f32" | "f64", None) => json!({ "type": "number" }),
+                ("String" | "str", None) => json!({ "type": "string" }),
+                ("char", None) => json!({ "type": "string", "minLength": 1, "maxLength": 1 }),
+                (name, _) if local_types.iter().any(|t| t == name) => {
+                    json!({ "$ref": format!("#/definitions/{name}") })
+                }
+                _ => json!({}),
+            }
+        }
+        _ => json!({}),
+    }
+}
@@ -0,0 +1,83 @@
+use std::collections::HashSet;
+
+use serde_json::Value;
+
+/// `CHOICE`s with at least this many alternatives are reported in [`GrammarStats::large_choices`].
+pub const LARGE_CHOICE_ARITY: usize = 16;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Statistics about a generated grammar, which can help spot rules that blow up
+/// the size of the parser before running the Tree Sitter generator.
+pub struct GrammarStats {
+    /// The total number of rules.
+    pub rules: usize,
+    /// The number of rules that do not refer to any other rule, which become tokens.
+    pub terminals: usize,
+    /// The number of rules that refer to other rules.
+    pub nonterminals: usize,
+    /// The largest number of distinct rules referred to by a single rule.
+    pub max_fan_out: usize,
+    /// The rules containing a `CHOICE` with at least [`LARGE_CHOICE_ARITY`] alternatives,
+    /// along with the number of alternatives in their largest `CHOICE`.
+    pub large_choices: Vec<(String, usize)>,
+}
+
+impl GrammarStats {
+    /// Computes the statistics of a grammar generated by Rust Sitter.
+    pub fn from_grammar(grammar: &Value) -> GrammarStats {
+        let rules = grammar["rules"].as_object().unwrap();
+
+        let mut stats = GrammarStats {
+            rules: rules.len(),
+            terminals: 0,
+            nonterminals: 0,
+            max_fan_out: 0,
+            large_choices: vec![],
+        };
+
+        rules.iter().for_each(|(name, rule)| {
+            let mut symbols = HashSet::new();
+            let mut max_choice = 0;
+            visit_rule(rule, &mut symbols, &mut max_choice);
+
+            if symbols.is_empty() {
+                stats.terminals += 1;
+            } else {
+                stats.nonterminals += 1;
+            }
+
+            stats.max_fan_out = stats.max_fan_out.max(symbols.len());
+
+            if max_choice >= LARGE_CHOICE_ARITY {
+                stats.large_choices.push((name.clone(), max_choice));
+            }
+        });
+
+        stats
+    }
+}
+
+fn visit_rule<'a>(rule: &'a Value, symbols: &mut HashSet<&'a str>, max_choice: &mut usize) {
+    match rule["type"].as_str() {
+        Some("SYMBOL") => {
+            symbols.insert(rule["name"].as_str().unwrap());
+        }
+        Some("CHOICE") => {
+            let members = rule["members"].as_array().unwrap();
+            *max_choice = (*max_choice).max(members.len());
+            members
+                .iter()
+                .for_each(|m| visit_rule(m, symbols, max_choice));
+        }
+        Some("SEQ") => rule["members"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .for_each(|m| visit_rule(m, symbols, max_choice)),
+        _ => {
+            if let Some(content) = rule.get("content") {
+                visit_rule(content, symbols, max_choice);
+            }
+        }
+    }
+}
@@ -0,0 +1,96 @@
+//! Builds the parser of a trivial grammar into a directory, as a build script would. This
+//! lives in its own test binary, since it sets the environment that Cargo gives build scripts.
+#![cfg(feature = "build_parsers")]
+
+use std::path::{Path, PathBuf};
+
+use rust_sitter_tool::{build_parser, BuildError};
+
+/// Writes a grammar to `main.rs` in a fresh directory and returns the directory.
+fn write_grammar(name: &str, source: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "rust_sitter_build_parser_{name}_{}",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("main.rs"), source).unwrap();
+    dir
+}
+
+/// Sets the variables that Cargo passes to build scripts, which `cc` reads, targeting the
+/// host.
+fn set_build_env() {
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let version = std::process::Command::new(rustc)
+        .arg("-vV")
+        .output()
+        .unwrap();
+    let host = String::from_utf8(version.stdout)
+        .unwrap()
+        .lines()
+        .find_map(|line| line.strip_prefix("host: ").map(|h| h.to_string()))
+        .unwrap();
+    std::env::set_var("TARGET", &host);
+    std::env::set_var("HOST", &host);
+    std::env::set_var("OPT_LEVEL", "0");
+}
+
+#[test]
+fn build_trivial_parser() {
+    set_build_env();
+    let dir = write_grammar(
+        "trivial",
+        r#"
+        #[rust_sitter::grammar("trivial")]
+        mod grammar {
+            #[rust_sitter::language]
+            pub struct Number {
+                #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                v: i32,
+            }
+        }
+        "#,
+    );
+    let out_dir = dir.join("out");
+
+    let src_dirs = build_parser(&dir.join("main.rs"), &out_dir).unwrap();
+    assert_eq!(src_dirs, vec![out_dir.join("trivial").join("src")]);
+    let grammar = std::fs::read_to_string(src_dirs[0].join("grammar.json")).unwrap();
+    assert!(grammar.starts_with(r#"{"$comment":"@generated by rust-sitter","#));
+    let parser = std::fs::read_to_string(src_dirs[0].join("parser.c")).unwrap();
+    assert!(parser.starts_with("// @generated by rust-sitter\n"));
+    assert!(src_dirs[0].join("tree_sitter").join("parser.h").exists());
+    assert!(has_static_lib(&out_dir, "trivial"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn build_missing_scanner() {
+    let dir = write_grammar(
+        "missing_scanner",
+        r#"
+        #[rust_sitter::grammar("scanned")]
+        #[rust_sitter::external(Never)]
+        mod grammar {
+            #[rust_sitter::language]
+            pub struct Number {
+                #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                v: i32,
+            }
+        }
+        "#,
+    );
+
+    let err = build_parser(&dir.join("main.rs"), &dir.join("out")).unwrap_err();
+    assert!(
+        matches!(&err, BuildError::MissingScanner { grammar, .. } if grammar == "scanned"),
+        "{err}"
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+fn has_static_lib(dir: &Path, name: &str) -> bool {
+    dir.join(format!("lib{name}.a")).exists() || dir.join(format!("{name}.lib")).exists()
+}
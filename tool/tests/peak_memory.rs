@@ -0,0 +1,81 @@
+//! Measures the peak memory of generating a large synthetic grammar. This lives in its own
+//! test binary, since the counting allocator would otherwise also see other tests. Only the
+//! allocations of the measuring thread are counted, since the test harness may allocate on
+//! its own threads at the same time.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+struct CountingAllocator;
+
+thread_local! {
+    static CURRENT: Cell<usize> = const { Cell::new(0) };
+    static PEAK: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // the counters are gone while the thread is being torn down
+        let _ = CURRENT.try_with(|current| {
+            current.set(current.get() + layout.size());
+            let _ = PEAK.try_with(|peak| peak.set(peak.get().max(current.get())));
+        });
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // memory allocated by another thread can be freed on this one
+        let _ =
+            CURRENT.try_with(|current| current.set(current.get().saturating_sub(layout.size())));
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Returns the peak number of bytes allocated by this thread while running `f`, above what
+/// it had allocated before.
+fn peak_during(f: impl FnOnce()) -> usize {
+    let before = CURRENT.with(Cell::get);
+    PEAK.with(|peak| peak.set(before));
+    f();
+    PEAK.with(Cell::get) - before
+}
+
+#[test]
+fn large_grammar_peak_memory() {
+    // a root enum with a struct of a few leaves per variant, where some of the structs are
+    // entry types so that several grammars as large as the main one are generated
+    let variants = 500;
+    let entries = 10;
+    let mut source = String::from(
+        "#[rust_sitter::grammar(\"large\")]\nmod grammar {\n#[rust_sitter::language]\npub enum Root {\n",
+    );
+    (0..variants).for_each(|i| source.push_str(&format!("V{i}(S{i}),\n")));
+    source.push_str("}\n");
+    (0..variants).for_each(|i| {
+        source.push_str(&format!(
+            "{}pub struct S{i} {{\n#[rust_sitter::leaf(text = \"k{i}\")]\n_k: (),\n#[rust_sitter::leaf(pattern = r\"\\d+\", transform = |v| v.parse().unwrap())]\nv: u32,\n}}\n",
+            if i < entries { "#[rust_sitter::entry]\n" } else { "" }
+        ))
+    });
+    source.push_str("}\n");
+
+    // the directory is removed when the guard is dropped, even if an assertion fails
+    let dir = tempfile::tempdir().unwrap();
+    let root_file = dir.path().join("main.rs");
+    std::fs::write(&root_file, source).unwrap();
+
+    let written = peak_during(|| {
+        rust_sitter_tool::write_grammars(&root_file, dir.path()).unwrap();
+    });
+    let collected = peak_during(|| {
+        let grammars = rust_sitter_tool::generate_grammars(&root_file);
+        assert_eq!(grammars.len(), entries + 1);
+    });
+
+    // most of the peak is the parsed module and the rules of one grammar, which both
+    // approaches need, but only collecting the grammars also holds all of their JSON
+    assert!(written < collected);
+}